@@ -0,0 +1,80 @@
+/* C interface to omflib, built with the capi feature:
+ *
+ *     cargo build --release --features capi
+ *
+ * and linked against libomflib.so (omflib.dll on Windows).
+ */
+#ifndef OMFLIB_H
+#define OMFLIB_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct OmfHandle OmfHandle;
+
+typedef struct {
+    size_t offset;
+    uint8_t record_type;
+    size_t length;
+    bool checksum_valid;
+} OmfRecordInfo;
+
+typedef enum {
+    OMF_SYMBOL_PUBLIC,
+    OMF_SYMBOL_EXTERNAL,
+    OMF_SYMBOL_COMMUNAL,
+    OMF_SYMBOL_COMDAT_EXTERNAL,
+    OMF_SYMBOL_COMDAT,
+    OMF_SYMBOL_ALIAS,
+} OmfSymbolKind;
+
+typedef struct {
+    const char *name;
+    OmfSymbolKind kind;
+    bool local;
+    /* 0 for symbols not defined in a segment. */
+    uint16_t segment_index;
+    uint32_t offset;
+} OmfSymbolInfo;
+
+typedef struct {
+    const char *name;
+    const char *class_name;
+    uint32_t length;
+    bool use32;
+} OmfSegmentInfo;
+
+/* Read an object module. Return NULL on failure; omf_last_error says why. */
+OmfHandle *omf_open(const char *path);
+OmfHandle *omf_open_bytes(const uint8_t *data, size_t len);
+void omf_free(OmfHandle *handle);
+
+/* The message for the last failure on this thread, or NULL. */
+const char *omf_last_error(void);
+
+/* Indices count from 0. The getters return false past the end. Strings in
+ * the info structs stay valid until the handle is freed. */
+size_t omf_record_count(const OmfHandle *handle);
+bool omf_record(const OmfHandle *handle, size_t index, OmfRecordInfo *out);
+
+/* Describe a record as omfdump does. Release with omf_string_free. */
+char *omf_record_text(const OmfHandle *handle, size_t index);
+void omf_string_free(char *s);
+
+size_t omf_symbol_count(const OmfHandle *handle);
+bool omf_symbol(const OmfHandle *handle, size_t index, OmfSymbolInfo *out);
+
+/* A segment's index in records is one more than its index here. */
+size_t omf_segment_count(const OmfHandle *handle);
+bool omf_segment(const OmfHandle *handle, size_t index, OmfSegmentInfo *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,92 @@
+use std::collections::VecDeque;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{
+    error::OmfError, ChecksumPolicy, NameEncoding, OmfInfo, OmfModule, OmfReader, OmfRecord,
+};
+
+// Reads records from an async stream, such as a socket or a response body,
+// without tying up a thread while waiting for it. Each record's bytes are
+// read asynchronously and then decoded by an OmfReader, so records, errors
+// and offsets are the same as reading the stream synchronously.
+pub struct AsyncOmfReader<R> {
+    r: R,
+    inner: OmfReader<VecDeque<u8>>,
+    failed: bool,
+}
+
+async fn read_up_to<R: AsyncRead + Unpin>(r: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match r.read(&mut buf[len..]).await? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+impl<R: AsyncRead + Unpin> AsyncOmfReader<R> {
+    pub fn new(r: R) -> Self {
+        AsyncOmfReader {
+            r,
+            inner: OmfReader::new(VecDeque::new()),
+            failed: false,
+        }
+    }
+
+    pub fn set_checksum_policy(&mut self, policy: ChecksumPolicy) {
+        self.inner.set_checksum_policy(policy);
+    }
+
+    pub fn set_name_encoding(&mut self, encoding: NameEncoding) {
+        self.inner.set_name_encoding(encoding);
+    }
+
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.inner.set_lenient(lenient);
+    }
+
+    pub fn info(&self) -> &OmfInfo {
+        self.inner.info()
+    }
+
+    pub fn into_info(self) -> OmfInfo {
+        self.inner.into_info()
+    }
+
+    // The next record, or None at the end of the stream or after an error.
+    pub async fn next_record(&mut self) -> Result<Option<OmfRecord>, OmfError> {
+        if self.failed {
+            return Ok(None);
+        }
+        let result = self.read_next().await;
+        self.failed = result.is_err();
+        result
+    }
+
+    async fn read_next(&mut self) -> Result<Option<OmfRecord>, OmfError> {
+        let mut header = [0u8; 3];
+        let header_len = read_up_to(&mut self.r, &mut header).await?;
+        let buf = &mut self.inner.r;
+        buf.clear();
+        buf.extend(&header[..header_len]);
+        if header_len == header.len() {
+            // A short body is left for the decoder to report.
+            let mut body = vec![0; u16::from_le_bytes([header[1], header[2]]) as usize];
+            let body_len = read_up_to(&mut self.r, &mut body).await?;
+            self.inner.r.extend(&body[..body_len]);
+        }
+        self.inner.get_next_record()
+    }
+
+    // Reads the rest of the stream as one module.
+    pub async fn read_module(mut self) -> Result<OmfModule, OmfError> {
+        let mut records = vec![];
+        while let Some(record) = self.read_next().await? {
+            records.push(record);
+        }
+        Ok(OmfModule::from_parts(records, self.inner.into_info()))
+    }
+}
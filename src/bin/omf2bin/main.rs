@@ -0,0 +1,99 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, ValueEnum};
+use omflib::{link, LayoutEntry, LinkOptions, OmfModule};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Bin,
+    Ihex,
+}
+
+fn parse_number(s: &str) -> Result<u32, String> {
+    let r = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
+    } else if let Some(hex) = s.strip_suffix('h').or_else(|| s.strip_suffix('H')) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        s.parse()
+    };
+    r.map_err(|e| format!("invalid number {s}: {e}"))
+}
+
+fn parse_placement(s: &str) -> Result<LayoutEntry, String> {
+    Ok(match s.split_once('@') {
+        Some((name, address)) => LayoutEntry {
+            name: name.to_string(),
+            address: Some(parse_number(address)?),
+        },
+        None => LayoutEntry {
+            name: s.to_string(),
+            address: None,
+        },
+    })
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Address of the first byte of the output
+    #[arg(long, value_parser = parse_number, default_value = "0")]
+    origin: u32,
+    /// Place a segment or class, optionally at an address (NAME or NAME@ADDR)
+    #[arg(short, long = "place", value_parser = parse_placement)]
+    placements: Vec<LayoutEntry>,
+    /// Compute offsets relative to each segment's paragraph instead of 0
+    #[arg(long)]
+    segmented: bool,
+    #[arg(short, long, value_enum, default_value = "bin")]
+    format: Format,
+    /// Write a link map to this file
+    #[arg(short, long)]
+    map: Option<PathBuf>,
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut modules = vec![];
+    for file in &args.files {
+        let mut f = fs::File::open(file).expect("Could not open input file");
+        match OmfModule::read(&mut f) {
+            Ok(m) => modules.push(m),
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let options = LinkOptions {
+        origin: args.origin,
+        flat_frames: !args.segmented,
+        segment_order: args.placements,
+    };
+    let image = link(&modules, &options).and_then(|program| {
+        if let Some(map) = &args.map {
+            let mut f = fs::File::create(map)?;
+            program.write_map(&mut f)?;
+        }
+        Ok(match args.format {
+            Format::Bin => program.bin_image(),
+            Format::Ihex => {
+                let mut hex = vec![];
+                program.write_intel_hex(&mut hex)?;
+                hex
+            }
+        })
+    });
+    let image = match image {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("{e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    fs::write(&args.output, image).expect("Could not write output file");
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,41 @@
+use std::{fs, io, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use omflib::{write_def, OmfModule};
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// Name for the LIBRARY statement
+    #[arg(short, long)]
+    library: Option<String>,
+    /// Write to this file instead of standard output
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut exports = vec![];
+    for file in &args.files {
+        let mut f = fs::File::open(file).expect("Could not open input file");
+        match OmfModule::read(&mut f).and_then(|m| m.exports()) {
+            Ok(e) => exports.extend(e),
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let result = match &args.output {
+        Some(path) => fs::File::create(path)
+            .and_then(|mut f| write_def(&mut f, args.library.as_deref(), &exports)),
+        None => write_def(&mut io::stdout(), args.library.as_deref(), &exports),
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
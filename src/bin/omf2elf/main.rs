@@ -0,0 +1,75 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, ValueEnum};
+use omflib::{omf_to_elf, ElfFlavor, OmfLibrary, OmfModule};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Arch {
+    I386,
+    I8086,
+}
+
+#[derive(Parser, Debug)]
+/// Convert an OMF object to an ELF relocatable object. Each member of a
+/// library is converted to NAME.o in the output directory.
+struct Args {
+    /// Target machine; by default i386 if the module has any 32-bit
+    /// segments, otherwise i8086 as understood by the ia16 toolchain
+    #[arg(short, long, value_enum)]
+    arch: Option<Arch>,
+    /// Output file, or directory for a library; defaults to the input with
+    /// a .o extension, or the current directory
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    input: PathBuf,
+}
+
+fn convert(args: &Args, module: &OmfModule) -> Result<Vec<u8>, String> {
+    let flavor = match args.arch {
+        Some(Arch::I386) => ElfFlavor::I386,
+        Some(Arch::I8086) => ElfFlavor::I8086,
+        None if module
+            .info()
+            .segments
+            .iter()
+            .any(|s| s.segment_attributes.bd32bit) =>
+        {
+            ElfFlavor::I386
+        }
+        None => ElfFlavor::I8086,
+    };
+    omf_to_elf(module, flavor).map_err(|e| e.to_string())
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let bytes = fs::read(&args.input).map_err(|e| e.to_string())?;
+    if OmfLibrary::is_library(&bytes) {
+        let library = OmfLibrary::from_bytes(&bytes).map_err(|e| e.to_string())?;
+        let dir = args.output.clone().unwrap_or_else(|| PathBuf::from("."));
+        for m in &library.members {
+            let elf = convert(args, &m.module).map_err(|e| format!("{}: {e}", m.name))?;
+            let path = dir.join(format!("{}.o", m.stem()));
+            fs::write(&path, elf).map_err(|e| format!("{}: {e}", path.display()))?;
+        }
+        Ok(())
+    } else {
+        let module = OmfModule::read(&mut &bytes[..]).map_err(|e| e.to_string())?;
+        let elf = convert(args, &module)?;
+        let path = args
+            .output
+            .clone()
+            .unwrap_or_else(|| args.input.with_extension("o"));
+        fs::write(&path, elf).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {e}", args.input.display());
+            ExitCode::FAILURE
+        }
+    }
+}
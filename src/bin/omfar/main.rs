@@ -0,0 +1,187 @@
+use std::{fs, io, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, ValueEnum};
+use omflib::{module_stem, LibraryBuilder, OmfLibrary, OmfModule, OmfRecordData};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Verb {
+    /// List the members
+    T,
+    /// Extract members (all of them if none are named) to NAME.obj
+    X,
+    /// Replace members with the given objects, adding any that are new
+    R,
+    /// Delete the named members
+    D,
+    /// Append the given objects without checking for existing members
+    Q,
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[arg(value_enum)]
+    verb: Verb,
+    library: PathBuf,
+    /// Object files for r and q, member names for x and d
+    files: Vec<String>,
+    /// Describe each member as it is processed
+    #[arg(short, long)]
+    verbose: bool,
+    /// Page size for a new library
+    #[arg(long, default_value_t = 16)]
+    page_size: u32,
+}
+
+// A member as it will be written: its module name and raw bytes.
+struct Member {
+    name: String,
+    stem: String,
+    bytes: Vec<u8>,
+}
+
+// Reads the library's page size, whether its dictionary is case sensitive,
+// and its members.
+fn read_library(args: &Args) -> Result<(u32, bool, Vec<Member>), String> {
+    let bytes = match fs::read(&args.library) {
+        Ok(bytes) => bytes,
+        // Only a library that isn't there yet is started afresh; anything
+        // else would be overwritten with whatever r or q adds.
+        Err(e) if e.kind() == io::ErrorKind::NotFound && matches!(args.verb, Verb::R | Verb::Q) => {
+            return Ok((args.page_size, false, vec![]))
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    let library = OmfLibrary::from_bytes(&bytes).map_err(|e| e.to_string())?;
+    let members = library
+        .members
+        .iter()
+        .map(|m| Member {
+            name: m.name.clone(),
+            stem: m.stem().to_string(),
+            bytes: bytes[m.range.clone()].to_vec(),
+        })
+        .collect();
+    Ok((library.page_size, library.flags & 1 != 0, members))
+}
+
+fn read_object(path: &str) -> Result<Member, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+    let module = OmfModule::read_one(&mut &bytes[..]).map_err(|e| format!("{path}: {e}"))?;
+    let name = module
+        .records
+        .iter()
+        .find_map(|r| match &r.data {
+            OmfRecordData::THeadr { name } => Some(name.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| path.to_string());
+    Ok(Member {
+        stem: module_stem(&name).to_string(),
+        name,
+        bytes,
+    })
+}
+
+fn write_library(
+    args: &Args,
+    page_size: u32,
+    case_sensitive: bool,
+    members: &[Member],
+) -> Result<(), String> {
+    let mut builder = LibraryBuilder::new();
+    builder
+        .set_page_size(page_size)
+        .map_err(|e| e.to_string())?;
+    builder.set_case_sensitive(case_sensitive);
+    for m in members {
+        builder.add_member(m.bytes.clone());
+    }
+    let mut out = vec![];
+    builder.write(&mut out).map_err(|e| e.to_string())?;
+    fs::write(&args.library, out).map_err(|e| e.to_string())
+}
+
+fn matches(member: &Member, name: &str) -> bool {
+    member.stem.eq_ignore_ascii_case(module_stem(name)) || member.name.eq_ignore_ascii_case(name)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let (page_size, case_sensitive, mut members) = read_library(args)?;
+    match args.verb {
+        Verb::T => {
+            for m in &members {
+                if args.verbose {
+                    println!("{:<20} {:>8} {}", m.stem, m.bytes.len(), m.name);
+                } else {
+                    println!("{}", m.stem);
+                }
+            }
+            Ok(())
+        }
+        Verb::X => {
+            for name in &args.files {
+                if !members.iter().any(|m| matches(m, name)) {
+                    return Err(format!("no member named {name}"));
+                }
+            }
+            for m in &members {
+                if !args.files.is_empty() && !args.files.iter().any(|n| matches(m, n)) {
+                    continue;
+                }
+                let path = format!("{}.obj", m.stem);
+                if args.verbose {
+                    println!("x - {path}");
+                }
+                fs::write(&path, &m.bytes).map_err(|e| format!("{path}: {e}"))?;
+            }
+            Ok(())
+        }
+        Verb::R | Verb::Q => {
+            for path in &args.files {
+                let object = read_object(path)?;
+                let existing = members
+                    .iter()
+                    .position(|m| m.stem.eq_ignore_ascii_case(&object.stem));
+                match existing {
+                    Some(i) if args.verb == Verb::R => {
+                        if args.verbose {
+                            println!("r - {path}");
+                        }
+                        members[i] = object;
+                    }
+                    _ => {
+                        if args.verbose {
+                            println!("a - {path}");
+                        }
+                        members.push(object);
+                    }
+                }
+            }
+            write_library(args, page_size, case_sensitive, &members)
+        }
+        Verb::D => {
+            for name in &args.files {
+                let i = members
+                    .iter()
+                    .position(|m| matches(m, name))
+                    .ok_or_else(|| format!("no member named {name}"))?;
+                if args.verbose {
+                    println!("d - {}", members[i].stem);
+                }
+                members.remove(i);
+            }
+            write_library(args, page_size, case_sensitive, &members)
+        }
+    }
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {e}", args.library.display());
+            ExitCode::FAILURE
+        }
+    }
+}
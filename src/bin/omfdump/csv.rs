@@ -0,0 +1,58 @@
+use omflib::OmfModule;
+
+use crate::{segments::segment_rows, symbols::symbol_row};
+
+// Quotes a field if it contains anything CSV treats specially.
+fn field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn print_row(fields: &[&str]) {
+    let fields: Vec<_> = fields.iter().map(|f| field(f)).collect();
+    println!("{}", fields.join(","));
+}
+
+pub fn print_symbols(modules: &[(String, OmfModule)], demangle: bool) {
+    print_row(&[
+        "module", "kind", "scope", "segment", "offset", "name", "detail",
+    ]);
+    for (label, module) in modules {
+        let info = module.info();
+        for s in info.symbols.iter() {
+            let row = symbol_row(info, s, demangle);
+            let offset = row.offset.map(|o| format!("{o:04X}")).unwrap_or_default();
+            print_row(&[
+                label,
+                row.kind,
+                row.scope,
+                &row.segment,
+                &offset,
+                &row.name,
+                &row.detail,
+            ]);
+        }
+    }
+}
+
+pub fn print_segments(modules: &[(String, OmfModule)]) {
+    print_row(&[
+        "module", "segment", "class", "group", "align", "combine", "length",
+    ]);
+    for (label, module) in modules {
+        for row in segment_rows(module) {
+            print_row(&[
+                label,
+                &row.name,
+                &row.class,
+                &row.group,
+                row.align,
+                &row.combine,
+                &row.length.to_string(),
+            ]);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use omflib::{DebugSymbol, DebugSymbolKind, DwarfSection, OmfModule};
+
+use crate::symbols::segment_name;
+
+fn kind_label(kind: &DebugSymbolKind) -> String {
+    match kind {
+        DebugSymbolKind::Public => "public".to_string(),
+        DebugSymbolKind::Data { global: true } => "global data".to_string(),
+        DebugSymbolKind::Data { global: false } => "local data".to_string(),
+        DebugSymbolKind::Procedure { global, length } => format!(
+            "{} proc, {length:X}h bytes",
+            if *global { "global" } else { "local" }
+        ),
+        DebugSymbolKind::Label => "label".to_string(),
+        DebugSymbolKind::Block { length } => format!("block, {length:X}h bytes"),
+        DebugSymbolKind::End => "end".to_string(),
+        DebugSymbolKind::Local { frame_offset } => format!("frame {frame_offset:+}"),
+        DebugSymbolKind::Register { register } => format!("register {register}"),
+        DebugSymbolKind::Constant { value } => format!("constant {value}"),
+        DebugSymbolKind::Typedef => "typedef".to_string(),
+        DebugSymbolKind::ObjectName => "object".to_string(),
+        DebugSymbolKind::Other(record_type) => format!("record {record_type:04X}h"),
+    }
+}
+
+fn location(module: &OmfModule, symbol: &DebugSymbol) -> String {
+    match symbol.segment {
+        Some(segment) => format!(
+            "{}:{:04X}",
+            segment_name(module.info(), segment),
+            symbol.offset
+        ),
+        None => String::new(),
+    }
+}
+
+// The debug format, then each debug symbol with where it is and its type,
+// then any DWARF sections.
+pub fn print_debug_info(module: &OmfModule) -> Result<(), String> {
+    let dwarf = module.dwarf_sections().map_err(|e| e.to_string())?;
+    let Some(debug) = module.debug_info().map_err(|e| e.to_string())? else {
+        if dwarf.is_empty() {
+            println!("No debug information");
+        }
+        print_dwarf_sections(&dwarf);
+        return Ok(());
+    };
+    println!(
+        "{}: {} symbols, {} types, {} lines",
+        debug.format,
+        debug.symbols.len(),
+        debug.types.len(),
+        debug.lines.len()
+    );
+    let mut depth = 0usize;
+    for symbol in &debug.symbols {
+        if symbol.kind == DebugSymbolKind::End {
+            depth = depth.saturating_sub(1);
+        }
+        let label = format!(
+            "{:indent$}{}",
+            "",
+            kind_label(&symbol.kind),
+            indent = depth * 2
+        );
+        let type_index = if symbol.type_index != 0 {
+            format!("type {:04X}h", symbol.type_index)
+        } else {
+            String::new()
+        };
+        let line = format!(
+            "{label:<28} {:<16} {:<10} {}",
+            location(module, symbol),
+            type_index,
+            symbol.name
+        );
+        println!("{}", line.trim_end());
+        if matches!(
+            symbol.kind,
+            DebugSymbolKind::Procedure { .. } | DebugSymbolKind::Block { .. }
+        ) {
+            depth += 1;
+        }
+    }
+    print_dwarf_sections(&dwarf);
+    Ok(())
+}
+
+// DWARF is left to DWARF tools, so only its sections are listed.
+fn print_dwarf_sections(sections: &[DwarfSection]) {
+    if sections.is_empty() {
+        return;
+    }
+    println!("DWARF: {} sections", sections.len());
+    for section in sections {
+        println!("{:<28} {:X}h bytes", section.name, section.data.len());
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::Path;
+
+use omflib::{OmfModule, OmfRecordData};
+
+// Comment classes: Borland dependency file and default library search.
+const COMENT_DEPENDENCY: u8 = 0xE9;
+const COMENT_DEFAULT_LIBRARY: u8 = 0x9F;
+
+pub struct Dependency {
+    pub name: String,
+    // DOS date and time of the file when the module was built.
+    pub date: u16,
+    pub time: u16,
+}
+
+#[derive(Default)]
+pub struct Dependencies {
+    pub module: Option<String>,
+    pub sources: Vec<Dependency>,
+    pub libraries: Vec<String>,
+}
+
+// A dependency comment is a DOS time and date followed by a counted file
+// name. An empty one marks the end of the list.
+fn parse_dependency(bytes: &[u8]) -> Option<Dependency> {
+    let time = u16::from_le_bytes([*bytes.first()?, *bytes.get(1)?]);
+    let date = u16::from_le_bytes([*bytes.get(2)?, *bytes.get(3)?]);
+    let len = *bytes.get(4)? as usize;
+    let name = bytes.get(5..5 + len)?;
+    Some(Dependency {
+        name: String::from_utf8_lossy(name).into_owned(),
+        date,
+        time,
+    })
+}
+
+pub fn collect_dependencies(module: &OmfModule) -> Dependencies {
+    let mut deps = Dependencies::default();
+    for record in &module.records {
+        match &record.data {
+            OmfRecordData::THeadr { name } if deps.module.is_none() => {
+                deps.module = Some(name.clone());
+            }
+            OmfRecordData::Coment {
+                comment_class: COMENT_DEPENDENCY,
+                comment_bytes,
+                ..
+            } => deps.sources.extend(parse_dependency(comment_bytes)),
+            OmfRecordData::Coment {
+                comment_class: COMENT_DEFAULT_LIBRARY,
+                comment_bytes,
+                ..
+            } => deps
+                .libraries
+                .push(String::from_utf8_lossy(comment_bytes).into_owned()),
+            _ => (),
+        }
+    }
+    deps
+}
+
+fn format_timestamp(date: u16, time: u16) -> String {
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        1980 + (date >> 9),
+        (date >> 5) & 0xF,
+        date & 0x1F,
+        time >> 11,
+        (time >> 5) & 0x3F,
+        (time & 0x1F) * 2
+    )
+}
+
+fn make_escape(path: &str) -> String {
+    path.replace('\\', "/").replace(' ', "\\ ")
+}
+
+pub fn print_dependencies(deps: &Dependencies) {
+    println!("Module: {}", deps.module.as_deref().unwrap_or(""));
+    if !deps.sources.is_empty() {
+        println!("Source files:");
+        for d in &deps.sources {
+            println!("  {:<40} {}", d.name, format_timestamp(d.date, d.time));
+        }
+    }
+    if !deps.libraries.is_empty() {
+        println!("Default libraries:");
+        for l in &deps.libraries {
+            println!("  {l}");
+        }
+    }
+}
+
+// Writes a make rule with the object file as the target. If the module has
+// no dependency comments, the THEADR name is the only prerequisite.
+pub fn print_make_rule(object: &Path, deps: &Dependencies) {
+    let mut prerequisites: Vec<&str> = deps.sources.iter().map(|d| d.name.as_str()).collect();
+    if prerequisites.is_empty() {
+        prerequisites.extend(deps.module.as_deref());
+    }
+    print!("{}:", make_escape(&object.to_string_lossy()));
+    for p in prerequisites {
+        print!(" \\\n  {}", make_escape(p));
+    }
+    println!();
+}
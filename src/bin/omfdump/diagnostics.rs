@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+#[cfg(feature = "serde")]
+use omflib::Rule;
+use omflib::{Diagnostic, Severity};
+#[cfg(feature = "serde")]
+use serde_json::{json, Value};
+
+// What --verify found in each file, with the library member each problem
+// is in.
+pub type Findings = [(PathBuf, Vec<(Option<String>, Diagnostic)>)];
+
+// One line per problem, then a count for each file.
+pub fn print_text(findings: &Findings) {
+    for (file, diagnostics) in findings {
+        for (_, d) in diagnostics {
+            println!("{}: {d}", file.display());
+        }
+        let count = |severity| {
+            diagnostics
+                .iter()
+                .filter(|(_, d)| d.severity == severity)
+                .count()
+        };
+        let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+        if errors == 0 && warnings == 0 {
+            println!("{}: OK", file.display());
+        } else {
+            println!("{}: {errors} errors, {warnings} warnings", file.display());
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    }
+}
+
+#[cfg(feature = "serde")]
+fn print(value: &Value) {
+    let stdout = std::io::stdout();
+    serde_json::to_writer_pretty(stdout.lock(), value).expect("Could not write JSON");
+    println!();
+}
+
+// Version 1 of omfdump's own format: a list of files, each with its
+// diagnostics in file order.
+#[cfg(feature = "serde")]
+pub fn print_json(findings: &Findings) {
+    let files: Vec<Value> = findings
+        .iter()
+        .map(|(file, diagnostics)| {
+            let diagnostics: Vec<Value> = diagnostics
+                .iter()
+                .map(|(member, d)| {
+                    json!({
+                        "member": member,
+                        "offset": d.offset,
+                        "record": d.record,
+                        "rule": d.rule.id(),
+                        "severity": level(d.severity),
+                        "message": d.message,
+                    })
+                })
+                .collect();
+            json!({
+                "file": file.display().to_string(),
+                "diagnostics": diagnostics,
+            })
+        })
+        .collect();
+    print(&json!({ "version": 1, "files": files }));
+}
+
+// A SARIF 2.1.0 log with one run, for code review and CI tools. Offsets
+// are byte offsets into the file; the record index and library member go
+// in each result's properties.
+#[cfg(feature = "serde")]
+pub fn print_sarif(findings: &Findings) {
+    let rules: Vec<Value> = Rule::ALL
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.description() },
+            })
+        })
+        .collect();
+    let mut results = vec![];
+    for (file, diagnostics) in findings {
+        for (member, d) in diagnostics {
+            let mut properties = json!({ "record": d.record });
+            if let Some(member) = member {
+                properties["member"] = json!(member);
+            }
+            results.push(json!({
+                "ruleId": d.rule.id(),
+                "ruleIndex": Rule::ALL.iter().position(|r| *r == d.rule),
+                "level": level(d.severity),
+                "message": { "text": d.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file.display().to_string() },
+                        "region": { "byteOffset": d.offset },
+                    },
+                }],
+                "properties": properties,
+            }));
+        }
+    }
+    print(&json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "omfdump",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    }));
+}
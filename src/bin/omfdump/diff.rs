@@ -0,0 +1,204 @@
+use omflib::{NameIndex, OmfModule, OmfRecordData, SegmentImage, SegmentIndex};
+
+use crate::symbols::{describe_symbol, segment_name};
+
+// Most differing bytes to list for one changed range.
+const MAX_BYTES_SHOWN: usize = 16;
+
+// Comment classes that only record when and how a module was built: the
+// translator string and Borland's dependency timestamps.
+fn is_volatile_comment(class: u8) -> bool {
+    matches!(class, 0x00 | 0xE9)
+}
+
+struct SegmentSummary {
+    name: String,
+    description: String,
+    image: Option<SegmentImage>,
+}
+
+fn segment_summaries(module: &OmfModule) -> Result<Vec<SegmentSummary>, String> {
+    let mut images = module.segment_images().map_err(|e| e.to_string())?;
+    let info = module.info();
+    let name = |index: NameIndex| index.resolve(info).ok().unwrap_or_default();
+    Ok(info
+        .segments
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let segment_index = SegmentIndex(i as u16 + 1);
+            let attrs = &s.segment_attributes;
+            SegmentSummary {
+                name: segment_name(info, segment_index),
+                description: format!(
+                    "class {}, {} {}, {}, {:X}h bytes",
+                    name(s.class_name_index),
+                    attrs.alignment.short_name(),
+                    attrs.combination,
+                    if attrs.bd32bit { "use32" } else { "use16" },
+                    s.length()
+                ),
+                image: images
+                    .iter()
+                    .position(|image| image.segment_index == segment_index)
+                    .map(|i| images.swap_remove(i)),
+            }
+        })
+        .collect())
+}
+
+fn comments(module: &OmfModule, ignore_volatile: bool) -> Vec<String> {
+    module
+        .records
+        .iter()
+        .filter_map(|r| match &r.data {
+            OmfRecordData::Coment {
+                comment_class,
+                comment_bytes,
+                ..
+            } if !(ignore_volatile && is_volatile_comment(*comment_class)) => Some(format!(
+                "COMENT {comment_class:02X}h {}",
+                String::from_utf8_lossy(comment_bytes).escape_debug()
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn module_name(module: &OmfModule) -> Option<&str> {
+    module.records.iter().find_map(|r| match &r.data {
+        OmfRecordData::THeadr { name } => Some(name.as_str()),
+        _ => None,
+    })
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    let shown: Vec<_> = bytes
+        .iter()
+        .take(MAX_BYTES_SHOWN)
+        .map(|b| format!("{b:02X}"))
+        .collect();
+    let more = if bytes.len() > MAX_BYTES_SHOWN {
+        " ..."
+    } else {
+        ""
+    };
+    format!("{}{more}", shown.join(" "))
+}
+
+// Lists lines present only in `a` (with "-") or only in `b` (with "+"),
+// counting repeated lines.
+fn diff_lines(heading: &str, a: &[String], b: &[String], out: &mut Vec<String>) {
+    let mut removed = a.to_vec();
+    let mut added = vec![];
+    for line in b {
+        match removed.iter().position(|r| r == line) {
+            Some(i) => {
+                removed.remove(i);
+            }
+            None => added.push(line),
+        }
+    }
+    if removed.is_empty() && added.is_empty() {
+        return;
+    }
+    out.push(format!("{heading}:"));
+    out.extend(removed.iter().map(|line| format!("  - {line}")));
+    out.extend(added.iter().map(|line| format!("  + {line}")));
+}
+
+// Reports the byte ranges that differ between two images of a segment.
+fn diff_data(name: &str, a: &SegmentImage, b: &SegmentImage, out: &mut Vec<String>) {
+    let len = a.data.len().max(b.data.len());
+    let byte = |image: &SegmentImage, i: usize| {
+        image
+            .is_initialized(i)
+            .then(|| image.data.get(i).copied())
+            .flatten()
+    };
+    let mut i = 0;
+    while i < len {
+        if byte(a, i) == byte(b, i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < len && byte(a, i) != byte(b, i) {
+            i += 1;
+        }
+        let slice = |image: &'_ SegmentImage| -> String {
+            let end = i.min(image.data.len());
+            hex_bytes(image.data.get(start..end).unwrap_or_default())
+        };
+        out.push(format!("  {name}:{start:04X}-{:04X}", i - 1));
+        out.push(format!("    - {}", slice(a)));
+        out.push(format!("    + {}", slice(b)));
+    }
+}
+
+// Compares two modules by what they define rather than how the records are
+// laid out. Returns the differences, one line each.
+pub fn diff_modules(
+    a: &OmfModule,
+    b: &OmfModule,
+    ignore_volatile: bool,
+) -> Result<Vec<String>, String> {
+    let mut out = vec![];
+
+    if !ignore_volatile && module_name(a) != module_name(b) {
+        out.push("Module name:".to_string());
+        out.push(format!("  - {}", module_name(a).unwrap_or_default()));
+        out.push(format!("  + {}", module_name(b).unwrap_or_default()));
+    }
+
+    diff_lines(
+        "Comments",
+        &comments(a, ignore_volatile),
+        &comments(b, ignore_volatile),
+        &mut out,
+    );
+
+    let segments_a = segment_summaries(a)?;
+    let segments_b = segment_summaries(b)?;
+    let describe = |segments: &[SegmentSummary]| -> Vec<String> {
+        segments
+            .iter()
+            .map(|s| format!("{:<16} {}", s.name, s.description))
+            .collect()
+    };
+    diff_lines(
+        "Segments",
+        &describe(&segments_a),
+        &describe(&segments_b),
+        &mut out,
+    );
+
+    let symbols = |module: &OmfModule| -> Vec<String> {
+        let info = module.info();
+        info.symbols
+            .iter()
+            .map(|s| describe_symbol(info, s, false))
+            .collect()
+    };
+    diff_lines("Symbols", &symbols(a), &symbols(b), &mut out);
+
+    let mut data = vec![];
+    let empty = SegmentImage::new(SegmentIndex::NONE, 0);
+    for sa in &segments_a {
+        let Some(sb) = segments_b.iter().find(|sb| sb.name == sa.name) else {
+            continue;
+        };
+        diff_data(
+            &sa.name,
+            sa.image.as_ref().unwrap_or(&empty),
+            sb.image.as_ref().unwrap_or(&empty),
+            &mut data,
+        );
+    }
+    if !data.is_empty() {
+        out.push("Data:".to_string());
+        out.extend(data);
+    }
+
+    Ok(out)
+}
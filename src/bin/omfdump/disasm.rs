@@ -0,0 +1,74 @@
+use iced_x86::{Decoder, DecoderOptions, Formatter, Instruction, MasmFormatter};
+use omflib::{OmfError, OmfModule, SegmentIndex, SymbolBinding, SymbolKind};
+
+use crate::{relocs::describe, symbols::segment_name};
+
+// Disassembles every segment whose class ends in CODE, labelling publics and
+// noting the fixups that apply to each instruction.
+pub fn print_disassembly(module: &OmfModule) -> Result<(), OmfError> {
+    let images = module.segment_images()?;
+    let relocations = module.relocations()?;
+    let info = module.info();
+    let mut formatter = MasmFormatter::new();
+    for (i, segment) in info.segments.iter().enumerate() {
+        let segment_index = SegmentIndex(i as u16 + 1);
+        let class = info
+            .class_of(segment_index)
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        if !class.ends_with("CODE") {
+            continue;
+        }
+        let bitness = if segment.segment_attributes.bd32bit {
+            32
+        } else {
+            16
+        };
+        println!(
+            "{} segment ({bitness}-bit)",
+            segment_name(info, segment_index)
+        );
+        let data = &images[i].data;
+        let mut decoder = Decoder::with_ip(bitness, data, 0, DecoderOptions::NONE);
+        let mut instruction = Instruction::default();
+        let mut text = String::new();
+        while decoder.can_decode() {
+            decoder.decode_out(&mut instruction);
+            let start = instruction.ip() as u32;
+            let end = start + instruction.len() as u32;
+            for s in info.symbols.iter() {
+                let here = matches!(s.binding, SymbolBinding::Segment { segment_index: si, .. } if si == segment_index);
+                if here && s.kind == SymbolKind::Public && s.offset == start {
+                    println!("{}:", s.name);
+                }
+            }
+            text.clear();
+            formatter.format(&instruction, &mut text);
+            let bytes: String = data[start as usize..end as usize]
+                .iter()
+                .map(|b| format!("{b:02X}"))
+                .collect();
+            let fixups: Vec<String> = relocations
+                .iter()
+                .filter(|r| r.segment_index == segment_index && (start..end).contains(&r.offset))
+                .map(|r| {
+                    let mut target = describe(info, &r.target);
+                    if r.target_displacement != 0 {
+                        target.push_str(&format!("+{:X}h", r.target_displacement));
+                    }
+                    format!("{} {target}", r.kind)
+                })
+                .collect();
+            if fixups.is_empty() {
+                println!("    {start:04X}  {bytes:<20} {text}");
+            } else {
+                println!(
+                    "    {start:04X}  {bytes:<20} {text:<32} ; {}",
+                    fixups.join(", ")
+                );
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
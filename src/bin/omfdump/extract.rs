@@ -0,0 +1,17 @@
+use std::{fs, path::Path};
+
+use omflib::OmfModule;
+
+use crate::symbols::segment_name;
+
+// Writes the reconstructed contents of the named segment, including any
+// uninitialized space as zeros.
+pub fn extract_segment(module: &OmfModule, name: &str, output: &Path) -> Result<(), String> {
+    let images = module.segment_images().map_err(|e| e.to_string())?;
+    let info = module.info();
+    let image = images
+        .iter()
+        .find(|image| segment_name(info, image.segment_index) == name)
+        .ok_or_else(|| format!("no segment named {name}"))?;
+    fs::write(output, &image.data).map_err(|e| format!("{}: {e}", output.display()))
+}
@@ -0,0 +1,161 @@
+use omflib::{
+    glob_match, ExternalIndex, FixupFrame, FixupSubrecord, FixupTarget, FixupThread, GroupIndex,
+    MAttrStart, NameIndex, OmfInfo, OmfRecord, OmfRecordData, SegmentIndex,
+};
+
+use crate::terse::Style;
+
+// The names a record defines or refers to: publics, externals, communals
+// and COMDATs, and the segments and groups it names or is based on.
+fn record_names(info: &OmfInfo, data: &OmfRecordData) -> Vec<String> {
+    let name = |i: NameIndex| info.name_from_index(i).ok();
+    let segment = |i: SegmentIndex| {
+        info.get_segment(i)
+            .ok()
+            .and_then(|s| name(s.segment_name_index))
+    };
+    let group = |i: GroupIndex| {
+        info.get_group(i)
+            .ok()
+            .and_then(|g| name(g.group_name_index))
+    };
+    let external = |i: ExternalIndex| info.get_external(i).ok();
+    let frame = |f: &FixupFrame| match *f {
+        FixupFrame::Segment(i) => segment(i),
+        FixupFrame::Group(i) => group(i),
+        FixupFrame::External(i) => external(i),
+        _ => None,
+    };
+    let target = |t: &FixupTarget| match *t {
+        FixupTarget::Segment(i) => segment(i),
+        FixupTarget::Group(i) => group(i),
+        FixupTarget::External(i) => external(i),
+        FixupTarget::FrameNumber(_) => None,
+    };
+    let names: Vec<Option<_>> = match data {
+        OmfRecordData::ExtDef { names } | OmfRecordData::LExtDef { names } => {
+            return names.iter().map(|n| n.name.clone()).collect();
+        }
+        OmfRecordData::PubDef {
+            base_group_index,
+            base_segment_index,
+            names,
+            ..
+        }
+        | OmfRecordData::LPubDef {
+            base_group_index,
+            base_segment_index,
+            names,
+            ..
+        } => names
+            .iter()
+            .map(|n| Some(n.name.as_str().into()))
+            .chain([group(*base_group_index), segment(*base_segment_index)])
+            .collect(),
+        OmfRecordData::ModEnd {
+            start: MAttrStart::Start(start),
+            ..
+        } => vec![target(&start.target), frame(&start.frame)],
+        OmfRecordData::LinNum {
+            base_group_index,
+            base_segment_index,
+            ..
+        } => vec![group(*base_group_index), segment(*base_segment_index)],
+        OmfRecordData::LNames { names } => names.iter().cloned().map(Some).collect(),
+        OmfRecordData::SegDef {
+            segment_name_index, ..
+        } => vec![name(*segment_name_index)],
+        OmfRecordData::GrpDef {
+            group_name_index,
+            segment_definitions,
+        } => segment_definitions
+            .iter()
+            .map(|c| segment(c.segment_definition))
+            .chain([name(*group_name_index)])
+            .collect(),
+        OmfRecordData::FixUpp { subrecords } => subrecords
+            .iter()
+            .flat_map(|s| match s {
+                FixupSubrecord::Fixup(fixup) => {
+                    [target(&fixup.fix_data.target), frame(&fixup.fix_data.frame)]
+                }
+                FixupSubrecord::Thread(FixupThread::Target { target: t, .. }) => [target(t), None],
+                FixupSubrecord::Thread(FixupThread::Frame { frame: f, .. }) => [frame(f), None],
+            })
+            .collect(),
+        OmfRecordData::LEData { segment_index, .. }
+        | OmfRecordData::LIData { segment_index, .. } => vec![segment(*segment_index)],
+        OmfRecordData::ComDef { definitions } => {
+            return definitions.iter().map(|d| d.name.clone()).collect();
+        }
+        OmfRecordData::CExtDef { names } => {
+            names.iter().map(|n| name(n.logical_name_index)).collect()
+        }
+        OmfRecordData::ComDat {
+            base_group_index,
+            base_segment_index,
+            public_name_index,
+            ..
+        } => vec![
+            name(*public_name_index),
+            group(*base_group_index),
+            segment(*base_segment_index),
+        ],
+        OmfRecordData::LinSym {
+            public_name_index, ..
+        } => vec![name(*public_name_index)],
+        OmfRecordData::Alias { aliases } => {
+            return aliases
+                .iter()
+                .flat_map(|a| [a.alias_name.clone(), a.substitute_name.clone()])
+                .collect();
+        }
+        _ => vec![],
+    };
+    names.into_iter().flatten().map(|n| n.to_string()).collect()
+}
+
+// The names in the record that match `pattern`, each once. A record with
+// none is left out of the dump.
+pub fn matching_names(pattern: &str, info: &OmfInfo, record: &OmfRecord) -> Vec<String> {
+    let mut found: Vec<String> = vec![];
+    for name in record_names(info, &record.data) {
+        if glob_match(pattern, &name, false) && !found.contains(&name) {
+            found.push(name);
+        }
+    }
+    found
+}
+
+fn is_name_char(c: Option<char>) -> bool {
+    c.is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+// Highlights each place `names` appear in `text` as a whole word, so _main
+// isn't highlighted inside __main.
+pub fn highlight(text: &str, names: &[String], style: Style) -> String {
+    let mut spans: Vec<(usize, usize)> = vec![];
+    for name in names.iter().filter(|n| !n.is_empty()) {
+        for (start, _) in text.match_indices(name.as_str()) {
+            let end = start + name.len();
+            if is_name_char(text[..start].chars().next_back())
+                || is_name_char(text[end..].chars().next())
+            {
+                continue;
+            }
+            if !spans.iter().any(|&(s, e)| start < e && s < end) {
+                spans.push((start, end));
+            }
+        }
+    }
+    spans.sort();
+    let mut out = String::new();
+    let mut at = 0;
+    for (start, end) in spans {
+        out += &text[at..start];
+        out += &style.matched(&text[start..end]);
+        at = end;
+    }
+    out += &text[at..];
+    out
+}
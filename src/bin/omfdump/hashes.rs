@@ -0,0 +1,56 @@
+use omflib::{ContentHash, HashedContent, OmfModule};
+
+fn kind(hash: &ContentHash) -> &'static str {
+    match hash.content {
+        HashedContent::Segment(_) => "SEGMENT",
+        HashedContent::Comdat => "COMDAT",
+    }
+}
+
+// One line per segment and COMDAT: its hash, its hash with fixups, and its
+// size.
+pub fn print_hashes(module: &OmfModule) -> Result<(), String> {
+    for hash in module.content_hashes().map_err(|e| e.to_string())? {
+        println!(
+            "{:016x} {:016x} {:>7X}h {:<8} {}",
+            hash.hash,
+            hash.hash_with_fixups,
+            hash.length,
+            kind(&hash),
+            hash.name
+        );
+    }
+    Ok(())
+}
+
+// Groups the non-empty segments and COMDATs of all the modules whose
+// contents and fixups are the same, listing each group that has more than
+// one member.
+pub fn print_duplicates(modules: &[(String, OmfModule)]) -> Result<(), String> {
+    let mut groups: Vec<(u64, usize, Vec<String>)> = vec![];
+    for (label, module) in modules {
+        for hash in module
+            .content_hashes()
+            .map_err(|e| format!("{label}: {e}"))?
+        {
+            if hash.length == 0 {
+                continue;
+            }
+            let name = format!("{label}: {} {}", kind(&hash), hash.name);
+            match groups
+                .iter_mut()
+                .find(|(h, len, _)| *h == hash.hash_with_fixups && *len == hash.length)
+            {
+                Some((_, _, names)) => names.push(name),
+                None => groups.push((hash.hash_with_fixups, hash.length, vec![name])),
+            }
+        }
+    }
+    for (hash, length, names) in groups.iter().filter(|(_, _, names)| names.len() > 1) {
+        println!("{hash:016x} {length:X}h bytes:");
+        for name in names {
+            println!("  {name}");
+        }
+    }
+    Ok(())
+}
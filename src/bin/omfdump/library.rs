@@ -0,0 +1,53 @@
+use omflib::{OmfLibrary, SymbolKind, SymbolScope};
+
+// One line per member, followed by the global publics it defines.
+pub fn print_members(library: &OmfLibrary) {
+    println!(
+        "Library: page size {}, {} members, {} dictionary entries",
+        library.page_size,
+        library.members.len(),
+        library.dictionary.len()
+    );
+    println!();
+    println!("{:<20} {:>6} {:>8}", "Member", "Page", "Size");
+    for member in &library.members {
+        println!(
+            "{:<20} {:>6} {:>7X}h",
+            member.name,
+            member.page,
+            member.range.len()
+        );
+        for symbol in member.module.symbols().iter() {
+            if symbol.kind == SymbolKind::Public && symbol.scope == SymbolScope::Global {
+                println!("    {}", symbol.name);
+            }
+        }
+    }
+}
+
+// One line per symbol: the members defining it, then the members
+// referencing it. Symbols nothing in the library defines are marked.
+pub fn print_cross_reference(library: &OmfLibrary) {
+    let members = |indices: &[usize]| {
+        indices
+            .iter()
+            .map(|&i| library.members[i].stem())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    println!("{:<32} {:<20} Referenced by", "Symbol", "Defined in");
+    for entry in library.cross_reference() {
+        let defined_in = if entry.defined_in.is_empty() {
+            "(undefined)".to_string()
+        } else {
+            members(&entry.defined_in)
+        };
+        let line = format!(
+            "{:<32} {:<20} {}",
+            entry.name,
+            defined_in,
+            members(&entry.referenced_by)
+        );
+        println!("{}", line.trim_end());
+    }
+}
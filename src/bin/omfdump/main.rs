@@ -1,19 +1,951 @@
-use std::{fs, path::PathBuf, process::ExitCode};
+mod csv;
+mod debug;
+mod deps;
+mod diagnostics;
+mod diff;
+#[cfg(feature = "disasm")]
+mod disasm;
+mod extract;
+mod grep;
+mod hashes;
+mod library;
+mod overlays;
+mod relocs;
+mod segments;
+mod stats;
+mod strings;
+mod symbols;
+mod template;
+mod terse;
+mod tree;
+#[cfg(feature = "tui")]
+mod tui;
 
-use clap::Parser;
-use omflib::OmfReader;
+use std::{
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use omflib::{
+    CallGraph, Diagnostic, HexOffset, HexOptions, NameEncoding, OmfInfo, OmfLibrary, OmfModule,
+    OmfReader, OmfRecord, RecordType, Severity,
+};
+use template::Template;
+use terse::{ColorChoice, Style};
+
+// Accepts a record name such as LEDATA, or a type number in hex (A0, A0h or
+// 0xA0).
+fn parse_record_type(s: &str) -> Result<RecordType, String> {
+    if let Some(t) = RecordType::from_name(s) {
+        return Ok(t);
+    }
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_suffix(['h', 'H']))
+        .unwrap_or(s);
+    u8::from_str_radix(digits, 16)
+        .map(RecordType::from_u8)
+        .map_err(|_| format!("unknown record type {s}"))
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Encoding {
+    Utf8,
+    Lossy,
+    Cp437,
+    Cp850,
+}
+
+impl From<Encoding> for NameEncoding {
+    fn from(encoding: Encoding) -> NameEncoding {
+        match encoding {
+            Encoding::Utf8 => NameEncoding::Utf8,
+            Encoding::Lossy => NameEncoding::Lossy,
+            Encoding::Cp437 => NameEncoding::Cp437,
+            Encoding::Cp850 => NameEncoding::Cp850,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum HexBase {
+    Record,
+    Segment,
+    File,
+}
+
+impl From<HexBase> for HexOffset {
+    fn from(base: HexBase) -> HexOffset {
+        match base {
+            HexBase::Record => HexOffset::Record,
+            HexBase::Segment => HexOffset::Segment,
+            HexBase::File => HexOffset::File,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum VerifyFormat {
+    Text,
+    #[cfg(feature = "serde")]
+    Json,
+    #[cfg(feature = "serde")]
+    Sarif,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Csv,
+}
 
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Print the records as JSON, with raw bytes in base64
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    json: bool,
+    /// Only show these record types (e.g. LEDATA,FIXUPP)
+    #[arg(long, value_delimiter = ',', value_parser = parse_record_type)]
+    only: Vec<RecordType>,
+    /// Hide these record types (e.g. COMENT)
+    #[arg(long, value_delimiter = ',', value_parser = parse_record_type)]
+    skip: Vec<RecordType>,
+    /// Only show records that define or refer to a public, external,
+    /// segment or group matching this name, where * and ? are wildcards
+    #[arg(long, value_name = "PATTERN")]
+    grep: Option<String>,
+    /// Print one line per record
+    #[arg(long)]
+    terse: bool,
+    /// Print one line per record from a template such as
+    /// "{offset:06X} {type} {summary}". The fields are index, offset,
+    /// type, type_number, length, checksum and summary.
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["terse", "verify"])]
+    format_str: Option<String>,
+    /// Highlight record types and symbol names
+    #[arg(long, value_enum, default_value = "auto")]
+    color: ColorChoice,
+    /// Also print the raw type and length bytes of each record
+    #[arg(long)]
+    raw_header: bool,
+    /// Bytes per line in hex dumps
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    hex_width: u16,
+    /// Bytes between the wider gaps in hex dumps, or 0 for none
+    #[arg(long, default_value_t = 8)]
+    hex_group: u16,
+    /// What hex dump addresses count from: the start of the bytes, the
+    /// segment offset they load at, or the start of the file
+    #[arg(long, value_enum, default_value = "record")]
+    hex_offset: HexBase,
+    /// Show at most this many bytes of each hex dump
+    #[arg(long)]
+    hex_max_bytes: Option<usize>,
+    /// Check checksums, record lengths and indices instead of dumping
+    #[arg(long)]
+    verify: bool,
+    /// How to report what --verify finds
+    #[arg(long, value_enum, default_value = "text", requires = "verify")]
+    verify_format: VerifyFormat,
+    /// Show records that can't be decoded as unknown and keep going
+    #[arg(long)]
+    lenient: bool,
+    /// Browse the records interactively
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with_all = ["terse", "verify"])]
+    tui: bool,
+    /// How to read names that aren't valid UTF-8
+    #[arg(long, value_enum, default_value = "utf8")]
+    encoding: Encoding,
+    /// Dump this member of a .LIB instead of listing the members
+    #[arg(long)]
+    member: Option<String>,
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Count records by type and comment class, and find the largest ones
+    Stats {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// List publics, externals, communals and COMDATs, one per line
+    Symbols {
+        /// Only list global publics defined in more than one module
+        #[arg(long, conflicts_with = "format")]
+        duplicates: bool,
+        /// Show C++ names in their readable form
+        #[arg(short = 'C', long)]
+        demangle: bool,
+        #[arg(long, value_enum, default_value = "text")]
+        format: Format,
+        /// Print one line per symbol from a template such as
+        /// "{name} {segment} {offset:04X}". The fields are module, kind,
+        /// scope, segment, offset, name and detail.
+        #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["format", "duplicates"])]
+        format_str: Option<String>,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Summarize segments and the total size of each class
+    Segments {
+        #[arg(long, value_enum, default_value = "text")]
+        format: Format,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Show groups and their member segments as a tree
+    Tree {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// List every fixup with its location, kind and target
+    Relocs {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Write a segment's reconstructed bytes to a file
+    Extract {
+        #[arg(short, long)]
+        segment: String,
+        #[arg(short, long)]
+        output: PathBuf,
+        file: PathBuf,
+    },
+    /// List printable strings in segment data and comments
+    Strings {
+        /// Shortest run of characters to report
+        #[arg(short = 'n', long, default_value_t = 4)]
+        min_length: usize,
+        /// Treat bytes 80h-FEh as code page 437 text
+        #[arg(long)]
+        cp437: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// List source files and default libraries the module depends on
+    Deps {
+        /// Write a make rule instead
+        #[arg(long)]
+        make: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Show the overlay tree, with each overlay's segments and size
+    Overlays {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Hash each segment's and COMDAT's contents, to find identical ones
+    Hashes {
+        /// List the segments and COMDATs that are the same in all the
+        /// modules given instead
+        #[arg(long)]
+        duplicates: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Guess which compiler or assembler produced each module
+    Identify {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Decode debug information such as CodeView symbols, and list DWARF sections
+    Debug {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Show which functions call which, from the fixups in code segments
+    Calls {
+        /// Write the graph in Graphviz DOT format
+        #[arg(long)]
+        dot: bool,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// List publics that no fixup in the given modules and libraries refers to
+    Unused {
+        /// Count a public as used, such as an entry point not given in
+        /// MODEND. May be given several times.
+        #[arg(long)]
+        root: Vec<String>,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// Show which members of a library define and reference each symbol
+    Xref { library: PathBuf },
+    /// Compare symbols, segments and data of two modules
+    Diff {
+        /// Ignore the module name and translator and dependency comments
+        #[arg(long)]
+        ignore_volatile: bool,
+        old: PathBuf,
+        new: PathBuf,
+    },
+    /// Disassemble code segments, with publics and fixups annotated
+    #[cfg(feature = "disasm")]
+    Disasm {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+impl Args {
+    fn shows(&self, record_type: RecordType) -> bool {
+        let t = record_type.base();
+        (self.only.is_empty() || self.only.iter().any(|o| o.base() == t))
+            && !self.skip.iter().any(|s| s.base() == t)
+    }
+
+    fn hex_options(&self) -> HexOptions {
+        HexOptions {
+            bytes_per_line: self.hex_width as usize,
+            group: self.hex_group as usize,
+            offset: self.hex_offset.into(),
+            max_bytes: self.hex_max_bytes.unwrap_or(usize::MAX),
+        }
+    }
+
+    // The names to highlight in a record, or None if the record isn't shown.
+    fn selects(&self, info: &OmfInfo, record: &OmfRecord) -> Option<Vec<String>> {
+        if !self.shows(record.record_type) {
+            return None;
+        }
+        match &self.grep {
+            Some(pattern) => {
+                let names = grep::matching_names(pattern, info, record);
+                (!names.is_empty()).then_some(names)
+            }
+            None => Some(vec![]),
+        }
+    }
+}
+
+fn read_module(file: &PathBuf) -> Option<OmfModule> {
+    let mut f = fs::File::open(file).expect("Could not open input file");
+    match OmfModule::read(&mut f) {
+        Ok(m) => Some(m),
+        Err(e) => {
+            eprintln!("{}: {e}", file.display());
+            None
+        }
+    }
+}
+
+// Reads each file as a module, or for a library, each of its members. The
+// label is the file name, with the member name in parentheses.
+fn read_modules(files: &[PathBuf]) -> Option<Vec<(String, OmfModule)>> {
+    let mut modules = vec![];
+    for file in files {
+        let bytes = fs::read(file).expect("Could not open input file");
+        let result = omflib::read_modules(&bytes).map(|modules| {
+            modules
+                .into_iter()
+                .map(|(name, m)| match name {
+                    Some(name) => (format!("{}({name})", file.display()), m),
+                    None => (file.display().to_string(), m),
+                })
+                .collect::<Vec<_>>()
+        });
+        match result {
+            Ok(m) => modules.extend(m),
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                return None;
+            }
+        }
+    }
+    Some(modules)
+}
+
+// Runs `f` on each module, printing the module's name first when there is
+// more than one.
+fn for_each_module(
+    files: &[PathBuf],
+    mut f: impl FnMut(&str, &OmfModule) -> Result<(), String>,
+) -> ExitCode {
+    let Some(modules) = read_modules(files) else {
+        return ExitCode::FAILURE;
+    };
+    let mut status = ExitCode::SUCCESS;
+    for (i, (label, module)) in modules.iter().enumerate() {
+        if modules.len() > 1 {
+            if i > 0 {
+                println!();
+            }
+            println!("{label}:");
+        }
+        if let Err(e) = f(label, module) {
+            eprintln!("{label}: {e}");
+            status = ExitCode::FAILURE;
+        }
+    }
+    status
+}
+
+// Where each module is in a file, with its name if it is a library member.
+type Members = Vec<(Option<String>, Range<usize>)>;
+
+// The whole file, or each member of a library, or just the one --member
+// names.
+fn select_members(args: &Args, bytes: &[u8]) -> Result<Members, String> {
+    if !OmfLibrary::is_library(bytes) {
+        if args.member.is_some() {
+            return Err("not a library".to_string());
+        }
+        return Ok(vec![(None, 0..bytes.len())]);
+    }
+    let library = OmfLibrary::from_bytes(bytes).map_err(|e| e.to_string())?;
+    Ok(match &args.member {
+        Some(name) => {
+            let member = library
+                .member(name)
+                .ok_or_else(|| format!("no member named {name}"))?;
+            vec![(Some(member.name.clone()), member.range.clone())]
+        }
+        None => library
+            .members
+            .into_iter()
+            .map(|m| (Some(m.name), m.range))
+            .collect(),
+    })
+}
+
+// The problems found in the file or its members, with offsets relative to
+// the start of the file.
+fn diagnose(args: &Args, file: &Path) -> Result<Vec<(Option<String>, Diagnostic)>, String> {
+    let bytes = fs::read(file).expect("Could not open input file");
+    let mut diagnostics = vec![];
+    for (name, range) in select_members(args, &bytes)? {
+        for mut d in omflib::verify(&bytes[range.clone()]) {
+            d.offset += range.start;
+            diagnostics.push((name.clone(), d));
+        }
+    }
+    Ok(diagnostics)
+}
+
+// Checks every file and prints what was found. Only errors make the check
+// fail.
+fn verify(args: &Args) -> ExitCode {
+    let mut status = ExitCode::SUCCESS;
+    let mut findings = vec![];
+    for file in &args.files {
+        match diagnose(args, file) {
+            Ok(diagnostics) => {
+                if diagnostics
+                    .iter()
+                    .any(|(_, d)| d.severity == Severity::Error)
+                {
+                    status = ExitCode::FAILURE;
+                }
+                findings.push((file.clone(), diagnostics));
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    match args.verify_format {
+        VerifyFormat::Text => diagnostics::print_text(&findings),
+        #[cfg(feature = "serde")]
+        VerifyFormat::Json => diagnostics::print_json(&findings),
+        #[cfg(feature = "serde")]
+        VerifyFormat::Sarif => diagnostics::print_sarif(&findings),
+    }
+    status
+}
+
+fn dump(args: &Args, file: &PathBuf) -> ExitCode {
+    let bytes = fs::read(file).expect("Could not open input file");
+    let mut data = &bytes[..];
+    let mut base = 0;
+    if OmfLibrary::is_library(&bytes) && args.member.is_none() {
+        match OmfLibrary::from_bytes(&bytes) {
+            Ok(library) => {
+                library::print_members(&library);
+                return ExitCode::SUCCESS;
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if args.member.is_some() {
+        match select_members(args, &bytes) {
+            Ok(members) => {
+                let range = members[0].1.clone();
+                data = &bytes[range.clone()];
+                base = range.start;
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let style = Style::new(args.color);
+    if args.terse {
+        let mut reader = OmfReader::new(&mut data);
+        reader.set_lenient(args.lenient);
+        reader.set_name_encoding(args.encoding.into());
+        return match OmfModule::from_reader(reader) {
+            Ok(module) => {
+                terse::print_terse(
+                    &module,
+                    base,
+                    |r| args.shows(r.record_type),
+                    args.grep.as_deref(),
+                    style,
+                );
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                ExitCode::FAILURE
+            }
+        };
+    }
+    if let Some(format) = &args.format_str {
+        let template = match Template::parse(format, terse::RECORD_FIELDS) {
+            Ok(template) => template,
+            Err(e) => {
+                eprintln!("{e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        let mut reader = OmfReader::with_offset(&mut data, base);
+        reader.set_lenient(args.lenient);
+        reader.set_name_encoding(args.encoding.into());
+        return match OmfModule::from_reader(reader) {
+            Ok(module) => {
+                let info = module.info();
+                let shows = |r: &OmfRecord| args.selects(info, r).is_some();
+                terse::print_formatted(&module, &template, shows);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                ExitCode::FAILURE
+            }
+        };
+    }
+    let mut reader = OmfReader::with_offset(&mut data, base);
+    reader.set_lenient(args.lenient);
+    reader.set_name_encoding(args.encoding.into());
+    #[cfg(feature = "serde")]
+    if args.json {
+        let mut records = vec![];
+        while let Some(record) = reader.next() {
+            match record {
+                Ok(record) if args.selects(reader.info(), &record).is_some() => {
+                    records.push(record)
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    eprintln!("{}: {e}", file.display());
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        let stdout = std::io::stdout();
+        serde_json::to_writer_pretty(stdout.lock(), &records).expect("Could not write JSON");
+        println!();
+        return ExitCode::SUCCESS;
+    }
+    // An LEDATA is held back until the record after it is read, so the
+    // bytes a FIXUPP there changes can be marked.
+    let mut held: Option<OmfRecord> = None;
+    loop {
+        let next = match reader.next().transpose() {
+            Ok(next) => next,
+            Err(e) => {
+                if let Some(data) = &held {
+                    print_record(args, style, reader.info(), data, None);
+                }
+                eprintln!("{}: {e}", file.display());
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Some(data) = held.take() {
+            let fixups = next
+                .as_ref()
+                .filter(|r| r.record_type.base() == RecordType::FixUpp);
+            print_record(args, style, reader.info(), &data, fixups);
+        }
+        match next {
+            Some(record) if record.record_type.base() == RecordType::LEData => held = Some(record),
+            Some(record) => print_record(args, style, reader.info(), &record, None),
+            None => break,
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_record(
+    args: &Args,
+    style: Style,
+    info: &OmfInfo,
+    section: &OmfRecord,
+    fixups: Option<&OmfRecord>,
+) {
+    let Some(names) = args.selects(info, section) else {
+        return;
+    };
+    let mut display = section
+        .display_with(info)
+        .with_hex_options(args.hex_options());
+    if let Some(fixups) = fixups {
+        display = display.with_fixups(fixups);
+    }
+    let text = display.to_string();
+    let (header, rest) = text.split_once('\n').unwrap_or((&text, ""));
+    let rest = grep::highlight(rest, &names, style);
+    println!("{}", style.record_type(header));
+    if let Some(error) = &section.error {
+        println!("{}", style.warning(&format!("Could not decode: {error}")));
+    }
+    if !section.checksum_valid {
+        println!(
+            "{}",
+            style.warning(&format!("Bad checksum {:02X}h", section.checksum))
+        );
+    }
+    if args.raw_header {
+        let [t, lo, hi] = section.header_bytes();
+        println!(
+            "Header bytes: {t:02X} {lo:02X} {hi:02X}, checksum {:02X}",
+            section.checksum
+        );
+    }
+    println!("{rest}");
+}
+
+// Reads the file or its members for browsing.
+#[cfg(feature = "tui")]
+fn browse(args: &Args, file: &Path) -> ExitCode {
+    let bytes = fs::read(file).expect("Could not open input file");
+    let members = match select_members(args, &bytes) {
+        Ok(members) => members,
+        Err(e) => {
+            eprintln!("{}: {e}", file.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut modules = vec![];
+    for (name, range) in members {
+        let mut data = &bytes[range.clone()];
+        let mut reader = OmfReader::with_offset(&mut data, range.start);
+        reader.set_lenient(args.lenient);
+        reader.set_name_encoding(args.encoding.into());
+        match OmfModule::from_reader(reader) {
+            Ok(module) => modules.push((name, module)),
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let shows = |r: &OmfRecord| args.shows(r.record_type);
+    match tui::browse(&bytes, &modules, shows, args.hex_options()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
 pub fn main() -> ExitCode {
     let args = Args::parse();
-    let mut f = fs::File::open(args.file).expect("Could not open input file");
-    let reader = OmfReader::new(&mut f);
-    for section in reader {
-        println!("{}", section);
+    match &args.command {
+        Some(Command::Stats { files }) => {
+            for (i, file) in files.iter().enumerate() {
+                if files.len() > 1 {
+                    if i > 0 {
+                        println!();
+                    }
+                    println!("{}:", file.display());
+                }
+                let bytes = fs::read(file).expect("Could not open input file");
+                stats::print_stats(&stats::collect_stats(&bytes));
+            }
+            ExitCode::SUCCESS
+        }
+        Some(Command::Symbols {
+            demangle,
+            format_str: Some(format),
+            files,
+            ..
+        }) => {
+            let template = match Template::parse(format, symbols::SYMBOL_FIELDS) {
+                Ok(template) => template,
+                Err(e) => {
+                    eprintln!("{e}");
+                    return ExitCode::FAILURE;
+                }
+            };
+            match read_modules(files) {
+                Some(modules) => {
+                    symbols::print_formatted(&modules, &template, *demangle);
+                    ExitCode::SUCCESS
+                }
+                None => ExitCode::FAILURE,
+            }
+        }
+        Some(Command::Symbols {
+            demangle,
+            format: Format::Csv,
+            files,
+            ..
+        }) => match read_modules(files) {
+            Some(modules) => {
+                csv::print_symbols(&modules, *demangle);
+                ExitCode::SUCCESS
+            }
+            None => ExitCode::FAILURE,
+        },
+        Some(Command::Symbols {
+            duplicates,
+            demangle,
+            files,
+            ..
+        }) => match read_modules(files) {
+            Some(modules) => {
+                if symbols::print_symbols(&modules, *duplicates, *demangle) && *duplicates {
+                    ExitCode::FAILURE
+                } else {
+                    ExitCode::SUCCESS
+                }
+            }
+            None => ExitCode::FAILURE,
+        },
+        Some(Command::Segments {
+            format: Format::Csv,
+            files,
+        }) => match read_modules(files) {
+            Some(modules) => {
+                csv::print_segments(&modules);
+                ExitCode::SUCCESS
+            }
+            None => ExitCode::FAILURE,
+        },
+        Some(Command::Segments { files, .. }) => {
+            let mut sum = vec![];
+            let mut count = 0;
+            let status = for_each_module(files, |_, module| {
+                let totals = segments::print_segments(module);
+                println!();
+                segments::print_totals(&totals);
+                segments::add_totals(&mut sum, totals);
+                count += 1;
+                Ok(())
+            });
+            if count > 1 {
+                println!();
+                println!("Total of {count} modules:");
+                segments::print_totals(&sum);
+            }
+            status
+        }
+        Some(Command::Tree { files }) => for_each_module(files, |_, module| {
+            tree::print_tree(module);
+            Ok(())
+        }),
+        Some(Command::Relocs { files }) => for_each_module(files, |_, module| {
+            relocs::print_relocations(module).map_err(|e| e.to_string())
+        }),
+        Some(Command::Extract {
+            segment,
+            output,
+            file,
+        }) => match read_module(file) {
+            Some(module) => match extract::extract_segment(&module, segment, output) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{}: {e}", file.display());
+                    ExitCode::FAILURE
+                }
+            },
+            None => ExitCode::FAILURE,
+        },
+        Some(Command::Strings {
+            min_length,
+            cp437,
+            files,
+        }) => for_each_module(files, |_, module| {
+            strings::print_strings(module, *min_length, *cp437)
+        }),
+        // Make rules already name their module, and headers would break them.
+        Some(Command::Deps { make: true, files }) => match read_modules(files) {
+            Some(modules) => {
+                for (label, module) in &modules {
+                    let deps = deps::collect_dependencies(module);
+                    deps::print_make_rule(Path::new(label), &deps);
+                }
+                ExitCode::SUCCESS
+            }
+            None => ExitCode::FAILURE,
+        },
+        Some(Command::Deps { make: false, files }) => for_each_module(files, |_, module| {
+            deps::print_dependencies(&deps::collect_dependencies(module));
+            Ok(())
+        }),
+        Some(Command::Overlays { files }) => {
+            for_each_module(files, |_, module| overlays::print_overlays(module))
+        }
+        Some(Command::Hashes {
+            duplicates: true,
+            files,
+        }) => match read_modules(files) {
+            Some(modules) => match hashes::print_duplicates(&modules) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::FAILURE
+                }
+            },
+            None => ExitCode::FAILURE,
+        },
+        Some(Command::Hashes {
+            duplicates: false,
+            files,
+        }) => for_each_module(files, |_, module| hashes::print_hashes(module)),
+        Some(Command::Identify { files }) => for_each_module(files, |_, module| {
+            let identification = module.identify();
+            match &identification.version {
+                Some(version) => println!("{} {version}", identification.toolchain),
+                None => println!("{}", identification.toolchain),
+            }
+            for clue in &identification.evidence {
+                println!("  {clue}");
+            }
+            Ok(())
+        }),
+        Some(Command::Debug { files }) => {
+            for_each_module(files, |_, module| debug::print_debug_info(module))
+        }
+        Some(Command::Calls { dot, files }) => {
+            let Some(modules) = read_modules(files) else {
+                return ExitCode::FAILURE;
+            };
+            let mut graph = CallGraph::new();
+            for (label, module) in &modules {
+                if let Err(e) = graph.add_module(module) {
+                    eprintln!("{label}: {e}");
+                    return ExitCode::FAILURE;
+                }
+            }
+            if *dot {
+                graph
+                    .write_dot(&mut io::stdout())
+                    .expect("Could not write output");
+            } else {
+                for caller in &graph.nodes {
+                    let callees = graph.callees(caller);
+                    if !callees.is_empty() {
+                        println!("{caller} -> {}", callees.join(", "));
+                    }
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        Some(Command::Unused { root, files }) => {
+            let Some(modules) = read_modules(files) else {
+                return ExitCode::FAILURE;
+            };
+            let (labels, modules): (Vec<_>, Vec<_>) = modules.into_iter().unzip();
+            let roots: Vec<&str> = root.iter().map(String::as_str).collect();
+            match omflib::unreferenced_publics(&modules, &roots) {
+                Ok(unused) => {
+                    for (m, name) in unused {
+                        println!("{}: {name}", labels[m]);
+                    }
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some(Command::Xref { library: file }) => {
+            let bytes = fs::read(file).expect("Could not open input file");
+            if !OmfLibrary::is_library(&bytes) {
+                eprintln!("{}: not a library", file.display());
+                return ExitCode::FAILURE;
+            }
+            match OmfLibrary::from_bytes(&bytes) {
+                Ok(library) => {
+                    library::print_cross_reference(&library);
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("{}: {e}", file.display());
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Some(Command::Diff {
+            ignore_volatile,
+            old,
+            new,
+        }) => {
+            let (Some(a), Some(b)) = (read_module(old), read_module(new)) else {
+                return ExitCode::FAILURE;
+            };
+            match diff::diff_modules(&a, &b, *ignore_volatile) {
+                Ok(lines) if lines.is_empty() => ExitCode::SUCCESS,
+                Ok(lines) => {
+                    for line in lines {
+                        println!("{line}");
+                    }
+                    ExitCode::FAILURE
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        #[cfg(feature = "disasm")]
+        Some(Command::Disasm { files }) => for_each_module(files, |_, module| {
+            disasm::print_disassembly(module).map_err(|e| e.to_string())
+        }),
+        #[cfg(feature = "tui")]
+        None if args.tui => match &args.files[..] {
+            [file] => browse(&args, file),
+            _ => {
+                eprintln!("--tui browses one file at a time");
+                ExitCode::FAILURE
+            }
+        },
+        None if args.verify => verify(&args),
+        None => {
+            let mut status = ExitCode::SUCCESS;
+            for (i, file) in args.files.iter().enumerate() {
+                if args.files.len() > 1 {
+                    if i > 0 {
+                        println!();
+                    }
+                    println!("{}:", file.display());
+                }
+                if dump(&args, file) != ExitCode::SUCCESS {
+                    status = ExitCode::FAILURE;
+                }
+            }
+            status
+        }
     }
-    ExitCode::SUCCESS
 }
@@ -0,0 +1,53 @@
+use omflib::{OmfModule, Overlay};
+
+use crate::symbols::segment_name;
+
+fn print_overlay(module: &OmfModule, overlays: &[Overlay], index: usize, depth: usize) {
+    let overlay = &overlays[index];
+    let label = format!("{:indent$}{}", "", overlay.name, indent = depth * 2);
+    let location = overlay
+        .location
+        .map_or(String::new(), |l| format!("at {l:X}h"));
+    let segments: Vec<String> = overlay
+        .segments
+        .iter()
+        .map(|&s| segment_name(module.info(), s))
+        .collect();
+    let line = format!(
+        "{label:<24} {:>6X}h {location:<10} {}",
+        overlay.size,
+        segments.join(" ")
+    );
+    println!("{}", line.trim_end());
+    for child in (0..overlays.len()).filter(|&i| overlays[i].parent == Some(index)) {
+        print_overlay(module, overlays, child, depth + 1);
+    }
+}
+
+// The resident segments, then each overlay under the one it is loaded
+// after, with its size and segments.
+pub fn print_overlays(module: &OmfModule) -> Result<(), String> {
+    let overlays = module.overlays().map_err(|e| e.to_string())?;
+    if overlays.is_empty() {
+        println!("No overlays");
+        return Ok(());
+    }
+    let info = module.info();
+    let resident: Vec<_> = info
+        .segments_with_names()
+        .filter(|(i, _, _)| !overlays.iter().any(|o| o.segments.contains(i)))
+        .collect();
+    let size: u32 = resident.iter().map(|(_, _, s)| s.length()).sum();
+    let names: Vec<&str> = resident.iter().map(|(_, name, _)| *name).collect();
+    let line = format!(
+        "{:<24} {size:>6X}h {:<10} {}",
+        "(resident)",
+        "",
+        names.join(" ")
+    );
+    println!("{}", line.trim_end());
+    for root in (0..overlays.len()).filter(|&i| overlays[i].parent.is_none()) {
+        print_overlay(module, &overlays, root, 1);
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use omflib::{OmfError, OmfInfo, OmfModule, RelocationRef};
+
+use crate::symbols::segment_name;
+
+pub fn describe(info: &OmfInfo, r: &RelocationRef) -> String {
+    match r {
+        RelocationRef::Segment(i) => segment_name(info, *i),
+        RelocationRef::Group(i) => i
+            .resolve(info)
+            .and_then(|g| g.group_name_index.resolve(info))
+            .map(|n| n.to_string())
+            .unwrap_or_else(|_| format!("group #{i}")),
+        RelocationRef::External(name) => name.to_string(),
+        RelocationRef::Absolute(frame) => format!("frame {frame:04X}h"),
+    }
+}
+
+// One line per fixup: where it applies, how, and what it refers to.
+pub fn print_relocations(module: &OmfModule) -> Result<(), OmfError> {
+    let relocations = module.relocations()?;
+    let info = module.info();
+    for r in relocations {
+        let location = format!("{}:{:04X}", segment_name(info, r.segment_index), r.offset);
+        let mode = if r.segment_relative {
+            "seg-rel"
+        } else {
+            "self-rel"
+        };
+        let mut target = describe(info, &r.target);
+        if r.target_displacement != 0 {
+            target.push_str(&format!("+{:X}h", r.target_displacement));
+        }
+        println!(
+            "{location:<16} {mode:<8} {:<24} {target:<24} frame {}",
+            r.kind.to_string(),
+            describe(info, &r.frame)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use omflib::{NameIndex, OmfModule, SegmentIndex};
+
+use crate::symbols::segment_name;
+
+pub struct SegmentRow {
+    pub name: String,
+    pub class: String,
+    pub group: String,
+    pub align: &'static str,
+    pub combine: String,
+    pub length: u32,
+}
+
+pub fn segment_rows(module: &OmfModule) -> Vec<SegmentRow> {
+    let info = module.info();
+    let name = |index: NameIndex| {
+        index
+            .resolve(info)
+            .map(|n| n.to_string())
+            .unwrap_or_default()
+    };
+    info.segments
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let segment_index = SegmentIndex(i as u16 + 1);
+            let group = info
+                .groups_with_names()
+                .find(|(_, _, g)| {
+                    g.segment_definitions
+                        .iter()
+                        .any(|c| c.segment_definition == segment_index)
+                })
+                .map(|(_, name, _)| name.to_string())
+                .unwrap_or_default();
+            let attrs = &s.segment_attributes;
+            SegmentRow {
+                name: segment_name(info, segment_index),
+                class: name(s.class_name_index),
+                group,
+                align: attrs.alignment.short_name(),
+                combine: attrs.combination.to_string(),
+                length: s.length(),
+            }
+        })
+        .collect()
+}
+
+// One line per segment. Returns the total length of each class.
+pub fn print_segments(module: &OmfModule) -> Vec<(String, u32)> {
+    let mut totals: Vec<(String, u32)> = vec![];
+    println!(
+        "{:<16} {:<10} {:<10} {:<6} {:<8} {:>6}",
+        "Segment", "Class", "Group", "Align", "Combine", "Length"
+    );
+    for row in segment_rows(module) {
+        println!(
+            "{:<16} {:<10} {:<10} {:<6} {:<8} {:>5X}h",
+            row.name, row.class, row.group, row.align, row.combine, row.length
+        );
+        add_totals(&mut totals, vec![(row.class, row.length)]);
+    }
+    totals
+}
+
+pub fn print_totals(totals: &[(String, u32)]) {
+    for (class, total) in totals {
+        println!("{class:<16} {total:>5X}h ({total} bytes)");
+    }
+}
+
+// Adds the class totals of one module to a running total.
+pub fn add_totals(sum: &mut Vec<(String, u32)>, totals: Vec<(String, u32)>) {
+    for (class, length) in totals {
+        match sum.iter_mut().find(|(c, _)| *c == class) {
+            Some(total) => total.1 += length,
+            None => sum.push((class, length)),
+        }
+    }
+}
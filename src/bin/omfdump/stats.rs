@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use omflib::{OmfReader, OmfRecordData, RecordType};
+
+const LARGEST_RECORDS: usize = 5;
+
+#[derive(Default)]
+pub struct Stats {
+    modules: usize,
+    // Count and total size, with header and checksum, of each record type.
+    types: BTreeMap<u8, (usize, usize)>,
+    comment_classes: BTreeMap<u8, usize>,
+    // Offset, type and length of the largest records, largest first.
+    largest: Vec<(usize, RecordType, usize)>,
+    unknown: usize,
+    undecoded: usize,
+    // What stopped reading before the end of the file.
+    error: Option<String>,
+}
+
+// Reads every record of an object, or of each member of a library, keeping
+// records that don't decode so they are counted too.
+pub fn collect_stats(bytes: &[u8]) -> Stats {
+    let mut stats = Stats::default();
+    let mut reader = OmfReader::new(bytes);
+    reader.set_lenient(true);
+    for module in reader.modules() {
+        let module = match module {
+            Ok(module) => module,
+            Err(e) => {
+                stats.error = Some(e.to_string());
+                break;
+            }
+        };
+        stats.modules += 1;
+        for record in &module.records {
+            let size = record.record_length + 3;
+            let entry = stats.types.entry(record.record_type.to_u8()).or_default();
+            entry.0 += 1;
+            entry.1 += size;
+            if let OmfRecordData::Coment { comment_class, .. } = record.data {
+                *stats.comment_classes.entry(comment_class).or_default() += 1;
+            }
+            if record.record_type.name().is_none() {
+                stats.unknown += 1;
+            } else if record.error.is_some() {
+                stats.undecoded += 1;
+            }
+            let at = stats.largest.partition_point(|&(_, _, len)| len >= size);
+            if at < LARGEST_RECORDS {
+                stats
+                    .largest
+                    .insert(at, (record.offset, record.record_type, size));
+                stats.largest.truncate(LARGEST_RECORDS);
+            }
+        }
+    }
+    stats
+}
+
+pub fn print_stats(stats: &Stats) {
+    let records: usize = stats.types.values().map(|t| t.0).sum();
+    let bytes: usize = stats.types.values().map(|t| t.1).sum();
+    println!(
+        "{records} records, {bytes} bytes, {} modules",
+        stats.modules
+    );
+    println!();
+    println!("{:<10} {:>7} {:>9}", "Type", "Count", "Bytes");
+    for (&record_type, (count, size)) in &stats.types {
+        let name = RecordType::from_u8(record_type).to_string();
+        println!("{name:<10} {count:>7} {size:>9}");
+    }
+    if !stats.comment_classes.is_empty() {
+        println!();
+        println!("{:<10} {:>7}", "Comment", "Count");
+        for (class, count) in &stats.comment_classes {
+            println!("{:<10} {count:>7}", format!("class {class:02X}h"));
+        }
+    }
+    if !stats.largest.is_empty() {
+        println!();
+        println!("Largest records:");
+        for (offset, record_type, size) in &stats.largest {
+            println!(
+                "  {offset:06X}h {:<10} {size:>7} bytes",
+                record_type.to_string()
+            );
+        }
+    }
+    println!();
+    println!("Unknown record types: {}", stats.unknown);
+    println!("Records that failed to decode: {}", stats.undecoded);
+    if let Some(e) = &stats.error {
+        println!("Stopped reading: {e}");
+    }
+}
@@ -0,0 +1,67 @@
+use omflib::{NameEncoding, OmfModule, OmfRecordData};
+
+use crate::symbols::segment_name;
+
+fn decode(byte: u8, cp437: bool) -> Option<char> {
+    match byte {
+        0x20..=0x7E => Some(byte as char),
+        // FFh is a non-breaking space, which is more likely padding than text.
+        0x80..=0xFE if cp437 => NameEncoding::Cp437.decode(&[byte]).ok()?.chars().next(),
+        _ => None,
+    }
+}
+
+// Finds runs of at least `min_length` printable characters, returning each
+// with its offset.
+fn find_strings(bytes: &[u8], min_length: usize, cp437: bool) -> Vec<(usize, String)> {
+    let mut strings = vec![];
+    let mut current = String::new();
+    let mut start = 0;
+    for (i, &b) in bytes.iter().chain(std::iter::once(&0)).enumerate() {
+        match decode(b, cp437) {
+            Some(c) => {
+                if current.is_empty() {
+                    start = i;
+                }
+                current.push(c);
+            }
+            None => {
+                if current.chars().count() >= min_length {
+                    strings.push((start, current.clone()));
+                }
+                current.clear();
+            }
+        }
+    }
+    strings
+}
+
+// Prints printable strings found in initialized segment data and in comment
+// records, with the segment and offset they start at.
+pub fn print_strings(module: &OmfModule, min_length: usize, cp437: bool) -> Result<(), String> {
+    let images = module.segment_images().map_err(|e| e.to_string())?;
+    let info = module.info();
+    for image in &images {
+        let name = segment_name(info, image.segment_index);
+        for range in &image.initialized {
+            for (offset, s) in find_strings(&image.data[range.clone()], min_length, cp437) {
+                let location = format!("{name}:{:04X}", range.start + offset);
+                println!("{location:<24} {s}");
+            }
+        }
+    }
+    for record in &module.records {
+        if let OmfRecordData::Coment {
+            comment_class,
+            comment_bytes,
+            ..
+        } = &record.data
+        {
+            for (offset, s) in find_strings(comment_bytes, min_length, cp437) {
+                let location = format!("COMENT {comment_class:02X}h+{offset:X}");
+                println!("{location:<24} {s}");
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use omflib::{
+    demangle_or_original, OmfInfo, OmfModule, SegmentIndex, Symbol, SymbolBinding, SymbolKind,
+    SymbolScope,
+};
+
+use crate::template::{Template, Value};
+
+pub fn segment_name(info: &OmfInfo, segment_index: SegmentIndex) -> String {
+    segment_index
+        .resolve(info)
+        .and_then(|s| s.segment_name_index.resolve(info))
+        .map(|n| n.to_string())
+        .unwrap_or_else(|_| format!("#{segment_index}"))
+}
+
+pub struct SymbolRow {
+    pub kind: &'static str,
+    pub scope: &'static str,
+    // Where a defined symbol lives, as a segment and offset.
+    pub segment: String,
+    pub offset: Option<u32>,
+    // A communal's length or an alias's substitute.
+    pub detail: String,
+    pub name: String,
+}
+
+pub fn symbol_row(info: &OmfInfo, s: &Symbol, demangle: bool) -> SymbolRow {
+    let name = |name: &str| {
+        if demangle {
+            demangle_or_original(name)
+        } else {
+            name.to_string()
+        }
+    };
+    let (segment, offset) = match (&s.kind, s.binding) {
+        (SymbolKind::Public | SymbolKind::Comdat, SymbolBinding::Segment { segment_index, .. }) => {
+            (segment_name(info, segment_index), Some(s.offset))
+        }
+        (SymbolKind::Public | SymbolKind::Comdat, SymbolBinding::Absolute { frame }) => {
+            (format!("abs {frame:04X}"), Some(s.offset))
+        }
+        _ => (String::new(), None),
+    };
+    let (kind, detail) = match &s.kind {
+        SymbolKind::Public => ("PUBLIC", String::new()),
+        SymbolKind::External => ("EXTERN", String::new()),
+        SymbolKind::Communal(length) => ("COMMON", length.to_string()),
+        SymbolKind::ComdatExternal => ("CEXTERN", String::new()),
+        SymbolKind::Comdat => ("COMDAT", String::new()),
+        SymbolKind::Alias(substitute) => ("ALIAS", format!("-> {}", name(substitute))),
+    };
+    let scope = match s.scope {
+        SymbolScope::Global => "global",
+        SymbolScope::Local => "local",
+    };
+    SymbolRow {
+        kind,
+        scope,
+        segment,
+        offset,
+        detail,
+        name: name(&s.name),
+    }
+}
+
+// Kind, scope, where the symbol lives, and its name.
+pub fn describe_symbol(info: &OmfInfo, s: &Symbol, demangle: bool) -> String {
+    let row = symbol_row(info, s, demangle);
+    let location = match row.offset {
+        Some(offset) => format!("{}:{offset:04X}", row.segment),
+        None => row.detail,
+    };
+    format!(
+        "{:<8} {:<6} {location:<24} {}",
+        row.kind, row.scope, row.name
+    )
+}
+
+// One line per symbol. With several modules, each line starts with the
+// module it came from, and global publics defined by more than one module
+// are listed at the end. Returns whether there were any such duplicates.
+pub fn print_symbols(
+    modules: &[(String, OmfModule)],
+    duplicates_only: bool,
+    demangle: bool,
+) -> bool {
+    let mut definitions: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for (label, module) in modules {
+        let info = module.info();
+        for s in info.symbols.iter() {
+            if !duplicates_only {
+                if modules.len() > 1 {
+                    println!("{label:<20} {}", describe_symbol(info, s, demangle));
+                } else {
+                    println!("{}", describe_symbol(info, s, demangle));
+                }
+            }
+            if s.kind != SymbolKind::Public || s.scope != SymbolScope::Global {
+                continue;
+            }
+            definitions.entry(s.name.clone()).or_default().push(label);
+        }
+    }
+    let duplicates: Vec<_> = definitions
+        .into_iter()
+        .filter(|(_, labels)| labels.len() > 1)
+        .collect();
+    if !duplicates.is_empty() {
+        if !duplicates_only {
+            println!();
+            println!("Duplicate definitions:");
+        }
+        for (name, labels) in &duplicates {
+            let name = if demangle {
+                demangle_or_original(name)
+            } else {
+                name.clone()
+            };
+            println!("{name:<24} {}", labels.join(", "));
+        }
+    }
+    !duplicates.is_empty()
+}
+
+pub const SYMBOL_FIELDS: &[&str] = &[
+    "module", "kind", "scope", "segment", "offset", "name", "detail",
+];
+
+// One line per symbol, filled in from the template.
+pub fn print_formatted(modules: &[(String, OmfModule)], template: &Template, demangle: bool) {
+    for (label, module) in modules {
+        let info = module.info();
+        for s in info.symbols.iter() {
+            let row = symbol_row(info, s, demangle);
+            let line = template.render(|field| match field {
+                "module" => Value::Text(label.clone()),
+                "kind" => Value::Text(row.kind.to_string()),
+                "scope" => Value::Text(row.scope.to_string()),
+                "segment" => Value::Text(row.segment.clone()),
+                "offset" => row.offset.map_or(Value::Empty, |o| Value::Number(o as u64)),
+                "name" => Value::Text(row.name.clone()),
+                "detail" => Value::Text(row.detail.clone()),
+                _ => Value::Empty,
+            });
+            println!("{line}");
+        }
+    }
+}
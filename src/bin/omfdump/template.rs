@@ -0,0 +1,143 @@
+// Templates for --format-str: text with fields in braces, such as
+// "{name} {segment} {offset:04X}". A field may have a spec after a colon
+// like Rust's: an alignment (< or >), 0 to pad with zeros, a width, and x
+// or X for hex. Doubled braces stand for themselves, and \t and \n for a
+// tab and a newline.
+
+pub enum Value {
+    Number(u64),
+    Text(String),
+    // Printed as nothing, such as the offset of an external.
+    Empty,
+}
+
+#[derive(Default)]
+struct Spec {
+    left: Option<bool>,
+    zero: bool,
+    width: usize,
+    hex: Option<bool>,
+}
+
+enum Part {
+    Literal(String),
+    Field(String, Spec),
+}
+
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+fn parse_spec(spec: &str) -> Result<Spec, String> {
+    let mut result = Spec::default();
+    let mut rest = spec;
+    if let Some(r) = rest.strip_prefix('<') {
+        result.left = Some(true);
+        rest = r;
+    } else if let Some(r) = rest.strip_prefix('>') {
+        result.left = Some(false);
+        rest = r;
+    }
+    if let Some(r) = rest.strip_prefix('0') {
+        result.zero = true;
+        rest = r;
+    }
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        result.width = rest[..digits]
+            .parse()
+            .map_err(|_| format!("bad width in {{:{spec}}}"))?;
+        rest = &rest[digits..];
+    }
+    result.hex = match rest {
+        "" => None,
+        "x" => Some(false),
+        "X" => Some(true),
+        _ => return Err(format!("bad format spec {{:{spec}}}")),
+    };
+    Ok(result)
+}
+
+impl Template {
+    // Parses a template that may only use the given fields.
+    pub fn parse(template: &str, fields: &[&str]) -> Result<Template, String> {
+        let mut parts = vec![];
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '\\' if chars.as_str().starts_with(['t', 'n']) => {
+                    literal.push(if chars.next() == Some('t') {
+                        '\t'
+                    } else {
+                        '\n'
+                    });
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or("unclosed { in format")?;
+                    let (name, spec) = rest[..end].split_once(':').unwrap_or((&rest[..end], ""));
+                    if !fields.contains(&name) {
+                        return Err(format!(
+                            "unknown field {{{name}}}, expected one of {}",
+                            fields.join(", ")
+                        ));
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(name.to_string(), parse_spec(spec)?));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err("unmatched } in format".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    // Fills in the template with what `field` gives for each field.
+    pub fn render(&self, field: impl Fn(&str) -> Value) -> String {
+        let mut out = String::new();
+        for part in &self.parts {
+            let (name, spec) = match part {
+                Part::Literal(text) => {
+                    out += text;
+                    continue;
+                }
+                Part::Field(name, spec) => (name, spec),
+            };
+            let (text, number) = match field(name) {
+                Value::Number(n) => match spec.hex {
+                    Some(true) => (format!("{n:X}"), true),
+                    Some(false) => (format!("{n:x}"), true),
+                    None => (n.to_string(), true),
+                },
+                Value::Text(text) => (text, false),
+                Value::Empty => (String::new(), false),
+            };
+            let pad = spec.width.saturating_sub(text.chars().count());
+            // Numbers go to the right unless told otherwise, as in Rust.
+            if spec.left.unwrap_or(!number) {
+                out += &text;
+                out.extend(std::iter::repeat_n(' ', pad));
+            } else {
+                let fill = if spec.zero && number { '0' } else { ' ' };
+                out.extend(std::iter::repeat_n(fill, pad));
+                out += &text;
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,335 @@
+use std::io::IsTerminal;
+
+use clap::ValueEnum;
+use omflib::{
+    FixupSubrecord, MAttrStart, NameIndex, OmfInfo, OmfModule, OmfRecord, OmfRecordData, RecordType,
+};
+
+use crate::{
+    grep,
+    symbols::segment_name,
+    template::{Template, Value},
+};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+// ANSI highlighting for record types and symbol names.
+#[derive(Clone, Copy)]
+pub struct Style {
+    enabled: bool,
+}
+
+impl Style {
+    pub fn new(choice: ColorChoice) -> Style {
+        let enabled = match choice {
+            ColorChoice::Auto => std::io::stdout().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        };
+        Style { enabled }
+    }
+
+    fn paint(&self, code: &str, s: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{code}m{s}\x1b[0m")
+        } else {
+            s.to_string()
+        }
+    }
+
+    pub fn record_type(&self, s: &str) -> String {
+        self.paint("1;36", s)
+    }
+
+    pub fn symbol(&self, s: &str) -> String {
+        self.paint("33", s)
+    }
+
+    pub fn warning(&self, s: &str) -> String {
+        self.paint("1;31", s)
+    }
+
+    // Names matching --grep.
+    pub fn matched(&self, s: &str) -> String {
+        self.paint("1;4;33", s)
+    }
+}
+
+fn name(info: &OmfInfo, index: NameIndex) -> String {
+    index
+        .resolve(info)
+        .map(|n| n.to_string())
+        .unwrap_or_else(|_| format!("#{index}"))
+}
+
+fn symbols<'a>(style: Style, names: impl Iterator<Item = &'a str>) -> String {
+    names
+        .map(|n| style.symbol(n))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn summary(info: &OmfInfo, data: &OmfRecordData, style: Style) -> String {
+    match data {
+        OmfRecordData::THeadr { name } => name.clone(),
+        OmfRecordData::Coment {
+            comment_class,
+            comment_bytes,
+            ..
+        } => {
+            if comment_bytes.iter().all(|b| (0x20..0x7F).contains(b)) {
+                format!(
+                    "class {comment_class:02X}h \"{}\"",
+                    String::from_utf8_lossy(comment_bytes)
+                )
+            } else {
+                format!("class {comment_class:02X}h, {} bytes", comment_bytes.len())
+            }
+        }
+        OmfRecordData::ModEnd { main, start } => {
+            let kind = if *main { "main module" } else { "module" };
+            match start {
+                MAttrStart::Start(_) => format!("{kind}, with start address"),
+                MAttrStart::NoStart => kind.to_string(),
+            }
+        }
+        OmfRecordData::ExtDef { names } | OmfRecordData::LExtDef { names } => {
+            symbols(style, names.iter().map(|n| n.name.as_str()))
+        }
+        OmfRecordData::PubDef {
+            base_segment_index,
+            names,
+            ..
+        }
+        | OmfRecordData::LPubDef {
+            base_segment_index,
+            names,
+            ..
+        } => format!(
+            "{}: {}",
+            segment_name(info, *base_segment_index),
+            names
+                .iter()
+                .map(|n| format!("{}@{:04X}", style.symbol(&n.name), n.public_offset))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        OmfRecordData::LinNum {
+            base_segment_index,
+            lines,
+            ..
+        } => format!(
+            "{}: {} lines",
+            segment_name(info, *base_segment_index),
+            lines.len()
+        ),
+        OmfRecordData::LNames { names } => names.join(", "),
+        OmfRecordData::SegDef {
+            segment_attributes,
+            segment_length,
+            segment_name_index,
+            class_name_index,
+            ..
+        } => {
+            let length = if segment_attributes.big {
+                0x10000
+            } else {
+                *segment_length as u32
+            };
+            format!(
+                "{} class {}, {length:X}h bytes",
+                name(info, *segment_name_index),
+                name(info, *class_name_index),
+            )
+        }
+        OmfRecordData::GrpDef {
+            group_name_index,
+            segment_definitions,
+        } => format!(
+            "{}: {}",
+            name(info, *group_name_index),
+            segment_definitions
+                .iter()
+                .map(|c| segment_name(info, c.segment_definition))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        OmfRecordData::FixUpp { subrecords } => {
+            let fixups = subrecords
+                .iter()
+                .filter(|s| matches!(s, FixupSubrecord::Fixup(_)))
+                .count();
+            let threads = subrecords.len() - fixups;
+            if threads > 0 {
+                format!("{fixups} fixups, {threads} threads")
+            } else {
+                format!("{fixups} fixups")
+            }
+        }
+        OmfRecordData::LEData {
+            segment_index,
+            enumerated_data_offset,
+            data,
+        } => format!(
+            "{}:{enumerated_data_offset:04X}, {} bytes",
+            segment_name(info, *segment_index),
+            data.len()
+        ),
+        OmfRecordData::LIData {
+            segment_index,
+            iterated_data_offset,
+            blocks,
+        } => format!(
+            "{}:{iterated_data_offset:04X}, {} blocks",
+            segment_name(info, *segment_index),
+            blocks.len()
+        ),
+        OmfRecordData::ComDef { definitions } => {
+            symbols(style, definitions.iter().map(|d| d.name.as_str()))
+        }
+        OmfRecordData::CExtDef { names } => {
+            let names: Vec<_> = names
+                .iter()
+                .map(|n| name(info, n.logical_name_index))
+                .collect();
+            symbols(style, names.iter().map(|n| n.as_str()))
+        }
+        OmfRecordData::ComDat {
+            public_name_index,
+            continuation,
+            ..
+        } => {
+            let name = style.symbol(&name(info, *public_name_index));
+            if *continuation {
+                format!("{name} (continued)")
+            } else {
+                name
+            }
+        }
+        OmfRecordData::LinSym {
+            public_name_index,
+            lines,
+            ..
+        } => format!(
+            "{}: {} lines",
+            style.symbol(&name(info, *public_name_index)),
+            lines.len()
+        ),
+        OmfRecordData::Alias { aliases } => aliases
+            .iter()
+            .map(|a| {
+                format!(
+                    "{} -> {}",
+                    style.symbol(&a.alias_name),
+                    style.symbol(&a.substitute_name)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+        OmfRecordData::Unknown { data } => format!("{} bytes", data.len()),
+    }
+}
+
+pub fn type_label(record_type: RecordType) -> String {
+    record_type
+        .base()
+        .name()
+        .map(|name| {
+            if record_type.is_32bit() {
+                format!("{name}32")
+            } else {
+                name.to_string()
+            }
+        })
+        .unwrap_or_else(|| "?".to_string())
+}
+
+// One line per record: file offset, type, length and a short summary.
+// `base` is the file offset of the module's first record. With a --grep
+// pattern, only records naming something that matches are printed.
+pub fn print_terse(
+    module: &OmfModule,
+    base: usize,
+    shows: impl Fn(&OmfRecord) -> bool,
+    grep: Option<&str>,
+    style: Style,
+) {
+    let info = module.info();
+    for record in module.records.iter().filter(|r| shows(r)) {
+        let summary = match grep {
+            Some(pattern) => {
+                let names = grep::matching_names(pattern, info, record);
+                if names.is_empty() {
+                    continue;
+                }
+                let plain = Style::new(ColorChoice::Never);
+                grep::highlight(&summary(info, &record.data, plain), &names, style)
+            }
+            None => summary(info, &record.data, style),
+        };
+        let mut flag = String::new();
+        if let Some(error) = &record.error {
+            flag += &style.warning(&format!(" [{error}]"));
+        }
+        if !record.checksum_valid {
+            flag += &style.warning(" [bad checksum]");
+        }
+        println!(
+            "{:06X} {:02X} {} {:>5} {}{flag}",
+            base + record.offset,
+            record.record_type.to_u8(),
+            style.record_type(&format!("{:<8}", type_label(record.record_type))),
+            record.record_length,
+            summary
+        );
+    }
+}
+
+pub const RECORD_FIELDS: &[&str] = &[
+    "index",
+    "offset",
+    "type",
+    "type_number",
+    "length",
+    "checksum",
+    "summary",
+];
+
+fn record_field(info: &OmfInfo, index: usize, record: &OmfRecord, field: &str) -> Value {
+    match field {
+        "index" => Value::Number(index as u64),
+        "offset" => Value::Number(record.offset as u64),
+        "type" => Value::Text(type_label(record.record_type)),
+        "type_number" => Value::Number(record.record_type.to_u8() as u64),
+        "length" => Value::Number(record.record_length as u64),
+        "checksum" => Value::Number(record.checksum as u64),
+        "summary" => {
+            let summary = format!("{:#}", record.display_with(info));
+            let rest = summary
+                .strip_prefix(&record.record_type.to_string())
+                .unwrap_or(&summary);
+            Value::Text(rest.trim_start().to_string())
+        }
+        _ => Value::Empty,
+    }
+}
+
+// One line per record `shows` accepts, filled in from the template.
+// Offsets are from the start of the file.
+pub fn print_formatted(
+    module: &OmfModule,
+    template: &Template,
+    shows: impl Fn(&OmfRecord) -> bool,
+) {
+    let info = module.info();
+    for (i, record) in module.records.iter().enumerate() {
+        if shows(record) {
+            println!("{}", template.render(|f| record_field(info, i, record, f)));
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use omflib::{OmfModule, SegmentIndex};
+
+use crate::segments::segment_rows;
+
+fn print_branch(label: &str, total: u32, children: &[(String, String, u32)]) {
+    println!("{label:<34} {total:>6X}h");
+    for (i, (segment, detail, length)) in children.iter().enumerate() {
+        let branch = if i + 1 == children.len() {
+            "└──"
+        } else {
+            "├──"
+        };
+        println!("{branch} {segment:<16} {detail:<13} {length:>6X}h");
+    }
+}
+
+// Groups with their member segments, then any segments outside a group.
+pub fn print_tree(module: &OmfModule) {
+    let rows = segment_rows(module);
+    let info = module.info();
+    let describe = |index: SegmentIndex| {
+        let row = &rows[index.0 as usize - 1];
+        (row.name.clone(), format!("class {}", row.class), row.length)
+    };
+    let mut grouped = vec![false; rows.len()];
+    for g in &info.groups {
+        let name = g.group_name_index.resolve(info).ok().unwrap_or_default();
+        let children: Vec<_> = g
+            .segment_definitions
+            .iter()
+            .filter(|c| (1..=rows.len()).contains(&(c.segment_definition.0 as usize)))
+            .map(|c| {
+                grouped[c.segment_definition.0 as usize - 1] = true;
+                describe(c.segment_definition)
+            })
+            .collect();
+        let total = children.iter().map(|c| c.2).sum();
+        print_branch(&name, total, &children);
+    }
+    let children: Vec<_> = (0..rows.len())
+        .filter(|&i| !grouped[i])
+        .map(|i| describe(SegmentIndex(i as u16 + 1)))
+        .collect();
+    if !children.is_empty() {
+        let total = children.iter().map(|c| c.2).sum();
+        print_branch("(no group)", total, &children);
+    }
+}
@@ -0,0 +1,254 @@
+use std::io;
+
+use omflib::{HexOptions, OmfModule, OmfRecord, RecordType};
+use pretty_hex::{HexConfig, PrettyHex};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, List, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use crate::grep;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    Detail,
+    Hex,
+}
+
+// Browses the records of a file's modules: a list of records on the left,
+// and the selected one decoded, or its bytes, on the right.
+struct Browser<'a> {
+    bytes: &'a [u8],
+    modules: &'a [(Option<String>, OmfModule)],
+    // The module and record index of each line of the list.
+    entries: Vec<(usize, usize)>,
+    lines: Vec<String>,
+    list: ListState,
+    // Lines in the list as last drawn, for paging.
+    page: usize,
+    pane: Pane,
+    scroll: u16,
+    hex: HexOptions,
+    // The pattern being typed after /, and the last one searched for.
+    input: Option<String>,
+    pattern: Option<String>,
+    message: String,
+}
+
+impl<'a> Browser<'a> {
+    fn new(
+        bytes: &'a [u8],
+        modules: &'a [(Option<String>, OmfModule)],
+        shows: impl Fn(&OmfRecord) -> bool,
+        hex: HexOptions,
+    ) -> Browser<'a> {
+        let mut entries = vec![];
+        let mut lines = vec![];
+        for (m, (name, module)) in modules.iter().enumerate() {
+            for (r, record) in module.records.iter().enumerate() {
+                if !shows(record) {
+                    continue;
+                }
+                let summary = format!("{:#}", record.display_with(module.info()));
+                lines.push(match name {
+                    Some(name) => format!("{:06X} {name:<12} {summary}", record.offset),
+                    None => format!("{:06X} {summary}", record.offset),
+                });
+                entries.push((m, r));
+            }
+        }
+        let mut list = ListState::default();
+        if !entries.is_empty() {
+            list.select(Some(0));
+        }
+        Browser {
+            bytes,
+            modules,
+            entries,
+            lines,
+            list,
+            page: 1,
+            pane: Pane::Detail,
+            scroll: 0,
+            hex,
+            input: None,
+            pattern: None,
+            message: String::new(),
+        }
+    }
+
+    fn selected(&self) -> Option<(&'a OmfModule, usize)> {
+        let (m, r) = *self.entries.get(self.list.selected()?)?;
+        Some((&self.modules[m].1, r))
+    }
+
+    fn select(&mut self, i: usize) {
+        if !self.entries.is_empty() {
+            self.list.select(Some(i.min(self.entries.len() - 1)));
+            self.scroll = 0;
+        }
+    }
+
+    fn detail(&self) -> String {
+        let Some((module, r)) = self.selected() else {
+            return String::new();
+        };
+        let record = &module.records[r];
+        let mut display = record
+            .display_with(module.info())
+            .with_hex_options(self.hex);
+        if let Some(next) = module.records.get(r + 1) {
+            if next.record_type.base() == RecordType::FixUpp {
+                display = display.with_fixups(next);
+            }
+        }
+        display.to_string()
+    }
+
+    // The record as it is in the file, header and checksum included.
+    fn raw(&self) -> String {
+        let Some((module, r)) = self.selected() else {
+            return String::new();
+        };
+        let record = &module.records[r];
+        let end = (record.offset + 3 + record.record_length).min(self.bytes.len());
+        let cfg = HexConfig {
+            width: self.hex.bytes_per_line,
+            group: self.hex.group,
+            display_offset: record.offset,
+            ..HexConfig::default()
+        };
+        format!(
+            "{:?}",
+            self.bytes[record.offset.min(end)..end].hex_conf(cfg)
+        )
+    }
+
+    // Selects the next record, or with `back` the previous one, naming
+    // something that matches the search pattern.
+    fn search(&mut self, back: bool) {
+        let Some(pattern) = &self.pattern else {
+            return;
+        };
+        let count = self.entries.len();
+        let start = self.list.selected().unwrap_or(0);
+        for step in 1..=count {
+            let i = if back {
+                (start + count - step) % count
+            } else {
+                (start + step) % count
+            };
+            let (m, r) = self.entries[i];
+            let module = &self.modules[m].1;
+            if !grep::matching_names(pattern, module.info(), &module.records[r]).is_empty() {
+                self.select(i);
+                self.message.clear();
+                return;
+            }
+        }
+        self.message = format!("Nothing matches {pattern}");
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+                .areas(main);
+        self.page = left.height.saturating_sub(2).max(1) as usize;
+        let list = List::new(self.lines.iter().map(String::as_str))
+            .block(Block::bordered().title("Records"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, left, &mut self.list);
+        let (title, text) = match self.pane {
+            Pane::Detail => ("Record", self.detail()),
+            Pane::Hex => ("Bytes", self.raw()),
+        };
+        let detail = Paragraph::new(text)
+            .block(Block::bordered().title(title))
+            .scroll((self.scroll, 0));
+        frame.render_widget(detail, right);
+        let status_line = match &self.input {
+            Some(input) => format!("/{input}"),
+            None if !self.message.is_empty() => self.message.clone(),
+            None => "q quit  / search  n/N next/previous match  Tab bytes  d/u scroll".to_string(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+
+    // Handles a key, returning false to quit.
+    fn key(&mut self, code: KeyCode) -> bool {
+        if let Some(input) = &mut self.input {
+            match code {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    self.pattern = self.input.take().filter(|p| !p.is_empty());
+                    self.search(false);
+                }
+                KeyCode::Esc => self.input = None,
+                _ => (),
+            }
+            return true;
+        }
+        let selected = self.list.selected().unwrap_or(0);
+        let half = (self.page / 2).max(1) as u16;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.select(selected + 1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(selected.saturating_sub(1)),
+            KeyCode::PageDown => self.select(selected + self.page),
+            KeyCode::PageUp => self.select(selected.saturating_sub(self.page)),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(usize::MAX),
+            KeyCode::Char('d') => self.scroll = self.scroll.saturating_add(half),
+            KeyCode::Char('u') => self.scroll = self.scroll.saturating_sub(half),
+            KeyCode::Tab => {
+                self.pane = match self.pane {
+                    Pane::Detail => Pane::Hex,
+                    Pane::Hex => Pane::Detail,
+                };
+                self.scroll = 0;
+            }
+            KeyCode::Char('/') => {
+                self.input = Some(String::new());
+                self.message.clear();
+            }
+            KeyCode::Char('n') => self.search(false),
+            KeyCode::Char('N') => self.search(true),
+            _ => (),
+        }
+        true
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+// Browses the records `shows` accepts of the modules read from `bytes`,
+// until the user quits.
+pub fn browse(
+    bytes: &[u8],
+    modules: &[(Option<String>, OmfModule)],
+    shows: impl Fn(&OmfRecord) -> bool,
+    hex: HexOptions,
+) -> io::Result<()> {
+    let mut browser = Browser::new(bytes, modules, shows, hex);
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,164 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser;
+use omflib::{glob_match, OmfLibrary, OmfModule, SymbolKind, SymbolScope};
+
+#[derive(Parser, Debug)]
+/// Find the modules that define or refer to a symbol. Directories are
+/// searched recursively for .OBJ and .LIB files.
+struct Args {
+    /// Symbol name, where * matches any run of characters and ? any one
+    pattern: String,
+    /// Only list definitions
+    #[arg(short, long, conflicts_with = "references")]
+    definitions: bool,
+    /// Only list references
+    #[arg(short, long)]
+    references: bool,
+    /// Ignore case when matching
+    #[arg(short, long)]
+    ignore_case: bool,
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+impl Args {
+    fn matches(&self, name: &str) -> bool {
+        glob_match(&self.pattern, name, self.ignore_case)
+    }
+
+    fn is_literal(&self) -> bool {
+        !self.pattern.contains(['*', '?'])
+    }
+
+    // Prints the module's matching definitions and references, leaving out
+    // definitions unless `with_definitions` is set. Returns whether anything
+    // matched.
+    fn search_module(&self, label: &str, module: &OmfModule, with_definitions: bool) -> bool {
+        let mut found = false;
+        for s in module.info().symbols.iter() {
+            if !self.matches(&s.name) {
+                continue;
+            }
+            let what = match (&s.kind, s.scope) {
+                (SymbolKind::External | SymbolKind::ComdatExternal, _) => "references",
+                (SymbolKind::Communal(_), _) => "declares communal",
+                (_, SymbolScope::Local) => "defines local",
+                _ => "defines",
+            };
+            let reference = what == "references";
+            if reference && self.definitions || !reference && (self.references || !with_definitions)
+            {
+                continue;
+            }
+            println!("{label}: {what} {}", s.name);
+            found = true;
+        }
+        found
+    }
+
+    // Definitions in a library come from its dictionary when it has one,
+    // so only references need the members themselves.
+    fn search_library(&self, path: &Path, library: &OmfLibrary) -> bool {
+        let mut found = false;
+        let use_dictionary = !library.dictionary.is_empty();
+        if use_dictionary && !self.references {
+            let mut pages: Vec<(u16, String)> = if self.is_literal() && !self.ignore_case {
+                library
+                    .lookup(&self.pattern)
+                    .map(|page| (page, self.pattern.clone()))
+                    .into_iter()
+                    .collect()
+            } else {
+                library
+                    .dictionary
+                    .iter()
+                    .filter(|(name, _)| !name.ends_with('!') && self.matches(name))
+                    .map(|(name, page)| (*page, name.clone()))
+                    .collect()
+            };
+            pages.sort();
+            for (page, name) in pages {
+                if let Some(m) = library.members.iter().find(|m| m.page == page as u32) {
+                    println!("{}({}): defines {name}", path.display(), m.name);
+                    found = true;
+                }
+            }
+        }
+        if !self.definitions || !use_dictionary {
+            for m in &library.members {
+                let label = format!("{}({})", path.display(), m.name);
+                found |= self.search_module(&label, &m.module, !use_dictionary);
+            }
+        }
+        found
+    }
+
+    fn search_file(&self, path: &Path) -> Result<bool, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        if OmfLibrary::is_library(&bytes) {
+            let library = OmfLibrary::from_bytes(&bytes).map_err(|e| e.to_string())?;
+            Ok(self.search_library(path, &library))
+        } else {
+            let module = OmfModule::read(&mut &bytes[..]).map_err(|e| e.to_string())?;
+            Ok(self.search_module(&path.display().to_string(), &module, true))
+        }
+    }
+}
+
+// Collects the .OBJ and .LIB files under a directory, in name order.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .and_then(|entries| entries.map(|e| e.map(|e| e.path())).collect())
+        .map_err(|e| format!("{}: {e}", dir.display()))?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("obj") || ext.eq_ignore_ascii_case("lib"))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut files = vec![];
+    let mut errors = false;
+    for path in &args.paths {
+        if path.is_dir() {
+            if let Err(e) = collect_files(path, &mut files) {
+                eprintln!("{e}");
+                errors = true;
+            }
+        } else {
+            files.push(path.clone());
+        }
+    }
+    let mut found = false;
+    for file in &files {
+        match args.search_file(file) {
+            Ok(f) => found |= f,
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                errors = true;
+            }
+        }
+    }
+    // Like grep: 0 if anything matched, 1 if nothing did, 2 on errors.
+    if errors {
+        ExitCode::from(2)
+    } else if found {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,135 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, ValueEnum};
+use omflib::{link, search_libraries, LayoutEntry, LinkOptions, OmfLibrary, OmfModule};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum Format {
+    Com,
+    Exe,
+    Bin,
+}
+
+fn parse_number(s: &str) -> Result<u32, String> {
+    let r = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
+    } else if let Some(hex) = s.strip_suffix('h').or_else(|| s.strip_suffix('H')) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        s.parse()
+    };
+    r.map_err(|e| format!("invalid number {s}: {e}"))
+}
+
+fn parse_placement(s: &str) -> Result<LayoutEntry, String> {
+    Ok(match s.split_once('@') {
+        Some((name, address)) => LayoutEntry {
+            name: name.to_string(),
+            address: Some(parse_number(address)?),
+        },
+        None => LayoutEntry {
+            name: s.to_string(),
+            address: None,
+        },
+    })
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[arg(short, long)]
+    output: PathBuf,
+    #[arg(short, long, value_enum, default_value = "com")]
+    format: Format,
+    /// Address of the first byte of a bin image
+    #[arg(long, value_parser = parse_number)]
+    origin: Option<u32>,
+    /// Place a segment or class first, optionally at an address (NAME or
+    /// NAME@ADDR). May be given several times.
+    #[arg(short, long = "place", value_parser = parse_placement)]
+    placements: Vec<LayoutEntry>,
+    /// In a bin image, compute offsets relative to each segment's paragraph
+    /// instead of 0
+    #[arg(long)]
+    segmented: bool,
+    /// Write a link map to this file
+    #[arg(short, long)]
+    map: Option<PathBuf>,
+    /// Objects to link, and libraries to search for the externals they need
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+// Reads the objects and libraries named on the command line, and adds the
+// library members the objects need after the objects.
+fn read_inputs(files: &[PathBuf]) -> Result<Vec<OmfModule>, String> {
+    let mut modules = vec![];
+    let mut libraries = vec![];
+    for file in files {
+        let bytes = fs::read(file).map_err(|e| format!("{}: {e}", file.display()))?;
+        if OmfLibrary::is_library(&bytes) {
+            let library =
+                OmfLibrary::from_bytes(&bytes).map_err(|e| format!("{}: {e}", file.display()))?;
+            libraries.push(library);
+        } else {
+            let module =
+                OmfModule::read(&mut &bytes[..]).map_err(|e| format!("{}: {e}", file.display()))?;
+            modules.push(module);
+        }
+    }
+    let selected = search_libraries(&modules, &libraries);
+    let mut members: Vec<Vec<Option<OmfModule>>> = libraries
+        .into_iter()
+        .map(|l| l.members.into_iter().map(|m| Some(m.module)).collect())
+        .collect();
+    for (l, m) in selected {
+        modules.extend(members[l][m].take());
+    }
+    Ok(modules)
+}
+
+fn run(args: Args) -> Result<(), String> {
+    if args.origin.is_some() && args.format != Format::Bin {
+        return Err("--origin only applies to bin images".to_string());
+    }
+    if args.segmented && args.format != Format::Bin {
+        return Err("--segmented only applies to bin images".to_string());
+    }
+    let modules = read_inputs(&args.files)?;
+    let options = match args.format {
+        Format::Com => LinkOptions::com(),
+        Format::Exe => LinkOptions::exe(),
+        Format::Bin => LinkOptions {
+            origin: args.origin.unwrap_or(0),
+            flat_frames: !args.segmented,
+            ..LinkOptions::default()
+        },
+    };
+    let options = LinkOptions {
+        segment_order: args.placements,
+        ..options
+    };
+    let image = link(&modules, &options)
+        .and_then(|program| {
+            if let Some(map) = &args.map {
+                let mut f = fs::File::create(map)?;
+                program.write_map(&mut f)?;
+            }
+            match args.format {
+                Format::Com => program.com_image(),
+                Format::Exe => program.exe_image(),
+                Format::Bin => Ok(program.bin_image()),
+            }
+        })
+        .map_err(|e| e.to_string())?;
+    fs::write(&args.output, image).map_err(|e| format!("{}: {e}", args.output.display()))
+}
+
+pub fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
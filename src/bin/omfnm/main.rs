@@ -0,0 +1,160 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use omflib::{
+    demangle_or_original, read_modules, OmfInfo, Symbol, SymbolBinding, SymbolKind, SymbolScope,
+};
+
+#[derive(Parser, Debug)]
+struct Args {
+    /// Only show global symbols
+    #[arg(short = 'g', long = "extern-only")]
+    extern_only: bool,
+    /// Only show undefined symbols
+    #[arg(short = 'u', long = "undefined-only")]
+    undefined_only: bool,
+    /// Only show defined symbols
+    #[arg(long)]
+    defined_only: bool,
+    /// Show C++ names in their readable form
+    #[arg(short = 'C', long)]
+    demangle: bool,
+    /// Sort by address instead of by name
+    #[arg(short = 'n', long = "numeric-sort")]
+    numeric_sort: bool,
+    /// Keep the order the symbols appear in the object
+    #[arg(short = 'p', long = "no-sort")]
+    no_sort: bool,
+    /// Reverse the sort order
+    #[arg(short = 'r', long = "reverse-sort")]
+    reverse_sort: bool,
+    /// Prefix each line with the file (and library member) name
+    #[arg(short = 'A', short_alias = 'o', long = "print-file-name")]
+    print_file_name: bool,
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+// The nm type letter: uppercase for global symbols, lowercase for local.
+fn type_letter(info: &OmfInfo, s: &Symbol) -> char {
+    let letter = match (&s.kind, s.binding) {
+        (SymbolKind::External | SymbolKind::ComdatExternal, _) => 'U',
+        (SymbolKind::Communal(_), _) => 'C',
+        (SymbolKind::Alias(_), _) => 'I',
+        (_, SymbolBinding::Absolute { .. }) => 'A',
+        (_, SymbolBinding::Segment { segment_index, .. }) => {
+            let class = segment_index
+                .resolve(info)
+                .and_then(|seg| seg.class_name_index.resolve(info))
+                .map(|c| c.to_ascii_uppercase())
+                .unwrap_or_default();
+            if class.ends_with("CODE") {
+                'T'
+            } else if class.ends_with("BSS") || class == "STACK" {
+                'B'
+            } else if class.ends_with("CONST") {
+                'R'
+            } else {
+                'D'
+            }
+        }
+        (_, SymbolBinding::Unbound) => 'T',
+    };
+    match s.scope {
+        SymbolScope::Global => letter,
+        SymbolScope::Local => letter.to_ascii_lowercase(),
+    }
+}
+
+struct Line {
+    value: Option<u32>,
+    letter: char,
+    name: String,
+}
+
+impl Args {
+    fn shows(&self, s: &Symbol) -> bool {
+        let undefined = matches!(s.kind, SymbolKind::External | SymbolKind::ComdatExternal);
+        !(self.extern_only && s.scope == SymbolScope::Local
+            || self.undefined_only && !undefined
+            || self.defined_only && undefined)
+    }
+
+    fn print(&self, prefix: &str, info: &OmfInfo) {
+        let mut lines: Vec<_> = info
+            .symbols
+            .iter()
+            .filter(|s| self.shows(s))
+            .map(|s| Line {
+                value: match s.kind {
+                    SymbolKind::Communal(length) => Some(length.size() as u32),
+                    SymbolKind::External | SymbolKind::ComdatExternal | SymbolKind::Alias(_) => {
+                        None
+                    }
+                    _ => Some(s.offset),
+                },
+                letter: type_letter(info, s),
+                name: if self.demangle {
+                    demangle_or_original(&s.name)
+                } else {
+                    s.name.clone()
+                },
+            })
+            .collect();
+        if !self.no_sort {
+            if self.numeric_sort {
+                lines.sort_by(|a, b| a.value.cmp(&b.value).then(a.name.cmp(&b.name)));
+            } else {
+                lines.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+            if self.reverse_sort {
+                lines.reverse();
+            }
+        }
+        for line in lines {
+            let value = match line.value {
+                Some(v) => format!("{v:08X}"),
+                None => " ".repeat(8),
+            };
+            println!("{prefix}{value} {} {}", line.letter, line.name);
+        }
+    }
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut status = ExitCode::SUCCESS;
+    let multiple = args.files.len() > 1;
+    for file in &args.files {
+        let modules = match fs::read(file)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| read_modules(&bytes).map_err(|e| e.to_string()))
+        {
+            Ok(modules) => modules,
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                status = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        for (member, module) in &modules {
+            let label = match member {
+                Some(member) => format!("{}({member})", file.display()),
+                None => file.display().to_string(),
+            };
+            if args.print_file_name {
+                args.print(&format!("{label}: "), module.info());
+                continue;
+            }
+            // Like nm, name each member of a library, and each file when
+            // there are several.
+            if let Some(member) = member {
+                println!("\n{member}:");
+            } else if multiple {
+                println!("\n{label}:");
+            }
+            args.print("", module.info());
+        }
+    }
+    status
+}
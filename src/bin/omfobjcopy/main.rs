@@ -0,0 +1,139 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, ValueEnum};
+use omflib::{
+    remove_segments, rename_class, rename_segment, rename_symbol, ChecksumMode, LibraryBuilder,
+    OmfLibrary, OmfModule, OmfWriter, RecordType, SegmentIndex,
+};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Checksum {
+    Computed,
+    Zero,
+    Preserve,
+}
+
+fn parse_rename(s: &str) -> Result<(String, String), String> {
+    let (old, new) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected OLD=NEW, got {s}"))?;
+    Ok((old.to_string(), new.to_string()))
+}
+
+fn parse_record_type(s: &str) -> Result<RecordType, String> {
+    let s = s.trim_end_matches(['h', 'H']);
+    u8::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map(RecordType::from_u8)
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Parser, Debug)]
+/// Copy an object or library, renaming and removing parts of each module
+/// along the way.
+struct Args {
+    /// Rename a segment (OLD=NEW)
+    #[arg(long, value_name = "OLD=NEW", value_parser = parse_rename)]
+    rename_segment: Vec<(String, String)>,
+    /// Rename a segment class (OLD=NEW)
+    #[arg(long, value_name = "OLD=NEW", value_parser = parse_rename)]
+    rename_class: Vec<(String, String)>,
+    /// Rename a symbol everywhere it is defined or referred to (OLD=NEW)
+    #[arg(long, value_name = "OLD=NEW", value_parser = parse_rename)]
+    redefine_sym: Vec<(String, String)>,
+    /// Keep only the named segments and what belongs to them
+    #[arg(short = 'j', long, value_name = "SEGMENT")]
+    only_segment: Vec<String>,
+    /// Remove the named segments and what belongs to them
+    #[arg(short = 'R', long, value_name = "SEGMENT")]
+    remove_segment: Vec<String>,
+    /// Remove every record of this type (in hex)
+    #[arg(long, value_name = "TYPE", value_parser = parse_record_type)]
+    remove_record: Vec<RecordType>,
+    /// What to put in record checksums: computed ones, zeros meaning none,
+    /// or the input's where they were zero or wrong
+    #[arg(long, value_enum, default_value = "computed")]
+    checksum: Checksum,
+    input: PathBuf,
+    /// Where to write the result; the input is replaced if not given
+    output: Option<PathBuf>,
+}
+
+fn segment_names(module: &OmfModule) -> Vec<String> {
+    module
+        .info()
+        .segments_with_names()
+        .map(|(_, name, _)| name.to_string())
+        .collect()
+}
+
+fn copy_module(args: &Args, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let module = OmfModule::read_one(&mut &bytes[..]).map_err(|e| e.to_string())?;
+    let segments = segment_names(&module);
+    let mut records = module.records;
+
+    let removed = |index: SegmentIndex| {
+        let name = &segments[index.0 as usize - 1];
+        args.remove_segment.contains(name)
+            || (!args.only_segment.is_empty() && !args.only_segment.contains(name))
+    };
+    if (1..=segments.len() as u16).map(SegmentIndex).any(removed) {
+        remove_segments(&mut records, removed).map_err(|e| e.to_string())?;
+    }
+    records.retain(|r| !args.remove_record.contains(&r.record_type));
+    for (old, new) in &args.rename_segment {
+        rename_segment(&mut records, old, new).map_err(|e| e.to_string())?;
+    }
+    for (old, new) in &args.rename_class {
+        rename_class(&mut records, old, new).map_err(|e| e.to_string())?;
+    }
+    for (old, new) in &args.redefine_sym {
+        rename_symbol(&mut records, old, new).map_err(|e| e.to_string())?;
+    }
+
+    let mut out = vec![];
+    let mut writer = OmfWriter::new(&mut out);
+    writer.set_checksum_mode(match args.checksum {
+        Checksum::Computed => ChecksumMode::Computed,
+        Checksum::Zero => ChecksumMode::Zero,
+        Checksum::Preserve => ChecksumMode::Preserve,
+    });
+    for record in &records {
+        writer.write_record(record).map_err(|e| e.to_string())?;
+    }
+    Ok(out)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let bytes = fs::read(&args.input).map_err(|e| e.to_string())?;
+    let out = if OmfLibrary::is_library(&bytes) {
+        let library = OmfLibrary::from_bytes(&bytes).map_err(|e| e.to_string())?;
+        let mut builder = LibraryBuilder::new();
+        builder
+            .set_page_size(library.page_size)
+            .map_err(|e| e.to_string())?;
+        builder.set_case_sensitive(library.flags & 1 != 0);
+        for m in &library.members {
+            let member = copy_module(args, &bytes[m.range.clone()])
+                .map_err(|e| format!("{}: {e}", m.name))?;
+            builder.add_member(member);
+        }
+        let mut out = vec![];
+        builder.write(&mut out).map_err(|e| e.to_string())?;
+        out
+    } else {
+        copy_module(args, &bytes)?
+    };
+    let path = args.output.as_ref().unwrap_or(&args.input);
+    fs::write(path, out).map_err(|e| e.to_string())
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {e}", args.input.display());
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use omflib::{read_modules, NameIndex, OmfInfo};
+
+#[derive(Parser, Debug)]
+/// List the code, data and uninitialized data sizes of objects and of each
+/// library member, from their segment lengths and classes.
+struct Args {
+    /// List each segment with its length and class instead
+    #[arg(short = 'A', long)]
+    segments: bool,
+    /// Add a line with the totals of all modules
+    #[arg(short, long)]
+    totals: bool,
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Sizes {
+    text: u32,
+    data: u32,
+    bss: u32,
+}
+
+fn name(info: &OmfInfo, index: NameIndex) -> &str {
+    index
+        .position()
+        .and_then(|i| info.names.get(i))
+        .map_or("", |n| n)
+}
+
+// Code and constants count as text, as `size` counts read-only data. Debug
+// information is left out.
+fn sizes(info: &OmfInfo) -> Sizes {
+    let mut sizes = Sizes::default();
+    for s in &info.segments {
+        let class = name(info, s.class_name_index).to_ascii_uppercase();
+        if matches!(class.as_str(), "DEBTYP" | "DEBSYM" | "DWARF")
+            || name(info, s.segment_name_index).starts_with("$$")
+        {
+            continue;
+        }
+        if class.ends_with("CODE") || class.ends_with("TEXT") || class.ends_with("CONST") {
+            sizes.text += s.length();
+        } else if class.ends_with("BSS") || class == "STACK" {
+            sizes.bss += s.length();
+        } else {
+            sizes.data += s.length();
+        }
+    }
+    sizes
+}
+
+fn print_sizes(sizes: Sizes, label: &str) {
+    let total = sizes.text + sizes.data + sizes.bss;
+    println!(
+        "{:>7} {:>7} {:>7} {total:>7} {total:>7x} {label}",
+        sizes.text, sizes.data, sizes.bss
+    );
+}
+
+fn print_segments(info: &OmfInfo, label: &str) {
+    println!("{label}:");
+    println!("{:<16} {:>7} class", "segment", "size");
+    let mut total = 0;
+    for s in &info.segments {
+        println!(
+            "{:<16} {:>7} {}",
+            name(info, s.segment_name_index),
+            s.length(),
+            name(info, s.class_name_index)
+        );
+        total += s.length();
+    }
+    println!("{:<16} {total:>7}", "Total");
+    println!();
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    let mut status = ExitCode::SUCCESS;
+    let mut totals = Sizes::default();
+    if !args.segments {
+        println!("   text    data     bss     dec     hex filename");
+    }
+    for file in &args.files {
+        let modules = match fs::read(file)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| read_modules(&bytes).map_err(|e| e.to_string()))
+        {
+            Ok(modules) => modules,
+            Err(e) => {
+                eprintln!("{}: {e}", file.display());
+                status = ExitCode::FAILURE;
+                continue;
+            }
+        };
+        for (member, module) in &modules {
+            let label = match member {
+                Some(member) => format!("{member} (ex {})", file.display()),
+                None => file.display().to_string(),
+            };
+            let info = module.info();
+            if args.segments {
+                print_segments(info, &label);
+                continue;
+            }
+            let s = sizes(info);
+            print_sizes(s, &label);
+            totals.text += s.text;
+            totals.data += s.data;
+            totals.bss += s.bss;
+        }
+    }
+    if args.totals && !args.segments {
+        print_sizes(totals, "(TOTALS)");
+    }
+    status
+}
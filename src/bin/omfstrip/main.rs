@@ -0,0 +1,155 @@
+use std::{collections::HashSet, fs, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use omflib::{
+    remove_segments, LibraryBuilder, NameIndex, OmfLibrary, OmfModule, OmfRecordData, OmfWriter,
+    RecordType, SegmentIndex,
+};
+
+// Comment classes a linker or librarian needs to get the same result:
+// DOSSEG, default libraries, OMF extensions, new-style libraries, link
+// pass separator, LIBMOD, EXESTR, INCERR, NOPAD, WKEXT and LZEXT.
+const REQUIRED_COMMENTS: &[u8] = &[
+    0x9D, 0x9E, 0x9F, 0xA0, 0xA2, 0xA3, 0xA4, 0xA6, 0xA7, 0xA8, 0xA9,
+];
+
+// Classes compilers give to segments holding debug information.
+const DEBUG_CLASSES: &[&str] = &["DEBTYP", "DEBSYM", "DWARF"];
+
+#[derive(Parser, Debug)]
+/// Remove comments, line numbers, debug information and local symbols from
+/// objects and libraries. With none of the options, removes all of them.
+struct Args {
+    /// Remove comments other than those needed for linking or marked no-purge
+    #[arg(long)]
+    comments: bool,
+    /// Remove line number records
+    #[arg(long)]
+    lines: bool,
+    /// Remove debug segments
+    #[arg(long)]
+    debug: bool,
+    /// Remove local publics that no LEXTDEF refers to
+    #[arg(long)]
+    locals: bool,
+    /// Keep comments of this class (in hex) as well
+    #[arg(long, value_name = "CLASS", value_parser = parse_class)]
+    keep_comment: Vec<u8>,
+    /// Write the result here instead of replacing the input
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    file: PathBuf,
+}
+
+fn parse_class(s: &str) -> Result<u8, String> {
+    let s = s.trim_end_matches(['h', 'H']);
+    u8::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|e| e.to_string())
+}
+
+fn is_debug_segment(module: &OmfModule, segment_index: SegmentIndex) -> bool {
+    let info = module.info();
+    let Ok(segment) = segment_index.resolve(info) else {
+        return false;
+    };
+    let name = |index: NameIndex| index.resolve(info).ok();
+    let class = name(segment.class_name_index);
+    let segment_name = name(segment.segment_name_index);
+    class.is_some_and(|c| DEBUG_CLASSES.iter().any(|d| c.eq_ignore_ascii_case(d)))
+        || segment_name.is_some_and(|n| n.starts_with("$$"))
+}
+
+fn strip_module(args: &Args, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let everything = !(args.comments || args.lines || args.debug || args.locals);
+    let module = OmfModule::read_one(&mut &bytes[..]).map_err(|e| e.to_string())?;
+    let segments = module.info().segments.len() as u16;
+    let debug: Vec<SegmentIndex> = (1..=segments)
+        .map(SegmentIndex)
+        .filter(|&i| is_debug_segment(&module, i))
+        .collect();
+    let mut records = module.records;
+    if (args.debug || everything) && !debug.is_empty() {
+        remove_segments(&mut records, |i| debug.contains(&i)).map_err(|e| e.to_string())?;
+    }
+    // Local publics an LEXTDEF refers to are needed to link the module.
+    let mut local_externals = HashSet::new();
+    let mut raw_local_externals = false;
+    for r in &records {
+        match &r.data {
+            OmfRecordData::LExtDef { names } => {
+                local_externals.extend(names.iter().map(|n| n.name.clone()))
+            }
+            OmfRecordData::Unknown { .. } if r.record_type == RecordType::LExtDef32 => {
+                raw_local_externals = true
+            }
+            _ => (),
+        }
+    }
+    let strip_locals = args.locals || everything;
+    records.retain_mut(|r| match &mut r.data {
+        OmfRecordData::Coment {
+            comment_type,
+            comment_class,
+            ..
+        } if args.comments || everything => {
+            comment_type.no_purge
+                || REQUIRED_COMMENTS.contains(comment_class)
+                || args.keep_comment.contains(comment_class)
+        }
+        OmfRecordData::LinNum { .. } | OmfRecordData::LinSym { .. } => !(args.lines || everything),
+        OmfRecordData::LPubDef { names, .. } if strip_locals && !raw_local_externals => {
+            names.retain(|n| local_externals.contains(&n.name));
+            !names.is_empty()
+        }
+        OmfRecordData::Unknown { .. } => match r.record_type {
+            RecordType::LinNum32 | RecordType::LinSym32 => !(args.lines || everything),
+            // Their names can't be checked against the LEXTDEFs.
+            RecordType::LPubDef32 => {
+                !strip_locals || raw_local_externals || !local_externals.is_empty()
+            }
+            _ => true,
+        },
+        _ => true,
+    });
+
+    let mut out = vec![];
+    let mut writer = OmfWriter::new(&mut out);
+    for record in &records {
+        writer.write_record(record).map_err(|e| e.to_string())?;
+    }
+    Ok(out)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let bytes = fs::read(&args.file).map_err(|e| e.to_string())?;
+    let out = if OmfLibrary::is_library(&bytes) {
+        let library = OmfLibrary::from_bytes(&bytes).map_err(|e| e.to_string())?;
+        let mut builder = LibraryBuilder::new();
+        builder
+            .set_page_size(library.page_size)
+            .map_err(|e| e.to_string())?;
+        builder.set_case_sensitive(library.flags & 1 != 0);
+        for m in &library.members {
+            let member = strip_module(args, &bytes[m.range.clone()])
+                .map_err(|e| format!("{}: {e}", m.name))?;
+            builder.add_member(member);
+        }
+        let mut out = vec![];
+        builder.write(&mut out).map_err(|e| e.to_string())?;
+        out
+    } else {
+        strip_module(args, &bytes)?
+    };
+    let path = args.output.as_ref().unwrap_or(&args.file);
+    fs::write(path, out).map_err(|e| e.to_string())
+}
+
+pub fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}: {e}", args.file.display());
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{
+    debug::DebugBytes, error::OmfError, DebugFormat, DebugInfo, DebugSymbol, DebugSymbolKind,
+    LineEntry, OmfModule, OmfRecordData, SegmentIndex,
+};
+
+// Comment classes Turbo Debugger's information travels in, alongside the
+// usual LINNUM records.
+const COMENT_PUBLIC_TYPE: u8 = 0xE1;
+const COMENT_BEGIN_SCOPE: u8 = 0xE5;
+const COMENT_LOCALS: u8 = 0xE6;
+const COMENT_END_SCOPE: u8 = 0xE7;
+const COMENT_SOURCE_FILE: u8 = 0xE8;
+
+// Storage classes of locals, in the low bits of each one's info byte.
+const LOCAL_STATIC: u8 = 0;
+const LOCAL_STACK: u8 = 2;
+const LOCAL_REGISTER: u8 = 4;
+
+fn is_borland_debug(comment_class: u8) -> bool {
+    (0xE0..=COMENT_SOURCE_FILE).contains(&comment_class)
+}
+
+pub(crate) fn read_borland(module: &OmfModule) -> Result<Option<DebugInfo>, OmfError> {
+    let comments = module
+        .records
+        .iter()
+        .filter_map(|record| match &record.data {
+            OmfRecordData::Coment {
+                comment_class,
+                comment_bytes,
+                ..
+            } if is_borland_debug(*comment_class) => Some((*comment_class, &comment_bytes[..])),
+            _ => None,
+        });
+    let mut symbols = vec![];
+    // Where each scope still open starts, to give its block a length when
+    // it ends.
+    let mut scopes = vec![];
+    let mut found = false;
+    for (comment_class, bytes) in comments {
+        found = true;
+        let mut r = DebugBytes::new(bytes);
+        match comment_class {
+            COMENT_PUBLIC_TYPE => read_public_types(&mut r, &mut symbols)?,
+            COMENT_BEGIN_SCOPE => {
+                let segment = SegmentIndex(r.index()?);
+                let offset = r.u16()? as u32;
+                scopes.push(symbols.len());
+                symbols.push(DebugSymbol {
+                    name: String::new(),
+                    kind: DebugSymbolKind::Block { length: 0 },
+                    segment: Some(segment),
+                    offset,
+                    type_index: 0,
+                });
+            }
+            COMENT_LOCALS => read_locals(&mut r, &mut symbols)?,
+            COMENT_END_SCOPE => {
+                let end = r.u16()? as u32;
+                if let Some(start) = scopes.pop() {
+                    let block = &mut symbols[start];
+                    block.kind = DebugSymbolKind::Block {
+                        length: end.saturating_sub(block.offset),
+                    };
+                }
+                symbols.push(DebugSymbol {
+                    name: String::new(),
+                    kind: DebugSymbolKind::End,
+                    segment: None,
+                    offset: 0,
+                    type_index: 0,
+                });
+            }
+            _ => (),
+        }
+    }
+    if !found {
+        return Ok(None);
+    }
+    Ok(Some(DebugInfo {
+        format: DebugFormat::Borland,
+        symbols,
+        types: vec![],
+        lines: read_lines(module),
+    }))
+}
+
+// Laid out like PUBDEF, with each public's type after its offset.
+fn read_public_types(r: &mut DebugBytes, symbols: &mut Vec<DebugSymbol>) -> Result<(), OmfError> {
+    r.index()?;
+    let segment = r.index()?;
+    if segment == 0 {
+        r.u16()?;
+    }
+    while r.unread() > 0 {
+        let name = r.name()?;
+        let offset = r.u16()? as u32;
+        let type_index = r.index()? as u32;
+        symbols.push(DebugSymbol {
+            name,
+            kind: DebugSymbolKind::Public,
+            segment: (segment != 0).then_some(SegmentIndex(segment)),
+            offset,
+            type_index,
+        });
+    }
+    Ok(())
+}
+
+// Each local is a name, a type and an info byte whose storage class says
+// what follows. A local of a class not handled here ends the comment, as
+// where the next one starts isn't known.
+fn read_locals(r: &mut DebugBytes, symbols: &mut Vec<DebugSymbol>) -> Result<(), OmfError> {
+    while r.unread() > 0 {
+        let name = r.name()?;
+        let type_index = r.index()? as u32;
+        let mut symbol = DebugSymbol {
+            name,
+            kind: DebugSymbolKind::Data { global: false },
+            segment: None,
+            offset: 0,
+            type_index,
+        };
+        match r.u8()? & 7 {
+            LOCAL_STATIC => {
+                symbol.segment = Some(SegmentIndex(r.index()?));
+                symbol.offset = r.u16()? as u32;
+            }
+            LOCAL_STACK => {
+                symbol.kind = DebugSymbolKind::Local {
+                    frame_offset: r.u16()? as i16 as i32,
+                }
+            }
+            LOCAL_REGISTER => {
+                symbol.kind = DebugSymbolKind::Register {
+                    register: r.u8()? as u16,
+                }
+            }
+            _ => {
+                symbols.push(symbol);
+                break;
+            }
+        }
+        symbols.push(symbol);
+    }
+    Ok(())
+}
+
+// LINNUM entries, attributed to the source file named by the last source
+// file comment, which changes as code from include files is compiled.
+fn read_lines(module: &OmfModule) -> Vec<LineEntry> {
+    let mut lines = vec![];
+    let mut file = String::new();
+    for record in &module.records {
+        match &record.data {
+            OmfRecordData::THeadr { name } if file.is_empty() => file = name.clone(),
+            OmfRecordData::Coment {
+                comment_class: COMENT_SOURCE_FILE,
+                comment_bytes,
+                ..
+            } => {
+                // A file number, the name, then its DOS time and date.
+                let mut r = DebugBytes::new(comment_bytes);
+                if let Ok(name) = r.u8().and_then(|_| r.name()) {
+                    file = name;
+                }
+            }
+            OmfRecordData::LinNum {
+                base_segment_index,
+                lines: numbers,
+                ..
+            } => lines.extend(numbers.iter().map(|l| LineEntry {
+                file: file.clone(),
+                line: l.line_number,
+                segment_index: *base_segment_index,
+                comdat: None,
+                offset: l.line_number_offset as u32,
+            })),
+            _ => (),
+        }
+    }
+    lines
+}
@@ -0,0 +1,159 @@
+use std::io::{self, Write};
+
+use crate::{
+    error::OmfError, OmfModule, RelocationRef, SegmentIndex, SymbolBinding, SymbolKind, SymbolTable,
+};
+
+// A call or jump from one function to another, found where the caller's
+// code has a self-relative fixup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallEdge {
+    // Indices into the graph's nodes.
+    pub caller: usize,
+    pub callee: usize,
+    // Where the fixup is in the module it came from.
+    pub segment_index: SegmentIndex,
+    pub offset: u32,
+}
+
+// Functions are the publics in code segments, plus whatever externals they
+// call. Code before a segment's first public is named after the segment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallGraph {
+    pub nodes: Vec<String>,
+    pub edges: Vec<CallEdge>,
+}
+
+// The publics in a segment, sorted by offset, to find which function an
+// offset falls in.
+fn segment_publics(symbols: &SymbolTable, segment: SegmentIndex) -> Vec<(u32, &str)> {
+    let mut publics: Vec<(u32, &str)> = symbols
+        .iter()
+        .filter(|s| {
+            s.kind == SymbolKind::Public
+                && matches!(s.binding, SymbolBinding::Segment { segment_index, .. }
+                    if segment_index == segment)
+        })
+        .map(|s| (s.offset, s.name.as_str()))
+        .collect();
+    publics.sort();
+    publics
+}
+
+fn containing<'a>(publics: &[(u32, &'a str)], offset: u32) -> Option<&'a str> {
+    let i = publics.partition_point(|&(start, _)| start <= offset);
+    i.checked_sub(1).map(|i| publics[i].1)
+}
+
+impl CallGraph {
+    pub fn new() -> CallGraph {
+        CallGraph::default()
+    }
+
+    // The index of the node with this name, adding it if it is new.
+    pub fn node(&mut self, name: &str) -> usize {
+        match self.nodes.iter().position(|n| n == name) {
+            Some(i) => i,
+            None => {
+                self.nodes.push(name.to_string());
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    // Adds the calls made by a module's code. Adding several modules joins
+    // their graphs through the names they share.
+    pub fn add_module(&mut self, module: &OmfModule) -> Result<(), OmfError> {
+        let info = module.info();
+        let symbols = module.symbols();
+        let segment_name = |i: SegmentIndex| {
+            info.get_segment(i)
+                .and_then(|s| info.name_from_index(s.segment_name_index))
+        };
+        for relocation in module.relocations()? {
+            if relocation.segment_relative {
+                continue;
+            }
+            if !info
+                .class_of(relocation.segment_index)?
+                .to_ascii_uppercase()
+                .ends_with("CODE")
+            {
+                continue;
+            }
+            let callee = match &relocation.target {
+                RelocationRef::External(name) => name.to_string(),
+                RelocationRef::Segment(i) => {
+                    let publics = segment_publics(symbols, *i);
+                    match containing(&publics, relocation.target_displacement) {
+                        Some(name) => name.to_string(),
+                        None => segment_name(*i)?.to_string(),
+                    }
+                }
+                _ => continue,
+            };
+            let publics = segment_publics(symbols, relocation.segment_index);
+            let caller = match containing(&publics, relocation.offset) {
+                Some(name) => name.to_string(),
+                None => segment_name(relocation.segment_index)?.to_string(),
+            };
+            let edge = CallEdge {
+                caller: self.node(&caller),
+                callee: self.node(&callee),
+                segment_index: relocation.segment_index,
+                offset: relocation.offset,
+            };
+            self.edges.push(edge);
+        }
+        Ok(())
+    }
+
+    // The names of the functions `name` calls, each once.
+    pub fn callees(&self, name: &str) -> Vec<&str> {
+        self.neighbours(name, |e| (e.caller, e.callee))
+    }
+
+    // The names of the functions that call `name`, each once.
+    pub fn callers(&self, name: &str) -> Vec<&str> {
+        self.neighbours(name, |e| (e.callee, e.caller))
+    }
+
+    fn neighbours(&self, name: &str, ends: impl Fn(&CallEdge) -> (usize, usize)) -> Vec<&str> {
+        let Some(node) = self.nodes.iter().position(|n| n == name) else {
+            return vec![];
+        };
+        let mut found = vec![];
+        for (from, to) in self.edges.iter().map(ends) {
+            if from == node && !found.contains(&to) {
+                found.push(to);
+            }
+        }
+        found.iter().map(|&i| self.nodes[i].as_str()).collect()
+    }
+
+    // Writes the graph for Graphviz, with one edge however many times one
+    // function calls another.
+    pub fn write_dot(&self, w: &mut dyn Write) -> io::Result<()> {
+        writeln!(w, "digraph calls {{")?;
+        for (i, name) in self.nodes.iter().enumerate() {
+            writeln!(w, "  n{i} [label=\"{}\"];", name.replace('"', "\\\""))?;
+        }
+        let mut written = vec![];
+        for edge in &self.edges {
+            if written.contains(&(edge.caller, edge.callee)) {
+                continue;
+            }
+            written.push((edge.caller, edge.callee));
+            writeln!(w, "  n{} -> n{};", edge.caller, edge.callee)?;
+        }
+        writeln!(w, "}}")
+    }
+}
+
+impl OmfModule {
+    pub fn call_graph(&self) -> Result<CallGraph, OmfError> {
+        let mut graph = CallGraph::new();
+        graph.add_module(self)?;
+        Ok(graph)
+    }
+}
@@ -0,0 +1,268 @@
+// A C interface for embedding the reader in C programs; include/omflib.h
+// declares it. Handles come from omf_open or omf_open_bytes and must be
+// released with omf_free. Pointers passed in must be valid for the call,
+// and the strings handed out stay valid until the handle is freed, except
+// for those from omf_record_text, which belong to the caller.
+#![allow(clippy::missing_safety_doc)]
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr, slice,
+};
+
+use crate::{
+    error::OmfError, NameIndex, OmfModule, SegmentIndex, SymbolBinding, SymbolKind, SymbolScope,
+};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+fn set_error(message: &str) {
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(c_string(message)));
+}
+
+pub struct OmfHandle {
+    module: OmfModule,
+    symbol_names: Vec<CString>,
+    segment_names: Vec<(CString, CString)>,
+}
+
+#[repr(C)]
+pub struct OmfRecordInfo {
+    pub offset: usize,
+    pub record_type: u8,
+    pub length: usize,
+    pub checksum_valid: bool,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub enum OmfSymbolKind {
+    Public,
+    External,
+    Communal,
+    ComdatExternal,
+    Comdat,
+    Alias,
+}
+
+#[repr(C)]
+pub struct OmfSymbolInfo {
+    pub name: *const c_char,
+    pub kind: OmfSymbolKind,
+    pub local: bool,
+    // 0 for symbols not defined in a segment.
+    pub segment_index: SegmentIndex,
+    pub offset: u32,
+}
+
+#[repr(C)]
+pub struct OmfSegmentInfo {
+    pub name: *const c_char,
+    pub class_name: *const c_char,
+    pub length: u32,
+    pub use32: bool,
+}
+
+fn open(read: impl FnOnce() -> Result<OmfModule, OmfError>) -> *mut OmfHandle {
+    let module = match catch_unwind(AssertUnwindSafe(read)) {
+        Ok(Ok(module)) => module,
+        Ok(Err(e)) => {
+            set_error(&e.to_string());
+            return ptr::null_mut();
+        }
+        Err(_) => {
+            set_error("internal error while reading");
+            return ptr::null_mut();
+        }
+    };
+    let info = module.info();
+    let name = |index: NameIndex| {
+        index
+            .resolve(info)
+            .map(|n| c_string(&n))
+            .unwrap_or_default()
+    };
+    let symbol_names = module.symbols().iter().map(|s| c_string(&s.name)).collect();
+    let segment_names = info
+        .segments
+        .iter()
+        .map(|s| (name(s.segment_name_index), name(s.class_name_index)))
+        .collect();
+    Box::into_raw(Box::new(OmfHandle {
+        module,
+        symbol_names,
+        segment_names,
+    }))
+}
+
+// Reads the object module in the file at `path`. Returns null on failure;
+// omf_last_error says why.
+#[no_mangle]
+pub unsafe extern "C" fn omf_open(path: *const c_char) -> *mut OmfHandle {
+    if path.is_null() {
+        set_error("null path");
+        return ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        set_error("path is not valid UTF-8");
+        return ptr::null_mut();
+    };
+    open(|| {
+        let mut f = std::fs::File::open(path)?;
+        OmfModule::read(&mut f)
+    })
+}
+
+// Reads an object module from `len` bytes at `data`, which are copied.
+#[no_mangle]
+pub unsafe extern "C" fn omf_open_bytes(data: *const u8, len: usize) -> *mut OmfHandle {
+    if data.is_null() && len != 0 {
+        set_error("null data");
+        return ptr::null_mut();
+    }
+    let bytes = if len == 0 {
+        &[][..]
+    } else {
+        slice::from_raw_parts(data, len)
+    };
+    open(|| OmfModule::read(&mut &bytes[..]))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn omf_free(handle: *mut OmfHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+// The message for the last failure on this thread, or null.
+#[no_mangle]
+pub extern "C" fn omf_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn omf_record_count(handle: *const OmfHandle) -> usize {
+    handle.as_ref().map_or(0, |h| h.module.records.len())
+}
+
+// Fills `out` with record `index`, counting from 0. Returns false when there
+// is no such record.
+#[no_mangle]
+pub unsafe extern "C" fn omf_record(
+    handle: *const OmfHandle,
+    index: usize,
+    out: *mut OmfRecordInfo,
+) -> bool {
+    let (Some(h), Some(out)) = (handle.as_ref(), out.as_mut()) else {
+        return false;
+    };
+    let Some(record) = h.module.records.get(index) else {
+        return false;
+    };
+    *out = OmfRecordInfo {
+        offset: record.offset,
+        record_type: record.record_type.to_u8(),
+        length: record.record_length,
+        checksum_valid: record.checksum_valid,
+    };
+    true
+}
+
+// Describes record `index` as omfdump does. The string must be released with
+// omf_string_free.
+#[no_mangle]
+pub unsafe extern "C" fn omf_record_text(handle: *const OmfHandle, index: usize) -> *mut c_char {
+    let Some(h) = handle.as_ref() else {
+        return ptr::null_mut();
+    };
+    match h.module.records.get(index) {
+        Some(record) => c_string(&record.display_with(h.module.info()).to_string()).into_raw(),
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn omf_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn omf_symbol_count(handle: *const OmfHandle) -> usize {
+    handle.as_ref().map_or(0, |h| h.module.symbols().len())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn omf_symbol(
+    handle: *const OmfHandle,
+    index: usize,
+    out: *mut OmfSymbolInfo,
+) -> bool {
+    let (Some(h), Some(out)) = (handle.as_ref(), out.as_mut()) else {
+        return false;
+    };
+    let Some(symbol) = h.module.symbols().iter().nth(index) else {
+        return false;
+    };
+    let kind = match symbol.kind {
+        SymbolKind::Public => OmfSymbolKind::Public,
+        SymbolKind::External => OmfSymbolKind::External,
+        SymbolKind::Communal(_) => OmfSymbolKind::Communal,
+        SymbolKind::ComdatExternal => OmfSymbolKind::ComdatExternal,
+        SymbolKind::Comdat => OmfSymbolKind::Comdat,
+        SymbolKind::Alias(_) => OmfSymbolKind::Alias,
+    };
+    let segment_index = match symbol.binding {
+        SymbolBinding::Segment { segment_index, .. } => segment_index,
+        _ => SegmentIndex::NONE,
+    };
+    *out = OmfSymbolInfo {
+        name: h.symbol_names[index].as_ptr(),
+        kind,
+        local: symbol.scope == SymbolScope::Local,
+        segment_index,
+        offset: symbol.offset,
+    };
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn omf_segment_count(handle: *const OmfHandle) -> usize {
+    handle.as_ref().map_or(0, |h| h.segment_names.len())
+}
+
+// Fills `out` with segment `index`, counting from 0; its segment index in
+// records is one more.
+#[no_mangle]
+pub unsafe extern "C" fn omf_segment(
+    handle: *const OmfHandle,
+    index: usize,
+    out: *mut OmfSegmentInfo,
+) -> bool {
+    let (Some(h), Some(out)) = (handle.as_ref(), out.as_mut()) else {
+        return false;
+    };
+    let (Some(segment), Some((name, class_name))) = (
+        h.module.info().segments.get(index),
+        h.segment_names.get(index),
+    ) else {
+        return false;
+    };
+    *out = OmfSegmentInfo {
+        name: name.as_ptr(),
+        class_name: class_name.as_ptr(),
+        length: segment.length(),
+        use32: segment.segment_attributes.bd32bit,
+    };
+    true
+}
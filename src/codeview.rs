@@ -0,0 +1,214 @@
+use crate::{
+    build_line_entries,
+    debug::{read_types, DebugBytes, DebugSegments},
+    error::OmfError,
+    DebugFormat, DebugInfo, DebugSymbol, DebugSymbolKind, OmfModule, SegmentIndex,
+};
+
+const SYMBOLS_SEGMENT: &str = "$$SYMBOLS";
+const TYPES_SEGMENT: &str = "$$TYPES";
+
+// CodeView 4's signature. Later versions number their records differently
+// and are only recognized.
+const CV4_SIGNATURE: u32 = 1;
+
+// Indices below this are CodeView's primitive types, which have no
+// records; the index itself encodes the type.
+const FIRST_TYPE_INDEX: u32 = 0x1000;
+
+// Reads the signature at the start of a CodeView segment. Data written
+// before CV4 starts straight with its records.
+fn signature(data: &[u8]) -> u32 {
+    match data.get(..4) {
+        Some(&[a, b, c, d]) if u32::from_le_bytes([a, b, c, d]) <= 4 => {
+            u32::from_le_bytes([a, b, c, d])
+        }
+        _ => 0,
+    }
+}
+
+pub(crate) fn read_codeview(
+    module: &OmfModule,
+    segments: &DebugSegments,
+) -> Result<Option<DebugInfo>, OmfError> {
+    let symbols = segments.by_name(SYMBOLS_SEGMENT);
+    let types = segments.by_name(TYPES_SEGMENT);
+    let signature = match (symbols, types) {
+        (None, None) => return Ok(None),
+        (Some((_, data)), _) | (None, Some((_, data))) => signature(data),
+    };
+    let mut info = DebugInfo {
+        format: DebugFormat::CodeView { signature },
+        symbols: vec![],
+        types: vec![],
+        lines: build_line_entries(&module.records, module.info())?,
+    };
+    if signature != CV4_SIGNATURE {
+        return Ok(Some(info));
+    }
+    if let Some((index, data)) = symbols {
+        info.symbols = read_symbols(segments, index, data)?;
+    }
+    if let Some((_, data)) = types {
+        // The types follow the signature.
+        info.types = read_types(data, 4, FIRST_TYPE_INDEX)?;
+    }
+    Ok(Some(info))
+}
+
+// An address in a symbol record: an offset, 16 or 32 bits, then a segment.
+fn address(
+    segments: &DebugSegments,
+    index: SegmentIndex,
+    r: &mut DebugBytes,
+    offset32: bool,
+) -> Result<(Option<SegmentIndex>, u32), OmfError> {
+    let offset_at = r.pos;
+    let offset = if offset32 { r.u32()? } else { r.u16()? as u32 };
+    let segment_at = r.pos;
+    r.u16()?;
+    Ok(segments.address(index, offset_at, segment_at, offset))
+}
+
+// A numeric leaf, as used for constants: values below 8000h are stored
+// directly, larger ones after a leaf saying how they are stored.
+fn numeric(r: &mut DebugBytes) -> Result<Option<i64>, OmfError> {
+    Ok(match r.u16()? {
+        value @ 0..=0x7FFF => Some(value as i64),
+        0x8000 => Some(r.u8()? as i8 as i64),
+        0x8001 => Some(r.u16()? as i16 as i64),
+        0x8002 => Some(r.u16()? as i64),
+        0x8003 => Some(r.u32()? as i32 as i64),
+        0x8004 => Some(r.u32()? as i64),
+        _ => None,
+    })
+}
+
+fn read_symbols(
+    segments: &DebugSegments,
+    index: SegmentIndex,
+    data: &[u8],
+) -> Result<Vec<DebugSymbol>, OmfError> {
+    let mut symbols = vec![];
+    let mut r = DebugBytes::new(data);
+    r.take(4)?;
+    while r.unread() >= 2 {
+        let length = r.u16()? as usize;
+        if length < 2 {
+            break;
+        }
+        let end = r.pos + length;
+        symbols.push(read_symbol(segments, index, &mut r)?);
+        r.pos = end;
+    }
+    Ok(symbols)
+}
+
+fn read_symbol(
+    segments: &DebugSegments,
+    index: SegmentIndex,
+    r: &mut DebugBytes,
+) -> Result<DebugSymbol, OmfError> {
+    let record_type = r.u16()?;
+    let mut symbol = DebugSymbol {
+        name: String::new(),
+        kind: DebugSymbolKind::Other(record_type),
+        segment: None,
+        offset: 0,
+        type_index: 0,
+    };
+    // Records numbered from 200h are the 16:32 forms of those from 100h.
+    let offset32 = record_type & 0xFF00 == 0x0200;
+    match record_type {
+        // S_REGISTER
+        0x0002 => {
+            symbol.type_index = r.u16()? as u32;
+            symbol.kind = DebugSymbolKind::Register { register: r.u16()? };
+            symbol.name = r.name()?;
+        }
+        // S_CONSTANT
+        0x0003 => {
+            symbol.type_index = r.u16()? as u32;
+            if let Some(value) = numeric(r)? {
+                symbol.kind = DebugSymbolKind::Constant { value };
+                symbol.name = r.name()?;
+            }
+        }
+        // S_UDT
+        0x0004 => {
+            symbol.type_index = r.u16()? as u32;
+            symbol.kind = DebugSymbolKind::Typedef;
+            symbol.name = r.name()?;
+        }
+        // S_END
+        0x0006 => symbol.kind = DebugSymbolKind::End,
+        // S_OBJNAME, after the signature of any precompiled types.
+        0x0009 => {
+            r.u32()?;
+            symbol.kind = DebugSymbolKind::ObjectName;
+            symbol.name = r.name()?;
+        }
+        // S_BPREL16, S_BPREL32
+        0x0100 | 0x0200 => {
+            let frame_offset = if offset32 {
+                r.u32()? as i32
+            } else {
+                r.u16()? as i16 as i32
+            };
+            symbol.kind = DebugSymbolKind::Local { frame_offset };
+            symbol.type_index = r.u16()? as u32;
+            symbol.name = r.name()?;
+        }
+        // S_LDATA, S_GDATA, S_PUB
+        0x0101..=0x0103 | 0x0201..=0x0203 => {
+            (symbol.segment, symbol.offset) = address(segments, index, r, offset32)?;
+            symbol.type_index = r.u16()? as u32;
+            symbol.kind = match record_type & 0xFF {
+                0x01 => DebugSymbolKind::Data { global: false },
+                0x02 => DebugSymbolKind::Data { global: true },
+                _ => DebugSymbolKind::Public,
+            };
+            symbol.name = r.name()?;
+        }
+        // S_LPROC, S_GPROC, after the parent, end and next pointers. The
+        // length is followed by where the prologue ends and the epilogue
+        // starts.
+        0x0104 | 0x0105 | 0x0204 | 0x0205 => {
+            r.take(12)?;
+            let length = if offset32 {
+                let length = r.u32()?;
+                r.take(8)?;
+                length
+            } else {
+                let length = r.u16()? as u32;
+                r.take(4)?;
+                length
+            };
+            (symbol.segment, symbol.offset) = address(segments, index, r, offset32)?;
+            symbol.type_index = r.u16()? as u32;
+            r.u8()?;
+            symbol.kind = DebugSymbolKind::Procedure {
+                global: record_type & 0xFF == 0x05,
+                length,
+            };
+            symbol.name = r.name()?;
+        }
+        // S_BLOCK, after the parent and end pointers.
+        0x0107 | 0x0207 => {
+            r.take(8)?;
+            let length = if offset32 { r.u32()? } else { r.u16()? as u32 };
+            (symbol.segment, symbol.offset) = address(segments, index, r, offset32)?;
+            symbol.kind = DebugSymbolKind::Block { length };
+            symbol.name = r.name()?;
+        }
+        // S_LABEL
+        0x0109 | 0x0209 => {
+            (symbol.segment, symbol.offset) = address(segments, index, r, offset32)?;
+            r.u8()?;
+            symbol.kind = DebugSymbolKind::Label;
+            symbol.name = r.name()?;
+        }
+        _ => (),
+    }
+    Ok(symbol)
+}
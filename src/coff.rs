@@ -0,0 +1,534 @@
+use std::collections::HashMap;
+
+use crate::{
+    elf::add_in_place,
+    error::OmfError,
+    record::{
+        Fixup, FixupData, FixupFrame, FixupTarget, LocationType, SegmentAlignment,
+        SegmentCombination,
+    },
+    symbols::{SymbolBinding, SymbolKind, SymbolScope},
+    ModuleBuilder, OmfModule, RelocationRef,
+};
+
+const IMAGE_FILE_MACHINE_I386: u16 = 0x14C;
+const IMAGE_FILE_32BIT_MACHINE: u16 = 0x100;
+const IMAGE_SCN_CNT_CODE: u32 = 0x20;
+const IMAGE_SCN_CNT_INITIALIZED_DATA: u32 = 0x40;
+const IMAGE_SCN_CNT_UNINITIALIZED_DATA: u32 = 0x80;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+const IMAGE_SCN_MEM_READ: u32 = 0x4000_0000;
+const IMAGE_SCN_MEM_WRITE: u32 = 0x8000_0000;
+const IMAGE_SYM_CLASS_EXTERNAL: u8 = 2;
+const IMAGE_SYM_CLASS_STATIC: u8 = 3;
+const IMAGE_SYM_ABSOLUTE: i16 = -1;
+const IMAGE_REL_I386_DIR16: u16 = 0x01;
+const IMAGE_REL_I386_REL16: u16 = 0x02;
+const IMAGE_REL_I386_DIR32: u16 = 0x06;
+const IMAGE_REL_I386_DIR32NB: u16 = 0x07;
+const IMAGE_REL_I386_SECTION: u16 = 0x0A;
+const IMAGE_REL_I386_REL32: u16 = 0x14;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoffFlavor {
+    I386,
+    I8086,
+}
+
+struct CoffSymbol {
+    name: String,
+    value: u32,
+    section: i16,
+    class: u8,
+    aux: Option<[u8; 18]>,
+}
+
+struct CoffSection {
+    name: String,
+    characteristics: u32,
+    size: u32,
+    data: Option<Vec<u8>>,
+    relocations: Vec<u8>,
+    relocation_count: usize,
+}
+
+struct CoffStrings {
+    data: Vec<u8>,
+}
+
+impl CoffStrings {
+    fn add(&mut self, s: &str) -> u32 {
+        let offset = self.data.len() as u32 + 4;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        offset
+    }
+}
+
+fn put16(v: &mut Vec<u8>, x: u16) {
+    v.extend_from_slice(&x.to_le_bytes());
+}
+
+fn put32(v: &mut Vec<u8>, x: u32) {
+    v.extend_from_slice(&x.to_le_bytes());
+}
+
+fn alignment_flag(bytes: u32) -> u32 {
+    match bytes {
+        0 | 1 => 0x0010_0000,
+        n => (n.trailing_zeros().min(13) + 1) << 20,
+    }
+}
+
+// Derives section characteristics from the segment class, following the
+// usual Microsoft naming: CODE classes are executable, CONST is read-only,
+// and BSS and STACK classes hold no initialized data.
+fn section_characteristics(class: &str, initialized: bool) -> u32 {
+    let class = class.to_ascii_uppercase();
+    if class.ends_with("CODE") {
+        IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ
+    } else if class.contains("CONST") {
+        IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ
+    } else if !initialized || class == "BSS" || class == "STACK" {
+        IMAGE_SCN_CNT_UNINITIALIZED_DATA | IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE
+    } else {
+        IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE
+    }
+}
+
+// Converts a module into a COFF object. Segments become sections with
+// characteristics derived from their class names, publics and externals
+// become EXTERNAL symbols (STATIC for local publics), and fixups become i386
+// relocations with their addends stored in the section contents.
+pub fn omf_to_coff(module: &OmfModule, flavor: CoffFlavor) -> Result<Vec<u8>, OmfError> {
+    let info = module.info();
+    let images = module.segment_images()?;
+    let mut strings = CoffStrings { data: vec![] };
+    let mut sections = vec![];
+    let mut symbols = vec![];
+
+    for (i, segment) in info.segments.iter().enumerate() {
+        let name = info.name(segment.segment_name_index)?.to_string();
+        let class = info.name(segment.class_name_index)?;
+        let image = &images[i];
+        let mut characteristics = section_characteristics(class, !image.initialized.is_empty());
+        characteristics |= alignment_flag(segment.segment_attributes.alignment.bytes());
+        let bss = characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0;
+        let mut aux = [0u8; 18];
+        aux[0..4].copy_from_slice(&segment.length().to_le_bytes());
+        symbols.push(CoffSymbol {
+            name: name.clone(),
+            value: 0,
+            section: i as i16 + 1,
+            class: IMAGE_SYM_CLASS_STATIC,
+            aux: Some(aux),
+        });
+        sections.push(CoffSection {
+            name,
+            characteristics,
+            size: segment.length(),
+            data: if bss { None } else { Some(image.data.clone()) },
+            relocations: vec![],
+            relocation_count: 0,
+        });
+    }
+
+    let mut symbol_index = HashMap::new();
+    let mut index = symbols.len() * 2;
+    for s in module.symbols().iter() {
+        let (value, section) = match (&s.kind, s.binding) {
+            (SymbolKind::Public, SymbolBinding::Segment { segment_index, .. }) => {
+                (s.offset, segment_index.0 as i16)
+            }
+            (SymbolKind::Public, SymbolBinding::Absolute { frame }) => {
+                (frame as u32 * 16 + s.offset, IMAGE_SYM_ABSOLUTE)
+            }
+            (SymbolKind::External | SymbolKind::ComdatExternal, _) => (0, 0),
+            (SymbolKind::Communal(length), _) => (length.size() as u32, 0),
+            _ => continue,
+        };
+        let local = s.scope == SymbolScope::Local && s.kind == SymbolKind::Public;
+        if !local {
+            if symbol_index.contains_key(&s.name) {
+                continue;
+            }
+            symbol_index.insert(s.name.clone(), index);
+        }
+        symbols.push(CoffSymbol {
+            name: s.name.clone(),
+            value,
+            section,
+            class: if local {
+                IMAGE_SYM_CLASS_STATIC
+            } else {
+                IMAGE_SYM_CLASS_EXTERNAL
+            },
+            aux: None,
+        });
+        index += 1;
+    }
+
+    for reloc in module.relocations()? {
+        let section = reloc.segment_index.0 as usize - 1;
+        let symbol = match &reloc.target {
+            RelocationRef::Segment(i) => (i.0 as usize - 1) * 2,
+            RelocationRef::External(name) => *symbol_index
+                .get(&**name)
+                .ok_or(OmfError::Value("external not in symbol table"))?,
+            RelocationRef::Group(_) => {
+                return Err(OmfError::Value(
+                    "group targets cannot be represented in COFF",
+                ))
+            }
+            RelocationRef::Absolute(_) => {
+                return Err(OmfError::Value(
+                    "absolute targets cannot be represented in COFF",
+                ))
+            }
+        };
+        let mut pieces: Vec<(u32, u16, usize)> = vec![];
+        match (reloc.kind, flavor, reloc.segment_relative) {
+            (LocationType::Offset | LocationType::LoaderOffset, _, true) => {
+                pieces.push((0, IMAGE_REL_I386_DIR16, 2))
+            }
+            (LocationType::Offset | LocationType::LoaderOffset, _, false) => {
+                pieces.push((0, IMAGE_REL_I386_REL16, 2))
+            }
+            (LocationType::Offset32 | LocationType::LoaderOffset32, _, true) => {
+                pieces.push((0, IMAGE_REL_I386_DIR32, 4))
+            }
+            (LocationType::Offset32 | LocationType::LoaderOffset32, _, false) => {
+                pieces.push((0, IMAGE_REL_I386_REL32, 4))
+            }
+            (LocationType::Base, _, true) => pieces.push((0, IMAGE_REL_I386_SECTION, 2)),
+            (LocationType::Pointer, CoffFlavor::I8086, true) => {
+                pieces.push((0, IMAGE_REL_I386_DIR16, 2));
+                pieces.push((2, IMAGE_REL_I386_SECTION, 2));
+            }
+            (LocationType::Pointer48, CoffFlavor::I386, true) => {
+                pieces.push((0, IMAGE_REL_I386_DIR32, 4));
+                pieces.push((4, IMAGE_REL_I386_SECTION, 2));
+            }
+            _ => return Err(OmfError::Value("fixup type cannot be represented in COFF")),
+        }
+        let s = &mut sections[section];
+        for (delta, r_type, size) in pieces {
+            let offset = reloc.offset + delta;
+            if r_type != IMAGE_REL_I386_SECTION {
+                let data = s
+                    .data
+                    .as_mut()
+                    .ok_or(OmfError::Value("fixup in uninitialized segment"))?;
+                add_in_place(
+                    data,
+                    offset as usize,
+                    size,
+                    reloc.target_displacement as i64,
+                )?;
+            }
+            put32(&mut s.relocations, offset);
+            put32(&mut s.relocations, symbol as u32);
+            put16(&mut s.relocations, r_type);
+            s.relocation_count += 1;
+        }
+    }
+    for (i, s) in sections.iter().enumerate() {
+        if s.relocation_count > 0xFFFF {
+            return Err(OmfError::Value("too many relocations in section"));
+        }
+        if let Some(aux) = &mut symbols[i].aux {
+            aux[4..6].copy_from_slice(&(s.relocation_count as u16).to_le_bytes());
+        }
+    }
+
+    let mut out = vec![];
+    put16(&mut out, IMAGE_FILE_MACHINE_I386);
+    put16(&mut out, sections.len() as u16);
+    put32(&mut out, 0);
+    let symtab_pos = out.len();
+    put32(&mut out, 0);
+    put32(&mut out, index as u32);
+    put16(&mut out, 0);
+    put16(
+        &mut out,
+        match flavor {
+            CoffFlavor::I386 => IMAGE_FILE_32BIT_MACHINE,
+            CoffFlavor::I8086 => 0,
+        },
+    );
+
+    let mut pointer = (20 + sections.len() * 40) as u32;
+    for s in &sections {
+        let mut name = [0u8; 8];
+        if s.name.len() <= 8 {
+            name[..s.name.len()].copy_from_slice(s.name.as_bytes());
+        } else {
+            let long = format!("/{}", strings.add(&s.name));
+            name[..long.len()].copy_from_slice(long.as_bytes());
+        }
+        out.extend_from_slice(&name);
+        put32(&mut out, 0);
+        put32(&mut out, 0);
+        put32(&mut out, s.size);
+        let data_pointer = match &s.data {
+            Some(data) => {
+                let p = pointer;
+                pointer += data.len() as u32;
+                p
+            }
+            None => 0,
+        };
+        put32(&mut out, data_pointer);
+        let relocation_pointer = if s.relocation_count > 0 {
+            let p = pointer;
+            pointer += s.relocations.len() as u32;
+            p
+        } else {
+            0
+        };
+        put32(&mut out, relocation_pointer);
+        put32(&mut out, 0);
+        put16(&mut out, s.relocation_count as u16);
+        put16(&mut out, 0);
+        put32(&mut out, s.characteristics);
+    }
+    for s in &sections {
+        if let Some(data) = &s.data {
+            out.extend_from_slice(data);
+        }
+        out.extend_from_slice(&s.relocations);
+    }
+
+    let symtab = out.len() as u32;
+    out[symtab_pos..symtab_pos + 4].copy_from_slice(&symtab.to_le_bytes());
+    for s in &symbols {
+        if s.name.len() <= 8 {
+            let mut name = [0u8; 8];
+            name[..s.name.len()].copy_from_slice(s.name.as_bytes());
+            out.extend_from_slice(&name);
+        } else {
+            put32(&mut out, 0);
+            put32(&mut out, strings.add(&s.name));
+        }
+        put32(&mut out, s.value);
+        put16(&mut out, s.section as u16);
+        put16(&mut out, 0);
+        out.push(s.class);
+        out.push(s.aux.is_some() as u8);
+        if let Some(aux) = &s.aux {
+            out.extend_from_slice(aux);
+        }
+    }
+    put32(&mut out, strings.data.len() as u32 + 4);
+    out.extend_from_slice(&strings.data);
+    Ok(out)
+}
+
+struct CoffReadSection {
+    name: String,
+    size: u32,
+    data_pointer: u32,
+    relocation_pointer: u32,
+    relocation_count: u16,
+    characteristics: u32,
+}
+
+fn get16(data: &[u8], offset: usize) -> Result<u16, OmfError> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(OmfError::Value("truncated COFF object"))
+}
+
+fn get32(data: &[u8], offset: usize) -> Result<u32, OmfError> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(OmfError::Value("truncated COFF object"))
+}
+
+fn coff_string(strings: &[u8], offset: usize) -> Result<String, OmfError> {
+    let bytes = strings
+        .get(offset..)
+        .ok_or(OmfError::Value("COFF string offset out of range"))?;
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    Ok(String::from_utf8(bytes[..end].to_vec())?)
+}
+
+fn short_name(bytes: &[u8]) -> Result<String, OmfError> {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    Ok(String::from_utf8(bytes[..end].to_vec())?)
+}
+
+fn segment_alignment(characteristics: u32) -> SegmentAlignment {
+    match (characteristics >> 20) & 0xF {
+        1 => SegmentAlignment::RelocatableByteAligned,
+        2 => SegmentAlignment::RelocatableWordAligned,
+        3 => SegmentAlignment::RelocatableDWordAligned,
+        4 | 5 => SegmentAlignment::RelocatableParagraphAligned,
+        0 => SegmentAlignment::RelocatableParagraphAligned,
+        _ => SegmentAlignment::RelocatablePageAligned,
+    }
+}
+
+// Reads an i386 COFF object and builds the equivalent OMF module. Sections
+// become public segments with a class of CODE, DATA or BSS, external symbols
+// become PUBDEFs, EXTDEFs or COMDEFs, and relocations become fixups. Only
+// objects whose sections and offsets fit the 16-bit record forms are
+// supported.
+pub fn coff_to_omf(coff: &[u8], module_name: &str) -> Result<ModuleBuilder, OmfError> {
+    if get16(coff, 0)? != IMAGE_FILE_MACHINE_I386 {
+        return Err(OmfError::Value("not an i386 COFF object"));
+    }
+    let section_count = get16(coff, 2)? as usize;
+    let symtab = get32(coff, 8)? as usize;
+    let symbol_count = get32(coff, 12)? as usize;
+    let optional_header = get16(coff, 16)? as usize;
+    let use32 = get16(coff, 18)? & IMAGE_FILE_32BIT_MACHINE != 0;
+    // Checked before anything is sized by the symbol count.
+    let strings_start = symbol_count
+        .checked_mul(18)
+        .and_then(|n| n.checked_add(symtab))
+        .filter(|&end| end <= coff.len())
+        .ok_or(OmfError::Value("truncated COFF symbol table"))?;
+    let strings = &coff[strings_start..];
+
+    let mut sections = vec![];
+    for i in 0..section_count {
+        let h = 20 + optional_header + i * 40;
+        let raw_name = coff
+            .get(h..h + 8)
+            .ok_or(OmfError::Value("truncated COFF object"))?;
+        let name = if raw_name[0] == b'/' {
+            let offset = short_name(&raw_name[1..])?
+                .parse::<usize>()
+                .map_err(|_| OmfError::Value("bad COFF long section name"))?;
+            coff_string(strings, offset)?
+        } else {
+            short_name(raw_name)?
+        };
+        sections.push(CoffReadSection {
+            name,
+            size: get32(coff, h + 16)?,
+            data_pointer: get32(coff, h + 20)?,
+            relocation_pointer: get32(coff, h + 24)?,
+            relocation_count: get16(coff, h + 32)?,
+            characteristics: get32(coff, h + 36)?,
+        });
+    }
+
+    let mut builder = ModuleBuilder::new(module_name);
+    let mut segment_map = vec![None; sections.len()];
+    for (i, s) in sections.iter().enumerate() {
+        // Skip linker directives, debug information and removable sections.
+        if s.characteristics & 0xA00 != 0 || s.name.starts_with(".debug") {
+            continue;
+        }
+        if s.size > 0x10000 {
+            return Err(OmfError::Value("COFF section longer than 64K"));
+        }
+        let class = if s.characteristics & IMAGE_SCN_CNT_CODE != 0 {
+            "CODE"
+        } else if s.characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0 {
+            "BSS"
+        } else {
+            "DATA"
+        };
+        segment_map[i] = Some(builder.add_segment(
+            &s.name,
+            class,
+            segment_alignment(s.characteristics),
+            SegmentCombination::Public,
+            use32,
+            s.size,
+        )?);
+    }
+
+    // Each COFF symbol maps to a fixup target and a displacement from it.
+    let mut targets: Vec<Option<(FixupTarget, u32)>> = vec![None; symbol_count];
+    let mut i = 0;
+    while i < symbol_count {
+        let s = symtab + i * 18;
+        let raw = coff
+            .get(s..s + 18)
+            .ok_or(OmfError::Value("truncated COFF symbol table"))?;
+        let name = if raw[0..4] == [0, 0, 0, 0] {
+            coff_string(strings, get32(raw, 4)? as usize)?
+        } else {
+            short_name(&raw[0..8])?
+        };
+        let value = get32(raw, 8)?;
+        let section = get16(raw, 12)? as i16;
+        let class = raw[16];
+        let aux = raw[17] as usize;
+        if section > 0 {
+            let segment = segment_map
+                .get(section as usize - 1)
+                .ok_or(OmfError::Value(
+                    "COFF symbol in a section that doesn't exist",
+                ))?;
+            if let Some(segment) = *segment {
+                targets[i] = Some((FixupTarget::Segment(segment), value));
+                if class == IMAGE_SYM_CLASS_EXTERNAL {
+                    let offset = u16::try_from(value)
+                        .map_err(|_| OmfError::Value("public offset does not fit in 16 bits"))?;
+                    builder.add_public(&name, segment, offset);
+                }
+            }
+        } else if section == 0 && class == IMAGE_SYM_CLASS_EXTERNAL {
+            if value == 0 {
+                let index = builder.add_external(&name)?;
+                targets[i] = Some((FixupTarget::External(index), 0));
+            } else {
+                let index = builder.add_communal(&name, value)?;
+                targets[i] = Some((FixupTarget::External(index), 0));
+            }
+        }
+        i += 1 + aux;
+    }
+
+    for (i, s) in sections.iter().enumerate() {
+        let Some(segment) = segment_map[i] else {
+            continue;
+        };
+        if s.characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0 || s.data_pointer == 0 {
+            continue;
+        }
+        let data = s
+            .data_pointer
+            .checked_add(s.size)
+            .and_then(|end| coff.get(s.data_pointer as usize..end as usize))
+            .ok_or(OmfError::Value("truncated COFF section data"))?;
+        let mut fixups = vec![];
+        for r in 0..s.relocation_count as usize {
+            let p = s.relocation_pointer as usize + r * 10;
+            let offset = get32(coff, p)?;
+            let symbol = get32(coff, p + 4)? as usize;
+            let r_type = get16(coff, p + 8)?;
+            let (target, displacement) = targets
+                .get(symbol)
+                .copied()
+                .flatten()
+                .ok_or(OmfError::Value("relocation against unsupported symbol"))?;
+            let (location, segment_relative) = match r_type {
+                IMAGE_REL_I386_DIR16 => (LocationType::Offset, true),
+                IMAGE_REL_I386_REL16 => (LocationType::Offset, false),
+                IMAGE_REL_I386_DIR32 | IMAGE_REL_I386_DIR32NB => (LocationType::Offset32, true),
+                IMAGE_REL_I386_REL32 => (LocationType::Offset32, false),
+                IMAGE_REL_I386_SECTION => (LocationType::Base, true),
+                _ => return Err(OmfError::Value("unsupported COFF relocation type")),
+            };
+            fixups.push(Fixup {
+                segment_relative,
+                location,
+                data_record_offset: u16::try_from(offset)
+                    .map_err(|_| OmfError::Value("relocation offset does not fit in 16 bits"))?,
+                fix_data: FixupData {
+                    frame: FixupFrame::Target,
+                    target,
+                    target_displacement: Some(displacement),
+                },
+            });
+        }
+        builder.add_data(segment, 0, data, &fixups)?;
+    }
+    Ok(builder)
+}
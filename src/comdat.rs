@@ -0,0 +1,139 @@
+use crate::{
+    error::OmfError,
+    expand_iterated_data,
+    record::{ComdatData, ComdatSelection, OmfRecord, OmfRecordData},
+    OmfInfo, OmfModule,
+};
+
+#[derive(Debug, Clone)]
+pub struct ComdatContribution {
+    pub module: usize,
+    pub name: String,
+    pub local: bool,
+    pub selection: ComdatSelection,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComdatConflictKind {
+    Duplicate,
+    SelectionMismatch,
+    SizeMismatch,
+    ContentMismatch,
+}
+
+#[derive(Debug, Clone)]
+pub struct ComdatConflict {
+    pub name: String,
+    pub kind: ComdatConflictKind,
+    pub selected_module: usize,
+    pub conflicting_module: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ComdatResolution {
+    pub selected: Vec<ComdatContribution>,
+    pub discarded: Vec<ComdatContribution>,
+    pub conflicts: Vec<ComdatConflict>,
+}
+
+// Gathers the COMDATs of one module, joining continuation records onto the
+// COMDAT they continue.
+pub fn collect_comdats(
+    module: usize,
+    records: &[OmfRecord],
+    info: &OmfInfo,
+) -> Result<Vec<ComdatContribution>, OmfError> {
+    let mut comdats: Vec<ComdatContribution> = vec![];
+    for record in records {
+        let OmfRecordData::ComDat {
+            continuation,
+            local,
+            selection,
+            enumerated_data_offset,
+            public_name_index,
+            data,
+            ..
+        } = &record.data
+        else {
+            continue;
+        };
+        let name = info.name_from_index(*public_name_index)?;
+        let bytes = match data {
+            ComdatData::Enumerated(data) => data.clone(),
+            ComdatData::Iterated(blocks) => expand_iterated_data(blocks, 0x10000)?,
+        };
+        let contribution = if *continuation {
+            comdats
+                .iter_mut()
+                .rev()
+                .find(|c| c.name == *name)
+                .ok_or(OmfError::Value("comdat continuation without start"))?
+        } else {
+            comdats.push(ComdatContribution {
+                module,
+                name: name.to_string(),
+                local: *local,
+                selection: *selection,
+                data: vec![],
+            });
+            comdats.last_mut().unwrap()
+        };
+        let offset = *enumerated_data_offset as usize;
+        if contribution.data.len() < offset + bytes.len() {
+            contribution.data.resize(offset + bytes.len(), 0);
+        }
+        contribution.data[offset..offset + bytes.len()].copy_from_slice(&bytes);
+    }
+    Ok(comdats)
+}
+
+// Applies the COMDAT selection rules across modules. The first contribution
+// of a name wins; later ones are discarded, and recorded as conflicts when
+// the selection criteria say they should not have appeared. Local COMDATs
+// never take part in selection.
+pub fn resolve_comdats(modules: &[OmfModule]) -> Result<ComdatResolution, OmfError> {
+    let mut resolution = ComdatResolution::default();
+    for (m, module) in modules.iter().enumerate() {
+        for c in collect_comdats(m, &module.records, module.info())? {
+            if c.local {
+                resolution.selected.push(c);
+                continue;
+            }
+            let Some(selected) = resolution
+                .selected
+                .iter()
+                .find(|s| !s.local && s.name == c.name)
+            else {
+                resolution.selected.push(c);
+                continue;
+            };
+            let kind = if selected.selection != c.selection {
+                Some(ComdatConflictKind::SelectionMismatch)
+            } else {
+                match selected.selection {
+                    ComdatSelection::NoMatch => Some(ComdatConflictKind::Duplicate),
+                    ComdatSelection::PickAny => None,
+                    ComdatSelection::SameSize if selected.data.len() != c.data.len() => {
+                        Some(ComdatConflictKind::SizeMismatch)
+                    }
+                    ComdatSelection::SameSize => None,
+                    ComdatSelection::ExactMatch if selected.data != c.data => {
+                        Some(ComdatConflictKind::ContentMismatch)
+                    }
+                    ComdatSelection::ExactMatch => None,
+                }
+            };
+            if let Some(kind) = kind {
+                resolution.conflicts.push(ComdatConflict {
+                    name: c.name.clone(),
+                    kind,
+                    selected_module: selected.module,
+                    conflicting_module: c.module,
+                });
+            }
+            resolution.discarded.push(c);
+        }
+    }
+    Ok(resolution)
+}
@@ -0,0 +1,238 @@
+use std::{fmt::Display, io};
+
+use crate::{
+    borland::read_borland, codeview::read_codeview, error::OmfError, hll::read_hll, LineEntry,
+    OmfInfo, OmfModule, Relocation, RelocationRef, SegmentImage, SegmentIndex,
+};
+
+// The debug information formats a module's debug data can be decoded from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugFormat {
+    // Microsoft CodeView in $$SYMBOLS and $$TYPES. The signature is 1 for
+    // CV4 and 2 for the 32-bit CV5; 0 means the segments have none, as in
+    // CodeView before CV4.
+    CodeView { signature: u32 },
+    // Turbo Debugger's, in Borland comment classes.
+    Borland,
+    // IBM's HLL, from OS/2 compilers, in $$SYMBOLS and $$TYPES like
+    // CodeView.
+    Hll { version: u8 },
+}
+
+impl Display for DebugFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugFormat::CodeView { signature: 0 } => write!(f, "CodeView"),
+            DebugFormat::CodeView { signature: 1 } => write!(f, "CodeView 4"),
+            DebugFormat::CodeView { signature: 2 } => write!(f, "CodeView 5"),
+            DebugFormat::CodeView { signature } => write!(f, "CodeView, signature {signature}"),
+            DebugFormat::Borland => write!(f, "Turbo Debugger"),
+            DebugFormat::Hll { version } => write!(f, "IBM HLL version {version}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugSymbolKind {
+    Public,
+    Data { global: bool },
+    Procedure { global: bool, length: u32 },
+    Label,
+    Block { length: u32 },
+    // Closes the procedure or block last opened.
+    End,
+    // A variable at this offset from the frame pointer.
+    Local { frame_offset: i32 },
+    Register { register: u16 },
+    Constant { value: i64 },
+    Typedef,
+    // The object file the symbols came from.
+    ObjectName,
+    // A record of this type that isn't decoded.
+    Other(u16),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugSymbol {
+    pub name: String,
+    pub kind: DebugSymbolKind,
+    // Where the symbol is, once the fixups on the debug data are applied.
+    // Symbols without an address, or whose address is external, have no
+    // segment.
+    pub segment: Option<SegmentIndex>,
+    pub offset: u32,
+    pub type_index: u32,
+}
+
+// A type record, left encoded. `leaf` says what kind of type it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugType {
+    pub index: u32,
+    pub leaf: u16,
+    pub data: Vec<u8>,
+}
+
+// Reads the type records in a $$TYPES segment, after a header of
+// `header_length` bytes. Each is a 16-bit length, then a 16-bit leaf and
+// the rest of the type's data. The types are numbered from `first_index`.
+pub(crate) fn read_types(
+    data: &[u8],
+    header_length: usize,
+    first_index: u32,
+) -> Result<Vec<DebugType>, OmfError> {
+    let mut types = vec![];
+    let mut r = DebugBytes::new(data);
+    r.take(header_length)?;
+    while r.unread() >= 2 {
+        let length = r.u16()? as usize;
+        if length < 2 {
+            break;
+        }
+        let leaf = r.u16()?;
+        let data = r.take(length - 2)?.to_vec();
+        types.push(DebugType {
+            index: first_index + types.len() as u32,
+            leaf,
+            data,
+        });
+    }
+    Ok(types)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugInfo {
+    pub format: DebugFormat,
+    pub symbols: Vec<DebugSymbol>,
+    pub types: Vec<DebugType>,
+    pub lines: Vec<LineEntry>,
+}
+
+impl OmfModule {
+    // Decodes the module's debug information, if it has any in a format
+    // that is recognized.
+    pub fn debug_info(&self) -> Result<Option<DebugInfo>, OmfError> {
+        let segments = DebugSegments::new(self)?;
+        // HLL uses the same segments as CodeView, so is looked for first.
+        if let Some(info) = read_hll(self, &segments)? {
+            return Ok(Some(info));
+        }
+        if let Some(info) = read_codeview(self, &segments)? {
+            return Ok(Some(info));
+        }
+        read_borland(self)
+    }
+}
+
+// The reconstructed contents of a module's segments, and the fixups that
+// fill in the addresses compilers leave out of their debug data.
+pub(crate) struct DebugSegments<'a> {
+    info: &'a OmfInfo,
+    images: Vec<SegmentImage>,
+    relocations: Vec<Relocation>,
+}
+
+impl<'a> DebugSegments<'a> {
+    fn new(module: &'a OmfModule) -> Result<DebugSegments<'a>, OmfError> {
+        Ok(DebugSegments {
+            info: module.info(),
+            images: module.segment_images()?,
+            relocations: module.relocations()?,
+        })
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<(SegmentIndex, &[u8])> {
+        let (index, _) = self.info.segment_by_name(name)?;
+        Some((index, self.data(index)))
+    }
+
+    pub fn data(&self, index: SegmentIndex) -> &[u8] {
+        self.images
+            .iter()
+            .find(|image| image.segment_index == index)
+            .map_or(&[], |image| &image.data)
+    }
+
+    // Where the address stored at `offset_at` (its offset) and `segment_at`
+    // (its segment) in a debug segment points. The offset is `stored` plus
+    // the displacement of any fixup on it.
+    pub fn address(
+        &self,
+        segment: SegmentIndex,
+        offset_at: usize,
+        segment_at: usize,
+        stored: u32,
+    ) -> (Option<SegmentIndex>, u32) {
+        let fixup_at = |at: usize| {
+            self.relocations
+                .iter()
+                .find(|r| r.segment_index == segment && r.offset as usize == at)
+        };
+        if let Some(r) = fixup_at(offset_at) {
+            let target = match r.target {
+                RelocationRef::Segment(i) => Some(i),
+                _ => None,
+            };
+            return (target, stored.wrapping_add(r.target_displacement));
+        }
+        match fixup_at(segment_at).map(|r| &r.target) {
+            Some(RelocationRef::Segment(i)) => (Some(*i), stored),
+            _ => (None, stored),
+        }
+    }
+}
+
+// Reads little-endian fields from debug data, keeping track of where each
+// is so fixups on it can be found.
+pub(crate) struct DebugBytes<'a> {
+    data: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> DebugBytes<'a> {
+    pub fn new(data: &'a [u8]) -> DebugBytes<'a> {
+        DebugBytes { data, pos: 0 }
+    }
+
+    pub fn unread(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], OmfError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "debug data too short"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, OmfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> Result<u16, OmfError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, OmfError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // An OMF index: one byte, or two when the first has its top bit set.
+    pub fn index(&mut self) -> Result<u16, OmfError> {
+        let first = self.u8()?;
+        if first & 0x80 == 0 {
+            return Ok(first as u16);
+        }
+        Ok(((first as u16 & 0x7F) << 8) | self.u8()? as u16)
+    }
+
+    // A name with a length byte in front. Debug data predates any agreement
+    // on encodings, so bytes that aren't UTF-8 are replaced.
+    pub fn name(&mut self) -> Result<String, OmfError> {
+        let len = self.u8()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
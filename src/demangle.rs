@@ -0,0 +1,687 @@
+// Demangling of C++ names as produced by the Microsoft, Borland and Watcom
+// compilers. Only the common subset of each scheme is understood; names that
+// use anything else (templates, function pointers, and so on) are left
+// alone.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManglingScheme {
+    Microsoft,
+    Borland,
+    Watcom,
+}
+
+pub fn mangling_scheme(name: &str) -> Option<ManglingScheme> {
+    if name.starts_with("W?") {
+        Some(ManglingScheme::Watcom)
+    } else if name.starts_with('?') {
+        Some(ManglingScheme::Microsoft)
+    } else if name.starts_with('@') {
+        Some(ManglingScheme::Borland)
+    } else {
+        None
+    }
+}
+
+// Returns the readable form of a mangled name, or None if the name is not
+// mangled or uses an encoding this module does not understand.
+pub fn demangle(name: &str) -> Option<String> {
+    match mangling_scheme(name)? {
+        ManglingScheme::Microsoft => Microsoft::new(&name[1..]).demangle(),
+        ManglingScheme::Borland => demangle_borland(&name[1..]),
+        ManglingScheme::Watcom => Watcom::new(&name[2..]).demangle(),
+    }
+}
+
+// The demangled form of a name if there is one, otherwise the name itself.
+pub fn demangle_or_original(name: &str) -> String {
+    demangle(name).unwrap_or_else(|| name.to_string())
+}
+
+struct Cursor<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(s: &'a str) -> Cursor<'a> {
+        Cursor {
+            s: s.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.s[self.pos..].starts_with(prefix.as_bytes())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.s.len()
+    }
+
+    // Reads up to (and consumes) the terminator.
+    fn until(&mut self, terminator: u8) -> Option<&'a str> {
+        let len = self.s[self.pos..].iter().position(|&c| c == terminator)?;
+        let s = std::str::from_utf8(&self.s[self.pos..self.pos + len]).ok()?;
+        self.pos += len + 1;
+        Some(s)
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a str> {
+        let bytes = self.s.get(self.pos..self.pos + len)?;
+        self.pos += len;
+        std::str::from_utf8(bytes).ok()
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.s[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+}
+
+fn cv_prefix(constant: bool, volatile: bool) -> &'static str {
+    match (constant, volatile) {
+        (false, false) => "",
+        (true, false) => "const ",
+        (false, true) => "volatile ",
+        (true, true) => "const volatile ",
+    }
+}
+
+struct Microsoft<'a> {
+    c: Cursor<'a>,
+    names: Vec<String>,
+    args: Vec<String>,
+}
+
+impl<'a> Microsoft<'a> {
+    fn new(s: &'a str) -> Microsoft<'a> {
+        Microsoft {
+            c: Cursor::new(s),
+            names: vec![],
+            args: vec![],
+        }
+    }
+
+    fn operator_name(&mut self) -> Option<&'static str> {
+        let code = match self.c.next()? {
+            b'_' => (b'_', self.c.next()?),
+            c => (0, c),
+        };
+        Some(match code {
+            (0, b'0') => "",
+            (0, b'1') => "~",
+            (0, b'2') => "operator new",
+            (0, b'3') => "operator delete",
+            (0, b'4') => "operator=",
+            (0, b'5') => "operator>>",
+            (0, b'6') => "operator<<",
+            (0, b'7') => "operator!",
+            (0, b'8') => "operator==",
+            (0, b'9') => "operator!=",
+            (0, b'A') => "operator[]",
+            (0, b'C') => "operator->",
+            (0, b'D') => "operator*",
+            (0, b'E') => "operator++",
+            (0, b'F') => "operator--",
+            (0, b'G') => "operator-",
+            (0, b'H') => "operator+",
+            (0, b'I') => "operator&",
+            (0, b'J') => "operator->*",
+            (0, b'K') => "operator/",
+            (0, b'L') => "operator%",
+            (0, b'M') => "operator<",
+            (0, b'N') => "operator<=",
+            (0, b'O') => "operator>",
+            (0, b'P') => "operator>=",
+            (0, b'Q') => "operator,",
+            (0, b'R') => "operator()",
+            (0, b'S') => "operator~",
+            (0, b'T') => "operator^",
+            (0, b'U') => "operator|",
+            (0, b'V') => "operator&&",
+            (0, b'W') => "operator||",
+            (0, b'X') => "operator*=",
+            (0, b'Y') => "operator+=",
+            (0, b'Z') => "operator-=",
+            (b'_', b'0') => "operator/=",
+            (b'_', b'1') => "operator%=",
+            (b'_', b'2') => "operator>>=",
+            (b'_', b'3') => "operator<<=",
+            (b'_', b'4') => "operator&=",
+            (b'_', b'5') => "operator|=",
+            (b'_', b'6') => "operator^=",
+            (b'_', b'7') => "`vftable'",
+            (b'_', b'U') => "operator new[]",
+            (b'_', b'V') => "operator delete[]",
+            _ => return None,
+        })
+    }
+
+    // A plain name fragment, or a back reference to an earlier one.
+    fn fragment(&mut self) -> Option<String> {
+        match self.c.peek()? {
+            c @ b'0'..=b'9' => {
+                self.c.next();
+                self.names.get((c - b'0') as usize).cloned()
+            }
+            // Templates and other nested names.
+            b'?' => None,
+            _ => {
+                let name = self.c.until(b'@')?.to_string();
+                self.names.push(name.clone());
+                Some(name)
+            }
+        }
+    }
+
+    // Scope fragments, innermost first, up to the terminating '@'.
+    fn scopes(&mut self) -> Option<Vec<String>> {
+        let mut scopes = vec![];
+        while !self.c.eat(b'@') {
+            scopes.push(self.fragment()?);
+        }
+        Some(scopes)
+    }
+
+    fn qualified(scopes: &[String], name: &str) -> String {
+        let mut parts: Vec<&str> = scopes.iter().rev().map(|s| s.as_str()).collect();
+        parts.push(name);
+        parts.join("::")
+    }
+
+    fn type_name(&mut self) -> Option<String> {
+        let name = self.fragment()?;
+        let scopes = self.scopes()?;
+        Some(Microsoft::qualified(&scopes, &name))
+    }
+
+    fn cv(&mut self) -> Option<&'static str> {
+        Some(match self.c.next()? {
+            b'A' => "",
+            b'B' => "const ",
+            b'C' => "volatile ",
+            b'D' => "const volatile ",
+            _ => return None,
+        })
+    }
+
+    fn data_type(&mut self) -> Option<String> {
+        let t = match self.c.next()? {
+            c @ b'0'..=b'9' => return self.args.get((c - b'0') as usize).cloned(),
+            b'C' => "signed char".to_string(),
+            b'D' => "char".to_string(),
+            b'E' => "unsigned char".to_string(),
+            b'F' => "short".to_string(),
+            b'G' => "unsigned short".to_string(),
+            b'H' => "int".to_string(),
+            b'I' => "unsigned int".to_string(),
+            b'J' => "long".to_string(),
+            b'K' => "unsigned long".to_string(),
+            b'M' => "float".to_string(),
+            b'N' => "double".to_string(),
+            b'O' => "long double".to_string(),
+            b'X' => "void".to_string(),
+            b'_' => match self.c.next()? {
+                b'J' => "__int64".to_string(),
+                b'K' => "unsigned __int64".to_string(),
+                b'N' => "bool".to_string(),
+                b'W' => "wchar_t".to_string(),
+                _ => return None,
+            },
+            p @ (b'P' | b'Q' | b'R' | b'S' | b'A') => {
+                self.c.eat(b'E');
+                let pointee_cv = self.cv()?;
+                let pointee = self.data_type()?;
+                let pointer_cv = match p {
+                    b'Q' => " const",
+                    b'R' => " volatile",
+                    b'S' => " const volatile",
+                    _ => "",
+                };
+                let sigil = if p == b'A' { "&" } else { "*" };
+                format!("{pointee_cv}{pointee} {sigil}{pointer_cv}")
+            }
+            b'T' => format!("union {}", self.type_name()?),
+            b'U' => format!("struct {}", self.type_name()?),
+            b'V' => format!("class {}", self.type_name()?),
+            b'W' => {
+                self.c.next()?;
+                format!("enum {}", self.type_name()?)
+            }
+            _ => return None,
+        };
+        Some(t)
+    }
+
+    fn calling_convention(&mut self) -> Option<&'static str> {
+        Some(match self.c.next()? {
+            b'A' | b'B' => "__cdecl",
+            b'C' | b'D' => "__pascal",
+            b'E' | b'F' => "__thiscall",
+            b'G' | b'H' => "__stdcall",
+            b'I' | b'J' => "__fastcall",
+            _ => return None,
+        })
+    }
+
+    fn arguments(&mut self) -> Option<String> {
+        if self.c.eat(b'X') {
+            return Some("void".to_string());
+        }
+        let mut args = vec![];
+        loop {
+            if self.c.eat(b'@') {
+                break;
+            }
+            if self.c.eat(b'Z') {
+                args.push("...".to_string());
+                break;
+            }
+            let start = self.c.pos;
+            let t = self.data_type()?;
+            // Only types longer than one character can be referred back to.
+            let encoded = &self.c.s[start..self.c.pos];
+            if encoded.len() > 1 && !encoded[0].is_ascii_digit() && self.args.len() < 10 {
+                self.args.push(t.clone());
+            }
+            args.push(t);
+        }
+        Some(args.join(","))
+    }
+
+    fn demangle(mut self) -> Option<String> {
+        let special = self.c.eat(b'?');
+        let name = if special {
+            self.operator_name()?.to_string()
+        } else {
+            self.fragment()?
+        };
+        let scopes = self.scopes()?;
+        // Constructors and destructors are named after their class.
+        let name = match name.as_str() {
+            "" | "~" => format!("{name}{}", scopes.first()?),
+            _ => name,
+        };
+        let qualified = Microsoft::qualified(&scopes, &name);
+
+        let kind = self.c.next()?;
+        let demangled = match kind {
+            b'3' => {
+                let t = self.data_type()?;
+                self.cv()?;
+                format!("{t} {qualified}")
+            }
+            b'0' | b'1' | b'2' => {
+                let access = ["private", "protected", "public"][(kind - b'0') as usize];
+                let t = self.data_type()?;
+                self.cv()?;
+                format!("{access}: static {t} {qualified}")
+            }
+            b'Y' | b'Z' => {
+                let convention = self.calling_convention()?;
+                let ret = self.return_type()?;
+                let args = self.arguments()?;
+                format!("{ret}{convention} {qualified}({args})")
+            }
+            b'A'..=b'V' => {
+                let index = (kind - b'A') / 8;
+                let access = ["private", "protected", "public"].get(index as usize)?;
+                let storage = match (kind - b'A') % 8 {
+                    0 | 1 => "",
+                    2 | 3 => "static ",
+                    4 | 5 => "virtual ",
+                    _ => return None,
+                };
+                let this_cv = if storage == "static " {
+                    ""
+                } else {
+                    self.c.eat(b'E');
+                    match self.cv()? {
+                        "" => "",
+                        "const " => " const",
+                        "volatile " => " volatile",
+                        _ => " const volatile",
+                    }
+                };
+                let convention = self.calling_convention()?;
+                let ret = self.return_type()?;
+                let args = self.arguments()?;
+                format!("{access}: {storage}{ret}{convention} {qualified}({args}){this_cv}")
+            }
+            _ => return None,
+        };
+        self.c.eat(b'Z');
+        Some(demangled)
+    }
+
+    // Constructors and destructors have no return type.
+    fn return_type(&mut self) -> Option<String> {
+        if self.c.eat(b'@') {
+            return Some(String::new());
+        }
+        if self.c.eat(b'?') {
+            self.cv()?;
+        }
+        Some(format!("{} ", self.data_type()?))
+    }
+}
+
+fn borland_operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "add" => "operator +",
+        "sub" => "operator -",
+        "mul" => "operator *",
+        "div" => "operator /",
+        "mod" => "operator %",
+        "asg" => "operator =",
+        "eql" => "operator ==",
+        "neq" => "operator !=",
+        "lss" => "operator <",
+        "gtr" => "operator >",
+        "leq" => "operator <=",
+        "geq" => "operator >=",
+        "not" => "operator !",
+        "new" => "operator new",
+        "dele" => "operator delete",
+        "nwa" => "operator new[]",
+        "dla" => "operator delete[]",
+        "subs" => "operator []",
+        "call" => "operator ()",
+        "ind" => "operator *",
+        "adr" => "operator &",
+        "and" => "operator &",
+        "or" => "operator |",
+        "xor" => "operator ^",
+        "cmp" => "operator ~",
+        "lsh" => "operator <<",
+        "rsh" => "operator >>",
+        "inc" => "operator ++",
+        "dec" => "operator --",
+        "rplu" => "operator +=",
+        "rmin" => "operator -=",
+        "rmul" => "operator *=",
+        "rdiv" => "operator /=",
+        "rmod" => "operator %=",
+        "rand" => "operator &=",
+        "ror" => "operator |=",
+        "rxor" => "operator ^=",
+        "rlsh" => "operator <<=",
+        "rrsh" => "operator >>=",
+        "arow" => "operator ->",
+        "arwm" => "operator ->*",
+        "coma" => "operator ,",
+        "land" => "operator &&",
+        "lor" => "operator ||",
+        _ => return None,
+    })
+}
+
+fn borland_type(c: &mut Cursor, args: &[String]) -> Option<String> {
+    let mut prefix = String::new();
+    loop {
+        match c.peek()? {
+            b'x' => prefix.push_str("const "),
+            b'w' => prefix.push_str("volatile "),
+            b'u' => prefix.push_str("unsigned "),
+            b'z' => prefix.push_str("signed "),
+            _ => break,
+        }
+        c.next();
+    }
+    let t = match c.next()? {
+        b'v' => "void".to_string(),
+        b'c' => "char".to_string(),
+        b's' => "short".to_string(),
+        b'i' => "int".to_string(),
+        b'l' => "long".to_string(),
+        b'j' => "__int64".to_string(),
+        b'f' => "float".to_string(),
+        b'd' => "double".to_string(),
+        b'g' => "long double".to_string(),
+        b'o' => "bool".to_string(),
+        b'b' => "wchar_t".to_string(),
+        b'e' => "...".to_string(),
+        b'p' => format!("{} *", borland_type(c, args)?),
+        b'r' => format!("{} &", borland_type(c, args)?),
+        b'a' => {
+            let len = c.number()?;
+            if !c.eat(b'$') {
+                return None;
+            }
+            format!("{}[{len}]", borland_type(c, args)?)
+        }
+        b't' => {
+            let n = (c.next()? as char).to_digit(36)? as usize;
+            args.get(n.checked_sub(1)?)?.clone()
+        }
+        b'0'..=b'9' => {
+            c.pos -= 1;
+            let len = c.number()?;
+            c.take(len)?.replace('@', "::")
+        }
+        _ => return None,
+    };
+    Some(format!("{prefix}{t}"))
+}
+
+fn demangle_borland(s: &str) -> Option<String> {
+    let (path, signature) = match s.find("$q").or_else(|| s.find("$xq")) {
+        Some(i) => (&s[..i], Some(&s[i..])),
+        None => (s, None),
+    };
+    let mut parts: Vec<&str> = path.split('@').collect();
+    let last = parts.pop()?;
+    // Without a signature, only scoped names are mangled. This also rules
+    // out __fastcall C names such as @func@8.
+    if signature.is_none() && (parts.is_empty() || last.bytes().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let name = match last.strip_prefix("$b") {
+        Some("ctr") => parts.last()?.to_string(),
+        Some("dtr") => format!("~{}", parts.last()?),
+        Some(op) => borland_operator(op)?.to_string(),
+        None if last.contains('$') || last.contains('%') => return None,
+        None => last.to_string(),
+    };
+    parts.push(&name);
+    let qualified = parts.join("::");
+    let Some(signature) = signature else {
+        return Some(qualified);
+    };
+
+    let (constant, signature) = match signature.strip_prefix("$xq") {
+        Some(rest) => (true, rest),
+        None => (false, &signature[2..]),
+    };
+    let mut c = Cursor::new(signature);
+    let mut args: Vec<String> = vec![];
+    while !c.is_empty() {
+        let t = borland_type(&mut c, &args)?;
+        args.push(t);
+    }
+    let args = if args.iter().all(|a| a == "void") {
+        "void".to_string()
+    } else {
+        args.join(", ")
+    };
+    let suffix = if constant { " const" } else { "" };
+    Some(format!("{qualified}({args}){suffix}"))
+}
+
+struct Watcom<'a> {
+    c: Cursor<'a>,
+}
+
+impl<'a> Watcom<'a> {
+    fn new(s: &'a str) -> Watcom<'a> {
+        Watcom { c: Cursor::new(s) }
+    }
+
+    fn operator_name(code: &str) -> Option<&'static str> {
+        Some(match code {
+            "ct" => "",
+            "dt" => "~",
+            "nw" => "operator new",
+            "dl" => "operator delete",
+            "na" => "operator new[]",
+            "da" => "operator delete[]",
+            "aa" => "operator =",
+            "ea" => "operator +=",
+            "fa" => "operator -=",
+            "ga" => "operator *=",
+            "ha" => "operator /=",
+            "oa" => "operator ==",
+            "ob" => "operator !=",
+            "oc" => "operator <",
+            "od" => "operator <=",
+            "oe" => "operator >",
+            "of" => "operator >=",
+            "ra" => "operator +",
+            "rb" => "operator -",
+            "rc" => "operator *",
+            "rd" => "operator /",
+            "re" => "operator %",
+            "ua" => "operator ++",
+            "ub" => "operator --",
+            "uc" => "operator !",
+            "ud" => "operator ~",
+            "ue" => "operator ->",
+            "ug" => "operator []",
+            "uh" => "operator ()",
+            _ => return None,
+        })
+    }
+
+    // A class name, written as $Name$$.
+    fn class_name(&mut self) -> Option<String> {
+        let start = self.c.pos;
+        while !self.c.starts_with("$$") {
+            self.c.next()?;
+        }
+        let name = std::str::from_utf8(&self.c.s[start..self.c.pos]).ok()?;
+        self.c.pos += 2;
+        Some(name.replace(':', "::"))
+    }
+
+    fn data_type(&mut self) -> Option<String> {
+        let mut constant = false;
+        let mut volatile = false;
+        let mut unsigned = false;
+        loop {
+            match self.c.peek()? {
+                b'x' => constant = true,
+                b'y' => volatile = true,
+                b'u' => unsigned = true,
+                _ => break,
+            }
+            self.c.next();
+        }
+        let cv = cv_prefix(constant, volatile);
+        let sign = if unsigned { "unsigned " } else { "" };
+        let t = match self.c.next()? {
+            b'a' => "signed char".to_string(),
+            b'b' => "bool".to_string(),
+            b'c' => "char".to_string(),
+            b'd' => "double".to_string(),
+            b'e' => "...".to_string(),
+            b'f' => "float".to_string(),
+            b'i' => "int".to_string(),
+            b'l' => "long".to_string(),
+            b's' => "short".to_string(),
+            b't' => "long double".to_string(),
+            b'v' => "void".to_string(),
+            b'w' => "wchar_t".to_string(),
+            p @ (b'p' | b'r') => {
+                // A far pointer's 'f' can't be told apart from float, so
+                // only near and huge are recognised.
+                let distance = match self.c.peek()? {
+                    b'n' => {
+                        self.c.next();
+                        ""
+                    }
+                    b'h' => {
+                        self.c.next();
+                        " __huge"
+                    }
+                    _ => "",
+                };
+                let sigil = if p == b'p' { "*" } else { "&" };
+                return Some(format!("{cv}{}{distance} {sigil}", self.data_type()?));
+            }
+            b'$' => self.class_name()?,
+            _ => return None,
+        };
+        Some(format!("{cv}{sign}{t}"))
+    }
+
+    fn demangle(mut self) -> Option<String> {
+        let name = if self.c.eat(b'$') {
+            let code = self.c.take(2)?;
+            Watcom::operator_name(code)?.to_string()
+        } else {
+            self.c.until(b'$')?.to_string()
+        };
+        let mut scopes = vec![];
+        while self.c.eat(b':') {
+            scopes.push(self.c.until(b'$')?.to_string());
+        }
+        // Constructors and destructors are named after their class.
+        let name = match name.as_str() {
+            "" | "~" => format!("{name}{}", scopes.first()?),
+            _ => name,
+        };
+        let mut parts: Vec<&str> = scopes.iter().rev().map(|s| s.as_str()).collect();
+        parts.push(&name);
+        let qualified = parts.join("::");
+
+        let distance = match self.c.next()? {
+            b'n' => "",
+            b'f' => "__far ",
+            _ => return None,
+        };
+        if !self.c.eat(b'(') {
+            let t = self.data_type()?;
+            return Some(format!("{t} {distance}{qualified}"));
+        }
+        let mut args = vec![];
+        while !self.c.eat(b')') {
+            args.push(self.data_type()?);
+        }
+        let ret = if self.c.eat(b'_') {
+            String::new()
+        } else {
+            format!("{} ", self.data_type()?)
+        };
+        let args = if args.is_empty() {
+            "void".to_string()
+        } else {
+            args.join(", ")
+        };
+        Some(format!("{ret}{distance}{qualified}({args})"))
+    }
+}
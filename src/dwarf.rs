@@ -0,0 +1,63 @@
+use crate::{error::OmfError, OmfModule};
+
+// The class Open Watcom gives the segments it puts DWARF sections in. Each
+// is named after its section, such as .debug_info.
+const DWARF_CLASS: &str = "DWARF";
+
+// The contents of a DWARF section, from the segments named after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DwarfSection {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl OmfModule {
+    // The module's DWARF sections, in the order their segments are defined.
+    // A section split over more than one segment has their contents joined
+    // in that order.
+    pub fn dwarf_sections(&self) -> Result<Vec<DwarfSection>, OmfError> {
+        let info = self.info();
+        let images = self.segment_images()?;
+        let mut sections: Vec<DwarfSection> = vec![];
+        for (i, segment) in info.segments.iter().enumerate() {
+            if !info
+                .name(segment.class_name_index)?
+                .eq_ignore_ascii_case(DWARF_CLASS)
+            {
+                continue;
+            }
+            let name = info.name(segment.segment_name_index)?;
+            let data = &images[i].data;
+            match sections.iter_mut().find(|s| s.name == name) {
+                Some(section) => section.data.extend_from_slice(data),
+                None => sections.push(DwarfSection {
+                    name: name.to_string(),
+                    data: data.clone(),
+                }),
+            }
+        }
+        Ok(sections)
+    }
+}
+
+// Looks up a section by name, for handing to DWARF readers.
+pub fn dwarf_section<'a>(sections: &'a [DwarfSection], name: &str) -> Option<&'a [u8]> {
+    sections
+        .iter()
+        .find(|s| s.name == name)
+        .map(|s| &s.data[..])
+}
+
+// Loads the sections for reading with gimli. Watcom only targets x86, so
+// they are always little-endian.
+#[cfg(feature = "gimli")]
+pub fn load_dwarf(
+    sections: &[DwarfSection],
+) -> Result<gimli::Dwarf<gimli::EndianSlice<'_, gimli::LittleEndian>>, gimli::Error> {
+    gimli::Dwarf::load(|id| {
+        Ok(gimli::EndianSlice::new(
+            dwarf_section(sections, id.name()).unwrap_or(&[]),
+            gimli::LittleEndian,
+        ))
+    })
+}
@@ -0,0 +1,371 @@
+use std::sync::Arc;
+
+use crate::{
+    error::OmfError, FixupData, FixupFrame, FixupSubrecord, FixupTarget, FixupThread, MAttrStart,
+    NameIndex, OmfRecord, OmfRecordData, RecordType, SegmentIndex,
+};
+
+// Removes the segments for which `remove` returns true, along with their
+// data, fixups and publics, and renumbers references to the segments that
+// remain. Fails if anything left behind still refers to a removed segment.
+pub fn remove_segments(
+    records: &mut Vec<OmfRecord>,
+    remove: impl Fn(SegmentIndex) -> bool,
+) -> Result<(), OmfError> {
+    // 32-bit records are kept as raw bytes, so their indices can't be fixed.
+    if records.iter().any(|r| {
+        matches!(r.data, OmfRecordData::Unknown { .. })
+            && matches!(
+                r.record_type,
+                RecordType::ModEnd32
+                    | RecordType::PubDef32
+                    | RecordType::LinNum32
+                    | RecordType::SegDef32
+                    | RecordType::FixUpp32
+                    | RecordType::LEData32
+                    | RecordType::LIData32
+                    | RecordType::LPubDef32
+                    | RecordType::ComDat32
+            )
+    }) {
+        return Err(OmfError::Value(
+            "cannot renumber segments in 32-bit records",
+        ));
+    }
+    let mut map = vec![0u16];
+    let mut next = 1u16;
+    let count = records
+        .iter()
+        .filter(|r| matches!(r.data, OmfRecordData::SegDef { .. }))
+        .count();
+    let count = u16::try_from(count).map_err(|_| OmfError::Value("too many segments"))?;
+    for index in 1..=count {
+        if remove(SegmentIndex(index)) {
+            map.push(0);
+        } else {
+            map.push(next);
+            next = next
+                .checked_add(1)
+                .ok_or(OmfError::Value("too many segments"))?;
+        }
+    }
+    let renumber = |index: SegmentIndex| -> Result<SegmentIndex, OmfError> {
+        match map.get(index.0 as usize) {
+            Some(0) if !index.is_none() => Err(OmfError::Value("reference to a removed segment")),
+            Some(&i) => Ok(SegmentIndex(i)),
+            None => Err(OmfError::Value("segment index not found")),
+        }
+    };
+
+    let mut segment = 0u16;
+    let mut dropping_data = false;
+    let mut kept = vec![];
+    for mut record in records.drain(..) {
+        let keep = match &mut record.data {
+            OmfRecordData::SegDef { .. } => {
+                segment += 1;
+                !remove(SegmentIndex(segment))
+            }
+            OmfRecordData::GrpDef {
+                segment_definitions,
+                ..
+            } => {
+                segment_definitions
+                    .retain(|c| map.get(c.segment_definition.0 as usize) != Some(&0));
+                for c in segment_definitions.iter_mut() {
+                    c.segment_definition = renumber(c.segment_definition)?;
+                }
+                true
+            }
+            OmfRecordData::PubDef {
+                base_segment_index, ..
+            }
+            | OmfRecordData::LPubDef {
+                base_segment_index, ..
+            }
+            | OmfRecordData::LinNum {
+                base_segment_index, ..
+            } => {
+                if !base_segment_index.is_none()
+                    && map.get(base_segment_index.0 as usize) == Some(&0)
+                {
+                    false
+                } else {
+                    *base_segment_index = renumber(*base_segment_index)?;
+                    true
+                }
+            }
+            OmfRecordData::ComDat {
+                base_segment_index, ..
+            } => {
+                // Fixups after a COMDAT are its own, even if the data record
+                // before it was dropped.
+                dropping_data = false;
+                *base_segment_index = renumber(*base_segment_index)?;
+                true
+            }
+            OmfRecordData::LEData { segment_index, .. }
+            | OmfRecordData::LIData { segment_index, .. } => {
+                // A FIXUPP applies to the data record before it.
+                dropping_data = map.get(segment_index.0 as usize) == Some(&0);
+                if !dropping_data {
+                    *segment_index = renumber(*segment_index)?;
+                }
+                !dropping_data
+            }
+            OmfRecordData::FixUpp { subrecords } => {
+                for s in subrecords.iter_mut() {
+                    match s {
+                        FixupSubrecord::Thread(FixupThread::Frame { frame, .. }) => {
+                            renumber_frame(frame, &renumber)?
+                        }
+                        FixupSubrecord::Thread(FixupThread::Target { target, .. }) => {
+                            renumber_target(target, &renumber)?
+                        }
+                        FixupSubrecord::Fixup(fixup) if !dropping_data => {
+                            renumber_fix_data(&mut fixup.fix_data, &renumber)?
+                        }
+                        FixupSubrecord::Fixup(_) => (),
+                    }
+                }
+                if dropping_data {
+                    subrecords.retain(|s| matches!(s, FixupSubrecord::Thread(_)));
+                    !subrecords.is_empty()
+                } else {
+                    true
+                }
+            }
+            OmfRecordData::ModEnd {
+                start: MAttrStart::Start(fix_data),
+                ..
+            } => {
+                renumber_fix_data(fix_data, &renumber)?;
+                true
+            }
+            _ => true,
+        };
+        if keep {
+            kept.push(record);
+        }
+    }
+    *records = kept;
+    Ok(())
+}
+
+fn renumber_frame(
+    frame: &mut FixupFrame,
+    renumber: &impl Fn(SegmentIndex) -> Result<SegmentIndex, OmfError>,
+) -> Result<(), OmfError> {
+    if let FixupFrame::Segment(index) = frame {
+        *index = renumber(*index)?;
+    }
+    Ok(())
+}
+
+fn renumber_target(
+    target: &mut FixupTarget,
+    renumber: &impl Fn(SegmentIndex) -> Result<SegmentIndex, OmfError>,
+) -> Result<(), OmfError> {
+    if let FixupTarget::Segment(index) = target {
+        *index = renumber(*index)?;
+    }
+    Ok(())
+}
+
+fn renumber_fix_data(
+    fix_data: &mut FixupData,
+    renumber: &impl Fn(SegmentIndex) -> Result<SegmentIndex, OmfError>,
+) -> Result<(), OmfError> {
+    renumber_frame(&mut fix_data.frame, renumber)?;
+    renumber_target(&mut fix_data.target, renumber)
+}
+
+fn names(records: &[OmfRecord]) -> Vec<Arc<str>> {
+    records
+        .iter()
+        .flat_map(|r| match &r.data {
+            OmfRecordData::LNames { names } => names.clone(),
+            _ => vec![],
+        })
+        .collect()
+}
+
+// Every name index in a record.
+fn name_indices(data: &mut OmfRecordData) -> Vec<&mut NameIndex> {
+    match data {
+        OmfRecordData::SegDef {
+            segment_name_index,
+            class_name_index,
+            overlay_name_index,
+            ..
+        } => vec![segment_name_index, class_name_index, overlay_name_index],
+        OmfRecordData::GrpDef {
+            group_name_index, ..
+        } => vec![group_name_index],
+        OmfRecordData::CExtDef { names } => names
+            .iter_mut()
+            .map(|n| &mut n.logical_name_index)
+            .collect(),
+        OmfRecordData::ComDat {
+            public_name_index, ..
+        }
+        | OmfRecordData::LinSym {
+            public_name_index, ..
+        } => vec![public_name_index],
+        _ => vec![],
+    }
+}
+
+// Adds a name to the last LNAMES record before `records[before]`, so the
+// record there can refer to it. Names defined by later LNAMES records move
+// up by one, and every reference to them is renumbered.
+fn insert_name(
+    records: &mut [OmfRecord],
+    before: usize,
+    name: &str,
+) -> Result<NameIndex, OmfError> {
+    if names(records).len() >= 0x7FFF {
+        return Err(OmfError::Value("too many names"));
+    }
+    let at = records[..before]
+        .iter()
+        .rposition(|r| matches!(r.data, OmfRecordData::LNames { .. }))
+        .ok_or(OmfError::Value("no LNAMES record before the name is used"))?;
+    let index = NameIndex(names(&records[..=at]).len() as u16 + 1);
+    let shifts = records[at + 1..]
+        .iter()
+        .any(|r| matches!(r.data, OmfRecordData::LNames { .. }));
+    // Names in raw records can't be renumbered.
+    if shifts
+        && records.iter().any(|r| {
+            matches!(r.data, OmfRecordData::Unknown { .. })
+                && matches!(
+                    r.record_type,
+                    RecordType::SegDef32
+                        | RecordType::ComDat32
+                        | RecordType::LinSym32
+                        | RecordType::NBkPat
+                        | RecordType::NBkPat32
+                        | RecordType::LLNames
+                )
+        })
+    {
+        return Err(OmfError::Value("cannot renumber names in raw records"));
+    }
+    if shifts {
+        for record in records.iter_mut() {
+            for name_index in name_indices(&mut record.data) {
+                if name_index.0 >= index.0 {
+                    name_index.0 += 1;
+                }
+            }
+        }
+    }
+    if let OmfRecordData::LNames { names } = &mut records[at].data {
+        names.push(name.into());
+    }
+    Ok(index)
+}
+
+// Points every name index `select` picks out that refers to `old` at a new
+// name `new`. Returns how many indices were changed.
+fn rename_indices(
+    records: &mut [OmfRecord],
+    old: &str,
+    new: &str,
+    select: impl Fn(&mut OmfRecordData) -> Vec<&mut NameIndex>,
+) -> Result<usize, OmfError> {
+    let is_old = |names: &[Arc<str>], index: NameIndex| {
+        names
+            .get((index.0 as usize).wrapping_sub(1))
+            .is_some_and(|name| **name == *old)
+    };
+    let defined = names(records);
+    let Some(first) = records.iter_mut().position(|r| {
+        select(&mut r.data)
+            .into_iter()
+            .any(|index| is_old(&defined, *index))
+    }) else {
+        return Ok(0);
+    };
+    let index = insert_name(records, first, new)?;
+    let defined = names(records);
+    let mut count = 0;
+    for record in records[first..].iter_mut() {
+        for name_index in select(&mut record.data) {
+            if is_old(&defined, *name_index) {
+                *name_index = index;
+                count += 1;
+            }
+        }
+    }
+    Ok(count)
+}
+
+// Renames the segments named `old`. Returns how many were renamed.
+pub fn rename_segment(records: &mut [OmfRecord], old: &str, new: &str) -> Result<usize, OmfError> {
+    rename_indices(records, old, new, |data| match data {
+        OmfRecordData::SegDef {
+            segment_name_index, ..
+        } => vec![segment_name_index],
+        _ => vec![],
+    })
+}
+
+// Moves the segments of class `old` to class `new`. Returns how many were
+// changed.
+pub fn rename_class(records: &mut [OmfRecord], old: &str, new: &str) -> Result<usize, OmfError> {
+    rename_indices(records, old, new, |data| match data {
+        OmfRecordData::SegDef {
+            class_name_index, ..
+        } => vec![class_name_index],
+        _ => vec![],
+    })
+}
+
+// Renames a symbol wherever it is defined or referred to: publics,
+// externals, communals, aliases, COMDATs and their line numbers. Returns how
+// many references were renamed.
+pub fn rename_symbol(records: &mut [OmfRecord], old: &str, new: &str) -> Result<usize, OmfError> {
+    let mut count = 0;
+    let mut rename = |name: &mut String| {
+        if name == old {
+            *name = new.to_string();
+            count += 1;
+        }
+    };
+    for record in records.iter_mut() {
+        match &mut record.data {
+            OmfRecordData::PubDef { names, .. } | OmfRecordData::LPubDef { names, .. } => {
+                names.iter_mut().for_each(|n| rename(&mut n.name))
+            }
+            OmfRecordData::ExtDef { names } | OmfRecordData::LExtDef { names } => {
+                names.iter_mut().for_each(|n| rename(&mut n.name))
+            }
+            OmfRecordData::ComDef { definitions } => {
+                definitions.iter_mut().for_each(|n| rename(&mut n.name))
+            }
+            OmfRecordData::Alias { aliases } => {
+                for a in aliases.iter_mut() {
+                    rename(&mut a.alias_name);
+                    rename(&mut a.substitute_name);
+                }
+            }
+            _ => (),
+        }
+    }
+    count += rename_indices(records, old, new, |data| match data {
+        OmfRecordData::CExtDef { names } => names
+            .iter_mut()
+            .map(|n| &mut n.logical_name_index)
+            .collect(),
+        OmfRecordData::ComDat {
+            public_name_index, ..
+        }
+        | OmfRecordData::LinSym {
+            public_name_index, ..
+        } => vec![public_name_index],
+        _ => vec![],
+    })?;
+    Ok(count)
+}
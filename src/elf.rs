@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::OmfError,
+    record::LocationType,
+    symbols::{SymbolBinding, SymbolKind, SymbolScope},
+    OmfModule, RelocationRef,
+};
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+const SHT_REL: u32 = 9;
+const SHF_WRITE: u32 = 1;
+const SHF_ALLOC: u32 = 2;
+const SHF_EXECINSTR: u32 = 4;
+const SHN_ABS: u16 = 0xFFF1;
+const SHN_COMMON: u16 = 0xFFF2;
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_SECTION: u8 = 3;
+const R_386_32: u8 = 1;
+const R_386_PC32: u8 = 2;
+const R_386_16: u8 = 20;
+const R_386_PC16: u8 = 21;
+const R_386_8: u8 = 22;
+const R_386_PC8: u8 = 23;
+const R_386_SEG16: u8 = 45;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ElfFlavor {
+    I386,
+    // 16-bit code as understood by the ia16 binutils port, which adds
+    // R_386_SEG16 for segment bases.
+    I8086,
+}
+
+struct StringTable {
+    data: Vec<u8>,
+}
+
+impl StringTable {
+    fn new() -> StringTable {
+        StringTable { data: vec![0] }
+    }
+
+    fn add(&mut self, s: &str) -> u32 {
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(s.as_bytes());
+        self.data.push(0);
+        offset
+    }
+}
+
+struct ElfSymbol {
+    name: u32,
+    value: u32,
+    size: u32,
+    info: u8,
+    shndx: u16,
+}
+
+struct ElfSection {
+    name: u32,
+    sh_type: u32,
+    flags: u32,
+    data: Vec<u8>,
+    size: u32,
+    link: u32,
+    info: u32,
+    align: u32,
+    entsize: u32,
+}
+
+fn put16(v: &mut Vec<u8>, x: u16) {
+    v.extend_from_slice(&x.to_le_bytes());
+}
+
+fn put32(v: &mut Vec<u8>, x: u32) {
+    v.extend_from_slice(&x.to_le_bytes());
+}
+
+pub(crate) fn add_in_place(
+    data: &mut [u8],
+    offset: usize,
+    size: usize,
+    addend: i64,
+) -> Result<(), OmfError> {
+    let bytes = data
+        .get_mut(offset..offset + size)
+        .ok_or(OmfError::Value("fixup location outside segment"))?;
+    let mut value = 0i64;
+    for (i, b) in bytes.iter().enumerate() {
+        value |= (*b as i64) << (i * 8);
+    }
+    value += addend;
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = (value >> (i * 8)) as u8;
+    }
+    Ok(())
+}
+
+// Converts a module into an ELF32 relocatable object. Each segment becomes a
+// section, publics and externals become symbols, and fixups become REL
+// relocations with their addends stored in the section contents.
+pub fn omf_to_elf(module: &OmfModule, flavor: ElfFlavor) -> Result<Vec<u8>, OmfError> {
+    let info = module.info();
+    let images = module.segment_images()?;
+    let mut shstrtab = StringTable::new();
+    let mut strtab = StringTable::new();
+    let mut sections = vec![];
+
+    for (i, segment) in info.segments.iter().enumerate() {
+        let name = info.name(segment.segment_name_index)?;
+        let class = info.name(segment.class_name_index)?.to_ascii_uppercase();
+        let image = &images[i];
+        let code = class.ends_with("CODE");
+        let mut flags = SHF_ALLOC;
+        if code {
+            flags |= SHF_EXECINSTR;
+        } else if !class.contains("CONST") {
+            flags |= SHF_WRITE;
+        }
+        let bss = image.initialized.is_empty() && !code;
+        sections.push(ElfSection {
+            name: shstrtab.add(name),
+            sh_type: if bss { SHT_NOBITS } else { SHT_PROGBITS },
+            flags,
+            data: if bss { vec![] } else { image.data.clone() },
+            size: segment.length(),
+            link: 0,
+            info: 0,
+            align: segment.segment_attributes.alignment.bytes(),
+            entsize: 0,
+        });
+    }
+
+    let mut symbols = vec![ElfSymbol {
+        name: 0,
+        value: 0,
+        size: 0,
+        info: 0,
+        shndx: 0,
+    }];
+    for i in 0..sections.len() {
+        symbols.push(ElfSymbol {
+            name: 0,
+            value: 0,
+            size: 0,
+            info: (STB_LOCAL << 4) | STT_SECTION,
+            shndx: i as u16 + 1,
+        });
+    }
+    let mut globals = vec![];
+    for s in module.symbols().iter() {
+        let (value, size, shndx, typ) = match (&s.kind, s.binding) {
+            (SymbolKind::Public, SymbolBinding::Segment { segment_index, .. }) => {
+                (s.offset, 0, segment_index.0, STT_NOTYPE)
+            }
+            (SymbolKind::Public, SymbolBinding::Absolute { frame }) => {
+                (frame as u32 * 16 + s.offset, 0, SHN_ABS, STT_NOTYPE)
+            }
+            (SymbolKind::External | SymbolKind::ComdatExternal, _) => (0, 0, 0, STT_NOTYPE),
+            (SymbolKind::Communal(length), _) => (2, length.size() as u32, SHN_COMMON, STT_OBJECT),
+            _ => continue,
+        };
+        let symbol = ElfSymbol {
+            name: strtab.add(&s.name),
+            value,
+            size,
+            info: typ,
+            shndx,
+        };
+        if s.scope == SymbolScope::Local && s.kind == SymbolKind::Public {
+            symbols.push(symbol);
+        } else {
+            globals.push((s.name.clone(), symbol));
+        }
+    }
+    let first_global = symbols.len();
+    let mut global_index = HashMap::new();
+    for (name, mut symbol) in globals {
+        if global_index.contains_key(&name) {
+            continue;
+        }
+        symbol.info |= STB_GLOBAL << 4;
+        global_index.insert(name, symbols.len());
+        symbols.push(symbol);
+    }
+
+    let mut rels: Vec<Vec<u8>> = vec![vec![]; sections.len()];
+    for reloc in module.relocations()? {
+        let section = reloc.segment_index.0 as usize - 1;
+        let symbol = match &reloc.target {
+            RelocationRef::Segment(i) => i.0 as usize,
+            RelocationRef::External(name) => *global_index
+                .get(&**name)
+                .ok_or(OmfError::Value("external not in symbol table"))?,
+            RelocationRef::Group(_) => {
+                return Err(OmfError::Value(
+                    "group targets cannot be represented in ELF",
+                ))
+            }
+            RelocationRef::Absolute(_) => {
+                return Err(OmfError::Value(
+                    "absolute targets cannot be represented in ELF",
+                ))
+            }
+        };
+        let self_relative = !reloc.segment_relative;
+        let mut pieces: Vec<(u32, u8, usize)> = vec![];
+        match (reloc.kind, flavor, self_relative) {
+            (LocationType::LowByte, _, false) => pieces.push((0, R_386_8, 1)),
+            (LocationType::LowByte, _, true) => pieces.push((0, R_386_PC8, 1)),
+            (LocationType::Offset | LocationType::LoaderOffset, _, false) => {
+                pieces.push((0, R_386_16, 2))
+            }
+            (LocationType::Offset | LocationType::LoaderOffset, _, true) => {
+                pieces.push((0, R_386_PC16, 2))
+            }
+            (LocationType::Offset32 | LocationType::LoaderOffset32, _, false) => {
+                pieces.push((0, R_386_32, 4))
+            }
+            (LocationType::Offset32 | LocationType::LoaderOffset32, _, true) => {
+                pieces.push((0, R_386_PC32, 4))
+            }
+            (LocationType::Base, ElfFlavor::I8086, false) => pieces.push((0, R_386_SEG16, 2)),
+            (LocationType::Pointer, ElfFlavor::I8086, false) => {
+                pieces.push((0, R_386_16, 2));
+                pieces.push((2, R_386_SEG16, 2));
+            }
+            (LocationType::Pointer48, ElfFlavor::I8086, false) => {
+                pieces.push((0, R_386_32, 4));
+                pieces.push((4, R_386_SEG16, 2));
+            }
+            _ => return Err(OmfError::Value("fixup type cannot be represented in ELF")),
+        }
+        for (delta, r_type, size) in pieces {
+            let offset = reloc.offset + delta;
+            if r_type != R_386_SEG16 {
+                let mut addend = reloc.target_displacement as i64;
+                if self_relative {
+                    addend -= size as i64;
+                }
+                add_in_place(&mut sections[section].data, offset as usize, size, addend)?;
+            }
+            put32(&mut rels[section], offset);
+            put32(&mut rels[section], ((symbol as u32) << 8) | r_type as u32);
+        }
+    }
+
+    let symtab_index = (sections.len() + rels.iter().filter(|r| !r.is_empty()).count() + 1) as u32;
+    for (i, rel) in rels.into_iter().enumerate() {
+        if rel.is_empty() {
+            continue;
+        }
+        let name = format!(".rel{}", info.name(info.segments[i].segment_name_index)?);
+        sections.push(ElfSection {
+            name: shstrtab.add(&name),
+            sh_type: SHT_REL,
+            flags: 0,
+            size: rel.len() as u32,
+            data: rel,
+            link: symtab_index,
+            info: i as u32 + 1,
+            align: 4,
+            entsize: 8,
+        });
+    }
+    let mut symtab = vec![];
+    for s in &symbols {
+        put32(&mut symtab, s.name);
+        put32(&mut symtab, s.value);
+        put32(&mut symtab, s.size);
+        symtab.push(s.info);
+        symtab.push(0);
+        put16(&mut symtab, s.shndx);
+    }
+    sections.push(ElfSection {
+        name: shstrtab.add(".symtab"),
+        sh_type: SHT_SYMTAB,
+        flags: 0,
+        size: symtab.len() as u32,
+        data: symtab,
+        link: symtab_index + 1,
+        info: first_global as u32,
+        align: 4,
+        entsize: 16,
+    });
+    sections.push(ElfSection {
+        name: shstrtab.add(".strtab"),
+        sh_type: SHT_STRTAB,
+        flags: 0,
+        size: strtab.data.len() as u32,
+        data: strtab.data,
+        link: 0,
+        info: 0,
+        align: 1,
+        entsize: 0,
+    });
+    let shstrtab_name = shstrtab.add(".shstrtab");
+    sections.push(ElfSection {
+        name: shstrtab_name,
+        sh_type: SHT_STRTAB,
+        flags: 0,
+        size: shstrtab.data.len() as u32,
+        data: shstrtab.data,
+        link: 0,
+        info: 0,
+        align: 1,
+        entsize: 0,
+    });
+
+    let mut out = vec![];
+    out.extend_from_slice(b"\x7fELF");
+    out.extend_from_slice(&[1, 1, 1, 0]);
+    out.extend_from_slice(&[0; 8]);
+    put16(&mut out, 1); // ET_REL
+    put16(&mut out, 3); // EM_386
+    put32(&mut out, 1);
+    put32(&mut out, 0);
+    put32(&mut out, 0);
+    let shoff_pos = out.len();
+    put32(&mut out, 0);
+    put32(&mut out, 0);
+    put16(&mut out, 52);
+    put16(&mut out, 0);
+    put16(&mut out, 0);
+    put16(&mut out, 40);
+    put16(&mut out, sections.len() as u16 + 1);
+    put16(&mut out, sections.len() as u16);
+
+    let mut offsets = vec![];
+    for s in &sections {
+        let align = s.align.max(1) as usize;
+        out.resize(out.len().div_ceil(align) * align, 0);
+        offsets.push(out.len() as u32);
+        out.extend_from_slice(&s.data);
+    }
+    out.resize(out.len().div_ceil(4) * 4, 0);
+    let shoff = out.len() as u32;
+    out[shoff_pos..shoff_pos + 4].copy_from_slice(&shoff.to_le_bytes());
+    out.extend_from_slice(&[0; 40]);
+    for (s, offset) in sections.iter().zip(offsets) {
+        put32(&mut out, s.name);
+        put32(&mut out, s.sh_type);
+        put32(&mut out, s.flags);
+        put32(&mut out, 0);
+        put32(&mut out, offset);
+        put32(&mut out, s.size);
+        put32(&mut out, s.link);
+        put32(&mut out, s.info);
+        put32(&mut out, s.align);
+        put32(&mut out, s.entsize);
+    }
+    Ok(out)
+}
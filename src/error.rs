@@ -1,10 +1,55 @@
-use std::{io, string::FromUtf8Error};
+use std::{fmt::Display, io, string::FromUtf8Error};
 
 #[derive(Debug)]
 pub enum OmfError {
     Io(io::Error),
     Utf(FromUtf8Error),
     Value(&'static str),
+    Link(String),
+    Checksum {
+        offset: usize,
+        record_type: u8,
+    },
+    // An error reading the record of this type starting at this offset.
+    Record {
+        offset: usize,
+        record_type: u8,
+        source: Box<OmfError>,
+    },
+}
+
+impl Display for OmfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OmfError::Io(e) => write!(f, "I/O error: {e}"),
+            OmfError::Utf(e) => write!(f, "invalid name: {e}"),
+            OmfError::Value(s) => write!(f, "invalid value: {s}"),
+            OmfError::Link(s) => write!(f, "link error: {s}"),
+            OmfError::Checksum {
+                offset,
+                record_type,
+            } => write!(
+                f,
+                "bad checksum in record {record_type:02X}h at {offset:06X}h"
+            ),
+            OmfError::Record {
+                offset,
+                record_type,
+                source,
+            } => write!(f, "record {record_type:02X}h at {offset:06X}h: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for OmfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OmfError::Io(e) => Some(e),
+            OmfError::Utf(e) => Some(e),
+            OmfError::Record { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
 }
 
 impl From<io::Error> for OmfError {
@@ -17,4 +62,4 @@ impl From<FromUtf8Error> for OmfError {
     fn from(value: FromUtf8Error) -> Self {
         OmfError::Utf(value)
     }
-}
\ No newline at end of file
+}
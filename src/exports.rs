@@ -0,0 +1,112 @@
+use std::io::{self, Write};
+
+use crate::{
+    error::OmfError,
+    record::{OmfRecord, OmfRecordData},
+    OmfModule,
+};
+
+const COMMENT_CLASS_OMF_EXTENSIONS: u8 = 0xA0;
+const EXTENSION_EXPDEF: u8 = 0x02;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    pub name: String,
+    pub internal_name: Option<String>,
+    pub ordinal: Option<u16>,
+    pub resident_name: bool,
+    pub no_data: bool,
+    pub parameter_count: u8,
+}
+
+fn take_string<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8], OmfError> {
+    let (&len, rest) = bytes
+        .split_first()
+        .ok_or(OmfError::Value("truncated EXPDEF"))?;
+    let s = rest
+        .get(..len as usize)
+        .ok_or(OmfError::Value("truncated EXPDEF"))?;
+    *bytes = &rest[len as usize..];
+    Ok(s)
+}
+
+// Decodes the body of an EXPDEF comment, following the subtype byte.
+pub fn parse_expdef(mut bytes: &[u8]) -> Result<Export, OmfError> {
+    let (&flag, rest) = bytes
+        .split_first()
+        .ok_or(OmfError::Value("truncated EXPDEF"))?;
+    bytes = rest;
+    let name = String::from_utf8(take_string(&mut bytes)?.to_vec())?;
+    let internal_name = String::from_utf8(take_string(&mut bytes)?.to_vec())?;
+    let ordinal = if flag & 0x80 != 0 {
+        let b = bytes.get(..2).ok_or(OmfError::Value("truncated EXPDEF"))?;
+        Some(u16::from_le_bytes([b[0], b[1]]))
+    } else {
+        None
+    };
+    Ok(Export {
+        name,
+        internal_name: if internal_name.is_empty() {
+            None
+        } else {
+            Some(internal_name)
+        },
+        ordinal,
+        resident_name: flag & 0x40 != 0,
+        no_data: flag & 0x20 != 0,
+        parameter_count: flag & 0x1F,
+    })
+}
+
+pub fn collect_exports(records: &[OmfRecord]) -> Result<Vec<Export>, OmfError> {
+    let mut exports = vec![];
+    for record in records {
+        if let OmfRecordData::Coment {
+            comment_class: COMMENT_CLASS_OMF_EXTENSIONS,
+            comment_bytes,
+            ..
+        } = &record.data
+        {
+            if let Some((&EXTENSION_EXPDEF, rest)) = comment_bytes.split_first() {
+                exports.push(parse_expdef(rest)?);
+            }
+        }
+    }
+    Ok(exports)
+}
+
+// Writes a module-definition file with a LIBRARY statement, if a name is
+// given, and an EXPORTS section listing every export.
+pub fn write_def(w: &mut dyn Write, library: Option<&str>, exports: &[Export]) -> io::Result<()> {
+    if let Some(library) = library {
+        writeln!(w, "LIBRARY {library}")?;
+        writeln!(w)?;
+    }
+    writeln!(w, "EXPORTS")?;
+    for e in exports {
+        write!(w, "    {}", e.name)?;
+        if let Some(internal_name) = &e.internal_name {
+            write!(w, "={internal_name}")?;
+        }
+        if let Some(ordinal) = e.ordinal {
+            write!(w, " @{ordinal}")?;
+            if e.resident_name {
+                write!(w, " RESIDENTNAME")?;
+            }
+        }
+        if e.no_data {
+            write!(w, " NODATA")?;
+        }
+        if e.parameter_count != 0 {
+            write!(w, " {}", e.parameter_count)?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+impl OmfModule {
+    pub fn exports(&self) -> Result<Vec<Export>, OmfError> {
+        collect_exports(&self.records)
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::OmfError, record::LocationType, GroupIndex, OmfModule, RelocationRef, ResolvedSymbol,
+    SegmentImage, SegmentIndex,
+};
+
+fn read_le(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0u32, |acc, &b| (acc << 8) | b as u32)
+}
+
+fn write_le(bytes: &mut [u8], value: u32) {
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = (value >> (i * 8)) as u8;
+    }
+}
+
+fn add_le(bytes: &mut [u8], value: u32) {
+    let sum = read_le(bytes).wrapping_add(value);
+    write_le(bytes, sum);
+}
+
+// Applies one fixup to the bytes at `location`, adding the target's offset
+// from `frame` (or from the end of the location, if self-relative) and the
+// frame's paragraph to what is stored there. Returns where in `bytes` a
+// paragraph was written, which a loader has to relocate.
+pub fn apply_fixup(
+    bytes: &mut [u8],
+    kind: LocationType,
+    location: u32,
+    target: u32,
+    frame: u32,
+    segment_relative: bool,
+) -> Result<Option<usize>, OmfError> {
+    let size = kind.size();
+    let bytes = bytes
+        .get_mut(..size)
+        .ok_or(OmfError::Value("fixup location outside image"))?;
+    let offset = if segment_relative {
+        target.wrapping_sub(frame)
+    } else {
+        target.wrapping_sub(location + size as u32)
+    };
+    let paragraph = frame >> 4;
+    Ok(match kind {
+        LocationType::LowByte => {
+            add_le(&mut bytes[..1], offset);
+            None
+        }
+        LocationType::HighByte => {
+            add_le(&mut bytes[..1], offset >> 8);
+            None
+        }
+        LocationType::Offset | LocationType::LoaderOffset => {
+            if segment_relative && offset > 0xFFFF {
+                return Err(OmfError::Link(format!("fixup overflow at {location:05X}h")));
+            }
+            add_le(&mut bytes[..2], offset);
+            None
+        }
+        LocationType::Offset32 | LocationType::LoaderOffset32 => {
+            add_le(&mut bytes[..4], offset);
+            None
+        }
+        LocationType::Base => {
+            add_le(&mut bytes[..2], paragraph);
+            Some(0)
+        }
+        LocationType::Pointer => {
+            add_le(&mut bytes[..2], offset);
+            add_le(&mut bytes[2..4], paragraph);
+            Some(2)
+        }
+        LocationType::Pointer48 => {
+            add_le(&mut bytes[..4], offset);
+            add_le(&mut bytes[4..6], paragraph);
+            Some(4)
+        }
+    })
+}
+
+// Where a module's segments, groups and externals are in memory, for
+// applying its fixups without linking it. Frames are byte addresses of
+// paragraphs, as in ResolvedSymbol.
+#[derive(Debug, Clone, Default)]
+pub struct FixupContext {
+    segments: HashMap<SegmentIndex, ResolvedSymbol>,
+    groups: HashMap<GroupIndex, ResolvedSymbol>,
+    symbols: HashMap<String, ResolvedSymbol>,
+}
+
+impl FixupContext {
+    pub fn new() -> FixupContext {
+        FixupContext::default()
+    }
+
+    pub fn set_segment(&mut self, index: SegmentIndex, base: ResolvedSymbol) {
+        self.segments.insert(index, base);
+    }
+
+    pub fn set_group(&mut self, index: GroupIndex, base: ResolvedSymbol) {
+        self.groups.insert(index, base);
+    }
+
+    pub fn set_symbol(&mut self, name: &str, value: ResolvedSymbol) {
+        self.symbols.insert(name.to_string(), value);
+    }
+
+    pub fn resolve(&self, r: &RelocationRef) -> Result<ResolvedSymbol, OmfError> {
+        match r {
+            RelocationRef::Segment(i) => self
+                .segments
+                .get(i)
+                .copied()
+                .ok_or(OmfError::Value("segment not placed")),
+            RelocationRef::Group(i) => self
+                .groups
+                .get(i)
+                .copied()
+                .ok_or(OmfError::Value("group not placed")),
+            RelocationRef::External(name) => self
+                .symbols
+                .get(name.as_ref())
+                .copied()
+                .ok_or_else(|| OmfError::Link(format!("undefined symbol {name}"))),
+            RelocationRef::Absolute(frame) => Ok(ResolvedSymbol {
+                address: *frame as u32 * 16,
+                frame: *frame as u32 * 16,
+            }),
+        }
+    }
+}
+
+impl OmfModule {
+    // Copies of the module's segment images with every fixup applied, for
+    // segments placed as `context` says. The bytes a fixup wrote count as
+    // initialized.
+    pub fn apply_fixups(&self, context: &FixupContext) -> Result<Vec<SegmentImage>, OmfError> {
+        let mut images = self.segment_images()?;
+        for reloc in self.relocations()? {
+            let base = context.resolve(&RelocationRef::Segment(reloc.segment_index))?;
+            let target = context.resolve(&reloc.target)?;
+            let frame = context.resolve(&reloc.frame)?.frame;
+            let image = images
+                .iter_mut()
+                .find(|image| image.segment_index == reloc.segment_index)
+                .ok_or(OmfError::Value("segment index not found"))?;
+            let start = reloc.offset as usize;
+            apply_fixup(
+                image
+                    .data
+                    .get_mut(start..)
+                    .ok_or(OmfError::Value("fixup location outside image"))?,
+                reloc.kind,
+                base.address + reloc.offset,
+                target.address + reloc.target_displacement,
+                frame,
+                reloc.segment_relative,
+            )?;
+            image.mark_initialized(start..start + reloc.kind.size());
+        }
+        Ok(images)
+    }
+}
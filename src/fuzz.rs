@@ -0,0 +1,561 @@
+use std::sync::Arc;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{
+    error::OmfError, AliasName, CExtName, ComName, ComdatAllocation, ComdatData, ComdatSelection,
+    CommentType, CommunalLength, ExtName, ExternalIndex, Fixup, FixupData, FixupFrame,
+    FixupSubrecord, FixupTarget, FixupThread, GroupComponent, GroupIndex, IteratedDataBlock,
+    IteratedDataContent, LineNumber, LocationType, MAttrStart, NameIndex, OmfRecordData, OmfWriter,
+    PubName, SegmentAlignment, SegmentAttributes, SegmentCombination, SegmentIndex, TypeIndex,
+};
+
+const NAME_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_$@?";
+
+// A module whose records are all well formed, refer only to names,
+// segments, groups and externals defined before them, and keep data and
+// fixups inside their segments. Reading `to_bytes()` back should give the
+// same records, which makes it a seed for fuzzing the reader and for
+// round-trip tests of the writer. Single records with arbitrary contents,
+// valid or not, come from the `Arbitrary` derives on the record types.
+#[derive(Debug, Clone)]
+pub struct ArbitraryModule {
+    pub records: Vec<OmfRecordData>,
+}
+
+impl ArbitraryModule {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, OmfError> {
+        let mut out = vec![];
+        let mut writer = OmfWriter::new(&mut out);
+        for data in &self.records {
+            writer.write_data(data)?;
+        }
+        Ok(out)
+    }
+}
+
+// What has been defined so far, so later records can refer to it.
+struct Generator<'u, 'a> {
+    u: &'u mut Unstructured<'a>,
+    names: u16,
+    segment_lengths: Vec<u16>,
+    groups: u16,
+    externals: u16,
+    records: Vec<OmfRecordData>,
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryModule {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut g = Generator {
+            u,
+            names: 0,
+            segment_lengths: vec![],
+            groups: 0,
+            externals: 0,
+            records: vec![],
+        };
+        g.generate()?;
+        Ok(ArbitraryModule { records: g.records })
+    }
+}
+
+impl Generator<'_, '_> {
+    fn generate(&mut self) -> arbitrary::Result<()> {
+        let name = self.name()?;
+        self.records.push(OmfRecordData::THeadr { name });
+        if self.u.arbitrary()? {
+            self.comment()?;
+        }
+        self.lnames()?;
+        for _ in 0..self.u.int_in_range(1..=6)? {
+            self.segdef()?;
+        }
+        for _ in 0..self.u.int_in_range(0..=2)? {
+            self.grpdef()?;
+        }
+        if self.u.arbitrary()? {
+            self.extdef()?;
+        }
+        if self.u.arbitrary()? {
+            self.comdef()?;
+        }
+        for _ in 0..self.u.int_in_range(0..=3)? {
+            self.pubdef()?;
+        }
+        for _ in 0..self.u.int_in_range(0..=6)? {
+            self.data()?;
+        }
+        if self.u.arbitrary()? {
+            self.comdat()?;
+        }
+        if self.u.arbitrary()? {
+            self.alias()?;
+        }
+        self.modend()
+    }
+
+    fn name(&mut self) -> arbitrary::Result<String> {
+        let len = self.u.int_in_range(1..=12)?;
+        let mut name = String::new();
+        for _ in 0..len {
+            name.push(*self.u.choose(NAME_CHARS)? as char);
+        }
+        Ok(name)
+    }
+
+    fn name_index(&mut self) -> arbitrary::Result<NameIndex> {
+        self.u.int_in_range(1..=self.names).map(NameIndex)
+    }
+
+    fn segment_index(&mut self) -> arbitrary::Result<SegmentIndex> {
+        self.u
+            .int_in_range(1..=self.segment_lengths.len() as u16)
+            .map(SegmentIndex)
+    }
+
+    fn small_vec<T>(
+        &mut self,
+        max: usize,
+        mut item: impl FnMut(&mut Self) -> arbitrary::Result<T>,
+    ) -> arbitrary::Result<Vec<T>> {
+        let len = self.u.int_in_range(1..=max)?;
+        (0..len).map(|_| item(self)).collect()
+    }
+
+    fn bytes(&mut self, len: usize) -> arbitrary::Result<Vec<u8>> {
+        Ok(self.u.bytes(len)?.to_vec())
+    }
+
+    fn comment(&mut self) -> arbitrary::Result<()> {
+        let comment_type = CommentType {
+            no_purge: self.u.arbitrary()?,
+            no_list: self.u.arbitrary()?,
+        };
+        let comment_class = self.u.arbitrary()?;
+        let len = self.u.int_in_range(0..=32)?;
+        let comment_bytes = self.bytes(len)?;
+        self.records.push(OmfRecordData::Coment {
+            comment_type,
+            comment_class,
+            comment_bytes,
+        });
+        Ok(())
+    }
+
+    fn lnames(&mut self) -> arbitrary::Result<()> {
+        let names = self.small_vec(16, |g| g.name().map(Arc::from))?;
+        self.names += names.len() as u16;
+        self.records.push(OmfRecordData::LNames { names });
+        Ok(())
+    }
+
+    fn segdef(&mut self) -> arbitrary::Result<()> {
+        let alignment = *self.u.choose(&[
+            SegmentAlignment::RelocatableByteAligned,
+            SegmentAlignment::RelocatableWordAligned,
+            SegmentAlignment::RelocatableParagraphAligned,
+            SegmentAlignment::RelocatablePageAligned,
+            SegmentAlignment::RelocatableDWordAligned,
+        ])?;
+        let combination = *self.u.choose(&[
+            SegmentCombination::Private,
+            SegmentCombination::Public,
+            SegmentCombination::Stack,
+            SegmentCombination::Common,
+        ])?;
+        let segment_length = self.u.arbitrary()?;
+        let segment_name_index = self.name_index()?;
+        let class_name_index = self.name_index()?;
+        let overlay_name_index = if self.u.arbitrary()? {
+            self.name_index()?
+        } else {
+            NameIndex::NONE
+        };
+        self.segment_lengths.push(segment_length);
+        self.records.push(OmfRecordData::SegDef {
+            segment_attributes: SegmentAttributes {
+                alignment,
+                combination,
+                big: false,
+                bd32bit: false,
+                absolute_segment_address: None,
+            },
+            segment_length,
+            segment_name_index,
+            class_name_index,
+            overlay_name_index,
+        });
+        Ok(())
+    }
+
+    fn grpdef(&mut self) -> arbitrary::Result<()> {
+        let group_name_index = self.name_index()?;
+        let segment_definitions = self.small_vec(4, |g| {
+            Ok(GroupComponent {
+                index: 0xFF,
+                segment_definition: g.segment_index()?,
+            })
+        })?;
+        self.groups += 1;
+        self.records.push(OmfRecordData::GrpDef {
+            group_name_index,
+            segment_definitions,
+        });
+        Ok(())
+    }
+
+    fn extdef(&mut self) -> arbitrary::Result<()> {
+        let names = self.small_vec(8, |g| {
+            Ok(ExtName {
+                name: g.name()?,
+                type_index: TypeIndex::NONE,
+            })
+        })?;
+        self.externals += names.len() as u16;
+        self.records.push(if self.u.arbitrary()? {
+            OmfRecordData::ExtDef { names }
+        } else {
+            OmfRecordData::LExtDef { names }
+        });
+        Ok(())
+    }
+
+    fn comdef(&mut self) -> arbitrary::Result<()> {
+        let definitions = self.small_vec(4, |g| {
+            let communal_length = match g.u.int_in_range(0..=2)? {
+                0 => CommunalLength::Near(g.u.arbitrary()?),
+                1 => CommunalLength::Far {
+                    number_of_elements: g.u.arbitrary()?,
+                    element_size: g.u.arbitrary()?,
+                },
+                _ => CommunalLength::Segment {
+                    segment_index: SegmentIndex(g.u.int_in_range(1..=0x5F)?),
+                    size: g.u.arbitrary()?,
+                },
+            };
+            Ok(ComName {
+                name: g.name()?,
+                type_index: TypeIndex::NONE,
+                communal_length,
+            })
+        })?;
+        self.externals += definitions.len() as u16;
+        self.records.push(OmfRecordData::ComDef { definitions });
+        Ok(())
+    }
+
+    fn pubdef(&mut self) -> arbitrary::Result<()> {
+        let base_segment_index = self.segment_index()?;
+        let length = self.segment_lengths[base_segment_index.0 as usize - 1];
+        let names = self.small_vec(8, |g| {
+            Ok(PubName {
+                name: g.name()?,
+                public_offset: g.u.int_in_range(0..=length)?,
+                type_index: TypeIndex::NONE,
+            })
+        })?;
+        let data = if self.u.arbitrary()? {
+            OmfRecordData::PubDef {
+                base_group_index: GroupIndex::NONE,
+                base_segment_index,
+                base_frame: 0,
+                names,
+            }
+        } else {
+            OmfRecordData::LPubDef {
+                base_group_index: GroupIndex::NONE,
+                base_segment_index,
+                base_frame: 0,
+                names,
+            }
+        };
+        self.records.push(data);
+        Ok(())
+    }
+
+    // An LEDATA or LIDATA record in a segment with room for it, with fixups
+    // for LEDATA and line numbers for the segment.
+    fn data(&mut self) -> arbitrary::Result<()> {
+        let segment_index = self.segment_index()?;
+        let length = self.segment_lengths[segment_index.0 as usize - 1] as usize;
+        if length == 0 {
+            return Ok(());
+        }
+        let offset = self.u.int_in_range(0..=length - 1)?;
+        let room = (length - offset).min(1024);
+        if self.u.arbitrary()? {
+            let len = self.u.int_in_range(1..=room)?;
+            let data = self.bytes(len)?;
+            self.records.push(OmfRecordData::LEData {
+                segment_index,
+                enumerated_data_offset: offset as u16,
+                data,
+            });
+            if self.u.arbitrary()? {
+                self.fixupp(len)?;
+            }
+        } else {
+            let (block, _) = self.iterated_block(room, 2)?;
+            self.records.push(OmfRecordData::LIData {
+                segment_index,
+                iterated_data_offset: offset as u16,
+                blocks: vec![block],
+            });
+        }
+        if self.u.arbitrary()? {
+            let lines = self.lines(length)?;
+            self.records.push(OmfRecordData::LinNum {
+                base_group_index: GroupIndex::NONE,
+                base_segment_index: segment_index,
+                lines,
+            });
+        }
+        Ok(())
+    }
+
+    // A block expanding to at most `room` bytes. Returns the block and its
+    // expanded length.
+    fn iterated_block(
+        &mut self,
+        room: usize,
+        depth: u8,
+    ) -> arbitrary::Result<(IteratedDataBlock, usize)> {
+        let repeat_count = self.u.int_in_range(1..=room.min(4) as u16)?;
+        let room = room / repeat_count as usize;
+        if depth == 0 || room < 2 || self.u.arbitrary()? {
+            let len = self.u.int_in_range(1..=room.clamp(1, 16))?;
+            let data = self.bytes(len)?;
+            let block = IteratedDataBlock {
+                repeat_count,
+                content: IteratedDataContent::Data(data),
+            };
+            return Ok((block, repeat_count as usize * len));
+        }
+        let (inner, len) = self.iterated_block(room, depth - 1)?;
+        let block = IteratedDataBlock {
+            repeat_count,
+            content: IteratedDataContent::Blocks(vec![inner]),
+        };
+        Ok((block, repeat_count as usize * len))
+    }
+
+    fn lines(&mut self, length: usize) -> arbitrary::Result<Vec<LineNumber>> {
+        self.small_vec(8, |g| {
+            Ok(LineNumber {
+                line_number: g.u.int_in_range(1..=0x7FFF)?,
+                line_number_offset: g.u.int_in_range(0..=length.min(0xFFFF) as u16)?,
+            })
+        })
+    }
+
+    fn frame(&mut self) -> arbitrary::Result<FixupFrame> {
+        Ok(match self.u.int_in_range(0..=5)? {
+            0 => FixupFrame::Segment(self.segment_index()?),
+            1 if self.groups > 0 => {
+                FixupFrame::Group(GroupIndex(self.u.int_in_range(1..=self.groups)?))
+            }
+            2 if self.externals > 0 => {
+                FixupFrame::External(ExternalIndex(self.u.int_in_range(1..=self.externals)?))
+            }
+            3 => FixupFrame::FrameNumber(self.u.arbitrary()?),
+            4 => FixupFrame::Location,
+            _ => FixupFrame::Target,
+        })
+    }
+
+    fn target(&mut self) -> arbitrary::Result<FixupTarget> {
+        Ok(match self.u.int_in_range(0..=3)? {
+            1 if self.groups > 0 => {
+                FixupTarget::Group(GroupIndex(self.u.int_in_range(1..=self.groups)?))
+            }
+            2 if self.externals > 0 => {
+                FixupTarget::External(ExternalIndex(self.u.int_in_range(1..=self.externals)?))
+            }
+            3 => FixupTarget::FrameNumber(self.u.arbitrary()?),
+            _ => FixupTarget::Segment(self.segment_index()?),
+        })
+    }
+
+    fn fix_data(&mut self) -> arbitrary::Result<FixupData> {
+        let frame = self.frame()?;
+        let target = self.target()?;
+        let target_displacement = if self.u.arbitrary()? {
+            Some(self.u.arbitrary::<u16>()? as u32)
+        } else {
+            None
+        };
+        Ok(FixupData {
+            frame,
+            target,
+            target_displacement,
+        })
+    }
+
+    fn fixupp(&mut self, data_length: usize) -> arbitrary::Result<()> {
+        let subrecords = self.small_vec(6, |g| {
+            if g.u.ratio(1, 4)? {
+                let thread = g.u.int_in_range(0..=3)?;
+                let thread = if g.u.arbitrary()? {
+                    let frame = match g.frame()? {
+                        FixupFrame::Location | FixupFrame::Target => FixupFrame::FrameNumber(0),
+                        frame => frame,
+                    };
+                    FixupThread::Frame { thread, frame }
+                } else {
+                    let target = g.target()?;
+                    FixupThread::Target { thread, target }
+                };
+                return Ok(FixupSubrecord::Thread(thread));
+            }
+            let locations: Vec<LocationType> = [
+                LocationType::LowByte,
+                LocationType::Offset,
+                LocationType::Base,
+                LocationType::Pointer,
+                LocationType::HighByte,
+                LocationType::LoaderOffset,
+            ]
+            .into_iter()
+            .filter(|l| l.size() <= data_length)
+            .collect();
+            let location = *g.u.choose(&locations)?;
+            let data_record_offset = g.u.int_in_range(0..=data_length - location.size())? as u16;
+            Ok(FixupSubrecord::Fixup(Fixup {
+                segment_relative: g.u.arbitrary()?,
+                location,
+                data_record_offset,
+                fix_data: g.fix_data()?,
+            }))
+        })?;
+        self.records.push(OmfRecordData::FixUpp { subrecords });
+        Ok(())
+    }
+
+    // A COMDAT for a name, declared with CEXTDEF first, and its line
+    // numbers.
+    fn comdat(&mut self) -> arbitrary::Result<()> {
+        let public_name_index = self.name_index()?;
+        self.records.push(OmfRecordData::CExtDef {
+            names: vec![CExtName {
+                logical_name_index: public_name_index,
+                type_index: TypeIndex::NONE,
+            }],
+        });
+        self.externals += 1;
+        let allocation = *self.u.choose(&[
+            ComdatAllocation::Explicit,
+            ComdatAllocation::FarCode,
+            ComdatAllocation::FarData,
+        ])?;
+        let base_segment_index = if allocation == ComdatAllocation::Explicit {
+            self.segment_index()?
+        } else {
+            SegmentIndex::NONE
+        };
+        let data = if self.u.arbitrary()? {
+            let len = self.u.int_in_range(0..=64)?;
+            ComdatData::Enumerated(self.bytes(len)?)
+        } else {
+            ComdatData::Iterated(vec![self.iterated_block(64, 1)?.0])
+        };
+        let alignment = if self.u.arbitrary()? {
+            None
+        } else {
+            Some(SegmentAlignment::RelocatableWordAligned)
+        };
+        self.records.push(OmfRecordData::ComDat {
+            continuation: false,
+            local: self.u.arbitrary()?,
+            selection: *self.u.choose(&[
+                ComdatSelection::NoMatch,
+                ComdatSelection::PickAny,
+                ComdatSelection::SameSize,
+                ComdatSelection::ExactMatch,
+            ])?,
+            allocation,
+            alignment,
+            enumerated_data_offset: 0,
+            type_index: TypeIndex::NONE,
+            base_group_index: GroupIndex::NONE,
+            base_segment_index,
+            base_frame: 0,
+            public_name_index,
+            data,
+        });
+        if self.u.arbitrary()? {
+            let lines = self.lines(64)?;
+            self.records.push(OmfRecordData::LinSym {
+                continuation: false,
+                public_name_index,
+                lines,
+            });
+        }
+        Ok(())
+    }
+
+    fn alias(&mut self) -> arbitrary::Result<()> {
+        let aliases = self.small_vec(4, |g| {
+            Ok(AliasName {
+                alias_name: g.name()?,
+                substitute_name: g.name()?,
+            })
+        })?;
+        self.records.push(OmfRecordData::Alias { aliases });
+        Ok(())
+    }
+
+    fn modend(&mut self) -> arbitrary::Result<()> {
+        let start = if self.u.arbitrary()? {
+            let segment_index = self.segment_index()?;
+            MAttrStart::Start(FixupData {
+                frame: FixupFrame::Segment(segment_index),
+                target: FixupTarget::Segment(segment_index),
+                target_displacement: Some(self.u.arbitrary::<u16>()? as u32),
+            })
+        } else {
+            MAttrStart::NoStart
+        };
+        self.records.push(OmfRecordData::ModEnd {
+            main: self.u.arbitrary()?,
+            start,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use super::ArbitraryModule;
+    use crate::{OmfReader, OmfWriter};
+
+    #[test]
+    fn read_write_round_trip() {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        for _ in 0..200 {
+            let bytes: Vec<u8> = (0..4096)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            let module = ArbitraryModule::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let written = module.to_bytes().unwrap();
+            let records = OmfReader::new(&written[..])
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            let data: Vec<_> = records.iter().map(|r| &r.data).collect();
+            assert_eq!(format!("{data:?}"), format!("{:?}", module.records));
+
+            let mut rewritten = vec![];
+            let mut writer = OmfWriter::new(&mut rewritten);
+            for record in &records {
+                writer.write_record(record).unwrap();
+            }
+            assert_eq!(rewritten, written);
+        }
+    }
+}
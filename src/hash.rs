@@ -0,0 +1,99 @@
+use crate::{collect_comdats, error::OmfError, OmfModule, RelocationRef, SegmentIndex};
+
+// 64-bit FNV-1a. Hashes are meant to be compared between runs and between
+// versions of this crate, so std's hashers, which may change, aren't used.
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(FNV_OFFSET)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    let mut h = Fnv::new();
+    h.write(bytes);
+    h.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashedContent {
+    Segment(SegmentIndex),
+    Comdat,
+}
+
+// Hashes of a segment's or COMDAT's reconstructed contents. `hash` covers
+// only the bytes. `hash_with_fixups` also covers each fixup's position,
+// kind and target, by name, so code that only differs in what it calls
+// hashes differently. COMDATs have only the one hash.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentHash {
+    pub name: String,
+    pub content: HashedContent,
+    pub length: usize,
+    pub hash: u64,
+    pub hash_with_fixups: u64,
+}
+
+impl OmfModule {
+    // Hashes every segment, in the order they are defined, then every
+    // COMDAT.
+    pub fn content_hashes(&self) -> Result<Vec<ContentHash>, OmfError> {
+        let info = self.info();
+        let images = self.segment_images()?;
+        let relocations = self.relocations()?;
+        let mut hashes = vec![];
+        for (index, name, _) in info.segments_with_names() {
+            let data = images
+                .iter()
+                .find(|image| image.segment_index == index)
+                .map_or(&[][..], |image| &image.data);
+            let mut h = Fnv::new();
+            h.write(data);
+            for r in relocations.iter().filter(|r| r.segment_index == index) {
+                h.write(&r.offset.to_le_bytes());
+                h.write(&[r.kind as u8, r.segment_relative as u8]);
+                let target = match &r.target {
+                    RelocationRef::Segment(i) => info
+                        .get_segment(*i)
+                        .and_then(|s| info.name_from_index(s.segment_name_index))?,
+                    RelocationRef::Group(i) => info
+                        .get_group(*i)
+                        .and_then(|g| info.name_from_index(g.group_name_index))?,
+                    RelocationRef::External(name) => name.clone(),
+                    RelocationRef::Absolute(frame) => frame.to_string().into(),
+                };
+                h.write(target.as_bytes());
+                h.write(&[0]);
+                h.write(&r.target_displacement.to_le_bytes());
+            }
+            hashes.push(ContentHash {
+                name: name.to_string(),
+                content: HashedContent::Segment(index),
+                length: data.len(),
+                hash: content_hash(data),
+                hash_with_fixups: h.0,
+            });
+        }
+        for comdat in collect_comdats(0, &self.records, info)? {
+            let hash = content_hash(&comdat.data);
+            hashes.push(ContentHash {
+                name: comdat.name,
+                content: HashedContent::Comdat,
+                length: comdat.data.len(),
+                hash,
+                hash_with_fixups: hash,
+            });
+        }
+        Ok(hashes)
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    build_line_entries,
+    debug::{read_types, DebugBytes, DebugSegments},
+    error::OmfError,
+    DebugFormat, DebugInfo, DebugSymbol, DebugSymbolKind, OmfModule, OmfRecordData, SegmentIndex,
+};
+
+// The comment naming the debug format: a version byte, then "HL" for IBM's
+// HLL or "CV" for CodeView.
+const COMENT_DEBUG_FORMAT: u8 = 0xA1;
+
+const SYMBOLS_SEGMENT: &str = "$$SYMBOLS";
+const TYPES_SEGMENT: &str = "$$TYPES";
+
+// HLL's primitive types take the indices below this.
+const FIRST_TYPE_INDEX: u32 = 0x200;
+
+// The HLL version the module says its debug data is in, if it says it is
+// HLL.
+fn hll_version(module: &OmfModule) -> Option<u8> {
+    module.records.iter().find_map(|record| match &record.data {
+        OmfRecordData::Coment {
+            comment_class: COMENT_DEBUG_FORMAT,
+            comment_bytes,
+            ..
+        } => match comment_bytes[..] {
+            [version, b'H', b'L', ..] => Some(version),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+// HLL records and names are counted like OMF indices: by one byte, or by
+// two when the first has its top bit set.
+fn name(r: &mut DebugBytes) -> Result<String, OmfError> {
+    let len = r.index()? as usize;
+    Ok(String::from_utf8_lossy(r.take(len)?).into_owned())
+}
+
+// An address in a symbol record: a 32-bit offset, then for some records a
+// segment. Offsets are left for fixups to fill in, as in CodeView.
+fn address(
+    segments: &DebugSegments,
+    index: SegmentIndex,
+    r: &mut DebugBytes,
+    with_segment: bool,
+) -> Result<(Option<SegmentIndex>, u32), OmfError> {
+    let offset_at = r.pos;
+    let offset = r.u32()?;
+    let segment_at = r.pos;
+    if with_segment {
+        r.u16()?;
+    }
+    Ok(segments.address(index, offset_at, segment_at, offset))
+}
+
+pub(crate) fn read_hll(
+    module: &OmfModule,
+    segments: &DebugSegments,
+) -> Result<Option<DebugInfo>, OmfError> {
+    let Some(version) = hll_version(module) else {
+        return Ok(None);
+    };
+    let symbols = match segments.by_name(SYMBOLS_SEGMENT) {
+        Some((index, data)) => read_symbols(segments, index, data)?,
+        None => vec![],
+    };
+    let types = match segments.by_name(TYPES_SEGMENT) {
+        Some((_, data)) => read_types(data, 0, FIRST_TYPE_INDEX)?,
+        None => vec![],
+    };
+    // Line number tables are carried in LINNUM records, each starting with
+    // an entry for line 0 that describes the table rather than a line.
+    let mut lines = build_line_entries(&module.records, module.info())?;
+    lines.retain(|l| l.line != 0);
+    Ok(Some(DebugInfo {
+        format: DebugFormat::Hll { version },
+        symbols,
+        types,
+        lines,
+    }))
+}
+
+fn read_symbols(
+    segments: &DebugSegments,
+    index: SegmentIndex,
+    data: &[u8],
+) -> Result<Vec<DebugSymbol>, OmfError> {
+    let mut symbols = vec![];
+    let mut r = DebugBytes::new(data);
+    while r.unread() > 0 {
+        let length = r.index()? as usize;
+        if length == 0 {
+            break;
+        }
+        let end = r.pos + length;
+        symbols.push(read_symbol(segments, index, &mut r)?);
+        r.pos = end;
+    }
+    Ok(symbols)
+}
+
+fn read_symbol(
+    segments: &DebugSegments,
+    index: SegmentIndex,
+    r: &mut DebugBytes,
+) -> Result<DebugSymbol, OmfError> {
+    let record_type = r.u8()?;
+    let mut symbol = DebugSymbol {
+        name: String::new(),
+        kind: DebugSymbolKind::Other(record_type as u16),
+        segment: None,
+        offset: 0,
+        type_index: 0,
+    };
+    match record_type {
+        // Begin block
+        0x00 => {
+            (symbol.segment, symbol.offset) = address(segments, index, r, false)?;
+            symbol.kind = DebugSymbolKind::Block { length: r.u32()? };
+            symbol.name = name(r)?;
+        }
+        // Procedure, and C++ member function, after which come the
+        // prologue length, where the body starts, its class and whether it
+        // is near or far.
+        0x01 | 0x1D => {
+            (symbol.segment, symbol.offset) = address(segments, index, r, false)?;
+            symbol.type_index = r.u16()? as u32;
+            let length = r.u32()?;
+            r.take(8)?;
+            symbol.kind = DebugSymbolKind::Procedure {
+                global: true,
+                length,
+            };
+            symbol.name = name(r)?;
+        }
+        // End of block or procedure
+        0x02 => symbol.kind = DebugSymbolKind::End,
+        // Automatic, relative to the frame
+        0x04 => {
+            symbol.kind = DebugSymbolKind::Local {
+                frame_offset: r.u32()? as i32,
+            };
+            symbol.type_index = r.u16()? as u32;
+            symbol.name = name(r)?;
+        }
+        // Static
+        0x05 => {
+            (symbol.segment, symbol.offset) = address(segments, index, r, true)?;
+            symbol.type_index = r.u16()? as u32;
+            symbol.kind = DebugSymbolKind::Data { global: false };
+            symbol.name = name(r)?;
+        }
+        // Code label, then whether it is near or far
+        0x0B => {
+            (symbol.segment, symbol.offset) = address(segments, index, r, false)?;
+            r.u8()?;
+            symbol.kind = DebugSymbolKind::Label;
+            symbol.name = name(r)?;
+        }
+        // Register
+        0x0D => {
+            symbol.type_index = r.u16()? as u32;
+            symbol.kind = DebugSymbolKind::Register {
+                register: r.u8()? as u16,
+            };
+            symbol.name = name(r)?;
+        }
+        // Typedef
+        0x12 => {
+            symbol.type_index = r.u16()? as u32;
+            symbol.kind = DebugSymbolKind::Typedef;
+            symbol.name = name(r)?;
+        }
+        // Public
+        0x13 => {
+            (symbol.segment, symbol.offset) = address(segments, index, r, true)?;
+            symbol.type_index = r.u16()? as u32;
+            symbol.kind = DebugSymbolKind::Public;
+            symbol.name = name(r)?;
+        }
+        _ => (),
+    }
+    Ok(symbol)
+}
@@ -0,0 +1,193 @@
+use std::fmt::Display;
+
+use crate::{OmfModule, OmfRecordData};
+
+const COMENT_TRANSLATOR: u8 = 0x00;
+const COMENT_WATCOM_MODEL: u8 = 0x9B;
+const COMENT_MEMORY_MODEL: u8 = 0x9D;
+const COMENT_DEFAULT_LIBRARY: u8 = 0x9F;
+const COMENT_DEBUG_FORMAT: u8 = 0xA1;
+const COMENT_DEPENDENCY: u8 = 0xE9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toolchain {
+    // Turbo C and Borland C++.
+    Borland,
+    Tasm,
+    Masm,
+    MicrosoftC,
+    Watcom,
+    Nasm,
+    Unknown,
+}
+
+impl Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Toolchain::Borland => "Borland/Turbo C",
+            Toolchain::Tasm => "Turbo Assembler",
+            Toolchain::Masm => "Microsoft Macro Assembler",
+            Toolchain::MicrosoftC => "Microsoft C",
+            Toolchain::Watcom => "Watcom",
+            Toolchain::Nasm => "NASM",
+            Toolchain::Unknown => "unknown",
+        })
+    }
+}
+
+// A guess at what produced a module, with the clues it is based on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identification {
+    pub toolchain: Toolchain,
+    // Only known when the translator comment gives one.
+    pub version: Option<String>,
+    pub evidence: Vec<String>,
+}
+
+// What a translator comment says produced the module. These are matched
+// in order, so assemblers come before the compilers of the same vendor.
+fn translator_toolchain(text: &str) -> Option<Toolchain> {
+    let text = text.to_ascii_lowercase();
+    let toolchain = if text.contains("turbo assembler") {
+        Toolchain::Tasm
+    } else if text.contains("turbo c") || text.contains("borland") || text.starts_with("tc86") {
+        Toolchain::Borland
+    } else if text.contains("macro assembler") {
+        Toolchain::Masm
+    } else if text.contains("microsoft") {
+        Toolchain::MicrosoftC
+    } else if text.contains("watcom") {
+        Toolchain::Watcom
+    } else if text.contains("netwide") {
+        Toolchain::Nasm
+    } else {
+        return None;
+    };
+    Some(toolchain)
+}
+
+// The version in a translator comment: the word after "Version", or else
+// the last word that starts with a digit.
+fn translator_version(text: &str) -> Option<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if let Some(i) = words.iter().position(|w| w.eq_ignore_ascii_case("version")) {
+        if let Some(version) = words.get(i + 1) {
+            return Some(version.to_string());
+        }
+    }
+    words
+        .iter()
+        .rev()
+        .find(|w| w.starts_with(|c: char| c.is_ascii_digit()))
+        .map(|w| w.to_string())
+}
+
+// Default libraries are named after the memory model, "SLIBCE" and the like
+// for Microsoft C, and "clib3r" and the like for Watcom.
+fn library_toolchain(library: &str) -> Option<Toolchain> {
+    let library = library.to_ascii_lowercase();
+    if library.starts_with("clib") || (library.starts_with("math") && library.ends_with('r')) {
+        Some(Toolchain::Watcom)
+    } else if library.contains("libc") {
+        Some(Toolchain::MicrosoftC)
+    } else {
+        None
+    }
+}
+
+// Segments only one toolchain's runtime or code generator uses.
+fn segment_toolchain(name: &str) -> Option<Toolchain> {
+    match name {
+        "CONST2" | "YIB" | "YI" | "YIE" => Some(Toolchain::Watcom),
+        "_INIT_" | "_EXIT_" | "_INITEND_" | "_EXITEND_" => Some(Toolchain::Borland),
+        "c_common" => Some(Toolchain::MicrosoftC),
+        _ => None,
+    }
+}
+
+impl OmfModule {
+    // Guesses which compiler or assembler produced the module. A translator
+    // comment naming one decides it; otherwise the comment classes and
+    // segment names it uses are counted as votes.
+    pub fn identify(&self) -> Identification {
+        let mut votes: Vec<(Toolchain, u32)> = vec![];
+        let mut evidence = vec![];
+        // The toolchain a translator comment names, with the version it
+        // gives.
+        let mut translator = None;
+        let mut vote = |toolchain: Toolchain, weight: u32| match votes
+            .iter_mut()
+            .find(|(t, _)| *t == toolchain)
+        {
+            Some((_, w)) => *w += weight,
+            None => votes.push((toolchain, weight)),
+        };
+        let mut borland_debug = false;
+        for record in &self.records {
+            let OmfRecordData::Coment {
+                comment_class,
+                comment_bytes,
+                ..
+            } = &record.data
+            else {
+                continue;
+            };
+            let text = String::from_utf8_lossy(comment_bytes);
+            match *comment_class {
+                COMENT_TRANSLATOR => {
+                    if let Some(toolchain) = translator_toolchain(&text) {
+                        vote(toolchain, 100);
+                        translator.get_or_insert((toolchain, translator_version(&text)));
+                    }
+                    evidence.push(format!("translator \"{}\"", text.trim()));
+                }
+                COMENT_WATCOM_MODEL => {
+                    vote(Toolchain::Watcom, 3);
+                    evidence.push(format!("Watcom processor and model \"{text}\""));
+                }
+                COMENT_MEMORY_MODEL => {
+                    vote(Toolchain::MicrosoftC, 3);
+                    evidence.push(format!("memory model \"{text}\""));
+                }
+                COMENT_DEFAULT_LIBRARY => {
+                    if let Some(toolchain) = library_toolchain(&text) {
+                        vote(toolchain, 3);
+                        evidence.push(format!("default library {text}"));
+                    }
+                }
+                COMENT_DEBUG_FORMAT => {
+                    vote(Toolchain::MicrosoftC, 1);
+                    vote(Toolchain::Masm, 1);
+                    evidence.push("CodeView debug information".to_string());
+                }
+                0xE0..=COMENT_DEPENDENCY if !borland_debug => {
+                    borland_debug = true;
+                    vote(Toolchain::Borland, 3);
+                    vote(Toolchain::Tasm, 2);
+                    evidence.push(format!("Borland comment class {comment_class:02X}h"));
+                }
+                _ => (),
+            }
+        }
+        let info = self.info();
+        for (_, name, _) in info.segments_with_names() {
+            if let Some(toolchain) = segment_toolchain(name) {
+                vote(toolchain, 2);
+                evidence.push(format!("segment {name}"));
+            }
+        }
+        // Ties go to whichever was seen first.
+        let toolchain = votes
+            .iter()
+            .rev()
+            .max_by_key(|(_, w)| *w)
+            .map_or(Toolchain::Unknown, |(t, _)| *t);
+        Identification {
+            toolchain,
+            version: translator
+                .filter(|(t, _)| *t == toolchain)
+                .and_then(|(_, version)| version),
+            evidence,
+        }
+    }
+}
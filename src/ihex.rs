@@ -0,0 +1,60 @@
+use std::io::{self, Write};
+
+use crate::LinkedProgram;
+
+fn write_record(w: &mut dyn Write, record_type: u8, address: u16, data: &[u8]) -> io::Result<()> {
+    let mut sum = data.len() as u8;
+    sum = sum.wrapping_add((address >> 8) as u8);
+    sum = sum.wrapping_add(address as u8);
+    sum = sum.wrapping_add(record_type);
+    write!(w, ":{:02X}{:04X}{:02X}", data.len(), address, record_type)?;
+    for b in data {
+        sum = sum.wrapping_add(*b);
+        write!(w, "{b:02X}")?;
+    }
+    writeln!(w, "{:02X}", sum.wrapping_neg())
+}
+
+// Writes each (address, bytes) region as Intel HEX data records, switching
+// the upper address with extended linear address records as needed.
+pub fn write_intel_hex<'a>(
+    w: &mut dyn Write,
+    regions: impl IntoIterator<Item = (u32, &'a [u8])>,
+    start: Option<u32>,
+) -> io::Result<()> {
+    let mut upper = 0u16;
+    for (address, data) in regions {
+        let mut address = address;
+        for chunk in data.chunks(16) {
+            // Don't let a record straddle a 64K boundary.
+            let room = (0x10000 - (address & 0xFFFF)) as usize;
+            let (first, rest) = chunk.split_at(chunk.len().min(room));
+            for part in [first, rest] {
+                if part.is_empty() {
+                    continue;
+                }
+                if (address >> 16) as u16 != upper {
+                    upper = (address >> 16) as u16;
+                    write_record(w, 0x04, 0, &upper.to_be_bytes())?;
+                }
+                write_record(w, 0x00, address as u16, part)?;
+                address += part.len() as u32;
+            }
+        }
+    }
+    if let Some(start) = start {
+        write_record(w, 0x05, 0, &start.to_be_bytes())?;
+    }
+    write_record(w, 0x01, 0, &[])
+}
+
+impl LinkedProgram {
+    pub fn write_intel_hex(&self, w: &mut dyn Write) -> io::Result<()> {
+        let regions = self.initialized.iter().map(|r| {
+            let start = (r.start - self.origin) as usize;
+            let end = (r.end - self.origin) as usize;
+            (r.start, &self.image[start..end])
+        });
+        write_intel_hex(w, regions, self.entry.map(|e| e.address))
+    }
+}
@@ -0,0 +1,231 @@
+use std::ops::Range;
+
+use crate::{
+    error::OmfError,
+    record::{IteratedDataBlock, IteratedDataContent, OmfRecord, OmfRecordData},
+    SegmentIndex,
+};
+
+// How deeply LIDATA blocks may nest. Real translators use a few levels;
+// more is only seen in files made to exhaust the stack.
+pub(crate) const MAX_ITERATED_DEPTH: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct SegmentImage {
+    pub segment_index: SegmentIndex,
+    pub data: Vec<u8>,
+    pub initialized: Vec<Range<usize>>,
+}
+
+impl SegmentImage {
+    pub fn new(segment_index: SegmentIndex, length: usize) -> SegmentImage {
+        SegmentImage {
+            segment_index,
+            data: vec![0u8; length],
+            initialized: vec![],
+        }
+    }
+
+    pub fn is_initialized(&self, offset: usize) -> bool {
+        self.initialized.iter().any(|r| r.contains(&offset))
+    }
+
+    pub fn uninitialized(&self) -> Vec<Range<usize>> {
+        let mut ranges = vec![];
+        let mut start = 0;
+        for r in &self.initialized {
+            if r.start > start {
+                ranges.push(start..r.start);
+            }
+            start = r.end;
+        }
+        if start < self.data.len() {
+            ranges.push(start..self.data.len());
+        }
+        ranges
+    }
+
+    fn apply(&mut self, offset: usize, bytes: &[u8]) -> Result<(), OmfError> {
+        let end = offset + bytes.len();
+        if end > self.data.len() {
+            return Err(OmfError::Value("data exceeds segment length"));
+        }
+        self.data[offset..end].copy_from_slice(bytes);
+        self.mark_initialized(offset..end);
+        Ok(())
+    }
+
+    pub(crate) fn mark_initialized(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        let mut merged = range;
+        let mut ranges = vec![];
+        for r in self.initialized.drain(..) {
+            if r.end < merged.start || r.start > merged.end {
+                ranges.push(r);
+            } else {
+                merged = merged.start.min(r.start)..merged.end.max(r.end);
+            }
+        }
+        ranges.push(merged);
+        ranges.sort_by_key(|r| r.start);
+        self.initialized = ranges;
+    }
+}
+
+fn expand_block(
+    block: &IteratedDataBlock,
+    out: &mut Vec<u8>,
+    limit: usize,
+    depth: usize,
+) -> Result<(), OmfError> {
+    if depth >= MAX_ITERATED_DEPTH {
+        return Err(OmfError::Value("iterated data nested too deeply"));
+    }
+    for _ in 0..block.repeat_count {
+        match &block.content {
+            IteratedDataContent::Blocks(blocks) => {
+                for b in blocks {
+                    expand_block(b, out, limit, depth + 1)?;
+                }
+            }
+            IteratedDataContent::Data(data) => {
+                if out.len() + data.len() > limit {
+                    return Err(OmfError::Value("iterated data exceeds size limit"));
+                }
+                out.extend_from_slice(data);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Expands an LIDATA block tree into flat bytes, failing if the result would
+// be larger than `limit` bytes.
+pub fn expand_iterated_data(
+    blocks: &[IteratedDataBlock],
+    limit: usize,
+) -> Result<Vec<u8>, OmfError> {
+    let mut out = vec![];
+    for b in blocks {
+        expand_block(b, &mut out, limit, 0)?;
+    }
+    Ok(out)
+}
+
+// Where the byte `record_offset` bytes into an LIDATA record's blocks ends
+// up once they are expanded. A fixup following LIDATA patches the blocks
+// before expansion, so it applies to every copy of the bytes it covers.
+pub(crate) fn iterated_offsets(
+    blocks: &[IteratedDataBlock],
+    record_offset: usize,
+) -> Result<Vec<usize>, OmfError> {
+    let (encoded, _, offsets) = locate_in_blocks(blocks, Some(record_offset), 0)?;
+    if record_offset >= encoded {
+        return Err(OmfError::Value("fixup past the end of iterated data"));
+    }
+    Ok(offsets)
+}
+
+// The encoded and expanded lengths of blocks laid out one after another,
+// and the expanded offsets of the byte at `at` in their encoding.
+fn locate_in_blocks(
+    blocks: &[IteratedDataBlock],
+    at: Option<usize>,
+    depth: usize,
+) -> Result<(usize, usize, Vec<usize>), OmfError> {
+    let (mut encoded, mut expanded, mut offsets) = (0, 0, vec![]);
+    for b in blocks {
+        let (e, x, o) = locate_in_block(b, at.and_then(|a| a.checked_sub(encoded)), depth)?;
+        offsets.extend(o.into_iter().map(|o| expanded + o));
+        encoded += e;
+        expanded += x;
+    }
+    Ok((encoded, expanded, offsets))
+}
+
+fn locate_in_block(
+    block: &IteratedDataBlock,
+    at: Option<usize>,
+    depth: usize,
+) -> Result<(usize, usize, Vec<usize>), OmfError> {
+    if depth >= MAX_ITERATED_DEPTH {
+        return Err(OmfError::Value("iterated data nested too deeply"));
+    }
+    // A block starts with its repeat and block counts, and data blocks
+    // with the length of their data.
+    let (header, encoded, once, inner) = match &block.content {
+        IteratedDataContent::Blocks(blocks) => {
+            let (e, x, o) = locate_in_blocks(blocks, at.and_then(|a| a.checked_sub(4)), depth + 1)?;
+            (4, 4 + e, x, o)
+        }
+        IteratedDataContent::Data(data) => {
+            let o = at
+                .and_then(|a| a.checked_sub(5))
+                .filter(|&a| a < data.len());
+            (5, 5 + data.len(), data.len(), o.into_iter().collect())
+        }
+    };
+    if at.is_some_and(|a| a < header) {
+        return Err(OmfError::Value("fixup on the counts of iterated data"));
+    }
+    let repeat = block.repeat_count as usize;
+    let expanded = once
+        .checked_mul(repeat)
+        .filter(|&n| n <= 0x10000)
+        .ok_or(OmfError::Value("iterated data exceeds size limit"))?;
+    let offsets = (0..repeat)
+        .flat_map(|r| inner.iter().map(move |o| r * once + o))
+        .collect();
+    Ok((encoded, expanded, offsets))
+}
+
+pub fn build_segment_images(records: &[OmfRecord]) -> Result<Vec<SegmentImage>, OmfError> {
+    let mut images = vec![];
+    for record in records {
+        match &record.data {
+            OmfRecordData::SegDef {
+                segment_attributes,
+                segment_length,
+                ..
+            } => {
+                let length = if segment_attributes.big {
+                    0x10000
+                } else {
+                    *segment_length as usize
+                };
+                images.push(SegmentImage::new(
+                    SegmentIndex(images.len() as u16 + 1),
+                    length,
+                ));
+            }
+            OmfRecordData::LEData {
+                segment_index,
+                enumerated_data_offset,
+                data,
+            } => {
+                let image = images
+                    .get_mut((segment_index.0 as usize).wrapping_sub(1))
+                    .ok_or(OmfError::Value("segment index not found"))?;
+                image.apply(*enumerated_data_offset as usize, data)?;
+            }
+            OmfRecordData::LIData {
+                segment_index,
+                iterated_data_offset,
+                blocks,
+            } => {
+                let image = images
+                    .get_mut((segment_index.0 as usize).wrapping_sub(1))
+                    .ok_or(OmfError::Value("segment index not found"))?;
+                let offset = *iterated_data_offset as usize;
+                let limit = image.data.len().saturating_sub(offset);
+                let data = expand_iterated_data(blocks, limit)
+                    .map_err(|_| OmfError::Value("data exceeds segment length"))?;
+                image.apply(offset, &data)?;
+            }
+            _ => (),
+        }
+    }
+    Ok(images)
+}
@@ -0,0 +1,77 @@
+use std::{fmt::Display, sync::Arc};
+
+use crate::{error::OmfError, GroupInfo, OmfInfo, SegmentInfo};
+
+// Indices count from 1 in the order the module defines things, and 0 stands
+// for none. Each kind of index gets its own type so one can't be passed where
+// another is expected.
+macro_rules! index_type {
+    ($name:ident) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        #[cfg_attr(
+            feature = "serde",
+            derive(serde::Serialize, serde::Deserialize),
+            serde(transparent)
+        )]
+        #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+        // Laid out as a plain u16 for the C interface.
+        #[repr(transparent)]
+        pub struct $name(pub u16);
+
+        impl $name {
+            pub const NONE: $name = $name(0);
+
+            pub fn is_none(self) -> bool {
+                self.0 == 0
+            }
+
+            // Where the index points in its list, or None for index 0.
+            pub fn position(self) -> Option<usize> {
+                (self.0 as usize).checked_sub(1)
+            }
+        }
+
+        impl From<u16> for $name {
+            fn from(index: u16) -> $name {
+                $name(index)
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+index_type!(NameIndex);
+index_type!(SegmentIndex);
+index_type!(GroupIndex);
+index_type!(ExternalIndex);
+// Types are only carried through; their TYPDEF records aren't decoded.
+index_type!(TypeIndex);
+
+impl NameIndex {
+    pub fn resolve(self, info: &OmfInfo) -> Result<Arc<str>, OmfError> {
+        info.name_from_index(self)
+    }
+}
+
+impl SegmentIndex {
+    pub fn resolve(self, info: &OmfInfo) -> Result<&SegmentInfo, OmfError> {
+        info.get_segment(self)
+    }
+}
+
+impl GroupIndex {
+    pub fn resolve(self, info: &OmfInfo) -> Result<&GroupInfo, OmfError> {
+        info.get_group(self)
+    }
+}
+
+impl ExternalIndex {
+    pub fn resolve(self, info: &OmfInfo) -> Result<Arc<str>, OmfError> {
+        info.get_external(self)
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    error::OmfError,
+    record::{SegmentAlignment, SegmentCombination},
+    OmfModule, SegmentIndex,
+};
+
+#[derive(Debug, Clone)]
+pub struct SegmentPlacement {
+    pub module: usize,
+    pub segment_index: SegmentIndex,
+    pub name: String,
+    pub class: String,
+    pub combination: SegmentCombination,
+    pub segment_base: u32,
+    pub base: u32,
+    pub length: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupPlacement {
+    pub name: String,
+    pub base: u32,
+    pub length: u32,
+    pub segments: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    pub segments: Vec<SegmentPlacement>,
+    pub groups: Vec<GroupPlacement>,
+}
+
+impl Layout {
+    pub fn segment(&self, module: usize, segment_index: SegmentIndex) -> Option<&SegmentPlacement> {
+        self.segments
+            .iter()
+            .find(|s| s.module == module && s.segment_index == segment_index)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&GroupPlacement> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+
+    pub fn end(&self) -> u32 {
+        self.segments
+            .iter()
+            .map(|s| s.base + s.length)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LayoutEntry {
+    pub name: String,
+    pub address: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LayoutSpec {
+    pub origin: u32,
+    pub entries: Vec<LayoutEntry>,
+}
+
+struct Contribution {
+    module: usize,
+    segment_index: SegmentIndex,
+    name: String,
+    class: String,
+    alignment: SegmentAlignment,
+    combination: SegmentCombination,
+    length: u32,
+    absolute: Option<u32>,
+}
+
+struct LogicalSegment {
+    class: String,
+    common: bool,
+    contributions: Vec<Contribution>,
+}
+
+fn align(addr: u32, alignment: u32) -> u32 {
+    addr.div_ceil(alignment) * alignment
+}
+
+// Places every segment of `modules` starting at `origin`. Public and stack
+// segments with the same name and class are concatenated, common segments
+// are overlaid, and logical segments are ordered by class in order of first
+// appearance. Absolute segments are placed at their fixed address.
+pub fn compute_layout(modules: &[OmfModule], origin: u32) -> Result<Layout, OmfError> {
+    compute_layout_with(
+        modules,
+        &LayoutSpec {
+            origin,
+            entries: vec![],
+        },
+    )
+}
+
+// Like compute_layout, but segments (or classes) named in the spec are placed
+// first, in the order given, optionally at a fixed address. Everything else
+// follows in the default order.
+pub fn compute_layout_with(modules: &[OmfModule], spec: &LayoutSpec) -> Result<Layout, OmfError> {
+    let mut logical: Vec<LogicalSegment> = vec![];
+    let mut classes: Vec<String> = vec![];
+    let mut layout = Layout::default();
+
+    for (m, module) in modules.iter().enumerate() {
+        let info = module.info();
+        for (i, s) in info.segments.iter().enumerate() {
+            let attrs = &s.segment_attributes;
+            let contribution = Contribution {
+                module: m,
+                segment_index: SegmentIndex(i as u16 + 1),
+                name: info.name(s.segment_name_index)?.to_string(),
+                class: info.name(s.class_name_index)?.to_string(),
+                alignment: attrs.alignment,
+                combination: attrs.combination,
+                length: s.length(),
+                absolute: attrs
+                    .absolute_segment_address
+                    .map(|a| a.frame_number as u32 * 16 + a.offset as u32),
+            };
+            if !classes.contains(&contribution.class) {
+                classes.push(contribution.class.clone());
+            }
+            let combinable = contribution.absolute.is_none()
+                && contribution.combination != SegmentCombination::Private;
+            let existing = if combinable {
+                logical.iter_mut().find(|l| {
+                    let first = &l.contributions[0];
+                    first.name == contribution.name
+                        && first.class == contribution.class
+                        && first.absolute.is_none()
+                        && first.combination != SegmentCombination::Private
+                })
+            } else {
+                None
+            };
+            match existing {
+                Some(l) => l.contributions.push(contribution),
+                None => logical.push(LogicalSegment {
+                    class: contribution.class.clone(),
+                    common: contribution.combination == SegmentCombination::Common,
+                    contributions: vec![contribution],
+                }),
+            }
+        }
+    }
+
+    let mut order: Vec<(usize, Option<u32>)> = vec![];
+    for entry in &spec.entries {
+        let unplaced = |i: &usize| !order.iter().any(|(o, _)| o == i);
+        let mut matches: Vec<usize> = (0..logical.len())
+            .filter(unplaced)
+            .filter(|&i| logical[i].contributions[0].name == entry.name)
+            .collect();
+        if matches.is_empty() {
+            matches = (0..logical.len())
+                .filter(unplaced)
+                .filter(|&i| logical[i].class == entry.name)
+                .collect();
+        }
+        for (k, i) in matches.into_iter().enumerate() {
+            order.push((i, if k == 0 { entry.address } else { None }));
+        }
+    }
+    for class in &classes {
+        for (i, l) in logical.iter().enumerate() {
+            if &l.class == class && !order.iter().any(|(o, _)| *o == i) {
+                order.push((i, None));
+            }
+        }
+    }
+
+    let mut addr = spec.origin;
+    for (i, fixed) in order {
+        let l = &logical[i];
+        if let Some(base) = l.contributions[0].absolute {
+            for c in &l.contributions {
+                layout.segments.push(placement(c, base, base));
+            }
+            continue;
+        }
+        if let Some(fixed) = fixed {
+            addr = fixed;
+        }
+        if l.common {
+            let alignment = l
+                .contributions
+                .iter()
+                .map(|c| c.alignment.bytes())
+                .max()
+                .unwrap_or(1);
+            let base = align(addr, alignment);
+            let mut length = 0;
+            for c in &l.contributions {
+                layout.segments.push(placement(c, base, base));
+                length = length.max(c.length);
+            }
+            addr = base + length;
+        } else {
+            let segment_base = align(addr, l.contributions[0].alignment.bytes());
+            addr = segment_base;
+            for c in &l.contributions {
+                let base = align(addr, c.alignment.bytes());
+                layout.segments.push(placement(c, segment_base, base));
+                addr = base + c.length;
+            }
+        }
+    }
+
+    for (m, module) in modules.iter().enumerate() {
+        let info = module.info();
+        for g in &info.groups {
+            let name = info.name(g.group_name_index)?.to_string();
+            let members: Vec<usize> = g
+                .segment_definitions
+                .iter()
+                .filter_map(|c| {
+                    layout
+                        .segments
+                        .iter()
+                        .position(|s| s.module == m && s.segment_index == c.segment_definition)
+                })
+                .collect();
+            match layout.groups.iter_mut().find(|p| p.name == name) {
+                Some(p) => p.segments.extend(members),
+                None => layout.groups.push(GroupPlacement {
+                    name,
+                    base: 0,
+                    length: 0,
+                    segments: members,
+                }),
+            }
+        }
+    }
+    for g in &mut layout.groups {
+        let start = g
+            .segments
+            .iter()
+            .map(|&i| layout.segments[i].segment_base)
+            .min()
+            .unwrap_or(spec.origin);
+        let end = g
+            .segments
+            .iter()
+            .map(|&i| layout.segments[i].base + layout.segments[i].length)
+            .max()
+            .unwrap_or(start);
+        g.base = start & !0xF;
+        g.length = end - g.base;
+    }
+
+    Ok(layout)
+}
+
+fn placement(c: &Contribution, segment_base: u32, base: u32) -> SegmentPlacement {
+    SegmentPlacement {
+        module: c.module,
+        segment_index: c.segment_index,
+        name: c.name.clone(),
+        class: c.class.clone(),
+        combination: c.combination,
+        segment_base,
+        base,
+        length: c.length,
+    }
+}
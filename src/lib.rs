@@ -1,94 +1,839 @@
+#[cfg(feature = "async")]
+mod async_reader;
+mod borland;
+mod callgraph;
+#[cfg(feature = "capi")]
+mod capi;
+mod codeview;
+mod coff;
+mod comdat;
+mod debug;
+mod demangle;
+mod dwarf;
+mod edit;
+mod elf;
 mod error;
+mod exports;
+mod fixup;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+mod hash;
+mod hll;
+mod identify;
+mod ihex;
+mod image;
+mod index;
+mod layout;
+mod library;
+mod lines;
+mod link;
+mod map;
+#[cfg(feature = "mmap")]
+mod mapped;
+mod module;
+mod names;
+mod overlay;
+#[cfg(feature = "python")]
+mod python;
 mod record;
+mod record_type;
+mod relocation;
+mod slice;
+mod symbols;
+mod verify;
+mod visitor;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod writer;
 
-use std::cell::RefCell;
-use std::io::{self, Read};
-use std::rc::Rc;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
 
-use error::OmfError;
+#[cfg(feature = "async")]
+pub use async_reader::AsyncOmfReader;
+pub use callgraph::{CallEdge, CallGraph};
+pub use coff::{coff_to_omf, omf_to_coff, CoffFlavor};
+pub use comdat::{
+    collect_comdats, resolve_comdats, ComdatConflict, ComdatConflictKind, ComdatContribution,
+    ComdatResolution,
+};
+pub use debug::{DebugFormat, DebugInfo, DebugSymbol, DebugSymbolKind, DebugType};
+pub use demangle::{demangle, demangle_or_original, mangling_scheme, ManglingScheme};
+#[cfg(feature = "gimli")]
+pub use dwarf::load_dwarf;
+pub use dwarf::{dwarf_section, DwarfSection};
+pub use edit::{remove_segments, rename_class, rename_segment, rename_symbol};
+pub use elf::{omf_to_elf, ElfFlavor};
+pub use error::OmfError;
+pub use exports::{collect_exports, parse_expdef, write_def, Export};
+pub use fixup::{apply_fixup, FixupContext};
+#[cfg(feature = "arbitrary")]
+pub use fuzz::ArbitraryModule;
+pub use hash::{content_hash, ContentHash, HashedContent};
+pub use identify::{Identification, Toolchain};
+pub use ihex::write_intel_hex;
+use image::MAX_ITERATED_DEPTH;
+pub use image::{build_segment_images, expand_iterated_data, SegmentImage};
+pub use index::{ExternalIndex, GroupIndex, NameIndex, SegmentIndex, TypeIndex};
+pub use layout::{
+    compute_layout, compute_layout_with, GroupPlacement, Layout, LayoutEntry, LayoutSpec,
+    SegmentPlacement,
+};
+pub use library::{
+    module_stem, read_modules, CrossReference, LibraryBuilder, LibraryMember, OmfLibrary,
+};
+pub use lines::{build_line_entries, LineEntry};
+pub use link::{
+    link, link_bin, link_com, link_exe, resolve_publics, search_libraries, unreferenced_publics,
+    LinkOptions, LinkedProgram, ResolvedSymbol,
+};
+pub use map::write_map;
+#[cfg(feature = "mmap")]
+pub use mapped::MappedFile;
+pub use module::{OmfModule, OmfModules};
+pub use names::{NameEncoding, RawName};
 use num_traits::FromPrimitive;
-use record::{
-    AbsoluteSegmentAddress, CommentType, ExtName, GroupComponent, MAttrStart, OmfRecord,
-    OmfRecordData, PubName, SegmentAlignment, SegmentAttributes,
+pub use overlay::{parse_ovldef, Overlay, OverlayDef};
+pub use record::{
+    AbsoluteSegmentAddress, AliasName, CExtName, ComName, ComdatAllocation, ComdatData,
+    ComdatSelection, CommentType, CommunalLength, ExtName, Fixup, FixupData, FixupFrame,
+    FixupSubrecord, FixupTarget, FixupThread, GroupComponent, HexOffset, HexOptions,
+    IteratedDataBlock, IteratedDataContent, LineNumber, LocationType, MAttrStart, OmfRecord,
+    OmfRecordData, PubName, RecordDisplay, SegmentAlignment, SegmentAttributes,
+    SegmentAttributesBuilder, SegmentCombination,
+};
+pub use record_type::RecordType;
+pub use relocation::{build_relocations, EntryPoint, Relocation, RelocationRef};
+pub use slice::{ExtNameRef, OmfSlice, PubNameRef, RecordDataRef, RecordRef};
+pub use symbols::{glob_match, Symbol, SymbolBinding, SymbolKind, SymbolScope, SymbolTable};
+pub use verify::{validate, verify, Diagnostic, Rule, Severity};
+pub use visitor::{visit_record, OmfVisitor, Visit};
+pub use writer::{
+    encode_record_data, encode_record_data_with, ChecksumMode, Comment, ImportEntry, ModuleBuilder,
+    OmfWriter, PresetSegments, SegmentPreset,
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SegmentInfo {
     pub segment_attributes: SegmentAttributes,
     pub segment_length: u16,
-    pub segment_name_index: u8,
-    pub class_name_index: u8,
-    pub overlay_name_index: u8,
+    pub segment_name_index: NameIndex,
+    pub class_name_index: NameIndex,
+    pub overlay_name_index: NameIndex,
+}
+
+impl SegmentInfo {
+    pub fn length(&self) -> u32 {
+        if self.segment_attributes.big {
+            0x10000
+        } else {
+            self.segment_length as u32
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GroupInfo {
-    pub group_name_index: u8,
+    pub group_name_index: NameIndex,
     pub segment_definitions: Vec<GroupComponent>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OmfInfo {
-    pub names: Vec<String>,
+    pub names: Vec<Arc<str>>,
     pub segments: Vec<SegmentInfo>,
     pub groups: Vec<GroupInfo>,
+    pub externals: Vec<Arc<str>>,
+    pub symbols: SymbolTable,
+    pub frame_threads: [Option<FixupFrame>; 4],
+    pub target_threads: [Option<FixupTarget>; 4],
 }
 
 impl OmfInfo {
     pub fn new() -> OmfInfo {
-        OmfInfo {
-            names: vec![],
-            segments: vec![],
-            groups: vec![],
+        OmfInfo::default()
+    }
+
+    pub fn name_from_index(&self, index: NameIndex) -> Result<Arc<str>, OmfError> {
+        self.names
+            .get((index.0 as usize).wrapping_sub(1))
+            .cloned()
+            .ok_or(OmfError::Value("name index not found"))
+    }
+
+    pub fn get_segment(&self, index: SegmentIndex) -> Result<&SegmentInfo, OmfError> {
+        self.segments
+            .get((index.0 as usize).wrapping_sub(1))
+            .ok_or(OmfError::Value("segment index not found"))
+    }
+
+    pub fn get_group(&self, index: GroupIndex) -> Result<&GroupInfo, OmfError> {
+        self.groups
+            .get((index.0 as usize).wrapping_sub(1))
+            .ok_or(OmfError::Value("group index not found"))
+    }
+
+    pub fn get_external(&self, index: ExternalIndex) -> Result<Arc<str>, OmfError> {
+        self.externals
+            .get((index.0 as usize).wrapping_sub(1))
+            .cloned()
+            .ok_or(OmfError::Value("external index not found"))
+    }
+
+    // The first segment with this name, and its index.
+    pub fn segment_by_name(&self, name: &str) -> Option<(SegmentIndex, &SegmentInfo)> {
+        self.segments_with_names()
+            .find(|&(_, n, _)| n == name)
+            .map(|(index, _, segment)| (index, segment))
+    }
+
+    pub fn group_by_name(&self, name: &str) -> Option<(GroupIndex, &GroupInfo)> {
+        self.groups_with_names()
+            .find(|&(_, n, _)| n == name)
+            .map(|(index, _, group)| (index, group))
+    }
+
+    pub fn class_of(&self, segment: SegmentIndex) -> Result<&str, OmfError> {
+        self.name(self.get_segment(segment)?.class_name_index)
+    }
+
+    // Each segment with its index and name. A name that doesn't resolve is
+    // empty.
+    pub fn segments_with_names(&self) -> impl Iterator<Item = (SegmentIndex, &str, &SegmentInfo)> {
+        self.segments.iter().enumerate().map(|(i, segment)| {
+            let name = self.name(segment.segment_name_index).unwrap_or_default();
+            (SegmentIndex(i as u16 + 1), name, segment)
+        })
+    }
+
+    pub fn groups_with_names(&self) -> impl Iterator<Item = (GroupIndex, &str, &GroupInfo)> {
+        self.groups.iter().enumerate().map(|(i, group)| {
+            let name = self.name(group.group_name_index).unwrap_or_default();
+            (GroupIndex(i as u16 + 1), name, group)
+        })
+    }
+
+    pub(crate) fn name(&self, index: NameIndex) -> Result<&str, OmfError> {
+        self.names
+            .get((index.0 as usize).wrapping_sub(1))
+            .map(|s| &**s)
+            .ok_or(OmfError::Value("name index not found"))
+    }
+
+    fn mark(&self) -> InfoMark {
+        InfoMark {
+            names: self.names.len(),
+            segments: self.segments.len(),
+            groups: self.groups.len(),
+            externals: self.externals.len(),
+            symbols: self.symbols.len(),
         }
     }
+
+    // Forgets everything defined after the mark was taken.
+    fn reset(&mut self, mark: InfoMark) {
+        self.names.truncate(mark.names);
+        self.segments.truncate(mark.segments);
+        self.groups.truncate(mark.groups);
+        self.externals.truncate(mark.externals);
+        self.symbols.truncate(mark.symbols);
+    }
+}
+
+// How much of each kind of definition an OmfInfo held at some point.
+#[derive(Clone, Copy)]
+struct InfoMark {
+    names: usize,
+    segments: usize,
+    groups: usize,
+    externals: usize,
+    symbols: usize,
+}
+
+// What the reader does with a record whose checksum is wrong. A checksum of
+// 0 means none was computed and is always accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ChecksumPolicy {
+    // Fail with OmfError::Checksum.
+    Error,
+    // Keep the record with checksum_valid cleared.
+    #[default]
+    Warn,
+    // Don't check checksums at all.
+    Ignore,
+}
+
+// Records that add to the names, segments, groups or externals later
+// records refer to by index.
+fn defines_indices(record_type: RecordType) -> bool {
+    matches!(
+        record_type,
+        RecordType::ExtDef
+            | RecordType::LNames
+            | RecordType::SegDef
+            | RecordType::GrpDef
+            | RecordType::ComDef
+            | RecordType::LExtDef
+            | RecordType::CExtDef
+    )
+}
+
+// Fills as much of `buf` as the stream has, returning how much that was.
+// Unlike read_exact, a short read at the end of the stream isn't an error.
+fn read_up_to(r: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match r.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+// Where a record is and what it is, from the three bytes that start it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordHeader {
+    pub offset: usize,
+    pub record_type: RecordType,
+    pub record_length: usize,
 }
 
-pub struct OmfReader<'a> {
-    r: &'a mut dyn Read,
-    info: Rc<RefCell<OmfInfo>>,
+type DataSink = Box<dyn FnMut(SegmentIndex, u16, &[u8]) + Send + Sync>;
+
+pub struct OmfReader<R> {
+    r: R,
+    // Set for readers made with with_seek.
+    seek: Option<fn(&mut R, SeekFrom) -> io::Result<u64>>,
+    info: OmfInfo,
+    offset: usize,
+    failed: bool,
+    // The current record after its header, up to and including the
+    // checksum, and how much of its content has been parsed.
+    body: Vec<u8>,
+    pos: usize,
+    // Sum of the current record's bytes.
+    sum: u8,
+    checksum_policy: ChecksumPolicy,
+    lenient: bool,
+    name_encoding: NameEncoding,
+    // Names in the current record that don't survive decoding, and how many
+    // names it has had.
+    raw_names: Vec<RawName>,
+    names_read: usize,
+    // The record skim read and hasn't been decoded yet, and why its body
+    // couldn't be read when reading leniently.
+    pending: Option<RecordHeader>,
+    body_error: Option<OmfError>,
+    // Cleared when skim seeked over the pending record's body.
+    body_loaded: bool,
+    data_sink: Option<DataSink>,
+    // Offsets of the records whose definitions are in the info, for
+    // readers that can seek back and read them again.
+    applied: Option<HashSet<usize>>,
 }
 
-impl<'a> OmfReader<'a> {
-    pub fn new(r: &'a mut dyn Read) -> OmfReader<'a> {
+impl OmfReader<BufReader<File>> {
+    // Reads a file through a buffer, keeping the file open for as long as
+    // the reader lives.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, OmfError> {
+        Ok(OmfReader::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+impl<'a> OmfReader<&'a [u8]> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        OmfReader::new(bytes)
+    }
+}
+
+impl<R: Read + Seek> OmfReader<R> {
+    // A reader over a stream it can seek in. Record bodies skim passes over
+    // are seeked past instead of read, and seek_to_record can go back to
+    // records read before. Offsets are positions in the stream.
+    pub fn with_seek(mut r: R) -> Result<Self, OmfError> {
+        let offset = r.stream_position()? as usize;
+        let mut reader = OmfReader::with_offset(r, offset);
+        reader.seek = Some(R::seek);
+        reader.applied = Some(HashSet::new());
+        Ok(reader)
+    }
+}
+
+impl<R: Read> OmfReader<R> {
+    pub fn new(r: R) -> Self {
+        OmfReader::with_offset(r, 0)
+    }
+
+    // A reader whose record offsets count from `offset`, for streams that
+    // start partway into a file.
+    pub fn with_offset(r: R, offset: usize) -> Self {
+        OmfReader::from_parts(r, OmfInfo::new(), offset)
+    }
+
+    // Continues parsing with the state built up by an earlier reader, for
+    // callers that hand the reader one record at a time.
+    pub(crate) fn with_info(r: R, info: OmfInfo) -> Self {
+        OmfReader::from_parts(r, info, 0)
+    }
+
+    fn from_parts(r: R, info: OmfInfo, offset: usize) -> Self {
         OmfReader {
             r,
-            info: Rc::new(RefCell::new(OmfInfo::new())),
+            seek: None,
+            info,
+            offset,
+            failed: false,
+            body: vec![],
+            pos: 0,
+            sum: 0,
+            checksum_policy: ChecksumPolicy::default(),
+            lenient: false,
+            name_encoding: NameEncoding::default(),
+            raw_names: vec![],
+            names_read: 0,
+            pending: None,
+            body_error: None,
+            body_loaded: false,
+            applied: None,
+            data_sink: None,
+        }
+    }
+
+    // Gives back the stream, positioned after the last record read.
+    pub fn into_inner(self) -> R {
+        self.r
+    }
+
+    pub fn set_checksum_policy(&mut self, policy: ChecksumPolicy) {
+        self.checksum_policy = policy;
+    }
+
+    pub fn set_name_encoding(&mut self, encoding: NameEncoding) {
+        self.name_encoding = encoding;
+    }
+
+    // Hands the data of each LEDATA record decoded to `sink`, with its
+    // segment index and offset, straight from the reader's buffer. The
+    // records themselves then come back with no data, so large objects can
+    // be read without keeping all their data in memory.
+    pub fn set_data_sink(
+        &mut self,
+        sink: impl FnMut(SegmentIndex, u16, &[u8]) + Send + Sync + 'static,
+    ) {
+        self.data_sink = Some(Box::new(sink));
+    }
+
+    // Records that fail to decode are returned as Unknown records with their
+    // error set, instead of ending the stream.
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.lenient = lenient;
+    }
+
+    // The names, segments, groups and symbols defined by the records read
+    // so far.
+    pub fn info(&self) -> &OmfInfo {
+        &self.info
+    }
+
+    // Takes the info built up by the reader.
+    pub fn into_info(self) -> OmfInfo {
+        self.info
+    }
+
+    // The content of the current record: its body without the checksum.
+    fn content(&self) -> &[u8] {
+        &self.body[..self.body.len().saturating_sub(1)]
+    }
+
+    // How much of the current record's content was left unparsed.
+    pub(crate) fn unread(&self) -> usize {
+        self.content().len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], io::Error> {
+        let start = self.pos;
+        if start + len > self.content().len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "record content too short",
+            ));
         }
+        self.pos += len;
+        Ok(&self.body[start..start + len])
     }
 
     fn read_u8(&mut self) -> Result<u8, io::Error> {
-        let mut buf = [0u8; 1];
-        self.r.read_exact(&mut buf)?;
-        Ok(buf[0])
+        Ok(self.take(1)?[0])
     }
 
     fn read_u16(&mut self) -> Result<u16, io::Error> {
-        let mut buf = [0u8; 2];
-        self.r.read_exact(&mut buf)?;
-        Ok(u16::from_le_bytes(buf))
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
     }
 
     fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, io::Error> {
-        let mut buf = Vec::new();
-        buf.resize(len, 0u8);
-        self.r.read_exact(&mut buf)?;
-        Ok(buf)
+        Ok(self.take(len)?.to_vec())
     }
 
     fn read_string(&mut self) -> Result<String, OmfError> {
         let len = self.read_u8()? as usize;
-        let str_vec = self.read_bytes(len)?;
-        Ok(String::from_utf8(str_vec)?)
+        let encoding = self.name_encoding;
+        let bytes = self.take(len)?;
+        let text = encoding.decode(bytes)?;
+        let lost = encoding.encode(&text).ok().as_deref() != Some(bytes);
+        if let Some(bytes) = lost.then(|| bytes.to_vec()) {
+            self.raw_names.push(RawName {
+                position: self.names_read,
+                bytes,
+                text: text.clone(),
+            });
+        }
+        self.names_read += 1;
+        Ok(text)
+    }
+
+    // The rest of the current record's content.
+    fn read_rest(&mut self) -> Vec<u8> {
+        let start = self.pos;
+        self.pos = self.content().len();
+        self.body[start..self.pos].to_vec()
+    }
+
+    // Indices from 80h up take two bytes, the first with its high bit set.
+    fn read_index<T: From<u16>>(&mut self) -> Result<T, OmfError> {
+        let first = self.read_u8()?;
+        if first & 0x80 == 0 {
+            return Ok(T::from(first as u16));
+        }
+        Ok(T::from(
+            ((first as u16 & 0x7F) << 8) | self.read_u8()? as u16,
+        ))
+    }
+
+    fn read_ext_names(&mut self) -> Result<Vec<ExtName>, OmfError> {
+        let mut names = vec![];
+        while self.unread() > 0 {
+            let name = self.read_string()?;
+            let type_index = self.read_index()?;
+            names.push(ExtName { name, type_index });
+        }
+        Ok(names)
+    }
+
+    fn read_line_numbers(&mut self) -> Result<Vec<LineNumber>, OmfError> {
+        let mut lines = vec![];
+        while self.unread() > 0 {
+            let line_number = self.read_u16()?;
+            let line_number_offset = self.read_u16()?;
+            lines.push(LineNumber {
+                line_number,
+                line_number_offset,
+            });
+        }
+        Ok(lines)
+    }
+
+    fn read_communal_length(&mut self) -> Result<u32, OmfError> {
+        let first = self.read_u8()?;
+        Ok(match first {
+            0..=0x80 => first as u32,
+            0x81 => self.read_u16()? as u32,
+            0x84 => {
+                let low = self.read_u16()? as u32;
+                let high = self.read_u8()? as u32;
+                low | high << 16
+            }
+            0x88 => {
+                let low = self.read_u16()? as u32;
+                let high = self.read_u16()? as u32;
+                low | high << 16
+            }
+            _ => return Err(OmfError::Value("communal length")),
+        })
+    }
+
+    fn read_fixup_frame(&mut self, method: u8) -> Result<FixupFrame, OmfError> {
+        Ok(match method {
+            0 => FixupFrame::Segment(self.read_index()?),
+            1 => FixupFrame::Group(self.read_index()?),
+            2 => FixupFrame::External(self.read_index()?),
+            3 => FixupFrame::FrameNumber(self.read_u16()?),
+            4 => FixupFrame::Location,
+            5 => FixupFrame::Target,
+            _ => return Err(OmfError::Value("frame method")),
+        })
+    }
+
+    fn read_fixup_target(&mut self, method: u8) -> Result<FixupTarget, OmfError> {
+        Ok(match method {
+            0 => FixupTarget::Segment(self.read_index()?),
+            1 => FixupTarget::Group(self.read_index()?),
+            2 => FixupTarget::External(self.read_index()?),
+            3 => FixupTarget::FrameNumber(self.read_u16()?),
+            _ => return Err(OmfError::Value("target method")),
+        })
+    }
+
+    fn read_fix_data(&mut self) -> Result<FixupData, OmfError> {
+        let fix_data = self.read_u8()?;
+        let frame_method = (fix_data >> 4) & 7;
+        let frame = if fix_data & 0x80 != 0 {
+            self.info.frame_threads[(frame_method & 3) as usize]
+                .ok_or(OmfError::Value("undefined frame thread"))?
+        } else {
+            self.read_fixup_frame(frame_method)?
+        };
+        let target = if fix_data & 0x08 != 0 {
+            self.info.target_threads[(fix_data & 3) as usize]
+                .ok_or(OmfError::Value("undefined target thread"))?
+        } else {
+            self.read_fixup_target(fix_data & 3)?
+        };
+        let target_displacement = if fix_data & 0x04 == 0 {
+            Some(self.read_u16()? as u32)
+        } else {
+            None
+        };
+        Ok(FixupData {
+            frame,
+            target,
+            target_displacement,
+        })
+    }
+
+    // `depth` counts the blocks this one is nested in.
+    fn read_iterated_block(&mut self, depth: usize) -> Result<IteratedDataBlock, OmfError> {
+        if depth >= MAX_ITERATED_DEPTH {
+            return Err(OmfError::Value("iterated data nested too deeply"));
+        }
+        let repeat_count = self.read_u16()?;
+        let block_count = self.read_u16()?;
+        let content = if block_count == 0 {
+            let len = self.read_u8()? as usize;
+            IteratedDataContent::Data(self.read_bytes(len)?)
+        } else {
+            let mut blocks = vec![];
+            for _ in 0..block_count {
+                blocks.push(self.read_iterated_block(depth + 1)?);
+            }
+            IteratedDataContent::Blocks(blocks)
+        };
+        Ok(IteratedDataBlock {
+            repeat_count,
+            content,
+        })
     }
 
     fn get_next_record(&mut self) -> Result<Option<OmfRecord>, OmfError> {
-        let record_type = match self.read_u8() {
-            Ok(v) => v,
-            Err(_) => return Ok(None),
+        match self.skim()? {
+            Some(_) => self.decode().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // Reads the next record without decoding it, returning its header. Call
+    // decode to decode it, or skim again to skip it. Skipped records that
+    // define names, segments, groups or externals are still decoded, so the
+    // indices in later records resolve; skipped FIXUPP threads are lost.
+    pub fn skim(&mut self) -> Result<Option<RecordHeader>, OmfError> {
+        if let Some(header) = self.pending {
+            if defines_indices(header.record_type) {
+                self.decode()?;
+            }
+        }
+        self.pending = None;
+        self.body_error = None;
+        let offset = self.offset;
+        let mut bytes = [0u8; 3];
+        let header_len = read_up_to(&mut self.r, &mut bytes)?;
+        if header_len == 0 {
+            return Ok(None);
+        }
+        self.offset += header_len;
+        let header = RecordHeader {
+            offset,
+            record_type: RecordType::from_u8(bytes[0]),
+            record_length: u16::from_le_bytes([bytes[1], bytes[2]]) as usize,
         };
-        let record_length = self.read_u16()? as usize;
+        if let Some(seek) = self.seek {
+            if header_len == bytes.len() && !defines_indices(header.record_type) {
+                // Read in decode, if at all.
+                self.offset += header.record_length;
+                seek(&mut self.r, SeekFrom::Start(self.offset as u64))?;
+                self.body.clear();
+                self.body_loaded = false;
+                self.pending = Some(header);
+                return Ok(Some(header));
+            }
+        }
+        self.body_loaded = true;
+        match self.read_body(&bytes, header_len) {
+            Ok(_) => (),
+            // Leniently read records are reported when they are decoded.
+            Err(e) if self.lenient => self.body_error = Some(e),
+            Err(e) => {
+                return Err(OmfError::Record {
+                    offset,
+                    record_type: header.record_type.to_u8(),
+                    source: Box::new(e),
+                })
+            }
+        }
+        self.pending = Some(header);
+        Ok(Some(header))
+    }
 
+    // Decodes the record skim last returned.
+    pub fn decode(&mut self) -> Result<OmfRecord, OmfError> {
+        let RecordHeader {
+            offset,
+            record_type,
+            record_length,
+        } = self
+            .pending
+            .take()
+            .ok_or(OmfError::Value("no record to decode"))?;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!(
+            "record",
+            offset,
+            record_type = %record_type,
+            length = record_length
+        )
+        .entered();
+        // Definitions read a second time after seeking back are already known.
+        let replay = self
+            .applied
+            .as_mut()
+            .is_some_and(|applied| !applied.insert(offset));
+        let mark = replay.then(|| self.info.mark());
+        let result = match self.body_error.take() {
+            Some(e) => Err(e),
+            None => self
+                .load_body(offset, record_type.to_u8(), record_length)
+                .and_then(|_| self.read_record(offset, record_type.to_u8(), record_length)),
+        };
+        if let Some(mark) = mark {
+            self.info.reset(mark);
+        }
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(_) => tracing::trace!("decoded"),
+            Err(e) if self.lenient && !matches!(e, OmfError::Checksum { .. }) => {
+                tracing::warn!(error = %e, "kept undecoded")
+            }
+            Err(e) => tracing::warn!(error = %e, "failed to decode"),
+        }
+        match result {
+            Ok(record) => Ok(record),
+            Err(e @ OmfError::Checksum { .. }) => Err(e),
+            Err(e) if self.lenient => {
+                let checksum = if self.body.len() == record_length {
+                    self.body.last().copied().unwrap_or(0)
+                } else {
+                    0
+                };
+                let data = OmfRecordData::Unknown {
+                    data: self.content().to_vec(),
+                };
+                let mut record = OmfRecord::new(offset, record_type, record_length, data, checksum);
+                record.error = Some(e.to_string());
+                Ok(record)
+            }
+            Err(e) => Err(OmfError::Record {
+                offset,
+                record_type: record_type.to_u8(),
+                source: Box::new(e),
+            }),
+        }
+    }
+
+    // Moves to the record starting at `offset`, such as one skim returned
+    // before, for readers made with with_seek. What the records already read
+    // defined stays known, so the records that follow decode as they did
+    // the first time, except that FIXUPP threads are the ones read last.
+    pub fn seek_to_record(&mut self, offset: usize) -> Result<(), OmfError> {
+        let Some(seek) = self.seek else {
+            return Err(OmfError::Value("reader cannot seek"));
+        };
+        seek(&mut self.r, SeekFrom::Start(offset as u64))?;
+        self.offset = offset;
+        self.pending = None;
+        self.body_error = None;
+        self.failed = false;
+        Ok(())
+    }
+
+    // Reads the body of a record skim seeked past, leaving the stream where
+    // it was.
+    fn load_body(
+        &mut self,
+        offset: usize,
+        record_type: u8,
+        record_length: usize,
+    ) -> Result<(), OmfError> {
+        if self.body_loaded {
+            return Ok(());
+        }
+        let Some(seek) = self.seek else {
+            unreachable!("only seekable readers skip bodies");
+        };
+        seek(&mut self.r, SeekFrom::Start(offset as u64 + 3))?;
+        let end = self.offset;
+        let length = (record_length as u16).to_le_bytes();
+        let result = self.read_body(&[record_type, length[0], length[1]], 3);
+        self.offset = end;
+        seek(&mut self.r, SeekFrom::Start(end as u64))?;
+        self.body_loaded = true;
+        result.map(|_| ())
+    }
+
+    // Reads everything after the header into the body buffer with a single
+    // read where the stream allows, so the stream is left at the next record
+    // whether or not the content makes sense.
+    fn read_body(&mut self, header: &[u8; 3], header_len: usize) -> Result<usize, OmfError> {
+        self.body.clear();
+        self.pos = 0;
+        if header_len < header.len() {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let record_length = u16::from_le_bytes([header[1], header[2]]) as usize;
+        self.body.resize(record_length, 0);
+        let len = read_up_to(&mut self.r, &mut self.body)?;
+        self.body.truncate(len);
+        self.offset += len;
+        if len < record_length {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        if record_length == 0 {
+            return Err(OmfError::Value("record has no checksum"));
+        }
+        self.sum = header
+            .iter()
+            .chain(self.body.iter())
+            .fold(0u8, |a, &b| a.wrapping_add(b));
+        Ok(record_length)
+    }
+
+    // Decodes the record in the body buffer.
+    fn read_record(
+        &mut self,
+        offset: usize,
+        record_type: u8,
+        record_length: usize,
+    ) -> Result<OmfRecord, OmfError> {
+        self.raw_names.clear();
+        self.names_read = 0;
+        let mut sink_data = None;
         let data = match record_type {
             0x80 => {
                 let name = self.read_string()?;
@@ -97,10 +842,10 @@ impl<'a> OmfReader<'a> {
             0x88 => {
                 let tmp = self.read_u8()?;
                 let no_purge = tmp & 0x80 != 0;
-                let no_list = tmp & 0x80 != 0;
+                let no_list = tmp & 0x40 != 0;
                 let comment_type = CommentType { no_purge, no_list };
                 let comment_class = self.read_u8()?;
-                let comment_bytes = self.read_bytes(record_length - 3)?;
+                let comment_bytes = self.read_rest();
                 OmfRecordData::Coment {
                     comment_type,
                     comment_class,
@@ -110,98 +855,143 @@ impl<'a> OmfReader<'a> {
             0x8A => {
                 let module_type = self.read_u8()?;
                 let main = module_type & 0x80 != 0;
-                let start = if module_type & 0x40 != 0 {
-                    MAttrStart::Start {
-                        end_data: self.read_u8()?,
-                        frame_datum: self.read_u8()?,
-                        target_datum: self.read_u8()?,
-                        target_displacement: self.read_u16()?,
-                    }
-                } else {
+                let start = if module_type & 0x40 == 0 {
                     MAttrStart::NoStart
+                } else if module_type & 0x01 != 0 {
+                    MAttrStart::Start(self.read_fix_data()?)
+                } else {
+                    let frame = self.read_u16()?;
+                    let offset = self.read_u16()?;
+                    MAttrStart::Start(FixupData {
+                        frame: FixupFrame::FrameNumber(frame),
+                        target: FixupTarget::FrameNumber(frame),
+                        target_displacement: Some(offset as u32),
+                    })
                 };
                 OmfRecordData::ModEnd { main, start }
             }
-            0x8C => {
-                let mut names = vec![];
-                let mut c = 0;
-                while c < record_length - 1 {
-                    let name = self.read_string()?;
-                    let type_index = self.read_u8()?;
-                    c += name.len() + 2;
-                    names.push(ExtName { name, type_index });
+            0x8C | 0xB4 => {
+                let names = self.read_ext_names()?;
+                let scope = if record_type == 0x8C {
+                    SymbolScope::Global
+                } else {
+                    SymbolScope::Local
+                };
+                let info = &mut self.info;
+                for n in &names {
+                    info.externals.push(n.name.as_str().into());
+                    info.symbols.push(Symbol {
+                        name: n.name.clone(),
+                        kind: SymbolKind::External,
+                        scope,
+                        binding: SymbolBinding::Unbound,
+                        offset: 0,
+                        type_index: n.type_index,
+                    });
+                }
+                if record_type == 0x8C {
+                    OmfRecordData::ExtDef { names }
+                } else {
+                    OmfRecordData::LExtDef { names }
                 }
-                OmfRecordData::ExtDef { names }
             }
-            0x90 => {
-                let base_group_index = self.read_u8()?;
-                let base_segment_index = self.read_u8()?;
-                let base_frame = if base_segment_index == 0 {
+            0x90 | 0xB6 => {
+                let base_group_index = self.read_index()?;
+                let base_segment_index: SegmentIndex = self.read_index()?;
+                let base_frame = if base_segment_index.is_none() {
                     self.read_u16()?
                 } else {
                     0u16
                 };
                 let mut names = vec![];
-                let mut c = 0;
-                let rep_len = record_length - 3 - if base_segment_index == 0 { 2 } else { 0 };
-                while c < rep_len {
+                while self.unread() > 0 {
                     let name = self.read_string()?;
                     let public_offset = self.read_u16()?;
-                    let type_index = self.read_u8()?;
-                    c += name.len() + 4;
+                    let type_index = self.read_index()?;
                     names.push(PubName {
                         name,
                         public_offset,
                         type_index,
                     });
                 }
-                OmfRecordData::PubDef {
+                let scope = if record_type == 0x90 {
+                    SymbolScope::Global
+                } else {
+                    SymbolScope::Local
+                };
+                let binding = if base_segment_index.is_none() {
+                    SymbolBinding::Absolute { frame: base_frame }
+                } else {
+                    SymbolBinding::Segment {
+                        group_index: base_group_index,
+                        segment_index: base_segment_index,
+                    }
+                };
+                let info = &mut self.info;
+                for n in &names {
+                    info.symbols.push(Symbol {
+                        name: n.name.clone(),
+                        kind: SymbolKind::Public,
+                        scope,
+                        binding,
+                        offset: n.public_offset as u32,
+                        type_index: n.type_index,
+                    });
+                }
+                if record_type == 0x90 {
+                    OmfRecordData::PubDef {
+                        base_group_index,
+                        base_segment_index,
+                        base_frame,
+                        names,
+                    }
+                } else {
+                    OmfRecordData::LPubDef {
+                        base_group_index,
+                        base_segment_index,
+                        base_frame,
+                        names,
+                    }
+                }
+            }
+            0x94 => {
+                let base_group_index = self.read_index()?;
+                let base_segment_index: SegmentIndex = self.read_index()?;
+                let lines = self.read_line_numbers()?;
+                OmfRecordData::LinNum {
                     base_group_index,
                     base_segment_index,
-                    base_frame,
-                    names,
+                    lines,
                 }
             }
             0x96 => {
                 let mut names = vec![];
-                let mut c = 0;
-                while c < record_length - 1 {
-                    let name = self.read_string()?;
-                    c += name.len() + 1;
-                    names.push(name);
+                while self.unread() > 0 {
+                    names.push(Arc::from(self.read_string()?));
                 }
-                self.info.borrow_mut().names.append(&mut (names.clone()));
+                self.info.names.extend(names.iter().cloned());
                 OmfRecordData::LNames { names }
             }
             0x98 => {
-                let tmp = self.read_u8()?;
-                let alignment =
-                    FromPrimitive::from_u8(tmp >> 5).ok_or(OmfError::Value("alignment"))?;
-                let combination =
-                    FromPrimitive::from_u8((tmp >> 2) & 3).ok_or(OmfError::Value("combination"))?;
-                let absolute_segment_address = if alignment == SegmentAlignment::AbsoluteSegment {
+                let mut segment_attributes = SegmentAttributes::from_acbp(self.read_u8()?)?;
+                if segment_attributes.alignment == SegmentAlignment::AbsoluteSegment {
                     let frame_number = self.read_u16()?;
                     let offset = self.read_u8()?;
-                    Some(AbsoluteSegmentAddress {
+                    segment_attributes.absolute_segment_address = Some(AbsoluteSegmentAddress {
                         frame_number,
                         offset,
-                    })
-                } else {
-                    None
-                };
-                let segment_attributes = SegmentAttributes {
-                    alignment,
-                    combination,
-                    big: tmp & 2 != 0,
-                    bd32bit: tmp & 1 != 0,
-                    absolute_segment_address,
-                };
+                    });
+                }
                 let segment_length = self.read_u16()?;
-                let segment_name_index = self.read_u8()?;
-                let class_name_index = self.read_u8()?;
-                let overlay_name_index = self.read_u8()?;
-                self.info.borrow_mut().segments.push(SegmentInfo {
-                    segment_attributes: segment_attributes,
+                #[cfg(feature = "tracing")]
+                if segment_attributes.big && segment_length != 0 {
+                    tracing::warn!(segment_length, "64K segment with a nonzero length");
+                }
+                let segment_name_index = self.read_index()?;
+                let class_name_index = self.read_index()?;
+                let overlay_name_index = self.read_index()?;
+                self.info.segments.push(SegmentInfo {
+                    segment_attributes,
                     segment_length,
                     segment_name_index,
                     class_name_index,
@@ -216,19 +1006,17 @@ impl<'a> OmfReader<'a> {
                 }
             }
             0x9A => {
-                let group_name_index = self.read_u8()?;
+                let group_name_index = self.read_index()?;
                 let mut segment_definitions = vec![];
-                let mut c = 0;
-                while c < record_length - 2 {
+                while self.unread() > 0 {
                     let index = self.read_u8()?;
-                    let segment_definition = self.read_u8()?;
-                    c += 2;
+                    let segment_definition = self.read_index()?;
                     segment_definitions.push(GroupComponent {
                         index,
                         segment_definition,
                     });
                 }
-                self.info.borrow_mut().groups.push(GroupInfo {
+                self.info.groups.push(GroupInfo {
                     group_name_index,
                     segment_definitions: segment_definitions.clone(),
                 });
@@ -237,37 +1025,364 @@ impl<'a> OmfReader<'a> {
                     segment_definitions,
                 }
             }
+            0x9C => {
+                let mut subrecords = vec![];
+                while self.unread() > 0 {
+                    let first = self.read_u8()?;
+                    if first & 0x80 == 0 {
+                        let method = (first >> 2) & 7;
+                        let thread = first & 3;
+                        let thread = if first & 0x40 != 0 {
+                            let frame = self.read_fixup_frame(method)?;
+                            self.info.frame_threads[thread as usize] = Some(frame);
+                            FixupThread::Frame { thread, frame }
+                        } else {
+                            let target = self.read_fixup_target(method & 3)?;
+                            self.info.target_threads[thread as usize] = Some(target);
+                            FixupThread::Target { thread, target }
+                        };
+                        subrecords.push(FixupSubrecord::Thread(thread));
+                    } else {
+                        let second = self.read_u8()?;
+                        let location = FromPrimitive::from_u8((first >> 2) & 0xF)
+                            .ok_or(OmfError::Value("location type"))?;
+                        let data_record_offset = ((first as u16 & 3) << 8) | second as u16;
+                        let fix_data = self.read_fix_data()?;
+                        subrecords.push(FixupSubrecord::Fixup(Fixup {
+                            segment_relative: first & 0x40 != 0,
+                            location,
+                            data_record_offset,
+                            fix_data,
+                        }));
+                    }
+                }
+                OmfRecordData::FixUpp { subrecords }
+            }
             0xA0 => {
-                let segment_index = self.read_u8()?;
+                let segment_index = self.read_index()?;
                 let enumerated_data_offset = self.read_u16()?;
-                let data = self.read_bytes(record_length - 4)?;
+                #[cfg(feature = "tracing")]
+                match self.info.get_segment(segment_index) {
+                    Ok(segment) => {
+                        let end = enumerated_data_offset as usize + self.unread();
+                        if end > segment.length() as usize {
+                            tracing::warn!(
+                                %segment_index,
+                                end,
+                                length = segment.length(),
+                                "data past the end of its segment"
+                            );
+                        }
+                    }
+                    Err(_) => tracing::warn!(%segment_index, "data for an undefined segment"),
+                }
+                let data = if self.data_sink.is_some() {
+                    // Handed to the sink once the checksum has been checked.
+                    sink_data = Some((segment_index, enumerated_data_offset, self.pos));
+                    self.pos = self.content().len();
+                    vec![]
+                } else {
+                    self.read_rest()
+                };
                 OmfRecordData::LEData {
                     segment_index,
                     enumerated_data_offset,
                     data,
                 }
             }
+            0xA2 => {
+                let segment_index = self.read_index()?;
+                let iterated_data_offset = self.read_u16()?;
+                let mut blocks = vec![];
+                while self.unread() > 0 {
+                    blocks.push(self.read_iterated_block(0)?);
+                }
+                OmfRecordData::LIData {
+                    segment_index,
+                    iterated_data_offset,
+                    blocks,
+                }
+            }
+            0xB0 => {
+                let mut definitions = vec![];
+                while self.unread() > 0 {
+                    let name = self.read_string()?;
+                    let type_index = self.read_index()?;
+                    let data_type = self.read_u8()?;
+                    let communal_length = match data_type {
+                        0x61 => {
+                            let number_of_elements = self.read_communal_length()?;
+                            let element_size = self.read_communal_length()?;
+                            CommunalLength::Far {
+                                number_of_elements,
+                                element_size,
+                            }
+                        }
+                        0x62 => {
+                            let size = self.read_communal_length()?;
+                            CommunalLength::Near(size)
+                        }
+                        0x01..=0x5F => {
+                            let size = self.read_communal_length()?;
+                            CommunalLength::Segment {
+                                segment_index: SegmentIndex(data_type as u16),
+                                size,
+                            }
+                        }
+                        _ => return Err(OmfError::Value("communal data type")),
+                    };
+                    definitions.push(ComName {
+                        name,
+                        type_index,
+                        communal_length,
+                    });
+                }
+                let info = &mut self.info;
+                for d in &definitions {
+                    info.externals.push(d.name.as_str().into());
+                    info.symbols.push(Symbol {
+                        name: d.name.clone(),
+                        kind: SymbolKind::Communal(d.communal_length),
+                        scope: SymbolScope::Global,
+                        binding: SymbolBinding::Unbound,
+                        offset: 0,
+                        type_index: d.type_index,
+                    });
+                }
+                OmfRecordData::ComDef { definitions }
+            }
+            0xBC => {
+                let mut names = vec![];
+                while self.unread() > 0 {
+                    let logical_name_index = self.read_index()?;
+                    let type_index = self.read_index()?;
+                    names.push(CExtName {
+                        logical_name_index,
+                        type_index,
+                    });
+                }
+                let info = &mut self.info;
+                for n in &names {
+                    let name = info.name_from_index(n.logical_name_index)?;
+                    info.externals.push(name.clone());
+                    info.symbols.push(Symbol {
+                        name: name.to_string(),
+                        kind: SymbolKind::ComdatExternal,
+                        scope: SymbolScope::Global,
+                        binding: SymbolBinding::Unbound,
+                        offset: 0,
+                        type_index: n.type_index,
+                    });
+                }
+                OmfRecordData::CExtDef { names }
+            }
+            0xC2 => {
+                let flags = self.read_u8()?;
+                let attributes = self.read_u8()?;
+                let selection = FromPrimitive::from_u8(attributes >> 4)
+                    .ok_or(OmfError::Value("comdat selection"))?;
+                let allocation = FromPrimitive::from_u8(attributes & 0xF)
+                    .ok_or(OmfError::Value("comdat allocation"))?;
+                let align = self.read_u8()?;
+                let alignment = if align == 0 {
+                    None
+                } else {
+                    Some(FromPrimitive::from_u8(align).ok_or(OmfError::Value("alignment"))?)
+                };
+                let enumerated_data_offset = self.read_u16()?;
+                let type_index = self.read_index()?;
+                let (base_group_index, base_segment_index, base_frame) =
+                    if allocation == ComdatAllocation::Explicit {
+                        let base_group_index = self.read_index()?;
+                        let base_segment_index: SegmentIndex = self.read_index()?;
+                        let base_frame = if base_segment_index.is_none() {
+                            self.read_u16()?
+                        } else {
+                            0u16
+                        };
+                        (base_group_index, base_segment_index, base_frame)
+                    } else {
+                        (GroupIndex::NONE, SegmentIndex::NONE, 0)
+                    };
+                let public_name_index = self.read_index()?;
+                let data = if flags & 0x02 != 0 {
+                    let mut blocks = vec![];
+                    while self.unread() > 0 {
+                        blocks.push(self.read_iterated_block(0)?);
+                    }
+                    ComdatData::Iterated(blocks)
+                } else {
+                    ComdatData::Enumerated(self.read_rest())
+                };
+                let local = flags & 0x04 != 0;
+                if flags & 0x01 == 0 {
+                    let info = &mut self.info;
+                    let name = info.name(public_name_index)?.to_string();
+                    info.symbols.push(Symbol {
+                        name,
+                        kind: SymbolKind::Comdat,
+                        scope: if local {
+                            SymbolScope::Local
+                        } else {
+                            SymbolScope::Global
+                        },
+                        binding: if allocation != ComdatAllocation::Explicit {
+                            SymbolBinding::Unbound
+                        } else if base_segment_index.is_none() {
+                            SymbolBinding::Absolute { frame: base_frame }
+                        } else {
+                            SymbolBinding::Segment {
+                                group_index: base_group_index,
+                                segment_index: base_segment_index,
+                            }
+                        },
+                        offset: 0,
+                        type_index,
+                    });
+                }
+                OmfRecordData::ComDat {
+                    continuation: flags & 0x01 != 0,
+                    local,
+                    selection,
+                    allocation,
+                    alignment,
+                    enumerated_data_offset,
+                    type_index,
+                    base_group_index,
+                    base_segment_index,
+                    base_frame,
+                    public_name_index,
+                    data,
+                }
+            }
+            0xC4 => {
+                let flags = self.read_u8()?;
+                let public_name_index = self.read_index()?;
+                let lines = self.read_line_numbers()?;
+                OmfRecordData::LinSym {
+                    continuation: flags & 1 != 0,
+                    public_name_index,
+                    lines,
+                }
+            }
+            0xC6 => {
+                let mut aliases = vec![];
+                while self.unread() > 0 {
+                    let alias_name = self.read_string()?;
+                    let substitute_name = self.read_string()?;
+                    aliases.push(AliasName {
+                        alias_name,
+                        substitute_name,
+                    });
+                }
+                let info = &mut self.info;
+                for a in &aliases {
+                    info.symbols.push(Symbol {
+                        name: a.alias_name.clone(),
+                        kind: SymbolKind::Alias(a.substitute_name.clone()),
+                        scope: SymbolScope::Global,
+                        binding: SymbolBinding::Unbound,
+                        offset: 0,
+                        type_index: TypeIndex::NONE,
+                    });
+                }
+                OmfRecordData::Alias { aliases }
+            }
             _ => {
-                let data = self.read_bytes(record_length - 1)?;
+                #[cfg(feature = "tracing")]
+                if RecordType::from_u8(record_type).name().is_none() {
+                    tracing::warn!("unknown record type");
+                }
+                let data = self.read_rest();
                 OmfRecordData::Unknown { data }
             }
         };
-        let checksum = self.read_u8()?;
+        let checksum = self.body[self.body.len() - 1];
+        let checksum_valid =
+            self.checksum_policy == ChecksumPolicy::Ignore || checksum == 0 || self.sum == 0;
+        if !checksum_valid && self.checksum_policy == ChecksumPolicy::Error {
+            return Err(OmfError::Checksum {
+                offset,
+                record_type,
+            });
+        }
+        #[cfg(feature = "tracing")]
+        if !checksum_valid {
+            tracing::warn!(checksum, "checksum mismatch");
+        }
+        if let (Some(sink), Some((segment_index, data_offset, start))) =
+            (&mut self.data_sink, sink_data)
+        {
+            sink(
+                segment_index,
+                data_offset,
+                &self.body[start..self.body.len() - 1],
+            );
+        }
 
-        Ok(Some(OmfRecord::new(
-            record_type,
-            record_length,
-            data,
-            checksum,
-            Rc::clone(&self.info),
-        )))
+        let record_type = RecordType::from_u8(record_type);
+        let mut record = OmfRecord::new(offset, record_type, record_length, data, checksum);
+        record.checksum_valid = checksum_valid;
+        record.raw_names = std::mem::take(&mut self.raw_names);
+        Ok(record)
     }
 }
 
-impl<'a> Iterator for OmfReader<'a> {
-    type Item = OmfRecord;
+// Reads every record in a file, stopping at the first error.
+pub fn parse_file(path: impl AsRef<Path>) -> Result<Vec<OmfRecord>, OmfError> {
+    OmfReader::from_path(path)?.collect()
+}
+
+// Iteration ends after the first error, as the reader no longer knows where
+// the next record starts.
+impl<R: Read> Iterator for OmfReader<R> {
+    type Item = Result<OmfRecord, OmfError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.get_next_record().expect("next")
+        if self.failed {
+            return None;
+        }
+        match self.get_next_record() {
+            Ok(record) => record.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
     }
 }
+
+// Everything public can be moved and shared between threads. A type that
+// stops being Send or Sync fails the build here.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<OmfReader<File>>();
+    assert_send_sync::<OmfWriter<Vec<u8>>>();
+    assert_send_sync::<OmfRecord>();
+    assert_send_sync::<OmfRecordData>();
+    assert_send_sync::<RecordDisplay>();
+    assert_send_sync::<RecordHeader>();
+    assert_send_sync::<OmfInfo>();
+    assert_send_sync::<SymbolTable>();
+    assert_send_sync::<OmfModule>();
+    assert_send_sync::<OmfLibrary>();
+    assert_send_sync::<LibraryMember>();
+    assert_send_sync::<LibraryBuilder>();
+    assert_send_sync::<ModuleBuilder>();
+    assert_send_sync::<OmfSlice>();
+    assert_send_sync::<RecordRef>();
+    assert_send_sync::<OmfError>();
+    assert_send_sync::<Diagnostic>();
+    assert_send_sync::<Relocation>();
+    assert_send_sync::<SegmentImage>();
+    assert_send_sync::<LineEntry>();
+    assert_send_sync::<ComdatResolution>();
+    assert_send_sync::<Layout>();
+    assert_send_sync::<LinkedProgram>();
+    assert_send_sync::<Export>();
+    #[cfg(feature = "mmap")]
+    assert_send_sync::<MappedFile>();
+    #[cfg(feature = "arbitrary")]
+    assert_send_sync::<ArbitraryModule>();
+};
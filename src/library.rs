@@ -0,0 +1,483 @@
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    ops::Range,
+};
+
+use crate::{error::OmfError, OmfModule, OmfRecordData, SymbolKind, SymbolScope};
+
+const LIBRARY_HEADER: u8 = 0xF0;
+const LIBRARY_END: u8 = 0xF1;
+const DICTIONARY_BLOCK_SIZE: usize = 512;
+const DICTIONARY_BUCKETS: usize = 37;
+// Entries start after the bucket table and the free space byte, rounded up
+// to a word.
+const DICTIONARY_FIRST_ENTRY: usize = 38;
+const FLAG_CASE_SENSITIVE: u8 = 0x01;
+
+#[derive(Debug)]
+pub struct LibraryMember {
+    // The module name from THEADR.
+    pub name: String,
+    pub page: u32,
+    // Byte range of the member within the library.
+    pub range: Range<usize>,
+    pub module: OmfModule,
+}
+
+impl LibraryMember {
+    // The module name without any directory or extension, which is how
+    // librarians usually refer to members.
+    pub fn stem(&self) -> &str {
+        module_stem(&self.name)
+    }
+}
+
+// A module name without any directory or extension.
+pub fn module_stem(name: &str) -> &str {
+    let base = name.rsplit(['/', '\\', ':']).next().unwrap_or(name);
+    base.rsplit_once('.').map_or(base, |(stem, _)| stem)
+}
+
+// A global symbol of a library, with the members that define it and those
+// that reference it, as indices into `members`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrossReference {
+    pub name: String,
+    pub defined_in: Vec<usize>,
+    pub referenced_by: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub struct OmfLibrary {
+    pub page_size: u32,
+    pub flags: u8,
+    pub members: Vec<LibraryMember>,
+    // Public names from the dictionary, with the page of the defining member.
+    pub dictionary: Vec<(String, u16)>,
+    dictionary_blocks: Vec<u8>,
+}
+
+impl OmfLibrary {
+    pub fn is_library(bytes: &[u8]) -> bool {
+        bytes.first() == Some(&LIBRARY_HEADER)
+    }
+
+    pub fn read(r: &mut dyn Read) -> Result<OmfLibrary, OmfError> {
+        let mut bytes = vec![];
+        r.read_to_end(&mut bytes)?;
+        OmfLibrary::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<OmfLibrary, OmfError> {
+        let header = bytes
+            .get(..10)
+            .ok_or(OmfError::Value("truncated library header"))?;
+        if header[0] != LIBRARY_HEADER {
+            return Err(OmfError::Value("not a library"));
+        }
+        let page_size = u16::from_le_bytes([header[1], header[2]]) as u32 + 3;
+        if !page_size.is_power_of_two() || page_size < 16 {
+            return Err(OmfError::Value("invalid library page size"));
+        }
+        let dictionary_offset =
+            u32::from_le_bytes([header[3], header[4], header[5], header[6]]) as usize;
+        let dictionary_blocks = u16::from_le_bytes([header[7], header[8]]) as usize;
+        let flags = header[9];
+
+        let mut members = vec![];
+        let mut pos = page_size as usize;
+        while pos < bytes.len() && bytes[pos] != LIBRARY_END {
+            let mut r = &bytes[pos..];
+            let module = OmfModule::read_one_at(&mut r, pos)?;
+            let end = bytes.len() - r.len();
+            let name = module
+                .records
+                .iter()
+                .find_map(|record| match &record.data {
+                    crate::OmfRecordData::THeadr { name } => Some(name.clone()),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            members.push(LibraryMember {
+                name,
+                page: pos as u32 / page_size,
+                range: pos..end,
+                module,
+            });
+            pos = end.div_ceil(page_size as usize) * page_size as usize;
+        }
+
+        let dictionary_bytes = bytes
+            .get(dictionary_offset..)
+            .and_then(|d| d.get(..dictionary_blocks * DICTIONARY_BLOCK_SIZE))
+            .ok_or(OmfError::Value("truncated library dictionary"))?;
+        let mut dictionary = vec![];
+        for block in dictionary_bytes.chunks(DICTIONARY_BLOCK_SIZE) {
+            for &bucket in &block[..DICTIONARY_BUCKETS] {
+                if bucket == 0 {
+                    continue;
+                }
+                let entry = bucket as usize * 2;
+                let len = block[entry] as usize;
+                let name = block
+                    .get(entry + 1..entry + 1 + len)
+                    .ok_or(OmfError::Value("bad library dictionary entry"))?;
+                let page = block
+                    .get(entry + 1 + len..entry + 3 + len)
+                    .ok_or(OmfError::Value("bad library dictionary entry"))?;
+                dictionary.push((
+                    String::from_utf8(name.to_vec())?,
+                    u16::from_le_bytes([page[0], page[1]]),
+                ));
+            }
+        }
+
+        Ok(OmfLibrary {
+            page_size,
+            flags,
+            members,
+            dictionary,
+            dictionary_blocks: dictionary_bytes.to_vec(),
+        })
+    }
+
+    // Finds a member by module name, ignoring case. The directory and
+    // extension of the name recorded in THEADR may be left off.
+    pub fn member(&self, name: &str) -> Option<&LibraryMember> {
+        self.members.iter().find(|m| {
+            m.name.eq_ignore_ascii_case(name) || m.stem().eq_ignore_ascii_case(module_stem(name))
+        })
+    }
+
+    // The page of the member defining a public, found by hashing into the
+    // dictionary as a linker would.
+    pub fn lookup(&self, name: &str) -> Option<u16> {
+        let dictionary = &self.dictionary_blocks;
+        let blocks = dictionary.len() / DICTIONARY_BLOCK_SIZE;
+        if blocks == 0 {
+            return None;
+        }
+        let case_sensitive = self.flags & FLAG_CASE_SENSITIVE != 0;
+        let hash = DictionaryHash::new(name.as_bytes(), blocks);
+        for (block, bucket) in hash.probes() {
+            let block = &dictionary[block * DICTIONARY_BLOCK_SIZE..][..DICTIONARY_BLOCK_SIZE];
+            let entry = block[bucket] as usize * 2;
+            if entry == 0 {
+                // An empty bucket ends the search unless the block is full.
+                if block[DICTIONARY_BUCKETS] == 0xFF {
+                    continue;
+                }
+                return None;
+            }
+            let len = block[entry] as usize;
+            let candidate = block.get(entry + 1..entry + 1 + len)?;
+            let matches = if case_sensitive {
+                candidate == name.as_bytes()
+            } else {
+                candidate.eq_ignore_ascii_case(name.as_bytes())
+            };
+            if matches {
+                let page = block.get(entry + 1 + len..entry + 3 + len)?;
+                return Some(u16::from_le_bytes([page[0], page[1]]));
+            }
+        }
+        None
+    }
+
+    // Every global public and external in the library, sorted by name. A
+    // member is counted as defining a symbol if it has a public for it or
+    // the dictionary points to it, so publics only the dictionary knows of
+    // are included too.
+    pub fn cross_reference(&self) -> Vec<CrossReference> {
+        let mut xref: BTreeMap<String, CrossReference> = BTreeMap::new();
+        let mut add = |name: &str, member: usize, defines: bool| {
+            let entry = xref
+                .entry(name.to_string())
+                .or_insert_with(|| CrossReference {
+                    name: name.to_string(),
+                    defined_in: vec![],
+                    referenced_by: vec![],
+                });
+            let members = if defines {
+                &mut entry.defined_in
+            } else {
+                &mut entry.referenced_by
+            };
+            if !members.contains(&member) {
+                members.push(member);
+            }
+        };
+        for (i, member) in self.members.iter().enumerate() {
+            for symbol in member.module.symbols().iter() {
+                if symbol.scope != SymbolScope::Global {
+                    continue;
+                }
+                match symbol.kind {
+                    SymbolKind::External | SymbolKind::ComdatExternal => {
+                        add(&symbol.name, i, false)
+                    }
+                    _ if symbol.is_defined() => add(&symbol.name, i, true),
+                    _ => (),
+                }
+            }
+        }
+        // Names ending in '!' are the entries librarians add for members.
+        for (name, page) in &self.dictionary {
+            if name.ends_with('!') {
+                continue;
+            }
+            if let Some(i) = self.members.iter().position(|m| m.page == *page as u32) {
+                add(name, i, true);
+            }
+        }
+        let mut xref: Vec<CrossReference> = xref.into_values().collect();
+        for entry in &mut xref {
+            entry.defined_in.sort();
+            entry.referenced_by.sort();
+        }
+        xref
+    }
+}
+
+// Reads a single object, or every member of a library. Library members are
+// returned with their module names; a plain object has no name.
+pub fn read_modules(bytes: &[u8]) -> Result<Vec<(Option<String>, OmfModule)>, OmfError> {
+    if OmfLibrary::is_library(bytes) {
+        let library = OmfLibrary::from_bytes(bytes)?;
+        Ok(library
+            .members
+            .into_iter()
+            .map(|m| (Some(m.name), m.module))
+            .collect())
+    } else {
+        Ok(vec![(None, OmfModule::read(&mut &bytes[..])?)])
+    }
+}
+
+// The starting block and bucket for a name, and the steps to take through
+// them when a slot is taken, as given by the OMF library specification.
+struct DictionaryHash {
+    blocks: usize,
+    block: usize,
+    block_step: usize,
+    bucket: usize,
+    bucket_step: usize,
+}
+
+impl DictionaryHash {
+    fn new(name: &[u8], blocks: usize) -> DictionaryHash {
+        let len = name.len();
+        let mut block_x = len as u16 | 0x20;
+        let mut bucket_d = len as u16 | 0x20;
+        let mut block_d = 0u16;
+        let mut bucket_x = 0u16;
+        for i in 0..len {
+            let back = name[len - 1 - i] as u16 | 0x20;
+            bucket_x = bucket_x.rotate_right(2) ^ back;
+            block_d = block_d.rotate_left(2) ^ back;
+            if i + 1 == len {
+                break;
+            }
+            let front = name[i] as u16 | 0x20;
+            block_x = block_x.rotate_left(2) ^ front;
+            bucket_d = bucket_d.rotate_right(2) ^ front;
+        }
+        DictionaryHash {
+            blocks,
+            block: block_x as usize % blocks,
+            block_step: (block_d as usize % blocks).max(1),
+            bucket: bucket_x as usize % DICTIONARY_BUCKETS,
+            bucket_step: (bucket_d as usize % DICTIONARY_BUCKETS).max(1),
+        }
+    }
+
+    // Every (block, bucket) pair in the order they are tried.
+    fn probes(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.blocks).flat_map(move |b| {
+            let block = (self.block + b * self.block_step) % self.blocks;
+            (0..DICTIONARY_BUCKETS).map(move |i| {
+                (
+                    block,
+                    (self.bucket + i * self.bucket_step) % DICTIONARY_BUCKETS,
+                )
+            })
+        })
+    }
+}
+
+// Block counts for the dictionary. The probe steps only reach every block
+// and bucket if the counts are prime.
+const DICTIONARY_SIZES: &[usize] = &[
+    1, 2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+    97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191,
+    193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251,
+];
+
+// Places each (name, page) entry in a dictionary of the given size, or
+// returns None if they don't all fit.
+fn build_dictionary(entries: &[(String, u16)], blocks: usize) -> Option<Vec<u8>> {
+    let mut dictionary = vec![0u8; blocks * DICTIONARY_BLOCK_SIZE];
+    for b in 0..blocks {
+        dictionary[b * DICTIONARY_BLOCK_SIZE + DICTIONARY_BUCKETS] =
+            (DICTIONARY_FIRST_ENTRY / 2) as u8;
+    }
+    'entries: for (name, page) in entries {
+        let hash = DictionaryHash::new(name.as_bytes(), blocks);
+        let size = (name.len() + 3 + 1) & !1;
+        for (block, bucket) in hash.probes() {
+            let block = &mut dictionary[block * DICTIONARY_BLOCK_SIZE..][..DICTIONARY_BLOCK_SIZE];
+            let free = block[DICTIONARY_BUCKETS] as usize * 2;
+            if block[DICTIONARY_BUCKETS] == 0xFF || free + size > DICTIONARY_BLOCK_SIZE {
+                block[DICTIONARY_BUCKETS] = 0xFF;
+                continue;
+            }
+            if block[bucket] != 0 {
+                continue;
+            }
+            block[bucket] = (free / 2) as u8;
+            block[free] = name.len() as u8;
+            block[free + 1..free + 1 + name.len()].copy_from_slice(name.as_bytes());
+            block[free + 1 + name.len()..free + 3 + name.len()]
+                .copy_from_slice(&page.to_le_bytes());
+            block[DICTIONARY_BUCKETS] = if free + size >= DICTIONARY_BLOCK_SIZE {
+                0xFF
+            } else {
+                ((free + size) / 2) as u8
+            };
+            continue 'entries;
+        }
+        return None;
+    }
+    Some(dictionary)
+}
+
+// Assembles a library from object modules. Each module's bytes are stored
+// as given, starting on a page boundary, and its global publics are entered
+// in the dictionary along with a "name!" entry for the module itself.
+#[derive(Debug)]
+pub struct LibraryBuilder {
+    page_size: u32,
+    case_sensitive: bool,
+    members: Vec<Vec<u8>>,
+}
+
+impl Default for LibraryBuilder {
+    fn default() -> Self {
+        LibraryBuilder::new()
+    }
+}
+
+impl LibraryBuilder {
+    pub fn new() -> LibraryBuilder {
+        LibraryBuilder {
+            page_size: 16,
+            case_sensitive: false,
+            members: vec![],
+        }
+    }
+
+    // The smallest page size to use. It is raised if the library would
+    // otherwise have more pages than can be numbered.
+    pub fn set_page_size(&mut self, page_size: u32) -> Result<(), OmfError> {
+        if !page_size.is_power_of_two() || !(16..=32768).contains(&page_size) {
+            return Err(OmfError::Value("invalid library page size"));
+        }
+        self.page_size = page_size;
+        Ok(())
+    }
+
+    pub fn set_case_sensitive(&mut self, case_sensitive: bool) {
+        self.case_sensitive = case_sensitive;
+    }
+
+    // Adds the bytes of a single object module, THEADR through MODEND.
+    pub fn add_member(&mut self, bytes: Vec<u8>) {
+        self.members.push(bytes);
+    }
+
+    fn page_size_for(&self) -> Result<u32, OmfError> {
+        let mut page_size = self.page_size;
+        loop {
+            let pages: usize = 1 + self
+                .members
+                .iter()
+                .map(|m| m.len().div_ceil(page_size as usize))
+                .sum::<usize>();
+            if pages <= 0x10000 {
+                return Ok(page_size);
+            }
+            if page_size == 32768 {
+                return Err(OmfError::Value("library too large"));
+            }
+            page_size *= 2;
+        }
+    }
+
+    pub fn write(&self, w: &mut dyn Write) -> Result<(), OmfError> {
+        let page_size = self.page_size_for()? as usize;
+        let mut body = vec![0u8; page_size];
+        let mut entries: Vec<(String, u16)> = vec![];
+        for bytes in &self.members {
+            let module = OmfModule::read_one(&mut &bytes[..])?;
+            let page = (body.len() / page_size) as u16;
+            let name = module.records.iter().find_map(|r| match &r.data {
+                OmfRecordData::THeadr { name } => Some(name.clone()),
+                _ => None,
+            });
+            if let Some(name) = name {
+                entries.push((format!("{}!", module_stem(&name)), page));
+            }
+            for s in module.symbols().iter() {
+                let public = matches!(
+                    s.kind,
+                    SymbolKind::Public | SymbolKind::Communal(_) | SymbolKind::Comdat
+                );
+                if public && s.scope == SymbolScope::Global {
+                    entries.push((s.name.clone(), page));
+                }
+            }
+            body.extend_from_slice(bytes);
+            body.resize(body.len().next_multiple_of(page_size), 0);
+        }
+        if !self.case_sensitive {
+            let mut seen = std::collections::HashSet::new();
+            entries.retain(|(name, _)| seen.insert(name.to_ascii_uppercase()));
+        } else {
+            let mut seen = std::collections::HashSet::new();
+            entries.retain(|(name, _)| seen.insert(name.clone()));
+        }
+        if entries.iter().any(|(name, _)| name.len() > 255) {
+            return Err(OmfError::Value("public name too long"));
+        }
+
+        // LIBEND pads the file out so the dictionary starts on a block.
+        let end = body.len() + 3;
+        let padding = end.next_multiple_of(DICTIONARY_BLOCK_SIZE) - end;
+        body.push(LIBRARY_END);
+        body.extend_from_slice(&(padding as u16).to_le_bytes());
+        body.resize(body.len() + padding, 0);
+        let dictionary_offset = body.len();
+
+        let estimate = entries.iter().map(|(n, _)| n.len() + 4).sum::<usize>()
+            / (DICTIONARY_BLOCK_SIZE - DICTIONARY_FIRST_ENTRY);
+        let (blocks, dictionary) = DICTIONARY_SIZES
+            .iter()
+            .filter(|&&b| b > estimate)
+            .find_map(|&b| build_dictionary(&entries, b).map(|d| (b, d)))
+            .ok_or(OmfError::Value("too many publics for the dictionary"))?;
+        body.extend_from_slice(&dictionary);
+
+        body[0] = LIBRARY_HEADER;
+        body[1..3].copy_from_slice(&((page_size - 3) as u16).to_le_bytes());
+        body[3..7].copy_from_slice(&(dictionary_offset as u32).to_le_bytes());
+        body[7..9].copy_from_slice(&(blocks as u16).to_le_bytes());
+        body[9] = if self.case_sensitive {
+            FLAG_CASE_SENSITIVE
+        } else {
+            0
+        };
+        w.write_all(&body)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{
+    error::OmfError,
+    record::{OmfRecord, OmfRecordData},
+    OmfInfo, OmfModule, SegmentIndex,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineEntry {
+    pub file: String,
+    pub line: u16,
+    pub segment_index: SegmentIndex,
+    pub comdat: Option<String>,
+    pub offset: u32,
+}
+
+// Collects LINNUM and LINSYM entries, attributing them to the source file
+// named by the most recent THEADR. LINSYM entries are relative to their
+// COMDAT and carry a segment index of 0.
+pub fn build_line_entries(
+    records: &[OmfRecord],
+    info: &OmfInfo,
+) -> Result<Vec<LineEntry>, OmfError> {
+    let mut entries = vec![];
+    let mut file = String::new();
+    for record in records {
+        match &record.data {
+            OmfRecordData::THeadr { name } => file = name.clone(),
+            OmfRecordData::LinNum {
+                base_segment_index,
+                lines,
+                ..
+            } => {
+                for l in lines {
+                    entries.push(LineEntry {
+                        file: file.clone(),
+                        line: l.line_number,
+                        segment_index: *base_segment_index,
+                        comdat: None,
+                        offset: l.line_number_offset as u32,
+                    });
+                }
+            }
+            OmfRecordData::LinSym {
+                public_name_index,
+                lines,
+                ..
+            } => {
+                let comdat = info.name_from_index(*public_name_index)?;
+                for l in lines {
+                    entries.push(LineEntry {
+                        file: file.clone(),
+                        line: l.line_number,
+                        segment_index: SegmentIndex::NONE,
+                        comdat: Some(comdat.to_string()),
+                        offset: l.line_number_offset as u32,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(entries)
+}
+
+impl OmfModule {
+    pub fn lines(&self) -> Result<impl Iterator<Item = LineEntry>, OmfError> {
+        Ok(build_line_entries(&self.records, self.info())?.into_iter())
+    }
+
+    // Finds the line whose code starts closest before `offset` in the given
+    // segment.
+    pub fn line_for_offset(
+        &self,
+        segment_index: SegmentIndex,
+        offset: u32,
+    ) -> Result<Option<LineEntry>, OmfError> {
+        Ok(self
+            .lines()?
+            .filter(|l| l.segment_index == segment_index && l.comdat.is_none())
+            .filter(|l| l.offset <= offset)
+            .max_by_key(|l| l.offset))
+    }
+}
@@ -0,0 +1,573 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    ops::Range,
+};
+
+use crate::{
+    compute_layout_with,
+    error::OmfError,
+    fixup::apply_fixup,
+    layout::{LayoutEntry, LayoutSpec},
+    record::SegmentCombination,
+    symbols::{SymbolBinding, SymbolKind, SymbolScope},
+    GroupIndex, Layout, OmfLibrary, OmfModule, RelocationRef, SegmentIndex,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkOptions {
+    pub origin: u32,
+    // Compute every frame as paragraph 0, as a .COM program sees it.
+    pub flat_frames: bool,
+    pub segment_order: Vec<LayoutEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolvedSymbol {
+    pub address: u32,
+    pub frame: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkedProgram {
+    pub layout: Layout,
+    pub origin: u32,
+    pub image: Vec<u8>,
+    pub initialized_end: u32,
+    pub initialized: Vec<Range<u32>>,
+    pub entry: Option<ResolvedSymbol>,
+    pub publics: Vec<(String, ResolvedSymbol)>,
+    // Addresses of segment base fixups, which need relocating at load time.
+    pub segment_fixups: Vec<u32>,
+}
+
+struct Resolver<'a> {
+    modules: &'a [OmfModule],
+    layout: &'a Layout,
+    flat_frames: bool,
+    globals: HashMap<String, ResolvedSymbol>,
+    locals: Vec<HashMap<String, ResolvedSymbol>>,
+    aliases: HashMap<String, String>,
+}
+
+impl Resolver<'_> {
+    fn frame_of(&self, address: u32) -> u32 {
+        if self.flat_frames {
+            0
+        } else {
+            address & !0xF
+        }
+    }
+
+    fn segment(&self, module: usize, index: SegmentIndex) -> Result<ResolvedSymbol, OmfError> {
+        let placement = self
+            .layout
+            .segment(module, index)
+            .ok_or(OmfError::Value("segment index not found"))?;
+        Ok(ResolvedSymbol {
+            address: placement.base,
+            frame: self.frame_of(placement.segment_base),
+        })
+    }
+
+    fn group(&self, module: usize, index: GroupIndex) -> Result<ResolvedSymbol, OmfError> {
+        let info = self.modules[module].info();
+        let group = info
+            .groups
+            .get((index.0 as usize).wrapping_sub(1))
+            .ok_or(OmfError::Value("group index not found"))?;
+        let name = info.name(group.group_name_index)?;
+        let placement = self
+            .layout
+            .group(name)
+            .ok_or(OmfError::Value("group not placed"))?;
+        Ok(ResolvedSymbol {
+            address: placement.base,
+            frame: self.frame_of(placement.base),
+        })
+    }
+
+    fn external(&self, module: usize, name: &str) -> Result<ResolvedSymbol, OmfError> {
+        if let Some(s) = self.locals[module].get(name) {
+            return Ok(*s);
+        }
+        if let Some(s) = self.globals.get(name) {
+            return Ok(*s);
+        }
+        if let Some(substitute) = self.aliases.get(name) {
+            return self.external(module, substitute);
+        }
+        Err(OmfError::Link(format!("undefined symbol {name}")))
+    }
+
+    fn resolve(&self, module: usize, r: &RelocationRef) -> Result<ResolvedSymbol, OmfError> {
+        match r {
+            RelocationRef::Segment(i) => self.segment(module, *i),
+            RelocationRef::Group(i) => self.group(module, *i),
+            RelocationRef::External(name) => self.external(module, name),
+            RelocationRef::Absolute(frame) => Ok(ResolvedSymbol {
+                address: *frame as u32 * 16,
+                frame: *frame as u32 * 16,
+            }),
+        }
+    }
+}
+
+fn collect_symbols(resolver: &mut Resolver, end: &mut u32) -> Result<(), OmfError> {
+    let mut communals: Vec<(String, u32)> = vec![];
+    for (m, module) in resolver.modules.iter().enumerate() {
+        let symbols = module.symbols();
+        let mut locals = HashMap::new();
+        for s in symbols.iter() {
+            match &s.kind {
+                SymbolKind::Public => {
+                    let resolved = match s.binding {
+                        SymbolBinding::Segment {
+                            group_index,
+                            segment_index,
+                        } => {
+                            let segment = resolver.segment(m, segment_index)?;
+                            let frame = if !group_index.is_none() {
+                                resolver.group(m, group_index)?.frame
+                            } else {
+                                segment.frame
+                            };
+                            ResolvedSymbol {
+                                address: segment.address + s.offset,
+                                frame,
+                            }
+                        }
+                        SymbolBinding::Absolute { frame } => ResolvedSymbol {
+                            address: frame as u32 * 16 + s.offset,
+                            frame: frame as u32 * 16,
+                        },
+                        SymbolBinding::Unbound => continue,
+                    };
+                    if s.scope == SymbolScope::Local {
+                        locals.insert(s.name.clone(), resolved);
+                    } else if resolver.globals.insert(s.name.clone(), resolved).is_some() {
+                        return Err(OmfError::Link(format!("duplicate symbol {}", s.name)));
+                    }
+                }
+                SymbolKind::Communal(length) => {
+                    let size = length.size() as u32;
+                    match communals.iter_mut().find(|(n, _)| n == &s.name) {
+                        Some(c) => c.1 = c.1.max(size),
+                        None => communals.push((s.name.clone(), size)),
+                    }
+                }
+                SymbolKind::Alias(substitute) => {
+                    resolver.aliases.insert(s.name.clone(), substitute.clone());
+                }
+                // COMDAT data isn't placed, so its symbols would be left
+                // undefined and its fixups unapplied.
+                SymbolKind::Comdat => {
+                    return Err(OmfError::Link(format!(
+                        "COMDAT {} can't be linked; COMDATs aren't supported",
+                        s.name
+                    )))
+                }
+                _ => (),
+            }
+        }
+        resolver.locals.push(locals);
+    }
+    for (name, size) in communals {
+        if resolver.globals.contains_key(&name) {
+            continue;
+        }
+        let address = end.div_ceil(2) * 2;
+        resolver.globals.insert(
+            name,
+            ResolvedSymbol {
+                address,
+                frame: resolver.frame_of(address),
+            },
+        );
+        *end = address + size;
+    }
+    Ok(())
+}
+
+// Resolves the publics of `modules` against a layout without linking, for
+// when only addresses are needed. Communals are allocated past the end of
+// the layout as the linker would.
+pub fn resolve_publics(
+    modules: &[OmfModule],
+    layout: &Layout,
+    flat_frames: bool,
+) -> Result<Vec<(String, ResolvedSymbol)>, OmfError> {
+    let mut end = layout.end();
+    let mut resolver = Resolver {
+        modules,
+        layout,
+        flat_frames,
+        globals: HashMap::new(),
+        locals: vec![],
+        aliases: HashMap::new(),
+    };
+    collect_symbols(&mut resolver, &mut end)?;
+    let mut publics: Vec<(String, ResolvedSymbol)> = resolver.globals.into_iter().collect();
+    publics.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(publics)
+}
+
+fn merge_ranges(mut ranges: Vec<Range<u32>>) -> Vec<Range<u32>> {
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<u32>> = vec![];
+    for r in ranges {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+// Links `modules` into a single memory image starting at `options.origin`,
+// resolving publics and externals and applying every fixup.
+pub fn link(modules: &[OmfModule], options: &LinkOptions) -> Result<LinkedProgram, OmfError> {
+    let layout = compute_layout_with(
+        modules,
+        &LayoutSpec {
+            origin: options.origin,
+            entries: options.segment_order.clone(),
+        },
+    )?;
+    if let Some(s) = layout.segments.iter().find(|s| s.base < options.origin) {
+        return Err(OmfError::Link(format!(
+            "segment {} placed below origin at {:05X}h",
+            s.name, s.base
+        )));
+    }
+    let mut end = layout.end().max(options.origin);
+    let mut resolver = Resolver {
+        modules,
+        layout: &layout,
+        flat_frames: options.flat_frames,
+        globals: HashMap::new(),
+        locals: vec![],
+        aliases: HashMap::new(),
+    };
+    collect_symbols(&mut resolver, &mut end)?;
+
+    let mut image = vec![0u8; (end - options.origin) as usize];
+    let mut initialized_end = options.origin;
+    let mut initialized: Vec<Range<u32>> = vec![];
+    let mut segment_fixups = vec![];
+    for (m, module) in modules.iter().enumerate() {
+        for segment in module.segment_images()? {
+            let placement = layout
+                .segment(m, segment.segment_index)
+                .ok_or(OmfError::Value("segment index not found"))?;
+            for r in &segment.initialized {
+                let start = (placement.base - options.origin) as usize + r.start;
+                let len = r.end - r.start;
+                image[start..start + len].copy_from_slice(&segment.data[r.clone()]);
+                initialized_end = initialized_end.max(placement.base + r.end as u32);
+                initialized.push(placement.base + r.start as u32..placement.base + r.end as u32);
+            }
+        }
+
+        for reloc in module.relocations()? {
+            let placement = layout
+                .segment(m, reloc.segment_index)
+                .ok_or(OmfError::Value("segment index not found"))?;
+            let location = placement.base + reloc.offset;
+            let target = resolver.resolve(m, &reloc.target)?;
+            let frame = resolver.resolve(m, &reloc.frame)?.frame;
+            let start = (location - options.origin) as usize;
+            let size = reloc.kind.size();
+            let bytes = image
+                .get_mut(start..)
+                .ok_or(OmfError::Value("fixup location outside image"))?;
+            let paragraph = apply_fixup(
+                bytes,
+                reloc.kind,
+                location,
+                target.address + reloc.target_displacement,
+                frame,
+                reloc.segment_relative,
+            )?;
+            segment_fixups.extend(paragraph.map(|p| location + p as u32));
+            initialized_end = initialized_end.max(location + size as u32);
+            initialized.push(location..location + size as u32);
+        }
+    }
+
+    let mut entry = None;
+    for (m, module) in modules.iter().enumerate() {
+        if let Some(e) = module.entry_point()? {
+            if entry.is_some() {
+                return Err(OmfError::Link("multiple entry points".to_string()));
+            }
+            let target = resolver.resolve(m, &e.target)?;
+            let frame = resolver.resolve(m, &e.frame)?;
+            entry = Some(ResolvedSymbol {
+                address: target.address + e.offset,
+                frame: frame.frame,
+            });
+        }
+    }
+
+    let mut publics: Vec<(String, ResolvedSymbol)> = resolver.globals.into_iter().collect();
+    publics.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(LinkedProgram {
+        layout,
+        origin: options.origin,
+        image,
+        initialized_end,
+        initialized: merge_ranges(initialized),
+        entry,
+        publics,
+        segment_fixups,
+    })
+}
+
+impl LinkOptions {
+    // Options for a tiny-model .COM program loaded at 100h.
+    pub fn com() -> LinkOptions {
+        LinkOptions {
+            origin: 0x100,
+            flat_frames: true,
+            ..LinkOptions::default()
+        }
+    }
+
+    pub fn exe() -> LinkOptions {
+        LinkOptions::default()
+    }
+}
+
+impl LinkedProgram {
+    // The image up to the last initialized byte, as loaded from a file.
+    pub fn bin_image(&self) -> Vec<u8> {
+        self.image[..(self.initialized_end - self.origin) as usize].to_vec()
+    }
+
+    // Checks that a program linked with LinkOptions::com() is a valid .COM
+    // program and returns its file image.
+    pub fn com_image(&self) -> Result<Vec<u8>, OmfError> {
+        if !self.segment_fixups.is_empty() {
+            return Err(OmfError::Link(
+                "segment fixups are not allowed in a .COM program".to_string(),
+            ));
+        }
+        if let Some(entry) = self.entry {
+            if entry.address != 0x100 {
+                return Err(OmfError::Link(format!(
+                    "entry point at {:04X}h, must be at 0100h",
+                    entry.address
+                )));
+            }
+        }
+        let image = self.bin_image();
+        if image.len() > 0xFF00 {
+            return Err(OmfError::Link(
+                ".COM program exceeds 65280 bytes".to_string(),
+            ));
+        }
+        Ok(image)
+    }
+
+    // Builds an MZ executable. The stack comes from the stack-combined
+    // segments (or class STACK) and the entry point from MODEND.
+    pub fn exe_image(&self) -> Result<Vec<u8>, OmfError> {
+        let entry = self
+            .entry
+            .ok_or(OmfError::Link("no entry point".to_string()))?;
+
+        let stack: Vec<_> = self
+            .layout
+            .segments
+            .iter()
+            .filter(|s| s.combination == SegmentCombination::Stack)
+            .collect();
+        let stack = if stack.is_empty() {
+            self.layout
+                .segments
+                .iter()
+                .filter(|s| s.class.eq_ignore_ascii_case("STACK"))
+                .collect()
+        } else {
+            stack
+        };
+        let (ss, sp) = match stack.first() {
+            Some(first) => {
+                let frame = first.segment_base & !0xF;
+                let end = stack
+                    .iter()
+                    .map(|s| s.base + s.length)
+                    .max()
+                    .unwrap_or(frame);
+                if end - frame > 0x10000 {
+                    return Err(OmfError::Link("stack exceeds 64K".to_string()));
+                }
+                ((frame >> 4) as u16, (end - frame) as u16)
+            }
+            None => (0, 0),
+        };
+
+        let load_size = self.initialized_end as usize;
+        let extra = self.image.len() - load_size;
+        let header_paragraphs = (0x1C + self.segment_fixups.len() * 4).div_ceil(16);
+        let header_size = header_paragraphs * 16;
+        let file_size = header_size + load_size;
+
+        let mut exe = Vec::with_capacity(file_size);
+        let mut push = |v: u16| exe.extend_from_slice(&v.to_le_bytes());
+        push(0x5A4D);
+        push((file_size % 512) as u16);
+        push(file_size.div_ceil(512) as u16);
+        push(self.segment_fixups.len() as u16);
+        push(header_paragraphs as u16);
+        push(extra.div_ceil(16) as u16);
+        push(0xFFFF);
+        push(ss);
+        push(sp);
+        push(0);
+        push((entry.address - entry.frame) as u16);
+        push((entry.frame >> 4) as u16);
+        push(0x1C);
+        push(0);
+        for f in &self.segment_fixups {
+            push((f & 0xF) as u16);
+            push((f >> 4) as u16);
+        }
+        exe.resize(header_size, 0);
+        exe.extend_from_slice(&self.image[..load_size]);
+        Ok(exe)
+    }
+}
+
+// Links a tiny-model program into a .COM image loaded at 100h.
+pub fn link_com(modules: &[OmfModule]) -> Result<Vec<u8>, OmfError> {
+    link(modules, &LinkOptions::com())?.com_image()
+}
+
+// Links a program into an MZ executable.
+pub fn link_exe(modules: &[OmfModule]) -> Result<Vec<u8>, OmfError> {
+    link(modules, &LinkOptions::exe())?.exe_image()
+}
+
+// Links a raw binary image whose first byte lives at `options.origin`, with
+// segments placed according to `options.segment_order`. Trailing
+// uninitialized space is not written.
+pub fn link_bin(modules: &[OmfModule], options: &LinkOptions) -> Result<Vec<u8>, OmfError> {
+    Ok(link(modules, options)?.bin_image())
+}
+
+// Names a module defines for other modules, and names it needs from them.
+fn defined_and_needed(module: &OmfModule) -> (Vec<String>, Vec<String>) {
+    let mut defined = vec![];
+    let mut needed = vec![];
+    for s in module.symbols().iter() {
+        match s.kind {
+            SymbolKind::External | SymbolKind::ComdatExternal => needed.push(s.name.clone()),
+            SymbolKind::Public if s.scope == SymbolScope::Local => (),
+            _ => defined.push(s.name.clone()),
+        }
+    }
+    (defined, needed)
+}
+
+// Finds the library members needed to resolve the externals of `modules`,
+// and of the members they pull in, searching the libraries in order by
+// their dictionaries. Returns (library, member) indices in the order the
+// members were pulled in. Names no library defines are left for the linker
+// to report.
+pub fn search_libraries(modules: &[OmfModule], libraries: &[OmfLibrary]) -> Vec<(usize, usize)> {
+    let mut defined = HashSet::new();
+    let mut needed = VecDeque::new();
+    for module in modules {
+        let (d, n) = defined_and_needed(module);
+        defined.extend(d);
+        needed.extend(n);
+    }
+    let mut selected = vec![];
+    while let Some(name) = needed.pop_front() {
+        if defined.contains(&name) {
+            continue;
+        }
+        let found = libraries.iter().enumerate().find_map(|(l, library)| {
+            let page = library.lookup(&name)? as u32;
+            let m = library.members.iter().position(|m| m.page == page)?;
+            Some((l, m))
+        });
+        let Some((l, m)) = found else {
+            continue;
+        };
+        if selected.contains(&(l, m)) {
+            continue;
+        }
+        selected.push((l, m));
+        let (d, n) = defined_and_needed(&libraries[l].members[m].module);
+        defined.extend(d);
+        needed.extend(n);
+    }
+    selected
+}
+
+// Finds the publics in `modules` that nothing refers to: no fixup in any of
+// the modules targets them, by name or by their offset in their segment,
+// and they aren't one of `roots`, a module's entry point or an export.
+// Returns (module index, name) pairs in module order. A public only used by
+// code that is itself unreferenced still counts as referenced.
+pub fn unreferenced_publics(
+    modules: &[OmfModule],
+    roots: &[&str],
+) -> Result<Vec<(usize, String)>, OmfError> {
+    let mut global_names: HashSet<String> = roots.iter().map(|r| r.to_string()).collect();
+    // Names are kept with the module referring to them, as local publics
+    // can only be referred to from their own module.
+    let mut names: HashSet<(usize, String)> = HashSet::new();
+    let mut addresses: HashSet<(usize, SegmentIndex, u32)> = HashSet::new();
+    for (m, module) in modules.iter().enumerate() {
+        let mut targets: Vec<(RelocationRef, u32)> = module
+            .relocations()?
+            .into_iter()
+            .map(|r| (r.target, r.target_displacement))
+            .collect();
+        if let Some(entry) = module.entry_point()? {
+            targets.push((entry.target, entry.offset));
+        }
+        for (target, offset) in targets {
+            match target {
+                RelocationRef::Segment(i) => {
+                    addresses.insert((m, i, offset));
+                }
+                RelocationRef::External(name) => {
+                    global_names.insert(name.to_string());
+                    names.insert((m, name.to_string()));
+                }
+                _ => (),
+            }
+        }
+        for export in crate::collect_exports(&module.records)? {
+            global_names.insert(export.internal_name.unwrap_or(export.name));
+        }
+    }
+
+    let mut unreferenced = vec![];
+    for (m, module) in modules.iter().enumerate() {
+        for s in module.symbols().iter() {
+            if s.kind != SymbolKind::Public {
+                continue;
+            }
+            let by_name = match s.scope {
+                SymbolScope::Global => global_names.contains(&s.name),
+                SymbolScope::Local => names.contains(&(m, s.name.clone())),
+            };
+            let by_address = match s.binding {
+                SymbolBinding::Segment { segment_index, .. } => {
+                    addresses.contains(&(m, segment_index, s.offset))
+                }
+                _ => false,
+            };
+            if !by_name && !by_address {
+                unreferenced.push((m, s.name.clone()));
+            }
+        }
+    }
+    Ok(unreferenced)
+}
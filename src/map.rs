@@ -0,0 +1,80 @@
+use std::io::{self, Write};
+
+use crate::{Layout, LinkedProgram, ResolvedSymbol};
+
+fn segmented(symbol: &ResolvedSymbol) -> String {
+    format!(
+        "{:04X}:{:04X}",
+        symbol.frame >> 4,
+        symbol.address.wrapping_sub(symbol.frame) & 0xFFFF
+    )
+}
+
+// Writes a link map in the shape of a Microsoft LINK .MAP file: segments by
+// address, groups, publics by name and by value, and the entry point.
+// Contributions to a combined segment are listed as one segment.
+pub fn write_map(
+    w: &mut dyn Write,
+    layout: &Layout,
+    publics: &[(String, ResolvedSymbol)],
+    entry: Option<ResolvedSymbol>,
+) -> io::Result<()> {
+    let mut segments: Vec<(u32, u32, &str, &str)> = vec![];
+    for s in &layout.segments {
+        let end = s.base + s.length;
+        match segments.iter_mut().find(|(start, _, name, class)| {
+            *name == s.name && *class == s.class && *start == s.segment_base
+        }) {
+            Some(existing) => existing.1 = existing.1.max(end),
+            None => segments.push((s.segment_base, end, &s.name, &s.class)),
+        }
+    }
+    segments.sort_by_key(|s| s.0);
+
+    writeln!(w)?;
+    writeln!(w, " Start  Stop   Length Name                   Class")?;
+    for (start, end, name, class) in &segments {
+        let stop = if end > start { end - 1 } else { *start };
+        writeln!(
+            w,
+            " {start:05X}H {stop:05X}H {:05X}H {name:<22} {class}",
+            end - start
+        )?;
+    }
+
+    if !layout.groups.is_empty() {
+        writeln!(w)?;
+        writeln!(w, " Origin   Group")?;
+        for g in &layout.groups {
+            writeln!(w, " {:04X}:0   {}", g.base >> 4, g.name)?;
+        }
+    }
+
+    writeln!(w)?;
+    writeln!(w, "  Address         Publics by Name")?;
+    writeln!(w)?;
+    for (name, symbol) in publics {
+        writeln!(w, " {}       {name}", segmented(symbol))?;
+    }
+
+    let mut by_value: Vec<&(String, ResolvedSymbol)> = publics.iter().collect();
+    by_value.sort_by(|a, b| a.1.address.cmp(&b.1.address).then(a.0.cmp(&b.0)));
+    writeln!(w)?;
+    writeln!(w, "  Address         Publics by Value")?;
+    writeln!(w)?;
+    for (name, symbol) in by_value {
+        writeln!(w, " {}       {name}", segmented(symbol))?;
+    }
+
+    if let Some(entry) = entry {
+        writeln!(w)?;
+        writeln!(w, "Program entry point at {}", segmented(&entry))?;
+    }
+    Ok(())
+}
+
+impl LinkedProgram {
+    pub fn write_map(&self, w: &mut dyn Write) -> io::Result<()> {
+        write_map(w, &self.layout, &self.publics, self.entry)
+    }
+}
@@ -0,0 +1,35 @@
+use std::{fs::File, ops::Deref, path::Path};
+
+use memmap2::Mmap;
+
+use crate::{error::OmfError, OmfSlice};
+
+// An object or library mapped into memory, so OmfSlice can read it without
+// any read calls or copies.
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<MappedFile, OmfError> {
+        let file = File::open(path)?;
+        // SAFETY: the mapping is only ever read, and its contents are only
+        // trusted as far as any other input is. Something truncating the
+        // file while it is mapped can still fault the process, as with any
+        // memory-mapped file.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile { map })
+    }
+
+    pub fn records(&self) -> OmfSlice<'_> {
+        OmfSlice::new(&self.map)
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
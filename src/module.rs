@@ -0,0 +1,152 @@
+use std::io::{self, Read};
+
+use crate::{
+    build_relocations, build_segment_images, error::OmfError, record::OmfRecord,
+    symbols::SymbolTable, OmfInfo, OmfReader, RecordType, Relocation, SegmentImage,
+};
+
+#[derive(Debug)]
+pub struct OmfModule {
+    pub records: Vec<OmfRecord>,
+    info: OmfInfo,
+}
+
+impl OmfModule {
+    pub fn read(r: &mut dyn Read) -> Result<OmfModule, OmfError> {
+        OmfModule::from_reader(OmfReader::new(r))
+    }
+
+    // Reads every record from a reader the caller has set up, for example to
+    // read leniently or with a different checksum policy.
+    pub fn from_reader<R: Read>(mut reader: OmfReader<R>) -> Result<OmfModule, OmfError> {
+        let mut records = vec![];
+        while let Some(record) = reader.get_next_record()? {
+            records.push(record);
+        }
+        Ok(OmfModule::from_parts(records, reader.info))
+    }
+
+    // Reads a single module, stopping after its MODEND record. Used for
+    // libraries, where modules are followed by padding and more modules.
+    pub fn read_one(r: &mut dyn Read) -> Result<OmfModule, OmfError> {
+        OmfModule::read_one_at(r, 0)
+    }
+
+    // As read_one, with record offsets counting from `offset`.
+    pub(crate) fn read_one_at(r: &mut dyn Read, offset: usize) -> Result<OmfModule, OmfError> {
+        let mut reader = OmfReader::with_offset(r, offset);
+        let mut records = vec![];
+        while let Some(record) = reader.get_next_record()? {
+            let end = record.record_type.base() == RecordType::ModEnd;
+            records.push(record);
+            if end {
+                break;
+            }
+        }
+        Ok(OmfModule::from_parts(records, reader.info))
+    }
+
+    pub(crate) fn from_parts(records: Vec<OmfRecord>, info: OmfInfo) -> OmfModule {
+        OmfModule { records, info }
+    }
+
+    pub fn info(&self) -> &OmfInfo {
+        &self.info
+    }
+
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.info.symbols
+    }
+
+    pub fn segment_images(&self) -> Result<Vec<SegmentImage>, OmfError> {
+        build_segment_images(&self.records)
+    }
+
+    pub fn relocations(&self) -> Result<Vec<Relocation>, OmfError> {
+        build_relocations(&self.records, &self.info)
+    }
+}
+
+// Reads a stream holding several modules back to back, such as
+// concatenated objects or a library, a module at a time. Each module runs
+// through its MODEND and gets its own info, so indices in one module never
+// resolve against another's definitions. A library's header and the
+// padding after its members are skipped, and its end record ends the
+// iteration before the dictionary.
+pub struct OmfModules<R> {
+    reader: OmfReader<R>,
+    page_size: Option<usize>,
+    done: bool,
+}
+
+impl<R: Read> OmfReader<R> {
+    pub fn modules(self) -> OmfModules<R> {
+        OmfModules {
+            reader: self,
+            page_size: None,
+            done: false,
+        }
+    }
+}
+
+impl<R: Read> OmfModules<R> {
+    fn next_module(&mut self) -> Result<Option<OmfModule>, OmfError> {
+        let mut records = vec![];
+        while let Some(record) = self.reader.get_next_record()? {
+            match record.record_type {
+                RecordType::LibHdr => {
+                    self.page_size = Some(record.record_length + 3);
+                    continue;
+                }
+                RecordType::LibEnd => {
+                    self.done = true;
+                    break;
+                }
+                _ => (),
+            }
+            let end = record.record_type.base() == RecordType::ModEnd;
+            records.push(record);
+            if end {
+                self.skip_padding()?;
+                break;
+            }
+        }
+        if records.is_empty() {
+            self.done = true;
+            return Ok(None);
+        }
+        let info = std::mem::take(&mut self.reader.info);
+        Ok(Some(OmfModule::from_parts(records, info)))
+    }
+
+    // Library members start on a page boundary.
+    fn skip_padding(&mut self) -> Result<(), OmfError> {
+        let Some(page_size) = self.page_size else {
+            return Ok(());
+        };
+        let padding = (page_size - self.reader.offset % page_size) % page_size;
+        let skipped = io::copy(
+            &mut (&mut self.reader.r).take(padding as u64),
+            &mut io::sink(),
+        )?;
+        self.reader.offset += skipped as usize;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for OmfModules<R> {
+    type Item = Result<OmfModule, OmfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_module() {
+            Ok(module) => module.map(Ok),
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::borrow::Cow;
+
+use crate::error::OmfError;
+
+// Code page glyphs for bytes 80h-FFh.
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀\
+αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+const CP850_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜø£Ø×ƒáíóúñÑªº¿®¬½¼¡«»\
+░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐└┴┬├─┼ãÃ╚╔╩╦╠═╬¤ðÐÊËÈıÍÎÏ┘┌█▄¦Ì▀\
+ÓßÔÒõÕµþÞÚÛÙýÝ¯´\u{ad}±‗¾¶§÷¸°¨·¹³²■\u{a0}";
+
+// A name as it was in the file, kept when its text can't be turned back
+// into the same bytes, such as invalid UTF-8 read with Lossy.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawName {
+    // Which of the record's names it is, counting from 0.
+    pub position: usize,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+// How names are turned from bytes into strings and back. DOS-era tools
+// wrote names in the system code page, which often isn't valid UTF-8.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NameEncoding {
+    // Fail on names that aren't valid UTF-8.
+    #[default]
+    Utf8,
+    // Replace invalid UTF-8 with U+FFFD. The records keep such names'
+    // bytes, which OmfWriter::write_record writes back.
+    Lossy,
+    Cp437,
+    Cp850,
+}
+
+impl NameEncoding {
+    fn high_half(self) -> Option<&'static str> {
+        match self {
+            NameEncoding::Cp437 => Some(CP437_HIGH),
+            NameEncoding::Cp850 => Some(CP850_HIGH),
+            _ => None,
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> Result<String, OmfError> {
+        match self.high_half() {
+            Some(high) => Ok(bytes
+                .iter()
+                .map(|&b| match b {
+                    0..=0x7F => b as char,
+                    _ => high.chars().nth(b as usize - 0x80).unwrap_or('?'),
+                })
+                .collect()),
+            None if self == NameEncoding::Lossy => Ok(String::from_utf8_lossy(bytes).into_owned()),
+            None => Ok(String::from_utf8(bytes.to_vec())?),
+        }
+    }
+
+    // As decode, but borrowing the bytes when they already read the same
+    // in UTF-8.
+    pub fn decode_borrowed(self, bytes: &[u8]) -> Result<Cow<'_, str>, OmfError> {
+        match self {
+            NameEncoding::Lossy => Ok(String::from_utf8_lossy(bytes)),
+            NameEncoding::Utf8 => match std::str::from_utf8(bytes) {
+                Ok(s) => Ok(Cow::Borrowed(s)),
+                // Only to get the error.
+                Err(_) => Ok(Cow::Owned(String::from_utf8(bytes.to_vec())?)),
+            },
+            _ if bytes.is_ascii() => NameEncoding::Utf8.decode_borrowed(bytes),
+            _ => self.decode(bytes).map(Cow::Owned),
+        }
+    }
+
+    // The bytes a name was read from, for the code pages. Fails on
+    // characters the code page doesn't have.
+    pub fn encode(self, name: &str) -> Result<Vec<u8>, OmfError> {
+        let Some(high) = self.high_half() else {
+            return Ok(name.as_bytes().to_vec());
+        };
+        name.chars()
+            .map(|c| match c {
+                '\0'..='\x7F' => Ok(c as u8),
+                _ => high
+                    .chars()
+                    .position(|h| h == c)
+                    .map(|i| i as u8 + 0x80)
+                    .ok_or(OmfError::Value("name has characters outside the code page")),
+            })
+            .collect()
+    }
+}
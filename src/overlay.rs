@@ -0,0 +1,134 @@
+use crate::{error::OmfError, OmfModule, OmfRecordData, RecordType, SegmentIndex};
+
+// OVLDEF attribute bits: the overlay shares memory with another, or is
+// loaded right after one.
+const OVERLAY_SHARED: u8 = 0x02;
+const OVERLAY_ADJACENT: u8 = 0x01;
+
+// An OVLDEF record, from Intel's overlay linkers. Overlays are referred to
+// by their 1-based position among the module's OVLDEFs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlayDef {
+    pub name: String,
+    // Where the overlay's data starts in the file.
+    pub location: u32,
+    pub shared_with: Option<u16>,
+    pub adjacent_to: Option<u16>,
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], OmfError> {
+    let taken = bytes
+        .get(..len)
+        .ok_or(OmfError::Value("truncated OVLDEF"))?;
+    *bytes = &bytes[len..];
+    Ok(taken)
+}
+
+fn take_index(bytes: &mut &[u8]) -> Result<u16, OmfError> {
+    let first = take(bytes, 1)?[0];
+    if first & 0x80 == 0 {
+        return Ok(first as u16);
+    }
+    Ok(((first as u16 & 0x7F) << 8) | take(bytes, 1)?[0] as u16)
+}
+
+// Decodes the body of an OVLDEF record: the name, the location, attributes,
+// then the indices of the overlays the attributes refer to.
+pub fn parse_ovldef(mut bytes: &[u8]) -> Result<OverlayDef, OmfError> {
+    let len = take(&mut bytes, 1)?[0] as usize;
+    let name = String::from_utf8_lossy(take(&mut bytes, len)?).into_owned();
+    let location = take(&mut bytes, 4)?;
+    let location = u32::from_le_bytes([location[0], location[1], location[2], location[3]]);
+    let attributes = take(&mut bytes, 1)?[0];
+    let shared_with = if attributes & OVERLAY_SHARED != 0 {
+        Some(take_index(&mut bytes)?)
+    } else {
+        None
+    };
+    let adjacent_to = if attributes & OVERLAY_ADJACENT != 0 {
+        Some(take_index(&mut bytes)?)
+    } else {
+        None
+    };
+    Ok(OverlayDef {
+        name,
+        location,
+        shared_with,
+        adjacent_to,
+    })
+}
+
+// An overlay and the segments in it. An overlay loaded after another is its
+// child; overlays sharing memory have the same parent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlay {
+    pub name: String,
+    // Index into the list of overlays, or None for one loaded over the
+    // resident part of the program.
+    pub parent: Option<usize>,
+    // Only known from an OVLDEF.
+    pub location: Option<u32>,
+    pub segments: Vec<SegmentIndex>,
+    pub size: u32,
+}
+
+impl OmfModule {
+    // The module's overlays: those its OVLDEFs define, in order, then any
+    // other overlays its segments name. Segments without an overlay name, or
+    // with an empty one as most translators give, are resident and not
+    // listed.
+    pub fn overlays(&self) -> Result<Vec<Overlay>, OmfError> {
+        let mut defs = vec![];
+        for record in &self.records {
+            if let (RecordType::OvlDef, OmfRecordData::Unknown { data }) =
+                (record.record_type, &record.data)
+            {
+                defs.push(parse_ovldef(data)?);
+            }
+        }
+        let parent_of = |def: &OverlayDef| {
+            let index = |i: u16| (i as usize).checked_sub(1).filter(|&i| i < defs.len());
+            match (def.adjacent_to, def.shared_with) {
+                (Some(i), _) => index(i),
+                (None, Some(i)) => index(i).and_then(|i| defs[i].adjacent_to).and_then(index),
+                (None, None) => None,
+            }
+        };
+        let mut overlays: Vec<Overlay> = defs
+            .iter()
+            .map(|def| Overlay {
+                name: def.name.clone(),
+                parent: parent_of(def),
+                location: Some(def.location),
+                segments: vec![],
+                size: 0,
+            })
+            .collect();
+        let info = self.info();
+        for (index, _, segment) in info.segments_with_names() {
+            if segment.overlay_name_index.is_none() {
+                continue;
+            }
+            let name = info.name_from_index(segment.overlay_name_index)?;
+            if name.is_empty() {
+                continue;
+            }
+            let overlay = match overlays.iter().position(|o| *o.name == *name) {
+                Some(i) => &mut overlays[i],
+                None => {
+                    overlays.push(Overlay {
+                        name: name.to_string(),
+                        parent: None,
+                        location: None,
+                        segments: vec![],
+                        size: 0,
+                    });
+                    overlays.last_mut().unwrap()
+                }
+            };
+            overlay.segments.push(index);
+            overlay.size += segment.length();
+        }
+        Ok(overlays)
+    }
+}
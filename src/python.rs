@@ -0,0 +1,300 @@
+use std::{ops::Range, path::PathBuf};
+
+use pyo3::{
+    exceptions::{PyOSError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+
+use crate::{
+    error::OmfError, read_modules, NameIndex, OmfModule, OmfRecord, Relocation, RelocationRef,
+    SegmentImage, Symbol, SymbolBinding, SymbolKind, SymbolScope,
+};
+
+fn to_py_err(e: OmfError) -> PyErr {
+    match e {
+        OmfError::Io(e) => PyOSError::new_err(e.to_string()),
+        e => PyValueError::new_err(e.to_string()),
+    }
+}
+
+// A RelocationRef as a (kind, value) pair, such as ("segment", 1) or
+// ("external", "_printf").
+fn reference(py: Python<'_>, r: &RelocationRef) -> PyResult<(&'static str, PyObject)> {
+    Ok(match r {
+        RelocationRef::Segment(i) => ("segment", i.0.into_pyobject(py)?.into_any().unbind()),
+        RelocationRef::Group(i) => ("group", i.0.into_pyobject(py)?.into_any().unbind()),
+        RelocationRef::External(name) => ("external", name.into_pyobject(py)?.into_any().unbind()),
+        RelocationRef::Absolute(frame) => {
+            ("absolute", frame.into_pyobject(py)?.into_any().unbind())
+        }
+    })
+}
+
+#[pyclass(name = "OmfModule", module = "omflib")]
+pub struct PyOmfModule {
+    module: OmfModule,
+    // The member name, for modules read from a library.
+    #[pyo3(get)]
+    name: Option<String>,
+}
+
+#[pymethods]
+impl PyOmfModule {
+    // Reads the object module in `data`.
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<PyOmfModule> {
+        let module = OmfModule::read(&mut &data[..]).map_err(to_py_err)?;
+        Ok(PyOmfModule { module, name: None })
+    }
+
+    fn records(&self) -> Vec<PyRecord> {
+        self.module
+            .records
+            .iter()
+            .map(|record| PyRecord {
+                text: record.display_with(self.module.info()).to_string(),
+                record: record.clone(),
+            })
+            .collect()
+    }
+
+    fn symbols(&self) -> Vec<PySymbol> {
+        self.module
+            .symbols()
+            .iter()
+            .cloned()
+            .map(PySymbol)
+            .collect()
+    }
+
+    // The defining entry for `name` if there is one, otherwise the first
+    // reference to it.
+    fn lookup(&self, name: &str) -> Option<PySymbol> {
+        self.module.symbols().lookup(name).cloned().map(PySymbol)
+    }
+
+    fn segments(&self) -> Vec<PySegment> {
+        let info = self.module.info();
+        let name = |index: NameIndex| {
+            index
+                .resolve(info)
+                .map(|n| n.to_string())
+                .unwrap_or_default()
+        };
+        info.segments
+            .iter()
+            .enumerate()
+            .map(|(i, s)| PySegment {
+                index: i as u16 + 1,
+                name: name(s.segment_name_index),
+                class_name: name(s.class_name_index),
+                length: s.length(),
+                use32: s.segment_attributes.bd32bit,
+            })
+            .collect()
+    }
+
+    fn segment_images(&self) -> PyResult<Vec<PySegmentImage>> {
+        let images = self.module.segment_images().map_err(to_py_err)?;
+        Ok(images.into_iter().map(PySegmentImage).collect())
+    }
+
+    fn relocations(&self) -> PyResult<Vec<PyRelocation>> {
+        let relocations = self.module.relocations().map_err(to_py_err)?;
+        Ok(relocations.into_iter().map(PyRelocation).collect())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<OmfModule {} with {} records>",
+            self.name.as_deref().unwrap_or("object"),
+            self.module.records.len()
+        )
+    }
+}
+
+#[pyclass(name = "Record", module = "omflib")]
+pub struct PyRecord {
+    record: OmfRecord,
+    text: String,
+}
+
+#[pymethods]
+impl PyRecord {
+    #[getter]
+    fn offset(&self) -> usize {
+        self.record.offset
+    }
+
+    #[getter]
+    fn record_type(&self) -> u8 {
+        self.record.record_type.to_u8()
+    }
+
+    // The record type's name, such as SEGDEF or LEDATA32.
+    #[getter]
+    fn type_name(&self) -> String {
+        self.record.record_type.to_string()
+    }
+
+    #[getter]
+    fn length(&self) -> usize {
+        self.record.record_length
+    }
+
+    #[getter]
+    fn checksum_valid(&self) -> bool {
+        self.record.checksum_valid
+    }
+
+    fn __str__(&self) -> &str {
+        &self.text
+    }
+}
+
+#[pyclass(name = "Symbol", module = "omflib")]
+pub struct PySymbol(Symbol);
+
+#[pymethods]
+impl PySymbol {
+    #[getter]
+    fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    // One of public, external, communal, comdat_external, comdat or alias.
+    #[getter]
+    fn kind(&self) -> &'static str {
+        match self.0.kind {
+            SymbolKind::Public => "public",
+            SymbolKind::External => "external",
+            SymbolKind::Communal(_) => "communal",
+            SymbolKind::ComdatExternal => "comdat_external",
+            SymbolKind::Comdat => "comdat",
+            SymbolKind::Alias(_) => "alias",
+        }
+    }
+
+    #[getter]
+    fn local(&self) -> bool {
+        self.0.scope == SymbolScope::Local
+    }
+
+    // The segment index for symbols defined in a segment.
+    #[getter]
+    fn segment(&self) -> Option<u16> {
+        match self.0.binding {
+            SymbolBinding::Segment { segment_index, .. } => Some(segment_index.0),
+            _ => None,
+        }
+    }
+
+    #[getter]
+    fn offset(&self) -> u32 {
+        self.0.offset
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Symbol {} {}>", self.kind(), self.0.name)
+    }
+}
+
+#[pyclass(name = "Segment", module = "omflib", get_all)]
+pub struct PySegment {
+    index: u16,
+    name: String,
+    class_name: String,
+    length: u32,
+    use32: bool,
+}
+
+#[pyclass(name = "SegmentImage", module = "omflib")]
+pub struct PySegmentImage(SegmentImage);
+
+#[pymethods]
+impl PySegmentImage {
+    #[getter]
+    fn segment_index(&self) -> u16 {
+        self.0.segment_index.0
+    }
+
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.data)
+    }
+
+    // The byte ranges some LEDATA or LIDATA record filled in.
+    #[getter]
+    fn initialized(&self) -> Vec<(usize, usize)> {
+        self.0
+            .initialized
+            .iter()
+            .map(|&Range { start, end }| (start, end))
+            .collect()
+    }
+}
+
+#[pyclass(name = "Relocation", module = "omflib")]
+pub struct PyRelocation(Relocation);
+
+#[pymethods]
+impl PyRelocation {
+    #[getter]
+    fn segment_index(&self) -> u16 {
+        self.0.segment_index.0
+    }
+
+    #[getter]
+    fn offset(&self) -> u32 {
+        self.0.offset
+    }
+
+    #[getter]
+    fn kind(&self) -> String {
+        self.0.kind.to_string()
+    }
+
+    #[getter]
+    fn segment_relative(&self) -> bool {
+        self.0.segment_relative
+    }
+
+    #[getter]
+    fn target(&self, py: Python<'_>) -> PyResult<(&'static str, PyObject)> {
+        reference(py, &self.0.target)
+    }
+
+    #[getter]
+    fn target_displacement(&self) -> u32 {
+        self.0.target_displacement
+    }
+
+    #[getter]
+    fn frame(&self, py: Python<'_>) -> PyResult<(&'static str, PyObject)> {
+        reference(py, &self.0.frame)
+    }
+}
+
+// Reads an object or library file, returning its modules.
+#[pyfunction]
+fn read_file(path: PathBuf) -> PyResult<Vec<PyOmfModule>> {
+    let bytes = std::fs::read(path)?;
+    let modules = read_modules(&bytes).map_err(to_py_err)?;
+    Ok(modules
+        .into_iter()
+        .map(|(name, module)| PyOmfModule { module, name })
+        .collect())
+}
+
+#[pymodule]
+fn omflib(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(read_file, m)?)?;
+    m.add_class::<PyOmfModule>()?;
+    m.add_class::<PyRecord>()?;
+    m.add_class::<PySymbol>()?;
+    m.add_class::<PySegment>()?;
+    m.add_class::<PySegmentImage>()?;
+    m.add_class::<PyRelocation>()?;
+    Ok(())
+}
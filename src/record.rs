@@ -1,41 +1,137 @@
-use std::{cell::RefCell, fmt::Display, rc::Rc};
+use std::{fmt::Display, ops::Range, sync::Arc};
 
 use num_derive::FromPrimitive;
+use num_traits::FromPrimitive as _;
 use pretty_hex::{HexConfig, PrettyHex};
 
-use crate::{error::OmfError, GroupInfo, OmfInfo, SegmentInfo};
+use crate::{
+    error::OmfError, expand_iterated_data, ExternalIndex, GroupIndex, NameIndex, OmfInfo, RawName,
+    RecordType, SegmentIndex, TypeIndex,
+};
 
-#[derive(Debug)]
+const MAX_DISPLAY_EXPANSION: usize = 0x10000;
+
+// Raw bytes serialize as base64 strings rather than arrays of numbers.
+#[cfg(feature = "serde")]
+pub(crate) mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        STANDARD.decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct CommentType {
     pub no_purge: bool,
     pub no_list: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum MAttrStart {
     NoStart,
-    Start {
-        end_data: u8,
-        frame_datum: u8,
-        target_datum: u8,
-        target_displacement: u16,
-    },
+    Start(FixupData),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct PubName {
     pub name: String,
     pub public_offset: u16,
-    pub type_index: u8,
+    pub type_index: TypeIndex,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ExtName {
     pub name: String,
-    pub type_index: u8,
+    pub type_index: TypeIndex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum CommunalLength {
+    Near(u32),
+    Far {
+        number_of_elements: u32,
+        element_size: u32,
+    },
+    Segment {
+        segment_index: SegmentIndex,
+        size: u32,
+    },
+}
+
+impl CommunalLength {
+    pub fn size(&self) -> u64 {
+        match self {
+            CommunalLength::Near(size) => *size as u64,
+            CommunalLength::Far {
+                number_of_elements,
+                element_size,
+            } => *number_of_elements as u64 * *element_size as u64,
+            CommunalLength::Segment { size, .. } => *size as u64,
+        }
+    }
+}
+
+impl Display for CommunalLength {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommunalLength::Near(size) => write!(f, "near size {size:X}h"),
+            CommunalLength::Far {
+                number_of_elements,
+                element_size,
+            } => write!(f, "far {number_of_elements} x {element_size:X}h"),
+            CommunalLength::Segment {
+                segment_index,
+                size,
+            } => write!(f, "segment {segment_index} size {size:X}h"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ComName {
+    pub name: String,
+    pub type_index: TypeIndex,
+    pub communal_length: CommunalLength,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CExtName {
+    pub logical_name_index: NameIndex,
+    pub type_index: TypeIndex,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct AliasName {
+    pub alias_name: String,
+    pub substitute_name: String,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum SegmentAlignment {
     AbsoluteSegment = 0,
     RelocatableByteAligned = 1,
@@ -45,6 +141,33 @@ pub enum SegmentAlignment {
     RelocatableDWordAligned = 5,
 }
 
+impl SegmentAlignment {
+    pub fn bytes(&self) -> u32 {
+        match self {
+            SegmentAlignment::AbsoluteSegment => 1,
+            SegmentAlignment::RelocatableByteAligned => 1,
+            SegmentAlignment::RelocatableWordAligned => 2,
+            SegmentAlignment::RelocatableParagraphAligned => 16,
+            SegmentAlignment::RelocatablePageAligned => 256,
+            SegmentAlignment::RelocatableDWordAligned => 4,
+        }
+    }
+}
+
+impl SegmentAlignment {
+    // The alignment as assemblers write it.
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            SegmentAlignment::AbsoluteSegment => "abs",
+            SegmentAlignment::RelocatableByteAligned => "byte",
+            SegmentAlignment::RelocatableWordAligned => "word",
+            SegmentAlignment::RelocatableParagraphAligned => "para",
+            SegmentAlignment::RelocatablePageAligned => "page",
+            SegmentAlignment::RelocatableDWordAligned => "dword",
+        }
+    }
+}
+
 impl Display for SegmentAlignment {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -63,6 +186,8 @@ impl Display for SegmentAlignment {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum SegmentCombination {
     Private = 0,
     Public = 2,
@@ -86,12 +211,16 @@ impl Display for SegmentCombination {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct AbsoluteSegmentAddress {
     pub frame_number: u16,
     pub offset: u8,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct SegmentAttributes {
     pub alignment: SegmentAlignment,
     pub combination: SegmentCombination,
@@ -100,83 +229,829 @@ pub struct SegmentAttributes {
     pub absolute_segment_address: Option<AbsoluteSegmentAddress>,
 }
 
+impl SegmentAttributes {
+    // Unpacks a SEGDEF's attribute byte. An absolute segment's address
+    // follows the byte in the record and is left for the caller to fill in.
+    pub fn from_acbp(acbp: u8) -> Result<SegmentAttributes, OmfError> {
+        let alignment = SegmentAlignment::from_u8(acbp >> 5).ok_or(OmfError::Value("alignment"))?;
+        let combination =
+            SegmentCombination::from_u8((acbp >> 2) & 7).ok_or(OmfError::Value("combination"))?;
+        Ok(SegmentAttributes {
+            alignment,
+            combination,
+            big: acbp & 2 != 0,
+            bd32bit: acbp & 1 != 0,
+            absolute_segment_address: None,
+        })
+    }
+
+    // Packs the attributes back into the byte from_acbp reads.
+    pub fn encode(&self) -> u8 {
+        let mut acbp = ((self.alignment as u8) << 5) | ((self.combination as u8) << 2);
+        if self.big {
+            acbp |= 0x02;
+        }
+        if self.bd32bit {
+            acbp |= 0x01;
+        }
+        acbp
+    }
+}
+
+// Puts together a SegmentAttributes, checking that an absolute segment has
+// an address and that nothing else does. Segments start out private.
+#[derive(Debug, Clone)]
+pub struct SegmentAttributesBuilder {
+    attributes: SegmentAttributes,
+}
+
+impl SegmentAttributesBuilder {
+    pub fn new(alignment: SegmentAlignment) -> SegmentAttributesBuilder {
+        SegmentAttributesBuilder {
+            attributes: SegmentAttributes {
+                alignment,
+                combination: SegmentCombination::Private,
+                big: false,
+                bd32bit: false,
+                absolute_segment_address: None,
+            },
+        }
+    }
+
+    pub fn set_combination(&mut self, combination: SegmentCombination) {
+        self.attributes.combination = combination;
+    }
+
+    // Takes the combination as it is stored in the attribute byte,
+    // rejecting the values that have no meaning.
+    pub fn set_combination_value(&mut self, value: u8) -> Result<(), OmfError> {
+        self.attributes.combination =
+            SegmentCombination::from_u8(value).ok_or(OmfError::Value("combination"))?;
+        Ok(())
+    }
+
+    // Set for a segment exactly 64K long, whose length field is then 0.
+    pub fn set_big(&mut self, big: bool) {
+        self.attributes.big = big;
+    }
+
+    pub fn set_use32(&mut self, use32: bool) {
+        self.attributes.bd32bit = use32;
+    }
+
+    pub fn set_absolute_address(&mut self, frame_number: u16, offset: u8) {
+        self.attributes.absolute_segment_address = Some(AbsoluteSegmentAddress {
+            frame_number,
+            offset,
+        });
+    }
+
+    pub fn build(&self) -> Result<SegmentAttributes, OmfError> {
+        let absolute = self.attributes.alignment == SegmentAlignment::AbsoluteSegment;
+        match (absolute, self.attributes.absolute_segment_address) {
+            (true, None) => Err(OmfError::Value("absolute segment without an address")),
+            (false, Some(_)) => Err(OmfError::Value("address for a relocatable segment")),
+            _ => Ok(self.attributes),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct GroupComponent {
     pub index: u8,
-    pub segment_definition: u8,
+    pub segment_definition: SegmentIndex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum ComdatSelection {
+    NoMatch = 0,
+    PickAny = 1,
+    SameSize = 2,
+    ExactMatch = 3,
+}
+
+impl Display for ComdatSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComdatSelection::NoMatch => write!(f, "no match"),
+            ComdatSelection::PickAny => write!(f, "pick any"),
+            ComdatSelection::SameSize => write!(f, "same size"),
+            ComdatSelection::ExactMatch => write!(f, "exact match"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum ComdatAllocation {
+    Explicit = 0,
+    FarCode = 1,
+    FarData = 2,
+    Code32 = 3,
+    Data32 = 4,
+}
+
+impl Display for ComdatAllocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComdatAllocation::Explicit => write!(f, "explicit"),
+            ComdatAllocation::FarCode => write!(f, "far code"),
+            ComdatAllocation::FarData => write!(f, "far data"),
+            ComdatAllocation::Code32 => write!(f, "code32"),
+            ComdatAllocation::Data32 => write!(f, "data32"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum ComdatData {
+    Enumerated(#[cfg_attr(feature = "serde", serde(with = "crate::record::base64_bytes"))] Vec<u8>),
+    Iterated(Vec<IteratedDataBlock>),
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct LineNumber {
+    pub line_number: u16,
+    pub line_number_offset: u16,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum LocationType {
+    LowByte = 0,
+    Offset = 1,
+    Base = 2,
+    Pointer = 3,
+    HighByte = 4,
+    LoaderOffset = 5,
+    Offset32 = 9,
+    Pointer48 = 11,
+    LoaderOffset32 = 13,
+}
+
+impl LocationType {
+    pub fn size(&self) -> usize {
+        match self {
+            LocationType::LowByte | LocationType::HighByte => 1,
+            LocationType::Offset | LocationType::Base | LocationType::LoaderOffset => 2,
+            LocationType::Pointer | LocationType::Offset32 | LocationType::LoaderOffset32 => 4,
+            LocationType::Pointer48 => 6,
+        }
+    }
+}
+
+impl Display for LocationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocationType::LowByte => write!(f, "low byte"),
+            LocationType::Offset => write!(f, "offset"),
+            LocationType::Base => write!(f, "base"),
+            LocationType::Pointer => write!(f, "pointer"),
+            LocationType::HighByte => write!(f, "high byte"),
+            LocationType::LoaderOffset => write!(f, "loader-resolved offset"),
+            LocationType::Offset32 => write!(f, "offset32"),
+            LocationType::Pointer48 => write!(f, "pointer48"),
+            LocationType::LoaderOffset32 => write!(f, "loader-resolved offset32"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum FixupFrame {
+    Segment(SegmentIndex),
+    Group(GroupIndex),
+    External(ExternalIndex),
+    FrameNumber(u16),
+    Location,
+    Target,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum FixupTarget {
+    Segment(SegmentIndex),
+    Group(GroupIndex),
+    External(ExternalIndex),
+    FrameNumber(u16),
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct FixupData {
+    pub frame: FixupFrame,
+    pub target: FixupTarget,
+    pub target_displacement: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum FixupThread {
+    Frame { thread: u8, frame: FixupFrame },
+    Target { thread: u8, target: FixupTarget },
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Fixup {
+    pub segment_relative: bool,
+    pub location: LocationType,
+    pub data_record_offset: u16,
+    pub fix_data: FixupData,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum FixupSubrecord {
+    Thread(FixupThread),
+    Fixup(Fixup),
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum IteratedDataContent {
+    Blocks(Vec<IteratedDataBlock>),
+    Data(#[cfg_attr(feature = "serde", serde(with = "crate::record::base64_bytes"))] Vec<u8>),
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct IteratedDataBlock {
+    pub repeat_count: u16,
+    pub content: IteratedDataContent,
+}
+
+impl IteratedDataBlock {
+    fn fmt_indented(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        indent: usize,
+        cfg: HexConfig,
+    ) -> std::fmt::Result {
+        match &self.content {
+            IteratedDataContent::Blocks(blocks) => {
+                writeln!(f, "{:indent$}Repeat {}:", "", self.repeat_count)?;
+                for b in blocks {
+                    b.fmt_indented(f, indent + 4, cfg)?;
+                }
+                Ok(())
+            }
+            IteratedDataContent::Data(data) => {
+                writeln!(f, "{:indent$}Repeat {}:", "", self.repeat_count)?;
+                writeln!(f, "{:?}", data.hex_conf(cfg))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OmfRecord {
-    pub record_type: u8,
+    // Where the record starts in the file.
+    pub offset: usize,
+    pub record_type: RecordType,
     pub record_length: usize,
     pub data: OmfRecordData,
     pub checksum: u8,
-    info: Rc<RefCell<OmfInfo>>,
+    // Cleared when the checksum doesn't match the record's bytes.
+    pub checksum_valid: bool,
+    // Why the record couldn't be decoded, when read leniently. Its data is
+    // then Unknown.
+    pub error: Option<String>,
+    // The names in the record, in order, whose text doesn't encode back to
+    // the bytes they were read from.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub raw_names: Vec<RawName>,
 }
 
 impl OmfRecord {
     pub fn new(
-        record_type: u8,
+        offset: usize,
+        record_type: RecordType,
         record_length: usize,
         data: OmfRecordData,
         checksum: u8,
-        info: Rc<RefCell<OmfInfo>>,
     ) -> OmfRecord {
         OmfRecord {
+            offset,
             record_type,
             record_length,
             data,
             checksum,
+            checksum_valid: true,
+            error: None,
+            raw_names: vec![],
+        }
+    }
+
+    // The type and length bytes that start the record in the file.
+    pub fn header_bytes(&self) -> [u8; 3] {
+        let length = (self.record_length as u16).to_le_bytes();
+        [self.record_type.to_u8(), length[0], length[1]]
+    }
+
+    // Shows the record with the names, segments, groups and externals it
+    // refers to looked up in `info`, such as the info of the module or
+    // reader it came from.
+    pub fn display_with<'r>(&'r self, info: &'r OmfInfo) -> RecordDisplay<'r> {
+        RecordDisplay {
+            record: self,
             info,
+            hex: HexOptions::default(),
+            fixups: None,
         }
     }
+}
+
+// What the addresses in a record's hex dumps count from: the start of the
+// bytes dumped, the segment offset the data is loaded at, or the start of
+// the file. Bytes without a segment offset, or not stored as they are in
+// the file, such as LIDATA blocks, count from their start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexOffset {
+    Record,
+    Segment,
+    File,
+}
 
-    pub fn name_from_index(&self, index: u8) -> Result<String, OmfError> {
-        let i = (index as usize) - 1;
-        let info = self.info.borrow();
-        let s = info
-            .names
-            .get(i)
-            .ok_or_else(|| OmfError::Value("name index not found"))?;
-        Ok(s.clone())
+// How record data is hex dumped. `group` is the number of bytes between
+// wider gaps, and bytes past `max_bytes` are left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexOptions {
+    pub bytes_per_line: usize,
+    pub group: usize,
+    pub offset: HexOffset,
+    pub max_bytes: usize,
+}
+
+impl Default for HexOptions {
+    fn default() -> HexOptions {
+        HexOptions {
+            bytes_per_line: 16,
+            group: 8,
+            offset: HexOffset::Record,
+            max_bytes: usize::MAX,
+        }
     }
+}
+
+pub struct RecordDisplay<'r> {
+    record: &'r OmfRecord,
+    info: &'r OmfInfo,
+    hex: HexOptions,
+    fixups: Option<&'r OmfRecord>,
+}
 
-    pub fn get_segment(&self, index: u8) -> Result<SegmentInfo, OmfError> {
-        let i = (index as usize) - 1;
-        let info = self.info.borrow();
-        let s = info
-            .segments
-            .get(i)
-            .ok_or_else(|| OmfError::Value("segment index not found"))?;
-        Ok(s.clone())
+impl<'r> RecordDisplay<'r> {
+    pub fn with_hex_options(self, hex: HexOptions) -> RecordDisplay<'r> {
+        RecordDisplay { hex, ..self }
     }
 
-    pub fn get_group(&self, index: u8) -> Result<GroupInfo, OmfError> {
-        let i = (index as usize) - 1;
-        let info = self.info.borrow();
-        let s = info
-            .groups
-            .get(i)
-            .ok_or_else(|| OmfError::Value("group index not found"))?;
-        Ok(s.clone())
+    // Marks the bytes of an LEDATA that the FIXUPP after it, `fixups`,
+    // changes, with what each fixup refers to. Other records ignore it.
+    pub fn with_fixups(self, fixups: &'r OmfRecord) -> RecordDisplay<'r> {
+        RecordDisplay {
+            fixups: Some(fixups),
+            ..self
+        }
     }
 }
 
-impl Display for OmfRecord {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let cfg = HexConfig {
-            group: 8,
-            ..HexConfig::default()
+// Writes a hex dump like pretty_hex's, with a line under each line of bytes
+// marking those in `marks` and giving the labels of the marks starting
+// there.
+fn write_marked_hex(
+    f: &mut std::fmt::Formatter<'_>,
+    data: &[u8],
+    cfg: HexConfig,
+    marks: &[(Range<usize>, String)],
+) -> std::fmt::Result {
+    writeln!(f, "Length: {0} (0x{0:x}) bytes", data.len())?;
+    let shown = &data[..data.len().min(cfg.max_bytes)];
+    let width = if cfg.width > 0 {
+        cfg.width
+    } else {
+        shown.len()
+    };
+    // Wide enough for the address of the last line, as pretty_hex works it
+    // out.
+    let last = match shown.len() {
+        len if len <= width => cfg.display_offset + len,
+        len => cfg.display_offset + len - width,
+    };
+    let digits = match last {
+        0..=0xFFFF => 4,
+        0x1_0000..=0xFF_FFFF => 6,
+        0x100_0000..=0xFFFF_FFFF => 8,
+        _ => 16,
+    };
+    let gap = |i: usize| match i {
+        0 => "",
+        _ if cfg.group > 0 && i.is_multiple_of(cfg.group) => "  ",
+        _ => " ",
+    };
+    for (line, bytes) in shown.chunks(width.max(1)).enumerate() {
+        let start = line * width;
+        let mut hex = String::new();
+        let mut under = String::new();
+        for i in 0..width {
+            hex += gap(i);
+            under += gap(i);
+            match bytes.get(i) {
+                Some(b) => hex += &format!("{b:02x}"),
+                None => hex += "  ",
+            }
+            let marked = marks.iter().any(|(r, _)| r.contains(&(start + i)));
+            under += if marked { "^^" } else { "  " };
+        }
+        let ascii: String = bytes
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let address = cfg.display_offset + start;
+        writeln!(f, "{address:0digits$x}:   {hex}   {ascii}")?;
+        let labels: Vec<&str> = marks
+            .iter()
+            .filter(|(r, _)| (start..start + bytes.len()).contains(&r.start))
+            .map(|(_, label)| label.as_str())
+            .collect();
+        if !under.trim().is_empty() {
+            let under = format!("{:digits$}    {under}   {}", "", labels.join("; "));
+            writeln!(f, "{}", under.trim_end())?;
+        }
+    }
+    if shown.len() < data.len() {
+        writeln!(
+            f,
+            "... {0} (0x{0:x}) bytes not shown ...",
+            data.len() - shown.len()
+        )?;
+    }
+    Ok(())
+}
+
+impl RecordDisplay<'_> {
+    // The bytes each fixup in `fixups` covers, in data `len` bytes long,
+    // labelled with its target.
+    fn fixup_marks(&self, len: usize) -> Option<Vec<(Range<usize>, String)>> {
+        let OmfRecordData::FixUpp { subrecords } = &self.fixups?.data else {
+            return None;
         };
+        let marks = subrecords
+            .iter()
+            .filter_map(|s| match s {
+                FixupSubrecord::Fixup(fixup) => Some(fixup),
+                FixupSubrecord::Thread(_) => None,
+            })
+            .map(|fixup| {
+                let start = (fixup.data_record_offset as usize).min(len);
+                let end = (start + fixup.location.size()).min(len);
+                let label = format!(
+                    "{}{}, {} {}",
+                    self.describe_target(&fixup.fix_data.target),
+                    match fixup.fix_data.target_displacement {
+                        Some(d) if d != 0 => format!(" + {d:04X}h"),
+                        _ => String::new(),
+                    },
+                    if fixup.segment_relative {
+                        "segment-relative"
+                    } else {
+                        "self-relative"
+                    },
+                    fixup.location,
+                );
+                (start..end, label)
+            })
+            .collect();
+        Some(marks)
+    }
+
+    // The settings for dumping `len` bytes, loaded at `segment_offset` if
+    // they have one. With `at_end`, the bytes are the last in the record
+    // before the checksum, so their place in the file is known.
+    fn hex_config(&self, len: usize, segment_offset: Option<usize>, at_end: bool) -> HexConfig {
+        let file_offset = (self.record.offset + 3 + self.record.record_length)
+            .checked_sub(1 + len)
+            .filter(|_| at_end);
+        let display_offset = match self.hex.offset {
+            HexOffset::Record => None,
+            HexOffset::Segment => segment_offset,
+            HexOffset::File => file_offset,
+        };
+        HexConfig {
+            width: self.hex.bytes_per_line,
+            group: self.hex.group,
+            max_bytes: self.hex.max_bytes,
+            display_offset: display_offset.unwrap_or(0),
+            ..HexConfig::default()
+        }
+    }
+
+    // Names for display, where a dangling index shows up in the output
+    // instead of failing the whole record.
+    fn display_name(&self, index: NameIndex) -> String {
+        self.info
+            .name_from_index(index)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|_| format!("<invalid index {index}>"))
+    }
+
+    fn display_segment(&self, index: SegmentIndex) -> String {
+        match self.info.get_segment(index) {
+            Ok(segment) => self.display_name(segment.segment_name_index),
+            Err(_) => format!("<invalid index {index}>"),
+        }
+    }
+
+    fn display_group(&self, index: GroupIndex) -> String {
+        match self.info.get_group(index) {
+            Ok(group) => self.display_name(group.group_name_index),
+            Err(_) => format!("<invalid index {index}>"),
+        }
+    }
+
+    fn display_external(&self, index: ExternalIndex) -> String {
+        self.info
+            .get_external(index)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|_| format!("<invalid index {index}>"))
+    }
+
+    fn describe_frame(&self, frame: &FixupFrame) -> String {
+        match frame {
+            FixupFrame::Segment(i) => format!("segment {} ({i})", self.display_segment(*i)),
+            FixupFrame::Group(i) => format!("group {} ({i})", self.display_group(*i)),
+            FixupFrame::External(i) => format!("external {} ({i})", self.display_external(*i)),
+            FixupFrame::FrameNumber(n) => format!("frame {n:04X}h"),
+            FixupFrame::Location => "location".to_string(),
+            FixupFrame::Target => "target".to_string(),
+        }
+    }
+
+    fn describe_target(&self, target: &FixupTarget) -> String {
+        match target {
+            FixupTarget::Segment(i) => self.describe_frame(&FixupFrame::Segment(*i)),
+            FixupTarget::Group(i) => self.describe_frame(&FixupFrame::Group(*i)),
+            FixupTarget::External(i) => self.describe_frame(&FixupFrame::External(*i)),
+            FixupTarget::FrameNumber(n) => self.describe_frame(&FixupFrame::FrameNumber(*n)),
+        }
+    }
+
+    // The one-line form, shown with {:#}: the record type followed by what
+    // the record holds, in short.
+    fn fmt_summary(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.record.record_type)?;
+        let join = |names: Vec<String>| names.join(", ");
+        match &self.record.data {
+            OmfRecordData::THeadr { name } => write!(f, " {name}"),
+            OmfRecordData::Coment {
+                comment_class,
+                comment_bytes,
+                ..
+            } => {
+                write!(f, " class={comment_class:02X}h")?;
+                if comment_bytes.iter().all(|b| (0x20..0x7F).contains(b)) {
+                    write!(f, " \"{}\"", String::from_utf8_lossy(comment_bytes))
+                } else {
+                    write!(f, " len={}", comment_bytes.len())
+                }
+            }
+            OmfRecordData::ModEnd { main, start } => {
+                if *main {
+                    write!(f, " main")?;
+                }
+                if let MAttrStart::Start(start) = start {
+                    write!(f, " start={}", self.describe_target(&start.target))?;
+                    if let Some(displacement) = start.target_displacement {
+                        write!(f, "+{displacement:X}")?;
+                    }
+                }
+                Ok(())
+            }
+            OmfRecordData::ExtDef { names } | OmfRecordData::LExtDef { names } => {
+                write!(
+                    f,
+                    " {}",
+                    join(names.iter().map(|n| n.name.clone()).collect())
+                )
+            }
+            OmfRecordData::PubDef {
+                base_segment_index,
+                base_frame,
+                names,
+                ..
+            }
+            | OmfRecordData::LPubDef {
+                base_segment_index,
+                base_frame,
+                names,
+                ..
+            } => {
+                if base_segment_index.is_none() {
+                    write!(f, " frame={base_frame:04X}")?;
+                } else {
+                    write!(f, " {}", self.display_segment(*base_segment_index))?;
+                }
+                let names = names
+                    .iter()
+                    .map(|n| format!("{}@{:X}", n.name, n.public_offset))
+                    .collect();
+                write!(f, ": {}", join(names))
+            }
+            OmfRecordData::LinNum {
+                base_segment_index,
+                lines,
+                ..
+            } => write!(
+                f,
+                " {} lines={}",
+                self.display_segment(*base_segment_index),
+                lines.len()
+            ),
+            OmfRecordData::LNames { names } => {
+                write!(
+                    f,
+                    " {}",
+                    join(names.iter().map(|n| n.to_string()).collect())
+                )
+            }
+            OmfRecordData::SegDef {
+                segment_attributes,
+                segment_length,
+                segment_name_index,
+                class_name_index,
+                ..
+            } => {
+                let length = if segment_attributes.big {
+                    0x10000
+                } else {
+                    *segment_length as u32
+                };
+                write!(
+                    f,
+                    " {} {} len={length:X} {} {}",
+                    self.display_name(*segment_name_index),
+                    self.display_name(*class_name_index),
+                    segment_attributes.alignment.short_name(),
+                    segment_attributes.combination
+                )?;
+                if segment_attributes.bd32bit {
+                    write!(f, " use32")?;
+                }
+                Ok(())
+            }
+            OmfRecordData::GrpDef {
+                group_name_index,
+                segment_definitions,
+            } => {
+                let segments = segment_definitions
+                    .iter()
+                    .map(|c| self.display_segment(c.segment_definition))
+                    .collect();
+                write!(
+                    f,
+                    " {}: {}",
+                    self.display_name(*group_name_index),
+                    join(segments)
+                )
+            }
+            OmfRecordData::FixUpp { subrecords } => {
+                let fixups = subrecords
+                    .iter()
+                    .filter(|s| matches!(s, FixupSubrecord::Fixup(_)))
+                    .count();
+                write!(f, " fixups={fixups}")?;
+                if fixups < subrecords.len() {
+                    write!(f, " threads={}", subrecords.len() - fixups)?;
+                }
+                Ok(())
+            }
+            OmfRecordData::LEData {
+                segment_index,
+                enumerated_data_offset,
+                data,
+            } => write!(
+                f,
+                " {}:{enumerated_data_offset:04X} len={:X}",
+                self.display_segment(*segment_index),
+                data.len()
+            ),
+            OmfRecordData::LIData {
+                segment_index,
+                iterated_data_offset,
+                blocks,
+            } => write!(
+                f,
+                " {}:{iterated_data_offset:04X} blocks={}",
+                self.display_segment(*segment_index),
+                blocks.len()
+            ),
+            OmfRecordData::ComDef { definitions } => {
+                let names = definitions
+                    .iter()
+                    .map(|d| format!("{} ({})", d.name, d.communal_length))
+                    .collect();
+                write!(f, " {}", join(names))
+            }
+            OmfRecordData::CExtDef { names } => {
+                let names = names
+                    .iter()
+                    .map(|n| self.display_name(n.logical_name_index))
+                    .collect();
+                write!(f, " {}", join(names))
+            }
+            OmfRecordData::ComDat {
+                continuation,
+                local,
+                selection,
+                allocation,
+                base_segment_index,
+                public_name_index,
+                enumerated_data_offset,
+                ..
+            } => {
+                write!(f, " {}", self.display_name(*public_name_index))?;
+                if *allocation == ComdatAllocation::Explicit {
+                    write!(f, " {}", self.display_segment(*base_segment_index))?;
+                } else {
+                    write!(f, " {allocation}")?;
+                }
+                write!(f, " {selection}")?;
+                if *local {
+                    write!(f, " local")?;
+                }
+                if *continuation {
+                    write!(f, " continued at {enumerated_data_offset:X}")?;
+                }
+                Ok(())
+            }
+            OmfRecordData::LinSym {
+                public_name_index,
+                lines,
+                ..
+            } => write!(
+                f,
+                " {} lines={}",
+                self.display_name(*public_name_index),
+                lines.len()
+            ),
+            OmfRecordData::Alias { aliases } => {
+                let aliases = aliases
+                    .iter()
+                    .map(|a| format!("{} -> {}", a.alias_name, a.substitute_name))
+                    .collect();
+                write!(f, " {}", join(aliases))
+            }
+            OmfRecordData::Unknown { data } => write!(f, " len={:X}", data.len()),
+        }
+    }
+}
+
+impl Display for RecordDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return self.fmt_summary(f);
+        }
+        // Iterated data isn't stored as it is dumped, so it only has
+        // offsets from its start.
+        let cfg = self.hex_config(0, None, false);
 
         writeln!(
             f,
-            "Record type {:02X}h length {}",
-            self.record_type, self.record_length
+            "Record type {:02X}h length {} at {:06X}h",
+            self.record.record_type.to_u8(),
+            self.record.record_length,
+            self.record.offset
         )?;
 
-        match &self.data {
+        match &self.record.data {
             OmfRecordData::THeadr { name } => {
                 writeln!(f, "Translator Header:")?;
                 writeln!(f, "    Name: {name}")
@@ -197,25 +1072,34 @@ impl Display for OmfRecord {
                     if comment_type.no_list { "no list " } else { "" },
                     comment_class
                 )?;
+                let cfg = self.hex_config(comment_bytes.len(), None, true);
                 writeln!(f, "{:?}", comment_bytes.hex_conf(cfg))
             }
             OmfRecordData::ModEnd { main, start } => {
                 writeln!(f, "Module End{}", if *main { " (MAIN)" } else { "" })?;
                 match start {
                     MAttrStart::NoStart => (),
-                    MAttrStart::Start {
-                        end_data,
-                        frame_datum,
-                        target_datum,
-                        target_displacement,
-                    } => {
-                        writeln!(f, "    End data: {end_data:02X}, frame: {frame_datum:02X}, target: {target_datum:02X}, displacement: {target_displacement:04X}")?;
+                    MAttrStart::Start(start) => {
+                        writeln!(
+                            f,
+                            "    Start: target {}{}, frame {}",
+                            self.describe_target(&start.target),
+                            match start.target_displacement {
+                                Some(d) => format!(" + {d:04X}h"),
+                                None => String::new(),
+                            },
+                            self.describe_frame(&start.frame)
+                        )?;
                     }
                 }
                 Ok(())
             }
-            OmfRecordData::ExtDef { names } => {
-                writeln!(f, "External Names Definition")?;
+            OmfRecordData::ExtDef { names } | OmfRecordData::LExtDef { names } => {
+                if let OmfRecordData::LExtDef { .. } = self.record.data {
+                    writeln!(f, "Local External Names Definition")?;
+                } else {
+                    writeln!(f, "External Names Definition")?;
+                }
                 for (i, n) in names.iter().enumerate() {
                     writeln!(f, "    {i:<4} {} type {}", n.name, n.type_index)?;
                 }
@@ -226,31 +1110,35 @@ impl Display for OmfRecord {
                 base_segment_index,
                 base_frame,
                 names,
+            }
+            | OmfRecordData::LPubDef {
+                base_group_index,
+                base_segment_index,
+                base_frame,
+                names,
             } => {
-                writeln!(f, "Public Names Definition")?;
-                if *base_group_index == 0 && *base_segment_index == 0 {
+                if let OmfRecordData::LPubDef { .. } = self.record.data {
+                    writeln!(f, "Local Public Names Definition")?;
+                } else {
+                    writeln!(f, "Public Names Definition")?;
+                }
+                if base_group_index.is_none() && base_segment_index.is_none() {
                     writeln!(f, "    Base Frame: {base_frame:04X}")?;
                 } else {
-                    if *base_group_index == 0 {
+                    if base_group_index.is_none() {
                         writeln!(f, "    Base Group: None")?;
                     } else {
-                        let base_group = self.get_group(*base_group_index).expect("group index");
                         writeln!(
                             f,
                             "    Base Group: {} ({})",
-                            self.name_from_index(base_group.group_name_index)
-                                .expect("name index"),
+                            self.display_group(*base_group_index),
                             base_group_index
                         )?;
                     }
-                    let base_segment = self
-                        .get_segment(*base_segment_index)
-                        .expect("segment index");
                     writeln!(
                         f,
                         "    Base Segment: {} ({})",
-                        self.name_from_index(base_segment.segment_name_index)
-                            .expect("name lookup"),
+                        self.display_segment(*base_segment_index),
                         base_segment_index
                     )?;
                 }
@@ -264,6 +1152,27 @@ impl Display for OmfRecord {
                 }
                 Ok(())
             }
+            OmfRecordData::LinNum {
+                base_group_index,
+                base_segment_index,
+                lines,
+            } => {
+                writeln!(
+                    f,
+                    "Line Numbers - {} ({}) group {}",
+                    self.display_segment(*base_segment_index),
+                    base_segment_index,
+                    base_group_index
+                )?;
+                for l in lines {
+                    writeln!(
+                        f,
+                        "    line {:<5} offset {:04X}h",
+                        l.line_number, l.line_number_offset
+                    )?;
+                }
+                Ok(())
+            }
             OmfRecordData::LNames { names } => {
                 writeln!(f, "List of Names")?;
                 for (i, n) in names.iter().enumerate() {
@@ -281,8 +1190,7 @@ impl Display for OmfRecord {
                 writeln!(
                     f,
                     "Segment Definition - {} ({})",
-                    self.name_from_index(*segment_name_index)
-                        .expect("name lookup"),
+                    self.display_name(*segment_name_index),
                     *segment_name_index
                 )?;
                 writeln!(
@@ -301,17 +1209,19 @@ impl Display for OmfRecord {
                 writeln!(
                     f,
                     "    Class name: {} ({})",
-                    self.name_from_index(*class_name_index)
-                        .expect("name lookup"),
+                    self.display_name(*class_name_index),
                     class_name_index
                 )?;
-                writeln!(
-                    f,
-                    "    Overlay name: {} ({})",
-                    self.name_from_index(*overlay_name_index)
-                        .expect("name lookup"),
-                    overlay_name_index
-                )?;
+                if overlay_name_index.is_none() {
+                    writeln!(f, "    Overlay name: None")?;
+                } else {
+                    writeln!(
+                        f,
+                        "    Overlay name: {} ({})",
+                        self.display_name(*overlay_name_index),
+                        overlay_name_index
+                    )?;
+                }
                 Ok(())
             }
             OmfRecordData::GrpDef {
@@ -321,51 +1231,234 @@ impl Display for OmfRecord {
                 writeln!(
                     f,
                     "Group Definition - {} ({})",
-                    self.name_from_index(*group_name_index)
-                        .expect("name lookup"),
+                    self.display_name(*group_name_index),
                     group_name_index
                 )?;
                 writeln!(f, "    Segments:")?;
                 for (i, s) in segment_definitions.iter().enumerate() {
-                    let segment = self
-                        .get_segment(s.segment_definition)
-                        .expect("segment index");
                     writeln!(
                         f,
                         "        {:<4} {} ({})",
                         i,
-                        self.name_from_index(segment.segment_name_index)
-                            .expect("name lookup"),
+                        self.display_segment(s.segment_definition),
                         s.segment_definition
                     )?;
                 }
                 Ok(())
             }
+            OmfRecordData::FixUpp { subrecords } => {
+                writeln!(f, "Fixups")?;
+                for s in subrecords {
+                    match s {
+                        FixupSubrecord::Thread(FixupThread::Frame { thread, frame }) => {
+                            writeln!(
+                                f,
+                                "    Frame thread {thread}: {}",
+                                self.describe_frame(frame)
+                            )?;
+                        }
+                        FixupSubrecord::Thread(FixupThread::Target { thread, target }) => {
+                            writeln!(
+                                f,
+                                "    Target thread {thread}: {}",
+                                self.describe_target(target)
+                            )?;
+                        }
+                        FixupSubrecord::Fixup(fixup) => {
+                            writeln!(
+                                f,
+                                "    {:03X}h {} {}: target {}{}, frame {}",
+                                fixup.data_record_offset,
+                                if fixup.segment_relative {
+                                    "segment-relative"
+                                } else {
+                                    "self-relative"
+                                },
+                                fixup.location,
+                                self.describe_target(&fixup.fix_data.target),
+                                match fixup.fix_data.target_displacement {
+                                    Some(d) => format!(" + {d:04X}h"),
+                                    None => String::new(),
+                                },
+                                self.describe_frame(&fixup.fix_data.frame)
+                            )?;
+                        }
+                    }
+                }
+                Ok(())
+            }
             OmfRecordData::LEData {
                 segment_index,
                 enumerated_data_offset,
                 data,
             } => {
-                let segment = self.get_segment(*segment_index).expect("segment index");
                 writeln!(
                     f,
                     "Logical Enumerated Data - {} ({}) offset {:04X}h",
-                    self.name_from_index(segment.segment_name_index)
-                        .expect("name lookup"),
+                    self.display_segment(*segment_index),
                     segment_index,
                     enumerated_data_offset
                 )?;
-                writeln!(f, "{:?}", data.hex_conf(cfg))
+                let offset = *enumerated_data_offset as usize;
+                let cfg = self.hex_config(data.len(), Some(offset), true);
+                match self.fixup_marks(data.len()) {
+                    Some(marks) => write_marked_hex(f, data, cfg, &marks),
+                    None => writeln!(f, "{:?}", data.hex_conf(cfg)),
+                }
+            }
+            OmfRecordData::LIData {
+                segment_index,
+                iterated_data_offset,
+                blocks,
+            } => {
+                writeln!(
+                    f,
+                    "Logical Iterated Data - {} ({}) offset {:04X}h",
+                    self.display_segment(*segment_index),
+                    segment_index,
+                    iterated_data_offset
+                )?;
+                for b in blocks {
+                    b.fmt_indented(f, 4, cfg)?;
+                }
+                match expand_iterated_data(blocks, MAX_DISPLAY_EXPANSION) {
+                    Ok(data) => {
+                        writeln!(f, "    Expanded:")?;
+                        let offset = *iterated_data_offset as usize;
+                        let cfg = self.hex_config(data.len(), Some(offset), false);
+                        writeln!(f, "{:?}", data.hex_conf(cfg))
+                    }
+                    Err(_) => writeln!(
+                        f,
+                        "    Expanded data exceeds {MAX_DISPLAY_EXPANSION} bytes, not shown"
+                    ),
+                }
+            }
+            OmfRecordData::ComDef { definitions } => {
+                writeln!(f, "Communal Names Definition")?;
+                for (i, d) in definitions.iter().enumerate() {
+                    writeln!(
+                        f,
+                        "    {i:<4} {} type {} {}",
+                        d.name, d.type_index, d.communal_length
+                    )?;
+                }
+                Ok(())
+            }
+            OmfRecordData::CExtDef { names } => {
+                writeln!(f, "COMDAT External Names Definition")?;
+                for (i, n) in names.iter().enumerate() {
+                    writeln!(
+                        f,
+                        "    {i:<4} {} ({}) type {}",
+                        self.display_name(n.logical_name_index),
+                        n.logical_name_index,
+                        n.type_index
+                    )?;
+                }
+                Ok(())
+            }
+            OmfRecordData::ComDat {
+                continuation,
+                local,
+                selection,
+                allocation,
+                alignment,
+                enumerated_data_offset,
+                type_index,
+                base_group_index,
+                base_segment_index,
+                base_frame,
+                public_name_index,
+                data,
+            } => {
+                writeln!(
+                    f,
+                    "Initialized Communal Data - {} ({}){}{}",
+                    self.display_name(*public_name_index),
+                    public_name_index,
+                    if *local { " (local)" } else { "" },
+                    if *continuation { " (continued)" } else { "" }
+                )?;
+                writeln!(
+                    f,
+                    "    Selection: {selection}; allocation: {allocation}; alignment: {}",
+                    match alignment {
+                        Some(a) => a.to_string(),
+                        None => "from segment".to_string(),
+                    }
+                )?;
+                if *allocation == ComdatAllocation::Explicit {
+                    if base_segment_index.is_none() {
+                        writeln!(f, "    Base Frame: {base_frame:04X}")?;
+                    } else {
+                        writeln!(
+                            f,
+                            "    Base Segment: {} ({}) group {}",
+                            self.display_segment(*base_segment_index),
+                            base_segment_index,
+                            base_group_index
+                        )?;
+                    }
+                }
+                writeln!(
+                    f,
+                    "    Offset {enumerated_data_offset:04X}h type {type_index}"
+                )?;
+                match data {
+                    ComdatData::Enumerated(data) => {
+                        let offset = *enumerated_data_offset as usize;
+                        let cfg = self.hex_config(data.len(), Some(offset), true);
+                        writeln!(f, "{:?}", data.hex_conf(cfg))
+                    }
+                    ComdatData::Iterated(blocks) => {
+                        for b in blocks {
+                            b.fmt_indented(f, 4, cfg)?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+            OmfRecordData::LinSym {
+                continuation,
+                public_name_index,
+                lines,
+            } => {
+                writeln!(
+                    f,
+                    "Line Numbers for Symbol - {} ({}){}",
+                    self.display_name(*public_name_index),
+                    public_name_index,
+                    if *continuation { " (continued)" } else { "" }
+                )?;
+                for l in lines {
+                    writeln!(
+                        f,
+                        "    line {:<5} offset {:04X}h",
+                        l.line_number, l.line_number_offset
+                    )?;
+                }
+                Ok(())
+            }
+            OmfRecordData::Alias { aliases } => {
+                writeln!(f, "Alias Definition")?;
+                for a in aliases {
+                    writeln!(f, "    {} -> {}", a.alias_name, a.substitute_name)?;
+                }
+                Ok(())
             }
             OmfRecordData::Unknown { data } => {
                 writeln!(f, "Unknown Data")?;
+                let cfg = self.hex_config(data.len(), None, true);
                 writeln!(f, "{:?}", data.hex_conf(cfg))
             }
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum OmfRecordData {
     THeadr {
         // 80
@@ -375,6 +1468,7 @@ pub enum OmfRecordData {
         // 88
         comment_type: CommentType,
         comment_class: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::record::base64_bytes"))]
         comment_bytes: Vec<u8>,
     },
     ModEnd {
@@ -388,35 +1482,97 @@ pub enum OmfRecordData {
     },
     PubDef {
         // 90
-        base_group_index: u8,
-        base_segment_index: u8,
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
         base_frame: u16,
         names: Vec<PubName>,
     },
+    LinNum {
+        // 94
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
+        lines: Vec<LineNumber>,
+    },
     LNames {
         // 96
-        names: Vec<String>,
+        names: Vec<Arc<str>>,
     },
     SegDef {
         // 98
         segment_attributes: SegmentAttributes,
         segment_length: u16,
-        segment_name_index: u8,
-        class_name_index: u8,
-        overlay_name_index: u8,
+        segment_name_index: NameIndex,
+        class_name_index: NameIndex,
+        overlay_name_index: NameIndex,
     },
     GrpDef {
         // 9A
-        group_name_index: u8,
+        group_name_index: NameIndex,
         segment_definitions: Vec<GroupComponent>,
     },
+    FixUpp {
+        // 9C
+        subrecords: Vec<FixupSubrecord>,
+    },
     LEData {
         // A0
-        segment_index: u8,
+        segment_index: SegmentIndex,
         enumerated_data_offset: u16,
+        #[cfg_attr(feature = "serde", serde(with = "crate::record::base64_bytes"))]
         data: Vec<u8>,
     },
+    LIData {
+        // A2
+        segment_index: SegmentIndex,
+        iterated_data_offset: u16,
+        blocks: Vec<IteratedDataBlock>,
+    },
+    ComDef {
+        // B0
+        definitions: Vec<ComName>,
+    },
+    LExtDef {
+        // B4
+        names: Vec<ExtName>,
+    },
+    LPubDef {
+        // B6
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
+        base_frame: u16,
+        names: Vec<PubName>,
+    },
+    CExtDef {
+        // BC
+        names: Vec<CExtName>,
+    },
+    ComDat {
+        // C2
+        continuation: bool,
+        local: bool,
+        selection: ComdatSelection,
+        allocation: ComdatAllocation,
+        alignment: Option<SegmentAlignment>,
+        enumerated_data_offset: u16,
+        type_index: TypeIndex,
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
+        base_frame: u16,
+        public_name_index: NameIndex,
+        data: ComdatData,
+    },
+    LinSym {
+        // C4
+        continuation: bool,
+        public_name_index: NameIndex,
+        lines: Vec<LineNumber>,
+    },
+    Alias {
+        // C6
+        aliases: Vec<AliasName>,
+    },
     Unknown {
+        #[cfg_attr(feature = "serde", serde(with = "crate::record::base64_bytes"))]
         data: Vec<u8>,
     },
 }
@@ -0,0 +1,135 @@
+use std::fmt::Display;
+
+macro_rules! record_types {
+    ($($variant:ident = $code:literal $name:literal,)*) => {
+        // The type byte that starts every record. Types with 32-bit forms
+        // have them at the next, odd, code.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+            serde(into = "u8", from = "u8"))]
+        pub enum RecordType {
+            $($variant,)*
+            // A type byte OMF doesn't define.
+            Unknown(u8),
+        }
+
+        impl RecordType {
+            pub fn from_u8(record_type: u8) -> RecordType {
+                match record_type {
+                    $($code => RecordType::$variant,)*
+                    _ => RecordType::Unknown(record_type),
+                }
+            }
+
+            pub fn to_u8(self) -> u8 {
+                match self {
+                    $(RecordType::$variant => $code,)*
+                    RecordType::Unknown(record_type) => record_type,
+                }
+            }
+
+            // The conventional name. The 32-bit forms share the name of their
+            // 16-bit counterpart.
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $(RecordType::$variant => Some($name),)*
+                    RecordType::Unknown(_) => None,
+                }
+            }
+
+            // Looks up a record type by name, ignoring case. The 16-bit form
+            // is returned.
+            pub fn from_name(name: &str) -> Option<RecordType> {
+                [$(RecordType::$variant,)*]
+                    .into_iter()
+                    .find(|t| !t.is_32bit() && t.name().is_some_and(|n| n.eq_ignore_ascii_case(name)))
+            }
+        }
+    };
+}
+
+record_types! {
+    OvlDef = 0x76 "OVLDEF",
+    THeadr = 0x80 "THEADR",
+    LHeadr = 0x82 "LHEADR",
+    Coment = 0x88 "COMENT",
+    ModEnd = 0x8A "MODEND",
+    ModEnd32 = 0x8B "MODEND",
+    ExtDef = 0x8C "EXTDEF",
+    PubDef = 0x90 "PUBDEF",
+    PubDef32 = 0x91 "PUBDEF",
+    LinNum = 0x94 "LINNUM",
+    LinNum32 = 0x95 "LINNUM",
+    LNames = 0x96 "LNAMES",
+    SegDef = 0x98 "SEGDEF",
+    SegDef32 = 0x99 "SEGDEF",
+    GrpDef = 0x9A "GRPDEF",
+    FixUpp = 0x9C "FIXUPP",
+    FixUpp32 = 0x9D "FIXUPP",
+    LEData = 0xA0 "LEDATA",
+    LEData32 = 0xA1 "LEDATA",
+    LIData = 0xA2 "LIDATA",
+    LIData32 = 0xA3 "LIDATA",
+    ComDef = 0xB0 "COMDEF",
+    BakPat = 0xB2 "BAKPAT",
+    BakPat32 = 0xB3 "BAKPAT",
+    LExtDef = 0xB4 "LEXTDEF",
+    LExtDef32 = 0xB5 "LEXTDEF",
+    LPubDef = 0xB6 "LPUBDEF",
+    LPubDef32 = 0xB7 "LPUBDEF",
+    LComDef = 0xB8 "LCOMDEF",
+    CExtDef = 0xBC "CEXTDEF",
+    ComDat = 0xC2 "COMDAT",
+    ComDat32 = 0xC3 "COMDAT",
+    LinSym = 0xC4 "LINSYM",
+    LinSym32 = 0xC5 "LINSYM",
+    Alias = 0xC6 "ALIAS",
+    NBkPat = 0xC8 "NBKPAT",
+    NBkPat32 = 0xC9 "NBKPAT",
+    LLNames = 0xCA "LLNAMES",
+    VerNum = 0xCC "VERNUM",
+    VendExt = 0xCE "VENDEXT",
+    LibHdr = 0xF0 "LIBHDR",
+    LibEnd = 0xF1 "LIBEND",
+}
+
+impl RecordType {
+    // Odd types below the library records are 32-bit, whether or not OMF
+    // defines them.
+    pub fn is_32bit(self) -> bool {
+        let record_type = self.to_u8();
+        record_type < 0xF0 && record_type & 1 != 0
+    }
+
+    // The 16-bit form of a 32-bit type, or the type itself.
+    pub fn base(self) -> RecordType {
+        if self.is_32bit() {
+            RecordType::from_u8(self.to_u8() & !1)
+        } else {
+            self
+        }
+    }
+}
+
+impl From<u8> for RecordType {
+    fn from(record_type: u8) -> RecordType {
+        RecordType::from_u8(record_type)
+    }
+}
+
+impl From<RecordType> for u8 {
+    fn from(record_type: RecordType) -> u8 {
+        record_type.to_u8()
+    }
+}
+
+// The name, with 32 added for the 32-bit forms, or the type byte in hex.
+impl Display for RecordType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) if self.is_32bit() => write!(f, "{name}32"),
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:02X}h", self.to_u8()),
+        }
+    }
+}
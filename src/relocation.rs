@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use crate::{
+    error::OmfError,
+    image::iterated_offsets,
+    record::{
+        FixupFrame, FixupSubrecord, FixupTarget, IteratedDataBlock, LocationType, MAttrStart,
+        OmfRecord, OmfRecordData,
+    },
+    GroupIndex, OmfInfo, OmfModule, SegmentIndex,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelocationRef {
+    Segment(SegmentIndex),
+    Group(GroupIndex),
+    External(Arc<str>),
+    Absolute(u16),
+}
+
+#[derive(Debug, Clone)]
+pub struct Relocation {
+    pub segment_index: SegmentIndex,
+    pub offset: u32,
+    pub kind: LocationType,
+    pub segment_relative: bool,
+    pub target: RelocationRef,
+    pub target_displacement: u32,
+    pub frame: RelocationRef,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPoint {
+    pub target: RelocationRef,
+    pub offset: u32,
+    pub frame: RelocationRef,
+}
+
+fn resolve_frame(
+    info: &OmfInfo,
+    frame: &FixupFrame,
+    location: Option<SegmentIndex>,
+    target: &RelocationRef,
+) -> Result<RelocationRef, OmfError> {
+    Ok(match frame {
+        FixupFrame::Segment(i) => RelocationRef::Segment(*i),
+        FixupFrame::Group(i) => RelocationRef::Group(*i),
+        FixupFrame::External(i) => RelocationRef::External(info.get_external(*i)?),
+        FixupFrame::FrameNumber(n) => RelocationRef::Absolute(*n),
+        FixupFrame::Location => RelocationRef::Segment(
+            location.ok_or(OmfError::Value("location frame without location"))?,
+        ),
+        FixupFrame::Target => target.clone(),
+    })
+}
+
+fn resolve_target(info: &OmfInfo, target: &FixupTarget) -> Result<RelocationRef, OmfError> {
+    Ok(match target {
+        FixupTarget::Segment(i) => RelocationRef::Segment(*i),
+        FixupTarget::Group(i) => RelocationRef::Group(*i),
+        FixupTarget::External(i) => RelocationRef::External(info.get_external(*i)?),
+        FixupTarget::FrameNumber(n) => RelocationRef::Absolute(*n),
+    })
+}
+
+// The data record a FIXUPP applies to.
+enum FixedData<'a> {
+    Enumerated(SegmentIndex, u32),
+    Iterated(SegmentIndex, u32, &'a [IteratedDataBlock]),
+    // COMDAT data only gets a place in a segment when it is linked, so its
+    // fixups aren't relocations in the module's segments.
+    Comdat,
+}
+
+// Converts the FIXUPP records following each LEDATA or LIDATA into
+// relocations against that record's segment, one for each copy of iterated
+// data. Frame methods F4 and F5 are resolved to the location segment and the
+// target respectively.
+pub fn build_relocations(
+    records: &[OmfRecord],
+    info: &OmfInfo,
+) -> Result<Vec<Relocation>, OmfError> {
+    let mut relocations = vec![];
+    let mut last_data = None;
+    for record in records {
+        match &record.data {
+            OmfRecordData::LEData {
+                segment_index,
+                enumerated_data_offset,
+                ..
+            } => {
+                last_data = Some(FixedData::Enumerated(
+                    *segment_index,
+                    *enumerated_data_offset as u32,
+                ))
+            }
+            OmfRecordData::LIData {
+                segment_index,
+                iterated_data_offset,
+                blocks,
+            } => {
+                last_data = Some(FixedData::Iterated(
+                    *segment_index,
+                    *iterated_data_offset as u32,
+                    blocks,
+                ))
+            }
+            OmfRecordData::ComDat { .. } => last_data = Some(FixedData::Comdat),
+            OmfRecordData::FixUpp { subrecords } => {
+                for s in subrecords {
+                    let FixupSubrecord::Fixup(fixup) = s else {
+                        continue;
+                    };
+                    let record_offset = fixup.data_record_offset as u32;
+                    let (segment_index, offsets) = match &last_data {
+                        None => return Err(OmfError::Value("fixup without preceding data")),
+                        Some(FixedData::Comdat) => continue,
+                        Some(FixedData::Enumerated(segment_index, offset)) => {
+                            (*segment_index, vec![offset + record_offset])
+                        }
+                        Some(FixedData::Iterated(segment_index, offset, blocks)) => (
+                            *segment_index,
+                            iterated_offsets(blocks, record_offset as usize)?
+                                .into_iter()
+                                .map(|o| offset + o as u32)
+                                .collect(),
+                        ),
+                    };
+                    let target = resolve_target(info, &fixup.fix_data.target)?;
+                    let frame =
+                        resolve_frame(info, &fixup.fix_data.frame, Some(segment_index), &target)?;
+                    for offset in offsets {
+                        relocations.push(Relocation {
+                            segment_index,
+                            offset,
+                            kind: fixup.location,
+                            segment_relative: fixup.segment_relative,
+                            target: target.clone(),
+                            target_displacement: fixup.fix_data.target_displacement.unwrap_or(0),
+                            frame: frame.clone(),
+                        });
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(relocations)
+}
+
+impl OmfModule {
+    // Decodes the start address given in MODEND, if the module has one.
+    pub fn entry_point(&self) -> Result<Option<EntryPoint>, OmfError> {
+        for record in &self.records {
+            if let OmfRecordData::ModEnd {
+                start: MAttrStart::Start(start),
+                ..
+            } = &record.data
+            {
+                let target = resolve_target(self.info(), &start.target)?;
+                let frame = resolve_frame(self.info(), &start.frame, None, &target)?;
+                return Ok(Some(EntryPoint {
+                    target,
+                    offset: start.target_displacement.unwrap_or(0),
+                    frame,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ComdatAllocation, ComdatData, ComdatSelection, Fixup, FixupData, FixupFrame,
+        FixupSubrecord, FixupTarget, GroupIndex, IteratedDataBlock, IteratedDataContent,
+        LocationType, MAttrStart, NameIndex, OmfModule, OmfRecordData, OmfWriter, SegmentAlignment,
+        SegmentAttributes, SegmentCombination, SegmentIndex, TypeIndex,
+    };
+
+    fn fixupp(data_record_offset: u16) -> OmfRecordData {
+        OmfRecordData::FixUpp {
+            subrecords: vec![FixupSubrecord::Fixup(Fixup {
+                segment_relative: false,
+                location: LocationType::Offset,
+                data_record_offset,
+                fix_data: FixupData {
+                    frame: FixupFrame::Target,
+                    target: FixupTarget::Segment(SegmentIndex(1)),
+                    target_displacement: Some(0),
+                },
+            })],
+        }
+    }
+
+    #[test]
+    fn fixups_follow_lidata_and_comdat() {
+        let records = [
+            OmfRecordData::THeadr {
+                name: "test".to_string(),
+            },
+            OmfRecordData::LNames {
+                names: vec!["".into(), "_TEXT".into(), "CODE".into(), "f".into()],
+            },
+            OmfRecordData::SegDef {
+                segment_attributes: SegmentAttributes {
+                    alignment: SegmentAlignment::RelocatableByteAligned,
+                    combination: SegmentCombination::Public,
+                    big: false,
+                    bd32bit: false,
+                    absolute_segment_address: None,
+                },
+                segment_length: 0x20,
+                segment_name_index: NameIndex(2),
+                class_name_index: NameIndex(3),
+                overlay_name_index: NameIndex(1),
+            },
+            OmfRecordData::LEData {
+                segment_index: SegmentIndex(1),
+                enumerated_data_offset: 0,
+                data: vec![0; 4],
+            },
+            fixupp(2),
+            // Three copies of a two-byte pointer, each fixed up.
+            OmfRecordData::LIData {
+                segment_index: SegmentIndex(1),
+                iterated_data_offset: 0x10,
+                blocks: vec![IteratedDataBlock {
+                    repeat_count: 3,
+                    content: IteratedDataContent::Data(vec![0, 0]),
+                }],
+            },
+            fixupp(5),
+            OmfRecordData::ComDat {
+                continuation: false,
+                local: false,
+                selection: ComdatSelection::PickAny,
+                allocation: ComdatAllocation::FarCode,
+                alignment: None,
+                enumerated_data_offset: 0,
+                type_index: TypeIndex::NONE,
+                base_group_index: GroupIndex::NONE,
+                base_segment_index: SegmentIndex::NONE,
+                base_frame: 0,
+                public_name_index: NameIndex(4),
+                data: ComdatData::Enumerated(vec![0; 4]),
+            },
+            fixupp(0),
+            OmfRecordData::ModEnd {
+                main: false,
+                start: MAttrStart::NoStart,
+            },
+        ];
+        let mut bytes = vec![];
+        let mut writer = OmfWriter::new(&mut bytes);
+        for data in &records {
+            writer.write_data(data).unwrap();
+        }
+        let module = OmfModule::read_one(&mut &bytes[..]).unwrap();
+        let offsets: Vec<_> = module
+            .relocations()
+            .unwrap()
+            .iter()
+            .map(|r| (r.segment_index.0, r.offset))
+            .collect();
+        assert_eq!(offsets, [(1, 2), (1, 0x10), (1, 0x12), (1, 0x14)]);
+    }
+}
@@ -0,0 +1,342 @@
+use std::{borrow::Cow, io};
+
+use crate::{
+    error::OmfError, CommentType, GroupIndex, NameEncoding, RecordType, SegmentIndex, TypeIndex,
+};
+
+const LIBRARY_HEADER: u8 = 0xF0;
+const LIBRARY_END: u8 = 0xF1;
+
+// Iterates over the records in a buffer without copying them. Names, comment
+// bytes and data are borrowed from the buffer, and only the records most
+// scanners look at are decoded; the rest come back as Other with their raw
+// content, and can be fully decoded with OmfReader.
+//
+// A library is read member by member, skipping the padding between them,
+// and the iterator stops at the end-of-library record before the
+// dictionary.
+pub struct OmfSlice<'data> {
+    data: &'data [u8],
+    pos: usize,
+    // Where `data` starts in the file, for reporting offsets.
+    base: usize,
+    // Set when reading a library, whose members start on page boundaries.
+    page_size: Option<usize>,
+    failed: bool,
+    name_encoding: NameEncoding,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordRef<'data> {
+    // Where the record starts in the file.
+    pub offset: usize,
+    pub record_type: RecordType,
+    // The record's bytes between its length and its checksum.
+    pub content: &'data [u8],
+    pub checksum: u8,
+    pub checksum_valid: bool,
+    pub data: RecordDataRef<'data>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtNameRef<'data> {
+    pub name: Cow<'data, str>,
+    pub type_index: TypeIndex,
+}
+
+#[derive(Debug, Clone)]
+pub struct PubNameRef<'data> {
+    pub name: Cow<'data, str>,
+    pub public_offset: u16,
+    pub type_index: TypeIndex,
+}
+
+#[derive(Debug, Clone)]
+pub enum RecordDataRef<'data> {
+    THeadr {
+        // 80
+        name: Cow<'data, str>,
+    },
+    Coment {
+        // 88
+        comment_type: CommentType,
+        comment_class: u8,
+        comment_bytes: &'data [u8],
+    },
+    ExtDef {
+        // 8C
+        names: Vec<ExtNameRef<'data>>,
+    },
+    PubDef {
+        // 90
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
+        base_frame: u16,
+        names: Vec<PubNameRef<'data>>,
+    },
+    LNames {
+        // 96
+        names: Vec<Cow<'data, str>>,
+    },
+    LEData {
+        // A0
+        segment_index: SegmentIndex,
+        enumerated_data_offset: u16,
+        data: &'data [u8],
+    },
+    LExtDef {
+        // B4
+        names: Vec<ExtNameRef<'data>>,
+    },
+    LPubDef {
+        // B6
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
+        base_frame: u16,
+        names: Vec<PubNameRef<'data>>,
+    },
+    // Any other record, left in the record's content.
+    Other,
+}
+
+// Reads fields from a record's content.
+struct Fields<'data> {
+    content: &'data [u8],
+    pos: usize,
+    name_encoding: NameEncoding,
+}
+
+impl<'data> Fields<'data> {
+    fn unread(&self) -> usize {
+        self.content.len() - self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'data [u8], OmfError> {
+        let bytes = self.content.get(self.pos..self.pos + len).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "record content too short")
+        })?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn rest(&mut self) -> &'data [u8] {
+        let bytes = &self.content[self.pos..];
+        self.pos = self.content.len();
+        bytes
+    }
+
+    fn u8(&mut self) -> Result<u8, OmfError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, OmfError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    // Indices from 80h up take two bytes, the first with its high bit set.
+    fn index<T: From<u16>>(&mut self) -> Result<T, OmfError> {
+        let first = self.u8()?;
+        if first & 0x80 == 0 {
+            return Ok(T::from(first as u16));
+        }
+        Ok(T::from(((first as u16 & 0x7F) << 8) | self.u8()? as u16))
+    }
+
+    fn string(&mut self) -> Result<Cow<'data, str>, OmfError> {
+        let len = self.u8()? as usize;
+        self.name_encoding.decode_borrowed(self.take(len)?)
+    }
+
+    fn ext_names(&mut self) -> Result<Vec<ExtNameRef<'data>>, OmfError> {
+        let mut names = vec![];
+        while self.unread() > 0 {
+            let name = self.string()?;
+            let type_index = self.index()?;
+            names.push(ExtNameRef { name, type_index });
+        }
+        Ok(names)
+    }
+
+    fn pub_names(
+        &mut self,
+    ) -> Result<(GroupIndex, SegmentIndex, u16, Vec<PubNameRef<'data>>), OmfError> {
+        let base_group_index = self.index()?;
+        let base_segment_index: SegmentIndex = self.index()?;
+        let base_frame = if base_segment_index.is_none() {
+            self.u16()?
+        } else {
+            0
+        };
+        let mut names = vec![];
+        while self.unread() > 0 {
+            let name = self.string()?;
+            let public_offset = self.u16()?;
+            let type_index = self.index()?;
+            names.push(PubNameRef {
+                name,
+                public_offset,
+                type_index,
+            });
+        }
+        Ok((base_group_index, base_segment_index, base_frame, names))
+    }
+}
+
+impl<'data> OmfSlice<'data> {
+    pub fn new(data: &'data [u8]) -> OmfSlice<'data> {
+        OmfSlice::with_offset(data, 0)
+    }
+
+    // A parser whose record offsets count from `offset`, for a buffer that
+    // starts partway into a file.
+    pub fn with_offset(data: &'data [u8], offset: usize) -> OmfSlice<'data> {
+        let page_size = match data {
+            [LIBRARY_HEADER, low, high, ..] => Some(u16::from_le_bytes([*low, *high]) as usize + 3),
+            _ => None,
+        };
+        OmfSlice {
+            data,
+            pos: 0,
+            base: offset,
+            page_size,
+            failed: false,
+            name_encoding: NameEncoding::default(),
+        }
+    }
+
+    pub fn set_name_encoding(&mut self, encoding: NameEncoding) {
+        self.name_encoding = encoding;
+    }
+
+    fn next_record(&mut self) -> Result<Option<RecordRef<'data>>, OmfError> {
+        let start = self.pos;
+        let Some(&record_type) = self.data.get(start) else {
+            return Ok(None);
+        };
+        let offset = self.base + start;
+        let wrap = |e: OmfError| OmfError::Record {
+            offset,
+            record_type,
+            source: Box::new(e),
+        };
+        let header = self
+            .data
+            .get(start..start + 3)
+            .ok_or_else(|| wrap(io::Error::from(io::ErrorKind::UnexpectedEof).into()))?;
+        let record_length = u16::from_le_bytes([header[1], header[2]]) as usize;
+        let end = start + 3 + record_length;
+        let record = self
+            .data
+            .get(start..end)
+            .ok_or_else(|| wrap(io::Error::from(io::ErrorKind::UnexpectedEof).into()))?;
+        if record_length == 0 {
+            return Err(wrap(OmfError::Value("record has no checksum")));
+        }
+        let content = &record[3..record.len() - 1];
+        let checksum = record[record.len() - 1];
+        let sum = record.iter().fold(0u8, |a, &b| a.wrapping_add(b));
+
+        let data = self.decode(record_type, content).map_err(wrap)?;
+        self.pos = match (self.page_size, record_type) {
+            (Some(_), LIBRARY_END) => self.data.len(),
+            // The next member starts on the next page.
+            (Some(page_size), 0x8A | 0x8B) => end.div_ceil(page_size) * page_size,
+            _ => end,
+        };
+        Ok(Some(RecordRef {
+            offset,
+            record_type: RecordType::from_u8(record_type),
+            content,
+            checksum,
+            checksum_valid: checksum == 0 || sum == 0,
+            data,
+        }))
+    }
+
+    fn decode(
+        &self,
+        record_type: u8,
+        content: &'data [u8],
+    ) -> Result<RecordDataRef<'data>, OmfError> {
+        let mut f = Fields {
+            content,
+            pos: 0,
+            name_encoding: self.name_encoding,
+        };
+        Ok(match record_type {
+            0x80 => RecordDataRef::THeadr { name: f.string()? },
+            0x88 => {
+                let tmp = f.u8()?;
+                let comment_type = CommentType {
+                    no_purge: tmp & 0x80 != 0,
+                    no_list: tmp & 0x40 != 0,
+                };
+                let comment_class = f.u8()?;
+                RecordDataRef::Coment {
+                    comment_type,
+                    comment_class,
+                    comment_bytes: f.rest(),
+                }
+            }
+            0x8C => RecordDataRef::ExtDef {
+                names: f.ext_names()?,
+            },
+            0xB4 => RecordDataRef::LExtDef {
+                names: f.ext_names()?,
+            },
+            0x90 => {
+                let (base_group_index, base_segment_index, base_frame, names) = f.pub_names()?;
+                RecordDataRef::PubDef {
+                    base_group_index,
+                    base_segment_index,
+                    base_frame,
+                    names,
+                }
+            }
+            0xB6 => {
+                let (base_group_index, base_segment_index, base_frame, names) = f.pub_names()?;
+                RecordDataRef::LPubDef {
+                    base_group_index,
+                    base_segment_index,
+                    base_frame,
+                    names,
+                }
+            }
+            0x96 => {
+                let mut names = vec![];
+                while f.unread() > 0 {
+                    names.push(f.string()?);
+                }
+                RecordDataRef::LNames { names }
+            }
+            0xA0 => {
+                let segment_index = f.index()?;
+                let enumerated_data_offset = f.u16()?;
+                RecordDataRef::LEData {
+                    segment_index,
+                    enumerated_data_offset,
+                    data: f.rest(),
+                }
+            }
+            _ => RecordDataRef::Other,
+        })
+    }
+}
+
+impl<'data> Iterator for OmfSlice<'data> {
+    type Item = Result<RecordRef<'data>, OmfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.next_record() {
+            Ok(record) => record.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+
+use crate::{record::CommunalLength, GroupIndex, SegmentIndex, TypeIndex};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SymbolKind {
+    Public,
+    External,
+    Communal(CommunalLength),
+    ComdatExternal,
+    Comdat,
+    Alias(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SymbolScope {
+    Global,
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SymbolBinding {
+    Segment {
+        group_index: GroupIndex,
+        segment_index: SegmentIndex,
+    },
+    Absolute {
+        frame: u16,
+    },
+    Unbound,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub scope: SymbolScope,
+    pub binding: SymbolBinding,
+    pub offset: u32,
+    pub type_index: TypeIndex,
+}
+
+impl Symbol {
+    pub fn is_defined(&self) -> bool {
+        matches!(
+            self.kind,
+            SymbolKind::Public | SymbolKind::Communal(_) | SymbolKind::Comdat
+        )
+    }
+}
+
+// Serializes as the list of symbols; the name index is rebuilt when read.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Vec<Symbol>", into = "Vec<Symbol>")
+)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    pub fn push(&mut self, symbol: Symbol) {
+        self.by_name
+            .entry(symbol.name.clone())
+            .or_default()
+            .push(self.symbols.len());
+        self.symbols.push(symbol);
+    }
+
+    // Removes the symbols pushed after the first `len`.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        for (i, symbol) in self.symbols.iter().enumerate().skip(len) {
+            if let Some(indices) = self.by_name.get_mut(&symbol.name) {
+                indices.retain(|&j| j != i);
+                if indices.is_empty() {
+                    self.by_name.remove(&symbol.name);
+                }
+            }
+        }
+        self.symbols.truncate(len);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    // Returns the defining entry for `name` if there is one, otherwise the
+    // first reference to it.
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        let mut all = self.lookup_all(name);
+        let first = all.next()?;
+        if first.is_defined() {
+            return Some(first);
+        }
+        Some(all.find(|s| s.is_defined()).unwrap_or(first))
+    }
+
+    pub fn lookup_all<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a Symbol> {
+        self.by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.symbols[i])
+    }
+}
+
+impl From<Vec<Symbol>> for SymbolTable {
+    fn from(symbols: Vec<Symbol>) -> SymbolTable {
+        let mut table = SymbolTable::new();
+        for symbol in symbols {
+            table.push(symbol);
+        }
+        table
+    }
+}
+
+impl From<SymbolTable> for Vec<Symbol> {
+    fn from(table: SymbolTable) -> Vec<Symbol> {
+        table.symbols
+    }
+}
+
+// Matches a whole symbol name against a pattern where * matches any run of
+// characters and ? any one. After a mismatch only the last * is made to
+// take one more character; earlier ones never need to, which keeps this
+// linear in practice.
+pub fn glob_match(pattern: &str, name: &str, ignore_case: bool) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let same = |c: u8, n: u8| c == b'?' || c == n || (ignore_case && c.eq_ignore_ascii_case(&n));
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, n));
+            }
+            Some(&c) if same(c, name[n]) => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, taken)) => {
+                    p = after;
+                    n = taken + 1;
+                    star = Some((after, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_match;
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("_main", "_main", false));
+        assert!(!glob_match("_main", "_MAIN", false));
+        assert!(glob_match("_main", "_MAIN", true));
+        assert!(glob_match("*", "", false));
+        assert!(glob_match("_?rint*", "_printf", false));
+        assert!(glob_match("*a*b", "xaab", false));
+        assert!(!glob_match("*a*b", "xaaba", false));
+        assert!(!glob_match("?", "", false));
+        // Would take exponential time if every * backtracked.
+        let name = "a".repeat(60);
+        assert!(!glob_match(&format!("{}b", "*a".repeat(20)), &name, false));
+    }
+}
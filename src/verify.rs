@@ -0,0 +1,578 @@
+use std::fmt::Display;
+
+use crate::{
+    error::OmfError, expand_iterated_data, ChecksumPolicy, ExternalIndex, FixupData, FixupFrame,
+    FixupSubrecord, FixupTarget, FixupThread, GroupIndex, MAttrStart, NameIndex, OmfInfo,
+    OmfReader, OmfRecord, OmfRecordData, RecordType, SegmentIndex,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    // Breaks the specification; tools may misread or reject the module.
+    Error,
+    // Readable, but probably not what the translator meant.
+    Warning,
+}
+
+// The check a diagnostic comes from. The ids are stable, for tools that
+// filter or count diagnostics by kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Rule {
+    TruncatedHeader,
+    ZeroLength,
+    PastEndOfFile,
+    BadChecksum,
+    Undecodable,
+    TrailingBytes,
+    NoRecords,
+    NoTheadr,
+    AfterModend,
+    NoModend,
+    UndefinedIndex,
+    NoDataSegment,
+    PublicPastSegment,
+    DataPastSegment,
+    BadIteratedData,
+    FixupWithoutData,
+    FixupPastData,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 17] = [
+        Rule::TruncatedHeader,
+        Rule::ZeroLength,
+        Rule::PastEndOfFile,
+        Rule::BadChecksum,
+        Rule::Undecodable,
+        Rule::TrailingBytes,
+        Rule::NoRecords,
+        Rule::NoTheadr,
+        Rule::AfterModend,
+        Rule::NoModend,
+        Rule::UndefinedIndex,
+        Rule::NoDataSegment,
+        Rule::PublicPastSegment,
+        Rule::DataPastSegment,
+        Rule::BadIteratedData,
+        Rule::FixupWithoutData,
+        Rule::FixupPastData,
+    ];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::TruncatedHeader => "truncated-header",
+            Rule::ZeroLength => "zero-length",
+            Rule::PastEndOfFile => "past-end-of-file",
+            Rule::BadChecksum => "bad-checksum",
+            Rule::Undecodable => "undecodable",
+            Rule::TrailingBytes => "trailing-bytes",
+            Rule::NoRecords => "no-records",
+            Rule::NoTheadr => "no-theadr",
+            Rule::AfterModend => "after-modend",
+            Rule::NoModend => "no-modend",
+            Rule::UndefinedIndex => "undefined-index",
+            Rule::NoDataSegment => "no-data-segment",
+            Rule::PublicPastSegment => "public-past-segment",
+            Rule::DataPastSegment => "data-past-segment",
+            Rule::BadIteratedData => "bad-iterated-data",
+            Rule::FixupWithoutData => "fixup-without-data",
+            Rule::FixupPastData => "fixup-past-data",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Rule::TruncatedHeader => "The file ends inside a record header",
+            Rule::ZeroLength => "A record has a length of zero",
+            Rule::PastEndOfFile => "A record extends past the end of the file",
+            Rule::BadChecksum => "A record's checksum doesn't match its bytes",
+            Rule::Undecodable => "A record's contents can't be decoded",
+            Rule::TrailingBytes => "A record has bytes left over after its contents",
+            Rule::NoRecords => "The module has no records",
+            Rule::NoTheadr => "The module doesn't start with THEADR",
+            Rule::AfterModend => "A record follows MODEND",
+            Rule::NoModend => "The module doesn't end with MODEND",
+            Rule::UndefinedIndex => "An index refers to something not defined before it",
+            Rule::NoDataSegment => "A data record has no segment",
+            Rule::PublicPastSegment => "A public is past the end of its segment",
+            Rule::DataPastSegment => "Data extends past the end of its segment",
+            Rule::BadIteratedData => "Iterated data can't be expanded",
+            Rule::FixupWithoutData => "A fixup has no data record before it",
+            Rule::FixupPastData => "A fixup is past the end of its data record",
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    // File offset of the record the problem was found in.
+    pub offset: usize,
+    // Which record that is, counting from 0, if the problem is with one.
+    pub record: Option<usize>,
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "{:06X}: {}", self.offset, self.message),
+            Severity::Warning => write!(f, "{:06X}: warning: {}", self.offset, self.message),
+        }
+    }
+}
+
+// Checks record framing and checksums and that each record's content fills
+// exactly its declared length, then validates the records that could be
+// read.
+pub fn verify(bytes: &[u8]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut records = vec![];
+    // The index among all the records of each one that could be read.
+    let mut indices = vec![];
+    let mut info = OmfInfo::new();
+    let mut offset = 0;
+    let mut index = 0;
+    while offset < bytes.len() {
+        let mut report = |rule: Rule, message: String| {
+            diagnostics.push(Diagnostic {
+                offset,
+                record: Some(index),
+                rule,
+                severity: Severity::Error,
+                message,
+            })
+        };
+        let Some(header) = bytes.get(offset..offset + 3) else {
+            report(Rule::TruncatedHeader, "truncated record header".to_string());
+            break;
+        };
+        let record_type = header[0];
+        let record_length = u16::from_le_bytes([header[1], header[2]]) as usize;
+        let end = offset + 3 + record_length;
+        if record_length == 0 {
+            report(
+                Rule::ZeroLength,
+                format!("record {record_type:02X}h has zero length"),
+            );
+            break;
+        }
+        if end > bytes.len() {
+            report(
+                Rule::PastEndOfFile,
+                format!(
+                    "record {record_type:02X}h extends {} bytes past the end of the file",
+                    end - bytes.len()
+                ),
+            );
+            break;
+        }
+        let record = &bytes[offset..end];
+        let checksum = record[record.len() - 1];
+        let sum = record.iter().fold(0u8, |a, &b| a.wrapping_add(b));
+        if checksum != 0 && sum != 0 {
+            report(
+                Rule::BadChecksum,
+                format!(
+                    "record {record_type:02X}h checksum is {checksum:02X}h, expected {:02X}h",
+                    checksum.wrapping_sub(sum)
+                ),
+            );
+        }
+
+        let mut r = record;
+        let mut reader = OmfReader::with_info(&mut r, std::mem::take(&mut info));
+        reader.set_checksum_policy(ChecksumPolicy::Ignore);
+        match reader.get_next_record() {
+            Ok(Some(mut record)) => {
+                let unread = reader.unread();
+                if unread > 0 {
+                    report(
+                        Rule::TrailingBytes,
+                        format!("record {record_type:02X}h has {unread} bytes past its content"),
+                    );
+                }
+                record.offset = offset;
+                records.push(record);
+                indices.push(index);
+            }
+            Ok(None) => unreachable!(),
+            // The reader only knows the offset within this record.
+            Err(OmfError::Record { source, .. }) => report(
+                Rule::Undecodable,
+                format!("record {record_type:02X}h: {source}"),
+            ),
+            Err(e) => report(Rule::Undecodable, format!("record {record_type:02X}h: {e}")),
+        }
+        info = reader.into_info();
+        offset = end;
+        index += 1;
+    }
+    for mut d in validate(&records) {
+        d.record = d.record.map(|i| indices[i]);
+        diagnostics.push(d);
+    }
+    diagnostics.sort_by_key(|d| d.offset);
+    diagnostics
+}
+
+// What the records seen so far have defined. Records may only refer to
+// things defined before them.
+#[derive(Default)]
+struct Defined {
+    names: usize,
+    segment_lengths: Vec<u32>,
+    groups: usize,
+    externals: usize,
+}
+
+// Checks a module's records against the specification: that it starts with
+// THEADR and ends with MODEND, that every name, segment, group and external
+// index refers to something defined before it, and that data and fixups
+// stay inside their segments and data records.
+pub fn validate(records: &[OmfRecord]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let mut defined = Defined::default();
+    // Length of the last data record, which FIXUPP records apply to.
+    let mut data_length = None;
+    let mut ended = false;
+
+    match records.first() {
+        None => diagnostics.push(Diagnostic {
+            offset: 0,
+            record: None,
+            rule: Rule::NoRecords,
+            severity: Severity::Error,
+            message: "module has no records".to_string(),
+        }),
+        Some(first) if !matches!(first.record_type, RecordType::THeadr | RecordType::LHeadr) => {
+            diagnostics.push(Diagnostic {
+                offset: first.offset,
+                record: Some(0),
+                rule: Rule::NoTheadr,
+                severity: Severity::Error,
+                message: "module does not start with THEADR".to_string(),
+            })
+        }
+        _ => (),
+    }
+
+    for (index, record) in records.iter().enumerate() {
+        let mut report = |rule: Rule, severity: Severity, message: String| {
+            diagnostics.push(Diagnostic {
+                offset: record.offset,
+                record: Some(index),
+                rule,
+                severity,
+                message,
+            })
+        };
+        if ended {
+            report(
+                Rule::AfterModend,
+                Severity::Warning,
+                format!("record {:02X}h after MODEND", record.record_type.to_u8()),
+            );
+        }
+        check_indices(&record.data, &defined, &mut |rule, m| {
+            report(rule, Severity::Error, m)
+        });
+
+        match &record.data {
+            OmfRecordData::LNames { names } => defined.names += names.len(),
+            OmfRecordData::SegDef {
+                segment_attributes,
+                segment_length,
+                ..
+            } => defined.segment_lengths.push(if segment_attributes.big {
+                0x10000
+            } else {
+                *segment_length as u32
+            }),
+            OmfRecordData::GrpDef { .. } => defined.groups += 1,
+            OmfRecordData::ExtDef { names } | OmfRecordData::LExtDef { names } => {
+                defined.externals += names.len()
+            }
+            OmfRecordData::ComDef { definitions } => defined.externals += definitions.len(),
+            OmfRecordData::CExtDef { names } => defined.externals += names.len(),
+            OmfRecordData::PubDef {
+                base_segment_index,
+                names,
+                ..
+            }
+            | OmfRecordData::LPubDef {
+                base_segment_index,
+                names,
+                ..
+            } => {
+                let length = segment_length(&defined, *base_segment_index);
+                for n in names {
+                    if length.is_some_and(|l| n.public_offset as u32 > l) {
+                        report(
+                            Rule::PublicPastSegment,
+                            Severity::Warning,
+                            format!("public {} is past the end of its segment", n.name),
+                        );
+                    }
+                }
+            }
+            OmfRecordData::LEData {
+                segment_index,
+                enumerated_data_offset,
+                data,
+            } => {
+                let end = *enumerated_data_offset as u32 + data.len() as u32;
+                check_data_fits(&defined, *segment_index, end, &mut report);
+                data_length = Some(data.len());
+            }
+            OmfRecordData::LIData {
+                segment_index,
+                iterated_data_offset,
+                blocks,
+            } => match expand_iterated_data(blocks, 0x10000) {
+                Ok(data) => {
+                    let end = *iterated_data_offset as u32 + data.len() as u32;
+                    check_data_fits(&defined, *segment_index, end, &mut report);
+                    data_length = Some(data.len());
+                }
+                Err(e) => {
+                    report(
+                        Rule::BadIteratedData,
+                        Severity::Error,
+                        format!("iterated data: {e}"),
+                    );
+                    data_length = None;
+                }
+            },
+            // Fixups for COMDAT data aren't checked against its length.
+            OmfRecordData::ComDat { .. } => data_length = Some(usize::MAX),
+            OmfRecordData::FixUpp { subrecords } => {
+                for s in subrecords {
+                    let FixupSubrecord::Fixup(fixup) = s else {
+                        continue;
+                    };
+                    let end = fixup.data_record_offset as usize + fixup.location.size();
+                    match data_length {
+                        None => report(
+                            Rule::FixupWithoutData,
+                            Severity::Error,
+                            "fixup without a preceding data record".to_string(),
+                        ),
+                        Some(length) if end > length => report(
+                            Rule::FixupPastData,
+                            Severity::Error,
+                            format!(
+                                "fixup at {:04X}h is past the end of its data record",
+                                fixup.data_record_offset
+                            ),
+                        ),
+                        _ => (),
+                    }
+                }
+            }
+            OmfRecordData::ModEnd { .. } => ended = true,
+            _ => (),
+        }
+    }
+
+    if let (false, Some(last)) = (ended, records.last()) {
+        diagnostics.push(Diagnostic {
+            offset: last.offset,
+            record: Some(records.len() - 1),
+            rule: Rule::NoModend,
+            severity: Severity::Error,
+            message: "module has no MODEND".to_string(),
+        });
+    }
+    diagnostics
+}
+
+fn segment_length(defined: &Defined, index: SegmentIndex) -> Option<u32> {
+    defined
+        .segment_lengths
+        .get((index.0 as usize).wrapping_sub(1))
+        .copied()
+}
+
+fn check_data_fits(
+    defined: &Defined,
+    segment_index: SegmentIndex,
+    end: u32,
+    report: &mut dyn FnMut(Rule, Severity, String),
+) {
+    if let Some(length) = segment_length(defined, segment_index) {
+        if end > length {
+            report(
+                Rule::DataPastSegment,
+                Severity::Error,
+                format!("data ends at {end:X}h, past the segment length {length:X}h"),
+            );
+        }
+    }
+}
+
+fn check_indices(data: &OmfRecordData, defined: &Defined, report: &mut dyn FnMut(Rule, String)) {
+    let mut name = |index: NameIndex, what: &str| {
+        if index.is_none() || index.0 as usize > defined.names {
+            report(
+                Rule::UndefinedIndex,
+                format!("{what} name index {index} is not defined"),
+            );
+        }
+    };
+    match data {
+        OmfRecordData::SegDef {
+            segment_name_index,
+            class_name_index,
+            overlay_name_index,
+            ..
+        } => {
+            name(*segment_name_index, "segment");
+            name(*class_name_index, "class");
+            if !overlay_name_index.is_none() {
+                name(*overlay_name_index, "overlay");
+            }
+        }
+        OmfRecordData::GrpDef {
+            group_name_index,
+            segment_definitions,
+        } => {
+            name(*group_name_index, "group");
+            for c in segment_definitions {
+                check_segment(c.segment_definition, defined, report);
+            }
+        }
+        OmfRecordData::CExtDef { names } => {
+            for n in names {
+                name(n.logical_name_index, "COMDAT external");
+            }
+        }
+        OmfRecordData::ComDat {
+            continuation,
+            base_group_index,
+            base_segment_index,
+            public_name_index,
+            ..
+        } => {
+            if !continuation {
+                name(*public_name_index, "COMDAT");
+            }
+            check_group(*base_group_index, defined, report);
+            check_segment(*base_segment_index, defined, report);
+        }
+        OmfRecordData::LinSym {
+            public_name_index, ..
+        } => name(*public_name_index, "LINSYM"),
+        OmfRecordData::PubDef {
+            base_group_index,
+            base_segment_index,
+            ..
+        }
+        | OmfRecordData::LPubDef {
+            base_group_index,
+            base_segment_index,
+            ..
+        }
+        | OmfRecordData::LinNum {
+            base_group_index,
+            base_segment_index,
+            ..
+        } => {
+            check_group(*base_group_index, defined, report);
+            check_segment(*base_segment_index, defined, report);
+        }
+        OmfRecordData::LEData { segment_index, .. }
+        | OmfRecordData::LIData { segment_index, .. } => {
+            if segment_index.is_none() {
+                report(
+                    Rule::NoDataSegment,
+                    "data record has no segment".to_string(),
+                );
+            }
+            check_segment(*segment_index, defined, report);
+        }
+        OmfRecordData::FixUpp { subrecords } => {
+            for s in subrecords {
+                match s {
+                    FixupSubrecord::Thread(FixupThread::Frame { frame, .. }) => {
+                        check_frame(frame, defined, report)
+                    }
+                    FixupSubrecord::Thread(FixupThread::Target { target, .. }) => {
+                        check_target(target, defined, report)
+                    }
+                    FixupSubrecord::Fixup(fixup) => {
+                        check_fix_data(&fixup.fix_data, defined, report)
+                    }
+                }
+            }
+        }
+        OmfRecordData::ModEnd {
+            start: MAttrStart::Start(fix_data),
+            ..
+        } => check_fix_data(fix_data, defined, report),
+        _ => (),
+    }
+}
+
+// Index 0 means "none" for segment and group references.
+fn check_segment(index: SegmentIndex, defined: &Defined, report: &mut dyn FnMut(Rule, String)) {
+    if index.0 as usize > defined.segment_lengths.len() {
+        report(
+            Rule::UndefinedIndex,
+            format!("segment index {index} is not defined"),
+        );
+    }
+}
+
+fn check_group(index: GroupIndex, defined: &Defined, report: &mut dyn FnMut(Rule, String)) {
+    if index.0 as usize > defined.groups {
+        report(
+            Rule::UndefinedIndex,
+            format!("group index {index} is not defined"),
+        );
+    }
+}
+
+fn check_external(index: ExternalIndex, defined: &Defined, report: &mut dyn FnMut(Rule, String)) {
+    if index.is_none() || index.0 as usize > defined.externals {
+        report(
+            Rule::UndefinedIndex,
+            format!("external index {index} is not defined"),
+        );
+    }
+}
+
+fn check_frame(frame: &FixupFrame, defined: &Defined, report: &mut dyn FnMut(Rule, String)) {
+    match *frame {
+        FixupFrame::Segment(i) => check_segment(i, defined, report),
+        FixupFrame::Group(i) => check_group(i, defined, report),
+        FixupFrame::External(i) => check_external(i, defined, report),
+        _ => (),
+    }
+}
+
+fn check_target(target: &FixupTarget, defined: &Defined, report: &mut dyn FnMut(Rule, String)) {
+    match *target {
+        FixupTarget::Segment(i) => check_segment(i, defined, report),
+        FixupTarget::Group(i) => check_group(i, defined, report),
+        FixupTarget::External(i) => check_external(i, defined, report),
+        FixupTarget::FrameNumber(_) => (),
+    }
+}
+
+fn check_fix_data(fix_data: &FixupData, defined: &Defined, report: &mut dyn FnMut(Rule, String)) {
+    check_frame(&fix_data.frame, defined, report);
+    check_target(&fix_data.target, defined, report);
+}
@@ -0,0 +1,252 @@
+use std::{io::Read, sync::Arc};
+
+use crate::{
+    error::OmfError, AliasName, CExtName, ComName, ComdatData, CommentType, ExtName,
+    FixupSubrecord, GroupComponent, GroupIndex, IteratedDataBlock, LineNumber, MAttrStart,
+    NameIndex, OmfInfo, OmfModule, OmfReader, OmfRecord, OmfRecordData, PubName, SegmentAttributes,
+    SegmentIndex,
+};
+
+// What every visitor method gets besides the record's fields: the record
+// itself, and the names, segments, groups and externals defined up to and
+// including it.
+#[derive(Clone, Copy)]
+pub struct Visit<'a> {
+    pub record: &'a OmfRecord,
+    pub info: &'a OmfInfo,
+}
+
+// Handles records one type at a time, as an alternative to matching on
+// OmfRecordData. Each method does nothing unless overridden. The 32-bit
+// forms of a record type go to the same method as the 16-bit one.
+#[allow(unused_variables)]
+pub trait OmfVisitor {
+    fn theadr(&mut self, visit: Visit<'_>, name: &str) {}
+
+    fn coment(
+        &mut self,
+        visit: Visit<'_>,
+        comment_type: &CommentType,
+        comment_class: u8,
+        comment_bytes: &[u8],
+    ) {
+    }
+
+    fn modend(&mut self, visit: Visit<'_>, main: bool, start: &MAttrStart) {}
+
+    fn extdef(&mut self, visit: Visit<'_>, names: &[ExtName]) {}
+
+    fn pubdef(
+        &mut self,
+        visit: Visit<'_>,
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
+        base_frame: u16,
+        names: &[PubName],
+    ) {
+    }
+
+    fn linnum(
+        &mut self,
+        visit: Visit<'_>,
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
+        lines: &[LineNumber],
+    ) {
+    }
+
+    fn lnames(&mut self, visit: Visit<'_>, names: &[Arc<str>]) {}
+
+    fn segdef(
+        &mut self,
+        visit: Visit<'_>,
+        segment_attributes: &SegmentAttributes,
+        segment_length: u16,
+        segment_name_index: NameIndex,
+        class_name_index: NameIndex,
+        overlay_name_index: NameIndex,
+    ) {
+    }
+
+    fn grpdef(
+        &mut self,
+        visit: Visit<'_>,
+        group_name_index: NameIndex,
+        segment_definitions: &[GroupComponent],
+    ) {
+    }
+
+    fn fixupp(&mut self, visit: Visit<'_>, subrecords: &[FixupSubrecord]) {}
+
+    fn ledata(&mut self, visit: Visit<'_>, segment_index: SegmentIndex, offset: u16, data: &[u8]) {}
+
+    fn lidata(
+        &mut self,
+        visit: Visit<'_>,
+        segment_index: SegmentIndex,
+        offset: u16,
+        blocks: &[IteratedDataBlock],
+    ) {
+    }
+
+    fn comdef(&mut self, visit: Visit<'_>, definitions: &[ComName]) {}
+
+    fn lextdef(&mut self, visit: Visit<'_>, names: &[ExtName]) {}
+
+    fn lpubdef(
+        &mut self,
+        visit: Visit<'_>,
+        base_group_index: GroupIndex,
+        base_segment_index: SegmentIndex,
+        base_frame: u16,
+        names: &[PubName],
+    ) {
+    }
+
+    fn cextdef(&mut self, visit: Visit<'_>, names: &[CExtName]) {}
+
+    // The selection, allocation and other attributes are in visit.record.
+    fn comdat(
+        &mut self,
+        visit: Visit<'_>,
+        public_name_index: NameIndex,
+        base_segment_index: SegmentIndex,
+        offset: u16,
+        data: &ComdatData,
+    ) {
+    }
+
+    fn linsym(
+        &mut self,
+        visit: Visit<'_>,
+        public_name_index: NameIndex,
+        continuation: bool,
+        lines: &[LineNumber],
+    ) {
+    }
+
+    fn alias(&mut self, visit: Visit<'_>, aliases: &[AliasName]) {}
+
+    // Records of a type that isn't decoded, and records that failed to decode
+    // when read leniently.
+    fn unknown(&mut self, visit: Visit<'_>, data: &[u8]) {}
+}
+
+// Hands one record to the visitor method for its type. `info` should be
+// what was known after reading the record.
+pub fn visit_record(record: &OmfRecord, info: &OmfInfo, visitor: &mut impl OmfVisitor) {
+    let visit = Visit { record, info };
+    match &record.data {
+        OmfRecordData::THeadr { name } => visitor.theadr(visit, name),
+        OmfRecordData::Coment {
+            comment_type,
+            comment_class,
+            comment_bytes,
+        } => visitor.coment(visit, comment_type, *comment_class, comment_bytes),
+        OmfRecordData::ModEnd { main, start } => visitor.modend(visit, *main, start),
+        OmfRecordData::ExtDef { names } => visitor.extdef(visit, names),
+        OmfRecordData::PubDef {
+            base_group_index,
+            base_segment_index,
+            base_frame,
+            names,
+        } => visitor.pubdef(
+            visit,
+            *base_group_index,
+            *base_segment_index,
+            *base_frame,
+            names,
+        ),
+        OmfRecordData::LinNum {
+            base_group_index,
+            base_segment_index,
+            lines,
+        } => visitor.linnum(visit, *base_group_index, *base_segment_index, lines),
+        OmfRecordData::LNames { names } => visitor.lnames(visit, names),
+        OmfRecordData::SegDef {
+            segment_attributes,
+            segment_length,
+            segment_name_index,
+            class_name_index,
+            overlay_name_index,
+        } => visitor.segdef(
+            visit,
+            segment_attributes,
+            *segment_length,
+            *segment_name_index,
+            *class_name_index,
+            *overlay_name_index,
+        ),
+        OmfRecordData::GrpDef {
+            group_name_index,
+            segment_definitions,
+        } => visitor.grpdef(visit, *group_name_index, segment_definitions),
+        OmfRecordData::FixUpp { subrecords } => visitor.fixupp(visit, subrecords),
+        OmfRecordData::LEData {
+            segment_index,
+            enumerated_data_offset,
+            data,
+        } => visitor.ledata(visit, *segment_index, *enumerated_data_offset, data),
+        OmfRecordData::LIData {
+            segment_index,
+            iterated_data_offset,
+            blocks,
+        } => visitor.lidata(visit, *segment_index, *iterated_data_offset, blocks),
+        OmfRecordData::ComDef { definitions } => visitor.comdef(visit, definitions),
+        OmfRecordData::LExtDef { names } => visitor.lextdef(visit, names),
+        OmfRecordData::LPubDef {
+            base_group_index,
+            base_segment_index,
+            base_frame,
+            names,
+        } => visitor.lpubdef(
+            visit,
+            *base_group_index,
+            *base_segment_index,
+            *base_frame,
+            names,
+        ),
+        OmfRecordData::CExtDef { names } => visitor.cextdef(visit, names),
+        OmfRecordData::ComDat {
+            public_name_index,
+            base_segment_index,
+            enumerated_data_offset,
+            data,
+            ..
+        } => visitor.comdat(
+            visit,
+            *public_name_index,
+            *base_segment_index,
+            *enumerated_data_offset,
+            data,
+        ),
+        OmfRecordData::LinSym {
+            continuation,
+            public_name_index,
+            lines,
+        } => visitor.linsym(visit, *public_name_index, *continuation, lines),
+        OmfRecordData::Alias { aliases } => visitor.alias(visit, aliases),
+        OmfRecordData::Unknown { data } => visitor.unknown(visit, data),
+    }
+}
+
+impl<R: Read> OmfReader<R> {
+    // Reads the remaining records, handing each to the visitor as it is
+    // read, so the info a visitor sees is as of that record.
+    pub fn visit(&mut self, visitor: &mut impl OmfVisitor) -> Result<(), OmfError> {
+        while let Some(record) = self.get_next_record()? {
+            visit_record(&record, self.info(), visitor);
+        }
+        Ok(())
+    }
+}
+
+impl OmfModule {
+    // Hands the module's records to the visitor in order. The info it sees
+    // is the module's, with every definition already known.
+    pub fn visit(&self, visitor: &mut impl OmfVisitor) {
+        for record in &self.records {
+            visit_record(record, self.info(), visitor);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{read_modules, OmfInfo, OmfRecord};
+
+#[derive(Serialize)]
+struct ModuleJson<'a> {
+    // The member name, for modules read from a library.
+    name: Option<String>,
+    records: &'a [OmfRecord],
+    info: &'a OmfInfo,
+}
+
+// Reads an object or library from the bytes of its file and returns its
+// modules as JSON: an array of objects with name, records and info.
+#[wasm_bindgen]
+pub fn parse(bytes: &[u8]) -> Result<String, JsError> {
+    let modules = read_modules(bytes)?;
+    let json: Vec<_> = modules
+        .iter()
+        .map(|(name, module)| ModuleJson {
+            name: name.clone(),
+            records: &module.records,
+            info: module.info(),
+        })
+        .collect();
+    Ok(serde_json::to_string(&json)?)
+}
+
+// Describes every record of an object or library as omfdump does.
+#[wasm_bindgen]
+pub fn dump(bytes: &[u8]) -> Result<String, JsError> {
+    let mut out = String::new();
+    for (name, module) in read_modules(bytes)? {
+        if let Some(name) = name {
+            out += &format!("Module {name}\n");
+        }
+        for record in &module.records {
+            out += &record.display_with(module.info()).to_string();
+        }
+    }
+    Ok(out)
+}
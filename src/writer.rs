@@ -0,0 +1,1479 @@
+use std::{io::Write, sync::Arc};
+
+use crate::{
+    error::OmfError,
+    names::{NameEncoding, RawName},
+    record::{
+        AbsoluteSegmentAddress, ComName, ComdatAllocation, ComdatData, CommentType, CommunalLength,
+        ExtName, Fixup, FixupData, FixupFrame, FixupSubrecord, FixupTarget, FixupThread,
+        GroupComponent, IteratedDataBlock, IteratedDataContent, LineNumber, MAttrStart, OmfRecord,
+        OmfRecordData, PubName, SegmentAlignment, SegmentAttributes, SegmentCombination,
+    },
+    Export, ExternalIndex, GroupIndex, NameIndex, SegmentIndex, TypeIndex,
+};
+
+const MAX_RECORD_BODY: usize = 0xFFFE;
+const MAX_DATA_CHUNK: usize = 1024;
+// The largest index the two-byte form can hold.
+const MAX_INDEX: u16 = 0x7FFF;
+// Runs of a repeated pattern at least this long go in LIDATA records. The
+// longest pattern looked for fits a far pointer table entry or a short
+// string.
+const MIN_REPEAT_RUN: usize = 64;
+const MAX_REPEAT_PATTERN: usize = 16;
+
+const COMENT_MEMORY_MODEL: u8 = 0x9D;
+const COMENT_DOSSEG: u8 = 0x9E;
+const COMENT_DEFAULT_LIBRARY: u8 = 0x9F;
+const COMENT_OMF_EXTENSIONS: u8 = 0xA0;
+const COMENT_LIBMOD: u8 = 0xA3;
+const EXTENSION_IMPDEF: u8 = 0x01;
+const EXTENSION_EXPDEF: u8 = 0x02;
+
+// The constant segments and their classes that presets put in DGROUP.
+const MICROSOFT_CONSTANTS: &[(&str, &str)] = &[("CONST", "CONST")];
+const WATCOM_CONSTANTS: &[(&str, &str)] = &[("CONST", "DATA"), ("CONST2", "DATA")];
+
+fn put_u16(body: &mut Vec<u8>, v: u16) {
+    body.extend_from_slice(&v.to_le_bytes());
+}
+
+// Indices from 80h up take two bytes, high byte first with its top bit set.
+fn put_index(body: &mut Vec<u8>, index: u16) -> Result<(), OmfError> {
+    match index {
+        0..=0x7F => body.push(index as u8),
+        0x80..=MAX_INDEX => {
+            body.push(0x80 | (index >> 8) as u8);
+            body.push(index as u8);
+        }
+        _ => return Err(OmfError::Value("index too large")),
+    }
+    Ok(())
+}
+
+fn put_string(body: &mut Vec<u8>, s: &str, encoding: NameEncoding) -> Result<(), OmfError> {
+    let bytes = encoding.encode(s)?;
+    if bytes.len() > 255 {
+        return Err(OmfError::Value("name longer than 255 bytes"));
+    }
+    body.push(bytes.len() as u8);
+    body.extend_from_slice(&bytes);
+    Ok(())
+}
+
+// Where a record's names come from: their text in an encoding, or the
+// bytes they were read from while that text is unchanged.
+struct Names<'r> {
+    encoding: NameEncoding,
+    raw: &'r [RawName],
+    // How many names have been written.
+    count: usize,
+}
+
+impl Names<'_> {
+    fn put(&mut self, body: &mut Vec<u8>, s: &str) -> Result<(), OmfError> {
+        let position = self.count;
+        self.count += 1;
+        match self
+            .raw
+            .iter()
+            .find(|r| r.position == position && r.text == s)
+        {
+            Some(raw) if raw.bytes.len() <= 255 => {
+                body.push(raw.bytes.len() as u8);
+                body.extend_from_slice(&raw.bytes);
+                Ok(())
+            }
+            _ => put_string(body, s, self.encoding),
+        }
+    }
+}
+
+fn put_line_numbers(body: &mut Vec<u8>, lines: &[LineNumber]) {
+    for l in lines {
+        put_u16(body, l.line_number);
+        put_u16(body, l.line_number_offset);
+    }
+}
+
+fn put_pub_names(
+    body: &mut Vec<u8>,
+    names: &[PubName],
+    encoder: &mut Names,
+) -> Result<(), OmfError> {
+    for n in names {
+        encoder.put(body, &n.name)?;
+        put_u16(body, n.public_offset);
+        put_index(body, n.type_index.0)?;
+    }
+    Ok(())
+}
+
+fn put_ext_names(
+    body: &mut Vec<u8>,
+    names: &[ExtName],
+    encoder: &mut Names,
+) -> Result<(), OmfError> {
+    for n in names {
+        encoder.put(body, &n.name)?;
+        put_index(body, n.type_index.0)?;
+    }
+    Ok(())
+}
+
+fn put_communal_length(body: &mut Vec<u8>, value: u32) {
+    match value {
+        0..=0x80 => body.push(value as u8),
+        0x81..=0xFFFF => {
+            body.push(0x81);
+            put_u16(body, value as u16);
+        }
+        0x10000..=0xFFFFFF => {
+            body.push(0x84);
+            put_u16(body, value as u16);
+            body.push((value >> 16) as u8);
+        }
+        _ => {
+            body.push(0x88);
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+fn frame_method(frame: &FixupFrame) -> u8 {
+    match frame {
+        FixupFrame::Segment(_) => 0,
+        FixupFrame::Group(_) => 1,
+        FixupFrame::External(_) => 2,
+        FixupFrame::FrameNumber(_) => 3,
+        FixupFrame::Location => 4,
+        FixupFrame::Target => 5,
+    }
+}
+
+fn put_frame_datum(body: &mut Vec<u8>, frame: &FixupFrame) -> Result<(), OmfError> {
+    match frame {
+        FixupFrame::Segment(i) => put_index(body, i.0)?,
+        FixupFrame::Group(i) => put_index(body, i.0)?,
+        FixupFrame::External(i) => put_index(body, i.0)?,
+        FixupFrame::FrameNumber(n) => put_u16(body, *n),
+        FixupFrame::Location | FixupFrame::Target => (),
+    }
+    Ok(())
+}
+
+fn target_method(target: &FixupTarget) -> u8 {
+    match target {
+        FixupTarget::Segment(_) => 0,
+        FixupTarget::Group(_) => 1,
+        FixupTarget::External(_) => 2,
+        FixupTarget::FrameNumber(_) => 3,
+    }
+}
+
+fn put_target_datum(body: &mut Vec<u8>, target: &FixupTarget) -> Result<(), OmfError> {
+    match target {
+        FixupTarget::Segment(i) => put_index(body, i.0)?,
+        FixupTarget::Group(i) => put_index(body, i.0)?,
+        FixupTarget::External(i) => put_index(body, i.0)?,
+        FixupTarget::FrameNumber(n) => put_u16(body, *n),
+    }
+    Ok(())
+}
+
+// Fix data is always written explicitly; threads are never referenced.
+fn put_fix_data(body: &mut Vec<u8>, fix_data: &FixupData) -> Result<(), OmfError> {
+    let mut byte = (frame_method(&fix_data.frame) << 4) | target_method(&fix_data.target);
+    if fix_data.target_displacement.is_none() {
+        byte |= 0x04;
+    }
+    body.push(byte);
+    put_frame_datum(body, &fix_data.frame)?;
+    put_target_datum(body, &fix_data.target)?;
+    if let Some(displacement) = fix_data.target_displacement {
+        let displacement = u16::try_from(displacement)
+            .map_err(|_| OmfError::Value("target displacement does not fit in 16 bits"))?;
+        put_u16(body, displacement);
+    }
+    Ok(())
+}
+
+fn put_fixup_subrecord(body: &mut Vec<u8>, subrecord: &FixupSubrecord) -> Result<(), OmfError> {
+    match subrecord {
+        FixupSubrecord::Thread(FixupThread::Frame { thread, frame }) => {
+            body.push(0x40 | (frame_method(frame) << 2) | (thread & 3));
+            put_frame_datum(body, frame)?;
+        }
+        FixupSubrecord::Thread(FixupThread::Target { thread, target }) => {
+            body.push((target_method(target) << 2) | (thread & 3));
+            put_target_datum(body, target)?;
+        }
+        FixupSubrecord::Fixup(fixup) => {
+            if fixup.data_record_offset > 0x3FF {
+                return Err(OmfError::Value("fixup data record offset out of range"));
+            }
+            let mut first = 0x80 | ((fixup.location as u8) << 2);
+            if fixup.segment_relative {
+                first |= 0x40;
+            }
+            first |= (fixup.data_record_offset >> 8) as u8;
+            body.push(first);
+            body.push(fixup.data_record_offset as u8);
+            put_fix_data(body, &fixup.fix_data)?;
+        }
+    }
+    Ok(())
+}
+
+fn put_iterated_block(body: &mut Vec<u8>, block: &IteratedDataBlock) -> Result<(), OmfError> {
+    put_u16(body, block.repeat_count);
+    match &block.content {
+        IteratedDataContent::Blocks(blocks) => {
+            put_u16(body, blocks.len() as u16);
+            for b in blocks {
+                put_iterated_block(body, b)?;
+            }
+        }
+        IteratedDataContent::Data(data) => {
+            if data.len() > 255 {
+                return Err(OmfError::Value(
+                    "iterated data content longer than 255 bytes",
+                ));
+            }
+            put_u16(body, 0);
+            body.push(data.len() as u8);
+            body.extend_from_slice(data);
+        }
+    }
+    Ok(())
+}
+
+// Encodes the body of a record, without the type, length or checksum.
+// Returns the record type along with the body. Unknown records have no
+// intrinsic type and must be written with OmfWriter::write_raw.
+pub fn encode_record_data(data: &OmfRecordData) -> Result<(u8, Vec<u8>), OmfError> {
+    encode_record_data_with(data, NameEncoding::Utf8)
+}
+
+// As encode_record_data, writing names in the given encoding.
+pub fn encode_record_data_with(
+    data: &OmfRecordData,
+    encoding: NameEncoding,
+) -> Result<(u8, Vec<u8>), OmfError> {
+    encode_encoder(data, encoding, &[])
+}
+
+fn encode_encoder(
+    data: &OmfRecordData,
+    encoding: NameEncoding,
+    raw_names: &[RawName],
+) -> Result<(u8, Vec<u8>), OmfError> {
+    let mut encoder = Names {
+        encoding,
+        raw: raw_names,
+        count: 0,
+    };
+    let mut body = vec![];
+    let record_type = match data {
+        OmfRecordData::THeadr { name } => {
+            encoder.put(&mut body, name)?;
+            0x80
+        }
+        OmfRecordData::Coment {
+            comment_type,
+            comment_class,
+            comment_bytes,
+        } => {
+            let mut tmp = 0;
+            if comment_type.no_purge {
+                tmp |= 0x80;
+            }
+            if comment_type.no_list {
+                tmp |= 0x40;
+            }
+            body.push(tmp);
+            body.push(*comment_class);
+            body.extend_from_slice(comment_bytes);
+            0x88
+        }
+        OmfRecordData::ModEnd { main, start } => {
+            let mut module_type = if *main { 0x80 } else { 0 };
+            match start {
+                MAttrStart::NoStart => body.push(module_type),
+                MAttrStart::Start(FixupData {
+                    frame: FixupFrame::FrameNumber(frame),
+                    target: FixupTarget::FrameNumber(target),
+                    target_displacement: Some(offset),
+                }) if frame == target && *offset <= 0xFFFF => {
+                    module_type |= 0x40;
+                    body.push(module_type);
+                    put_u16(&mut body, *frame);
+                    put_u16(&mut body, *offset as u16);
+                }
+                MAttrStart::Start(fix_data) => {
+                    module_type |= 0x41;
+                    body.push(module_type);
+                    put_fix_data(&mut body, fix_data)?;
+                }
+            }
+            0x8A
+        }
+        OmfRecordData::ExtDef { names } => {
+            put_ext_names(&mut body, names, &mut encoder)?;
+            0x8C
+        }
+        OmfRecordData::LExtDef { names } => {
+            put_ext_names(&mut body, names, &mut encoder)?;
+            0xB4
+        }
+        OmfRecordData::PubDef {
+            base_group_index,
+            base_segment_index,
+            base_frame,
+            names,
+        }
+        | OmfRecordData::LPubDef {
+            base_group_index,
+            base_segment_index,
+            base_frame,
+            names,
+        } => {
+            put_index(&mut body, base_group_index.0)?;
+            put_index(&mut body, base_segment_index.0)?;
+            if base_segment_index.is_none() {
+                put_u16(&mut body, *base_frame);
+            }
+            put_pub_names(&mut body, names, &mut encoder)?;
+            if matches!(data, OmfRecordData::PubDef { .. }) {
+                0x90
+            } else {
+                0xB6
+            }
+        }
+        OmfRecordData::LinNum {
+            base_group_index,
+            base_segment_index,
+            lines,
+        } => {
+            put_index(&mut body, base_group_index.0)?;
+            put_index(&mut body, base_segment_index.0)?;
+            put_line_numbers(&mut body, lines);
+            0x94
+        }
+        OmfRecordData::LNames { names } => {
+            for n in names {
+                encoder.put(&mut body, n)?;
+            }
+            0x96
+        }
+        OmfRecordData::SegDef {
+            segment_attributes,
+            segment_length,
+            segment_name_index,
+            class_name_index,
+            overlay_name_index,
+        } => {
+            body.push(segment_attributes.encode());
+            if segment_attributes.alignment == SegmentAlignment::AbsoluteSegment {
+                let address =
+                    segment_attributes
+                        .absolute_segment_address
+                        .unwrap_or(AbsoluteSegmentAddress {
+                            frame_number: 0,
+                            offset: 0,
+                        });
+                put_u16(&mut body, address.frame_number);
+                body.push(address.offset);
+            }
+            put_u16(&mut body, *segment_length);
+            put_index(&mut body, segment_name_index.0)?;
+            put_index(&mut body, class_name_index.0)?;
+            put_index(&mut body, overlay_name_index.0)?;
+            0x98
+        }
+        OmfRecordData::GrpDef {
+            group_name_index,
+            segment_definitions,
+        } => {
+            put_index(&mut body, group_name_index.0)?;
+            for c in segment_definitions {
+                body.push(c.index);
+                put_index(&mut body, c.segment_definition.0)?;
+            }
+            0x9A
+        }
+        OmfRecordData::FixUpp { subrecords } => {
+            for s in subrecords {
+                put_fixup_subrecord(&mut body, s)?;
+            }
+            0x9C
+        }
+        OmfRecordData::LEData {
+            segment_index,
+            enumerated_data_offset,
+            data,
+        } => {
+            put_index(&mut body, segment_index.0)?;
+            put_u16(&mut body, *enumerated_data_offset);
+            body.extend_from_slice(data);
+            0xA0
+        }
+        OmfRecordData::LIData {
+            segment_index,
+            iterated_data_offset,
+            blocks,
+        } => {
+            put_index(&mut body, segment_index.0)?;
+            put_u16(&mut body, *iterated_data_offset);
+            for b in blocks {
+                put_iterated_block(&mut body, b)?;
+            }
+            0xA2
+        }
+        OmfRecordData::ComDef { definitions } => {
+            for d in definitions {
+                encoder.put(&mut body, &d.name)?;
+                put_index(&mut body, d.type_index.0)?;
+                match d.communal_length {
+                    CommunalLength::Far {
+                        number_of_elements,
+                        element_size,
+                    } => {
+                        body.push(0x61);
+                        put_communal_length(&mut body, number_of_elements);
+                        put_communal_length(&mut body, element_size);
+                    }
+                    CommunalLength::Near(size) => {
+                        body.push(0x62);
+                        put_communal_length(&mut body, size);
+                    }
+                    CommunalLength::Segment {
+                        segment_index,
+                        size,
+                    } => {
+                        // The data type byte only has room for segments
+                        // 1 to 5Fh; the rest are far and near.
+                        if !(0x01..=0x5F).contains(&segment_index.0) {
+                            return Err(OmfError::Value("communal segment index out of range"));
+                        }
+                        body.push(segment_index.0 as u8);
+                        put_communal_length(&mut body, size);
+                    }
+                }
+            }
+            0xB0
+        }
+        OmfRecordData::CExtDef { names } => {
+            for n in names {
+                put_index(&mut body, n.logical_name_index.0)?;
+                put_index(&mut body, n.type_index.0)?;
+            }
+            0xBC
+        }
+        OmfRecordData::ComDat {
+            continuation,
+            local,
+            selection,
+            allocation,
+            alignment,
+            enumerated_data_offset,
+            type_index,
+            base_group_index,
+            base_segment_index,
+            base_frame,
+            public_name_index,
+            data,
+        } => {
+            let mut flags = 0;
+            if *continuation {
+                flags |= 0x01;
+            }
+            if matches!(data, ComdatData::Iterated(_)) {
+                flags |= 0x02;
+            }
+            if *local {
+                flags |= 0x04;
+            }
+            body.push(flags);
+            body.push(((*selection as u8) << 4) | *allocation as u8);
+            body.push(alignment.map(|a| a as u8).unwrap_or(0));
+            put_u16(&mut body, *enumerated_data_offset);
+            put_index(&mut body, type_index.0)?;
+            if *allocation == ComdatAllocation::Explicit {
+                put_index(&mut body, base_group_index.0)?;
+                put_index(&mut body, base_segment_index.0)?;
+                if base_segment_index.is_none() {
+                    put_u16(&mut body, *base_frame);
+                }
+            }
+            put_index(&mut body, public_name_index.0)?;
+            match data {
+                ComdatData::Enumerated(bytes) => body.extend_from_slice(bytes),
+                ComdatData::Iterated(blocks) => {
+                    for b in blocks {
+                        put_iterated_block(&mut body, b)?;
+                    }
+                }
+            }
+            0xC2
+        }
+        OmfRecordData::LinSym {
+            continuation,
+            public_name_index,
+            lines,
+        } => {
+            body.push(*continuation as u8);
+            put_index(&mut body, public_name_index.0)?;
+            put_line_numbers(&mut body, lines);
+            0xC4
+        }
+        OmfRecordData::Alias { aliases } => {
+            for a in aliases {
+                encoder.put(&mut body, &a.alias_name)?;
+                encoder.put(&mut body, &a.substitute_name)?;
+            }
+            0xC6
+        }
+        OmfRecordData::Unknown { .. } => {
+            return Err(OmfError::Value("cannot encode a record of unknown type"))
+        }
+    };
+    Ok((record_type, body))
+}
+
+// How an IMPDEF names the entry point in the DLL: by ordinal, or by name,
+// where None means the same as the internal name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportEntry {
+    Ordinal(u16),
+    Name(Option<String>),
+}
+
+// A COMENT with a well-known class, encoded as linkers and librarians expect
+// so callers don't have to pack the bytes themselves.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Comment {
+    // An import from a DLL, as found in import libraries.
+    Import {
+        internal_name: String,
+        module_name: String,
+        entry: ImportEntry,
+    },
+    Export(Export),
+    DefaultLibrary(String),
+    // Microsoft's model string, such as "3sOd": the processor, then the
+    // memory model and optimization letters.
+    MemoryModel(String),
+    DosSeg,
+    // The module's name in a library, which LIB keeps when extracting it.
+    LibMod(String),
+}
+
+impl Comment {
+    pub fn to_record_data(&self) -> Result<OmfRecordData, OmfError> {
+        let encoding = NameEncoding::Utf8;
+        let mut body = vec![];
+        let comment_class = match self {
+            Comment::Import {
+                internal_name,
+                module_name,
+                entry,
+            } => {
+                body.push(EXTENSION_IMPDEF);
+                body.push(matches!(entry, ImportEntry::Ordinal(_)) as u8);
+                put_string(&mut body, internal_name, encoding)?;
+                put_string(&mut body, module_name, encoding)?;
+                match entry {
+                    ImportEntry::Ordinal(ordinal) => put_u16(&mut body, *ordinal),
+                    ImportEntry::Name(name) => {
+                        put_string(&mut body, name.as_deref().unwrap_or(""), encoding)?
+                    }
+                }
+                COMENT_OMF_EXTENSIONS
+            }
+            Comment::Export(export) => {
+                if export.parameter_count > 0x1F {
+                    return Err(OmfError::Value("export has more than 31 parameter words"));
+                }
+                let mut flag = export.parameter_count;
+                if export.ordinal.is_some() {
+                    flag |= 0x80;
+                }
+                if export.resident_name {
+                    flag |= 0x40;
+                }
+                if export.no_data {
+                    flag |= 0x20;
+                }
+                body.push(EXTENSION_EXPDEF);
+                body.push(flag);
+                put_string(&mut body, &export.name, encoding)?;
+                put_string(
+                    &mut body,
+                    export.internal_name.as_deref().unwrap_or(""),
+                    encoding,
+                )?;
+                if let Some(ordinal) = export.ordinal {
+                    put_u16(&mut body, ordinal);
+                }
+                COMENT_OMF_EXTENSIONS
+            }
+            // These two hold the bare text, without a length byte.
+            Comment::DefaultLibrary(name) => {
+                body = encoding.encode(name)?;
+                COMENT_DEFAULT_LIBRARY
+            }
+            Comment::MemoryModel(model) => {
+                body = encoding.encode(model)?;
+                COMENT_MEMORY_MODEL
+            }
+            Comment::DosSeg => COMENT_DOSSEG,
+            Comment::LibMod(name) => {
+                put_string(&mut body, name, encoding)?;
+                COMENT_LIBMOD
+            }
+        };
+        Ok(OmfRecordData::Coment {
+            comment_type: CommentType {
+                no_purge: false,
+                no_list: false,
+            },
+            comment_class,
+            comment_bytes: body,
+        })
+    }
+}
+
+// What the writer puts in each record's checksum byte. Linkers differ in
+// whether they check it, and some choke on one convention or the other.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ChecksumMode {
+    #[default]
+    Computed,
+    // Always 0, which readers take to mean none was computed.
+    Zero,
+    // Records passed to write_record keep a checksum of 0, or one that
+    // didn't match, as they were read; the rest are computed, so they stay
+    // right for records that have been changed.
+    Preserve,
+}
+
+pub struct OmfWriter<W> {
+    w: W,
+    name_encoding: NameEncoding,
+    checksum_mode: ChecksumMode,
+}
+
+impl<W: Write> OmfWriter<W> {
+    pub fn new(w: W) -> OmfWriter<W> {
+        OmfWriter {
+            w,
+            name_encoding: NameEncoding::Utf8,
+            checksum_mode: ChecksumMode::default(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    // Names are written as UTF-8 unless set otherwise here, which should
+    // match the encoding they were read with.
+    pub fn set_name_encoding(&mut self, encoding: NameEncoding) {
+        self.name_encoding = encoding;
+    }
+
+    pub fn set_checksum_mode(&mut self, mode: ChecksumMode) {
+        self.checksum_mode = mode;
+    }
+
+    // Writes a record with the given type and body, adding the length and
+    // checksum.
+    pub fn write_raw(&mut self, record_type: u8, body: &[u8]) -> Result<(), OmfError> {
+        self.write_raw_with(record_type, body, None)
+    }
+
+    // As write_raw, with the checksum of the record the body was read from,
+    // if any, and whether it matched.
+    fn write_raw_with(
+        &mut self,
+        record_type: u8,
+        body: &[u8],
+        original: Option<(u8, bool)>,
+    ) -> Result<(), OmfError> {
+        if body.len() > MAX_RECORD_BODY {
+            return Err(OmfError::Value("record too long"));
+        }
+        let length = (body.len() + 1) as u16;
+        let mut header = [record_type, 0, 0];
+        header[1..3].copy_from_slice(&length.to_le_bytes());
+        let sum = header
+            .iter()
+            .chain(body.iter())
+            .fold(0u8, |acc, b| acc.wrapping_add(*b));
+        let checksum = match (self.checksum_mode, original) {
+            (ChecksumMode::Zero, _) => 0,
+            (ChecksumMode::Preserve, Some((checksum, valid))) if checksum == 0 || !valid => {
+                checksum
+            }
+            _ => sum.wrapping_neg(),
+        };
+        self.w.write_all(&header)?;
+        self.w.write_all(body)?;
+        self.w.write_all(&[checksum])?;
+        Ok(())
+    }
+
+    pub fn write_data(&mut self, data: &OmfRecordData) -> Result<(), OmfError> {
+        let (record_type, body) = encode_record_data_with(data, self.name_encoding)?;
+        self.write_raw(record_type, &body)
+    }
+
+    // Writes a record read from another module. Unknown records are copied
+    // verbatim, and names kept as raw bytes are written as they were read.
+    pub fn write_record(&mut self, record: &OmfRecord) -> Result<(), OmfError> {
+        let original = Some((record.checksum, record.checksum_valid));
+        match &record.data {
+            OmfRecordData::Unknown { data } => {
+                self.write_raw_with(record.record_type.to_u8(), data, original)
+            }
+            data => {
+                let (record_type, body) =
+                    encode_encoder(data, self.name_encoding, &record.raw_names)?;
+                self.write_raw_with(record_type, &body, original)
+            }
+        }
+    }
+}
+
+// The segment names, classes and DGROUP membership a compiler's runtime
+// expects, so generated modules link with its startup code and libraries.
+// Borland and Watcom are their small models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentPreset {
+    MicrosoftSmall,
+    // Code goes in a segment of its own named after the module.
+    MicrosoftLarge,
+    Borland,
+    Watcom,
+}
+
+// The segments and group a preset adds to a ModuleBuilder.
+#[derive(Debug, Clone, Copy)]
+pub struct PresetSegments {
+    pub code: SegmentIndex,
+    pub data: SegmentIndex,
+    pub bss: SegmentIndex,
+    pub stack: SegmentIndex,
+    pub dgroup: GroupIndex,
+}
+
+// Assembles a module from its parts. Definitions are collected as they are
+// added and emitted in the conventional order: THEADR, comments, LNAMES,
+// SEGDEF, GRPDEF, EXTDEF and COMDEF, PUBDEF, data with its fixups, and MODEND.
+#[derive(Debug)]
+pub struct ModuleBuilder {
+    module_name: String,
+    comments: Vec<OmfRecordData>,
+    names: Vec<String>,
+    segments: Vec<OmfRecordData>,
+    groups: Vec<OmfRecordData>,
+    // Externals and communals in index order; communals carry their size.
+    externals: Vec<(String, Option<u32>)>,
+    publics: Vec<(SegmentIndex, String, u16)>,
+    data: Vec<OmfRecordData>,
+    start: Option<FixupData>,
+    compress_data: bool,
+}
+
+// Finds the longest run of a pattern repeated from the start of `data`,
+// returning the pattern length and the repeat count. Bytes under a fixup
+// can't be repeated, so the run stops short of them.
+fn repeated_run(data: &[u8], fixed: &[bool]) -> Option<(usize, usize)> {
+    let mut best: Option<(usize, usize)> = None;
+    for len in 1..=MAX_REPEAT_PATTERN.min(data.len() / 2) {
+        if fixed[..len].contains(&true) {
+            break;
+        }
+        let pattern = &data[..len];
+        let mut count = 1;
+        while let Some(next) = data.get(count * len..(count + 1) * len) {
+            if next != pattern || fixed[count * len..(count + 1) * len].contains(&true) {
+                break;
+            }
+            count += 1;
+        }
+        if count > 1 && best.is_none_or(|(l, c)| count * len > l * c) {
+            best = Some((len, count));
+        }
+    }
+    best.filter(|(len, count)| len * count >= MIN_REPEAT_RUN)
+}
+
+impl ModuleBuilder {
+    pub fn new(module_name: &str) -> ModuleBuilder {
+        ModuleBuilder {
+            module_name: module_name.to_string(),
+            comments: vec![],
+            names: vec![],
+            segments: vec![],
+            groups: vec![],
+            externals: vec![],
+            publics: vec![],
+            data: vec![],
+            start: None,
+            compress_data: true,
+        }
+    }
+
+    // Long runs of repeated bytes given to add_data are written as LIDATA,
+    // as MASM does for DUP, unless this is turned off.
+    pub fn set_compress_data(&mut self, compress: bool) {
+        self.compress_data = compress;
+    }
+
+    pub fn add_comment(&mut self, comment_class: u8, comment_bytes: &[u8]) {
+        self.comments.push(OmfRecordData::Coment {
+            comment_type: CommentType {
+                no_purge: false,
+                no_list: false,
+            },
+            comment_class,
+            comment_bytes: comment_bytes.to_vec(),
+        });
+    }
+
+    pub fn add_known_comment(&mut self, comment: &Comment) -> Result<(), OmfError> {
+        self.comments.push(comment.to_record_data()?);
+        Ok(())
+    }
+
+    pub fn add_import(
+        &mut self,
+        internal_name: &str,
+        module_name: &str,
+        entry: ImportEntry,
+    ) -> Result<(), OmfError> {
+        self.add_known_comment(&Comment::Import {
+            internal_name: internal_name.to_string(),
+            module_name: module_name.to_string(),
+            entry,
+        })
+    }
+
+    pub fn add_export(&mut self, export: &Export) -> Result<(), OmfError> {
+        self.add_known_comment(&Comment::Export(export.clone()))
+    }
+
+    pub fn add_default_library(&mut self, name: &str) -> Result<(), OmfError> {
+        self.add_known_comment(&Comment::DefaultLibrary(name.to_string()))
+    }
+
+    pub fn add_memory_model(&mut self, model: &str) -> Result<(), OmfError> {
+        self.add_known_comment(&Comment::MemoryModel(model.to_string()))
+    }
+
+    pub fn add_dosseg(&mut self) {
+        self.add_comment(COMENT_DOSSEG, &[]);
+    }
+
+    pub fn add_libmod(&mut self, name: &str) -> Result<(), OmfError> {
+        self.add_known_comment(&Comment::LibMod(name.to_string()))
+    }
+
+    // Returns the LNAMES index of `name`, adding it if needed.
+    pub fn add_name(&mut self, name: &str) -> Result<NameIndex, OmfError> {
+        if let Some(i) = self.names.iter().position(|n| n == name) {
+            return Ok(NameIndex(i as u16 + 1));
+        }
+        if self.names.len() >= MAX_INDEX as usize {
+            return Err(OmfError::Value("too many names"));
+        }
+        self.names.push(name.to_string());
+        Ok(NameIndex(self.names.len() as u16))
+    }
+
+    pub fn add_segment(
+        &mut self,
+        name: &str,
+        class: &str,
+        alignment: SegmentAlignment,
+        combination: SegmentCombination,
+        use32: bool,
+        length: u32,
+    ) -> Result<SegmentIndex, OmfError> {
+        if length > 0x10000 {
+            return Err(OmfError::Value("segment longer than 64K"));
+        }
+        if self.segments.len() >= MAX_INDEX as usize {
+            return Err(OmfError::Value("too many segments"));
+        }
+        let segment_name_index = self.add_name(name)?;
+        let class_name_index = self.add_name(class)?;
+        let overlay_name_index = self.add_name("")?;
+        self.segments.push(OmfRecordData::SegDef {
+            segment_attributes: SegmentAttributes {
+                alignment,
+                combination,
+                big: length == 0x10000,
+                bd32bit: use32,
+                absolute_segment_address: None,
+            },
+            segment_length: length as u16,
+            segment_name_index,
+            class_name_index,
+            overlay_name_index,
+        });
+        Ok(SegmentIndex(self.segments.len() as u16))
+    }
+
+    // Changes the length given to add_segment, such as once all the data
+    // in the segment is known.
+    pub fn set_segment_length(
+        &mut self,
+        segment_index: SegmentIndex,
+        length: u32,
+    ) -> Result<(), OmfError> {
+        if length > 0x10000 {
+            return Err(OmfError::Value("segment longer than 64K"));
+        }
+        let Some(OmfRecordData::SegDef {
+            segment_attributes,
+            segment_length,
+            ..
+        }) = (segment_index.0 as usize)
+            .checked_sub(1)
+            .and_then(|i| self.segments.get_mut(i))
+        else {
+            return Err(OmfError::Value("no such segment"));
+        };
+        segment_attributes.big = length == 0x10000;
+        *segment_length = length as u16;
+        Ok(())
+    }
+
+    // Adds the code, data, BSS and stack segments and DGROUP as `preset`
+    // names them, along with any constant segments it puts in DGROUP. All
+    // are empty; set their lengths with set_segment_length.
+    pub fn add_preset_segments(
+        &mut self,
+        preset: SegmentPreset,
+    ) -> Result<PresetSegments, OmfError> {
+        // The large model's code segment is named after the source file,
+        // so HELLO_TEXT for a module named c:\src\hello.c.
+        let code_name = match preset {
+            SegmentPreset::MicrosoftLarge => {
+                let file = self.module_name.rsplit(['/', '\\', ':']).next();
+                let stem = file.and_then(|f| f.split('.').next()).unwrap_or("");
+                format!("{}_TEXT", stem.to_uppercase())
+            }
+            _ => "_TEXT".to_string(),
+        };
+        let code_alignment = match preset {
+            SegmentPreset::MicrosoftSmall | SegmentPreset::MicrosoftLarge => {
+                SegmentAlignment::RelocatableWordAligned
+            }
+            SegmentPreset::Borland | SegmentPreset::Watcom => {
+                SegmentAlignment::RelocatableByteAligned
+            }
+        };
+        let public = SegmentCombination::Public;
+        let word = SegmentAlignment::RelocatableWordAligned;
+        let code = self.add_segment(&code_name, "CODE", code_alignment, public, false, 0)?;
+        // Constants are kept apart from data by Microsoft and Watcom, after
+        // or before _DATA as their runtimes order DGROUP.
+        let (before, after) = match preset {
+            SegmentPreset::MicrosoftSmall | SegmentPreset::MicrosoftLarge => {
+                (&[][..], MICROSOFT_CONSTANTS)
+            }
+            SegmentPreset::Borland => (&[][..], &[][..]),
+            SegmentPreset::Watcom => (WATCOM_CONSTANTS, &[][..]),
+        };
+        let mut members = vec![];
+        for (name, class) in before {
+            members.push(self.add_segment(name, class, word, public, false, 0)?);
+        }
+        let data = self.add_segment("_DATA", "DATA", word, public, false, 0)?;
+        members.push(data);
+        for (name, class) in after {
+            members.push(self.add_segment(name, class, word, public, false, 0)?);
+        }
+        let bss = self.add_segment("_BSS", "BSS", word, public, false, 0)?;
+        let stack = self.add_segment(
+            "STACK",
+            "STACK",
+            SegmentAlignment::RelocatableParagraphAligned,
+            SegmentCombination::Stack,
+            false,
+            0,
+        )?;
+        members.extend([bss, stack]);
+        let dgroup = self.add_group("DGROUP", &members)?;
+        Ok(PresetSegments {
+            code,
+            data,
+            bss,
+            stack,
+            dgroup,
+        })
+    }
+
+    pub fn add_group(
+        &mut self,
+        name: &str,
+        segments: &[SegmentIndex],
+    ) -> Result<GroupIndex, OmfError> {
+        if self.groups.len() >= MAX_INDEX as usize {
+            return Err(OmfError::Value("too many groups"));
+        }
+        let group_name_index = self.add_name(name)?;
+        self.groups.push(OmfRecordData::GrpDef {
+            group_name_index,
+            segment_definitions: segments
+                .iter()
+                .map(|&s| GroupComponent {
+                    index: 0xFF,
+                    segment_definition: s,
+                })
+                .collect(),
+        });
+        Ok(GroupIndex(self.groups.len() as u16))
+    }
+
+    // Returns the external index of `name`, adding it if needed. Communals
+    // share the external index space.
+    pub fn add_external(&mut self, name: &str) -> Result<ExternalIndex, OmfError> {
+        self.add_external_entry(name, None)
+    }
+
+    pub fn add_communal(&mut self, name: &str, size: u32) -> Result<ExternalIndex, OmfError> {
+        self.add_external_entry(name, Some(size))
+    }
+
+    fn add_external_entry(
+        &mut self,
+        name: &str,
+        size: Option<u32>,
+    ) -> Result<ExternalIndex, OmfError> {
+        if let Some(i) = self.externals.iter().position(|(n, _)| n == name) {
+            return Ok(ExternalIndex(i as u16 + 1));
+        }
+        if self.externals.len() >= MAX_INDEX as usize {
+            return Err(OmfError::Value("too many externals"));
+        }
+        self.externals.push((name.to_string(), size));
+        Ok(ExternalIndex(self.externals.len() as u16))
+    }
+
+    pub fn add_public(&mut self, name: &str, segment_index: SegmentIndex, offset: u16) {
+        self.publics.push((segment_index, name.to_string(), offset));
+    }
+
+    // Adds initialized data at `offset` in a segment. The fixups' data record
+    // offsets are relative to the start of `data`; the data is split into
+    // LEDATA records as needed without separating a fixup from its bytes, and
+    // long repeated runs without fixups go in LIDATA records.
+    pub fn add_data(
+        &mut self,
+        segment_index: SegmentIndex,
+        offset: u16,
+        data: &[u8],
+        fixups: &[Fixup],
+    ) -> Result<(), OmfError> {
+        if offset as usize + data.len() > 0x10000 {
+            return Err(OmfError::Value("data extends past the end of the segment"));
+        }
+        if !self.compress_data || data.is_empty() {
+            return self.add_enumerated_data(segment_index, offset, data, fixups);
+        }
+        let mut fixed = vec![false; data.len()];
+        for f in fixups {
+            let start = f.data_record_offset as usize;
+            let end = (start + f.location.size()).min(data.len());
+            fixed[start.min(end)..end].fill(true);
+        }
+        // Everything from `literal` up to a run goes in LEDATA first.
+        let mut literal = 0;
+        let mut at = 0;
+        while at < data.len() {
+            let Some((len, count)) = repeated_run(&data[at..], &fixed[at..]) else {
+                at += 1;
+                continue;
+            };
+            if literal < at {
+                self.add_literal_data(segment_index, offset, data, fixups, literal..at)?;
+            }
+            self.add_iterated_data(
+                segment_index,
+                offset + at as u16,
+                &data[at..at + len],
+                count,
+            );
+            at += len * count;
+            literal = at;
+        }
+        if literal < data.len() {
+            self.add_literal_data(segment_index, offset, data, fixups, literal..data.len())?;
+        }
+        Ok(())
+    }
+
+    // Adds part of the data given to add_data as LEDATA, with the fixups that
+    // fall in it.
+    fn add_literal_data(
+        &mut self,
+        segment_index: SegmentIndex,
+        offset: u16,
+        data: &[u8],
+        fixups: &[Fixup],
+        range: std::ops::Range<usize>,
+    ) -> Result<(), OmfError> {
+        let fixups: Vec<Fixup> = fixups
+            .iter()
+            .filter(|f| range.contains(&(f.data_record_offset as usize)))
+            .map(|f| {
+                let mut f = *f;
+                f.data_record_offset -= range.start as u16;
+                f
+            })
+            .collect();
+        self.add_enumerated_data(
+            segment_index,
+            offset + range.start as u16,
+            &data[range],
+            &fixups,
+        )
+    }
+
+    // Adds `count` copies of `pattern` as one LIDATA record, in blocks of up
+    // to the largest repeat count.
+    fn add_iterated_data(
+        &mut self,
+        segment_index: SegmentIndex,
+        offset: u16,
+        pattern: &[u8],
+        mut count: usize,
+    ) {
+        let mut blocks = vec![];
+        while count > 0 {
+            let repeat_count = count.min(0xFFFF);
+            blocks.push(IteratedDataBlock {
+                repeat_count: repeat_count as u16,
+                content: IteratedDataContent::Data(pattern.to_vec()),
+            });
+            count -= repeat_count;
+        }
+        self.data.push(OmfRecordData::LIData {
+            segment_index,
+            iterated_data_offset: offset,
+            blocks,
+        });
+    }
+
+    fn add_enumerated_data(
+        &mut self,
+        segment_index: SegmentIndex,
+        offset: u16,
+        data: &[u8],
+        fixups: &[Fixup],
+    ) -> Result<(), OmfError> {
+        let mut fixups = fixups.to_vec();
+        fixups.sort_by_key(|f| f.data_record_offset);
+        let mut start = 0;
+        while start < data.len() || (data.is_empty() && start == 0) {
+            let mut end = (start + MAX_DATA_CHUNK).min(data.len());
+            for f in &fixups {
+                let f_start = f.data_record_offset as usize;
+                let f_end = f_start + f.location.size();
+                if f_start < end && f_end > end {
+                    end = f_start;
+                }
+            }
+            if end == start && !data.is_empty() {
+                return Err(OmfError::Value("fixup does not fit in a data record"));
+            }
+            self.data.push(OmfRecordData::LEData {
+                segment_index,
+                enumerated_data_offset: offset + start as u16,
+                data: data[start..end].to_vec(),
+            });
+            let subrecords: Vec<FixupSubrecord> = fixups
+                .iter()
+                .filter(|f| (start..end).contains(&(f.data_record_offset as usize)))
+                .map(|f| {
+                    let mut f = *f;
+                    f.data_record_offset -= start as u16;
+                    FixupSubrecord::Fixup(f)
+                })
+                .collect();
+            if !subrecords.is_empty() {
+                self.data.push(OmfRecordData::FixUpp { subrecords });
+            }
+            if data.is_empty() {
+                break;
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    pub fn set_start(&mut self, start: FixupData) {
+        self.start = Some(start);
+    }
+
+    pub fn build(&self) -> Vec<OmfRecordData> {
+        let mut records = vec![OmfRecordData::THeadr {
+            name: self.module_name.clone(),
+        }];
+        records.extend(self.comments.iter().cloned());
+        let mut chunk: Vec<Arc<str>> = vec![];
+        let mut size = 0;
+        for n in &self.names {
+            if size + n.len() + 1 > MAX_DATA_CHUNK {
+                records.push(OmfRecordData::LNames {
+                    names: std::mem::take(&mut chunk),
+                });
+                size = 0;
+            }
+            size += n.len() + 1;
+            chunk.push(n.as_str().into());
+        }
+        if !chunk.is_empty() {
+            records.push(OmfRecordData::LNames { names: chunk });
+        }
+        records.extend(self.segments.iter().cloned());
+        records.extend(self.groups.iter().cloned());
+        // Consecutive externals and communals share a record, keeping the
+        // indices in the order they were handed out.
+        for run in self
+            .externals
+            .chunk_by(|a, b| a.1.is_some() == b.1.is_some())
+            .flat_map(|run| run.chunks(32))
+        {
+            records.push(if run[0].1.is_none() {
+                OmfRecordData::ExtDef {
+                    names: run
+                        .iter()
+                        .map(|(name, _)| ExtName {
+                            name: name.clone(),
+                            type_index: TypeIndex::NONE,
+                        })
+                        .collect(),
+                }
+            } else {
+                OmfRecordData::ComDef {
+                    definitions: run
+                        .iter()
+                        .map(|(name, size)| ComName {
+                            name: name.clone(),
+                            type_index: TypeIndex::NONE,
+                            communal_length: CommunalLength::Near(size.unwrap_or(0)),
+                        })
+                        .collect(),
+                }
+            });
+        }
+        let mut segment_indices: Vec<SegmentIndex> = vec![];
+        for p in &self.publics {
+            if !segment_indices.contains(&p.0) {
+                segment_indices.push(p.0);
+            }
+        }
+        for segment_index in segment_indices {
+            let names: Vec<PubName> = self
+                .publics
+                .iter()
+                .filter(|p| p.0 == segment_index)
+                .map(|p| PubName {
+                    name: p.1.clone(),
+                    public_offset: p.2,
+                    type_index: TypeIndex::NONE,
+                })
+                .collect();
+            for chunk in names.chunks(32) {
+                records.push(OmfRecordData::PubDef {
+                    base_group_index: GroupIndex::NONE,
+                    base_segment_index: segment_index,
+                    base_frame: 0,
+                    names: chunk.to_vec(),
+                });
+            }
+        }
+        records.extend(self.data.iter().cloned());
+        records.push(OmfRecordData::ModEnd {
+            main: self.start.is_some(),
+            start: match self.start {
+                Some(start) => MAttrStart::Start(start),
+                None => MAttrStart::NoStart,
+            },
+        });
+        records
+    }
+
+    pub fn write(&self, w: &mut dyn Write) -> Result<(), OmfError> {
+        let mut writer = OmfWriter::new(w);
+        for record in self.build() {
+            writer.write_data(&record)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChecksumMode, ModuleBuilder, OmfWriter};
+    use crate::{
+        expand_iterated_data, Fixup, FixupData, FixupFrame, FixupSubrecord, FixupTarget,
+        IteratedDataContent, LocationType, OmfReader, OmfRecordData, SegmentAlignment,
+        SegmentCombination, SegmentIndex,
+    };
+
+    fn builder_with_data(data: &[u8], fixups: &[Fixup], compress: bool) -> Vec<OmfRecordData> {
+        let mut builder = ModuleBuilder::new("test");
+        builder.set_compress_data(compress);
+        let segment = builder
+            .add_segment(
+                "_DATA",
+                "DATA",
+                SegmentAlignment::RelocatableWordAligned,
+                SegmentCombination::Public,
+                false,
+                data.len() as u32,
+            )
+            .unwrap();
+        builder.add_data(segment, 0, data, fixups).unwrap();
+        builder.build()
+    }
+
+    // The data records in order, as (is LIDATA, offset, expanded bytes),
+    // checking that together they give back `data`.
+    fn data_records(records: &[OmfRecordData], data: &[u8]) -> Vec<(bool, u16, Vec<u8>)> {
+        let mut image = vec![0xCC; data.len()];
+        let mut out = vec![];
+        for record in records {
+            let (iterated, offset, bytes) = match record {
+                OmfRecordData::LEData {
+                    enumerated_data_offset,
+                    data,
+                    ..
+                } => (false, *enumerated_data_offset, data.clone()),
+                OmfRecordData::LIData {
+                    iterated_data_offset,
+                    blocks,
+                    ..
+                } => (
+                    true,
+                    *iterated_data_offset,
+                    expand_iterated_data(blocks, 0x10000).unwrap(),
+                ),
+                _ => continue,
+            };
+            image[offset as usize..offset as usize + bytes.len()].copy_from_slice(&bytes);
+            out.push((iterated, offset, bytes));
+        }
+        assert_eq!(image, data);
+        out
+    }
+
+    #[test]
+    fn repeated_runs_go_in_lidata() {
+        let mut data = b"HEAD".to_vec();
+        data.extend([0x90; 200]);
+        data.extend(b"TAIL");
+        let records = builder_with_data(&data, &[], true);
+        let kinds: Vec<_> = data_records(&records, &data)
+            .into_iter()
+            .map(|(iterated, offset, bytes)| (iterated, offset, bytes.len()))
+            .collect();
+        assert_eq!(kinds, [(false, 0, 4), (true, 4, 200), (false, 204, 4)]);
+        let blocks = records
+            .iter()
+            .find_map(|r| match r {
+                OmfRecordData::LIData { blocks, .. } => Some(blocks),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].repeat_count, 200);
+        assert!(matches!(&blocks[0].content, IteratedDataContent::Data(d) if d == &[0x90]));
+    }
+
+    #[test]
+    fn fixed_up_bytes_stay_in_ledata() {
+        let data = [0; 160];
+        let fixup = Fixup {
+            segment_relative: true,
+            location: LocationType::Offset,
+            data_record_offset: 80,
+            fix_data: FixupData {
+                frame: FixupFrame::Segment(SegmentIndex(1)),
+                target: FixupTarget::Segment(SegmentIndex(1)),
+                target_displacement: Some(0),
+            },
+        };
+        let records = builder_with_data(&data, &[fixup], true);
+        let kinds: Vec<_> = data_records(&records, &data)
+            .into_iter()
+            .map(|(iterated, offset, bytes)| (iterated, offset, bytes.len()))
+            .collect();
+        assert_eq!(kinds, [(true, 0, 80), (false, 80, 2), (true, 82, 78)]);
+        let fixups: Vec<_> = records
+            .iter()
+            .filter_map(|r| match r {
+                OmfRecordData::FixUpp { subrecords } => Some(subrecords),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(fixups.len(), 1);
+        assert!(matches!(fixups[0], FixupSubrecord::Fixup(f) if f.data_record_offset == 0));
+    }
+
+    #[test]
+    fn short_runs_and_uncompressed_data_stay_in_ledata() {
+        let data = [0; 63];
+        let records = builder_with_data(&data, &[], true);
+        assert!(data_records(&records, &data).iter().all(|(i, ..)| !i));
+
+        let data = [0; 200];
+        let records = builder_with_data(&data, &[], false);
+        assert!(data_records(&records, &data).iter().all(|(i, ..)| !i));
+    }
+
+    // THEADR with no checksum, LNAMES with a wrong one and MODEND with a
+    // right one.
+    const CHECKSUMS: &[u8] = b"\x80\x03\x00\x01a\x00\x96\x03\x00\x01A\x12\x8A\x02\x00\x00\x74";
+
+    fn rewrite(bytes: &[u8], mode: ChecksumMode, rename: bool) -> Vec<u8> {
+        let mut out = vec![];
+        let mut writer = OmfWriter::new(&mut out);
+        writer.set_checksum_mode(mode);
+        for record in OmfReader::new(bytes) {
+            let mut record = record.unwrap();
+            if let OmfRecordData::THeadr { name } = &mut record.data {
+                if rename {
+                    *name = "b".to_string();
+                }
+            }
+            writer.write_record(&record).unwrap();
+        }
+        out
+    }
+
+    #[test]
+    fn checksum_modes() {
+        let computed = rewrite(CHECKSUMS, ChecksumMode::Computed, false);
+        assert_eq!(computed[5], 0x1B);
+        assert_eq!(computed[11], 0x25);
+        assert_eq!(computed[16], 0x74);
+
+        let zero = rewrite(CHECKSUMS, ChecksumMode::Zero, false);
+        assert_eq!([zero[5], zero[11], zero[16]], [0, 0, 0]);
+
+        assert_eq!(rewrite(CHECKSUMS, ChecksumMode::Preserve, false), CHECKSUMS);
+        // A changed record keeps its missing checksum.
+        let renamed = rewrite(CHECKSUMS, ChecksumMode::Preserve, true);
+        assert_eq!(&renamed[3..6], b"\x01b\x00");
+        assert_eq!(renamed[6..], CHECKSUMS[6..]);
+    }
+}